@@ -15,6 +15,9 @@ fuzz_target!(|data: &[u8]| {
             // Modify these values to explore additional parts of wasmer.
             simd: false,
             threads: false,
+            bulk_memory: false,
+            reference_types: false,
+            multi_value: false,
         },
     );
 });
@@ -9,9 +9,9 @@ use crate::{
 use cranelift_codegen::entity::EntityRef;
 use cranelift_codegen::ir::{self, Ebb, Function, InstBuilder};
 use cranelift_codegen::isa::CallConv;
-use cranelift_codegen::{cursor::FuncCursor, isa};
+use cranelift_codegen::{cursor::Cursor, cursor::FuncCursor, isa};
 use cranelift_frontend::{FunctionBuilder, Position, Variable};
-use cranelift_wasm::{self, FuncTranslator, ModuleTranslationState};
+use cranelift_wasm::{self, DummyEnvironment, FuncTranslator, ModuleTranslationState};
 use cranelift_wasm::{get_vmctx_value_label, translate_operator};
 use cranelift_wasm::{FuncEnvironment, ReturnMode, TargetEnvironment, WasmError};
 use std::mem;
@@ -26,17 +26,18 @@ use wasmer_runtime_core::{
     structures::{Map, TypedIndex},
     types::{
         FuncIndex, FuncSig, GlobalIndex, LocalFuncIndex, LocalOrImport, MemoryIndex, SigIndex,
-        TableIndex,
+        TableIndex, Type,
     },
     vm,
 };
-use wasmparser::Type as WpType;
+use wasmparser::{Operator, Type as WpType};
 
 pub struct CraneliftModuleCodeGenerator {
     isa: Box<dyn isa::TargetIsa>,
     signatures: Option<Arc<Map<SigIndex, FuncSig>>>,
     pub clif_signatures: Map<SigIndex, ir::Signature>,
     function_signatures: Option<Arc<Map<FuncIndex, SigIndex>>>,
+    module_translation_state: Arc<ModuleTranslationState>,
     functions: Vec<CraneliftFunctionCodeGenerator>,
 }
 
@@ -50,6 +51,7 @@ impl ModuleCodeGenerator<CraneliftFunctionCodeGenerator, Caller, CodegenError>
             clif_signatures: Map::new(),
             functions: vec![],
             function_signatures: None,
+            module_translation_state: Arc::new(ModuleTranslationState::new()),
             signatures: None,
         }
     }
@@ -94,6 +96,7 @@ impl ModuleCodeGenerator<CraneliftFunctionCodeGenerator, Caller, CodegenError>
             func_translator,
             next_local: 0,
             position: Position::default(),
+            module_translation_state: Arc::clone(&self.module_translation_state),
             func_env: FunctionEnvironment {
                 module_info: Arc::clone(&module_info),
                 target_config: self.isa.frontend_config().clone(),
@@ -181,6 +184,10 @@ impl ModuleCodeGenerator<CraneliftFunctionCodeGenerator, Caller, CodegenError>
             self.clif_signatures
                 .push(convert_func_sig(func_sig, call_conv));
         }
+        self.module_translation_state = Arc::new(translate_signatures(
+            self.signatures.as_ref().unwrap(),
+            self.isa.frontend_config(),
+        )?);
         Ok(())
     }
 
@@ -201,6 +208,50 @@ impl ModuleCodeGenerator<CraneliftFunctionCodeGenerator, Caller, CodegenError>
     }
 }
 
+/// Builds the state cranelift-wasm reads the types of multi-value blocks from.
+///
+/// It can only be made by translating a module, so this translates one with just a type
+/// section holding `signatures`.
+fn translate_signatures(
+    signatures: &Map<SigIndex, FuncSig>,
+    target_config: isa::TargetFrontendConfig,
+) -> Result<ModuleTranslationState, CodegenError> {
+    fn push_leb(bytes: &mut Vec<u8>, mut n: usize) {
+        while n >= 0x80 {
+            bytes.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+        bytes.push(n as u8);
+    }
+
+    fn push_types(bytes: &mut Vec<u8>, types: &[Type]) {
+        push_leb(bytes, types.len());
+        bytes.extend(types.iter().map(|ty| match ty {
+            Type::I32 => 0x7f,
+            Type::I64 => 0x7e,
+            Type::F32 => 0x7d,
+            Type::F64 => 0x7c,
+            Type::V128 => 0x7b,
+            Type::FuncRef => 0x70,
+            Type::AnyRef => 0x6f,
+        }));
+    }
+
+    let mut section = vec![];
+    push_leb(&mut section, signatures.len());
+    for (_, sig) in signatures.iter() {
+        section.push(0x60);
+        push_types(&mut section, sig.params());
+        push_types(&mut section, sig.returns());
+    }
+    let mut wasm = b"\0asm\x01\0\0\0\x01".to_vec();
+    push_leb(&mut wasm, section.len());
+    wasm.extend(section);
+
+    let mut environ = DummyEnvironment::new(target_config, ReturnMode::NormalReturns, false);
+    Ok(cranelift_wasm::translate_module(&wasm, &mut environ)?)
+}
+
 fn convert_func_sig(sig: &FuncSig, call_conv: CallConv) -> ir::Signature {
     ir::Signature {
         params: sig
@@ -238,6 +289,7 @@ pub struct CraneliftFunctionCodeGenerator {
     func_translator: FuncTranslator,
     next_local: usize,
     position: Position,
+    module_translation_state: Arc<ModuleTranslationState>,
     func_env: FunctionEnvironment,
 }
 
@@ -939,95 +991,292 @@ impl FuncEnvironment for FunctionEnvironment {
     }
     fn translate_memory_copy(
         &mut self,
-        _pos: FuncCursor,
-        _clif_mem_index: cranelift_wasm::MemoryIndex,
+        mut pos: FuncCursor,
+        clif_mem_index: cranelift_wasm::MemoryIndex,
         _heap: ir::Heap,
-        _dst: ir::Value,
-        _src: ir::Value,
-        _len: ir::Value,
+        dst: ir::Value,
+        src: ir::Value,
+        len: ir::Value,
     ) -> cranelift_wasm::WasmResult<()> {
-        unimplemented!("memory.copy not yet implemented");
+        let (namespace, mem_index) = self.memory_namespace(clif_mem_index);
+        let const_mem_index = pos.ins().iconst(ir::types::I32, mem_index as i64);
+
+        self.call_vmcall(
+            &mut pos,
+            namespace,
+            call_names::MEMORY_COPY,
+            &[const_mem_index, dst, src, len],
+            &[],
+        );
+        Ok(())
     }
 
     fn translate_memory_fill(
         &mut self,
-        _pos: FuncCursor,
-        _clif_mem_index: cranelift_wasm::MemoryIndex,
+        mut pos: FuncCursor,
+        clif_mem_index: cranelift_wasm::MemoryIndex,
         _heap: ir::Heap,
-        _dst: ir::Value,
-        _val: ir::Value,
-        _len: ir::Value,
+        dst: ir::Value,
+        val: ir::Value,
+        len: ir::Value,
     ) -> cranelift_wasm::WasmResult<()> {
-        unimplemented!("memory.fill not yet implemented");
+        let (namespace, mem_index) = self.memory_namespace(clif_mem_index);
+        let const_mem_index = pos.ins().iconst(ir::types::I32, mem_index as i64);
+
+        self.call_vmcall(
+            &mut pos,
+            namespace,
+            call_names::MEMORY_FILL,
+            &[const_mem_index, dst, val, len],
+            &[],
+        );
+        Ok(())
     }
 
     fn translate_memory_init(
         &mut self,
-        _pos: FuncCursor,
-        _clif_mem_index: cranelift_wasm::MemoryIndex,
+        mut pos: FuncCursor,
+        clif_mem_index: cranelift_wasm::MemoryIndex,
         _heap: ir::Heap,
-        _seg_index: u32,
-        _dst: ir::Value,
-        _src: ir::Value,
-        _len: ir::Value,
+        seg_index: u32,
+        dst: ir::Value,
+        src: ir::Value,
+        len: ir::Value,
     ) -> cranelift_wasm::WasmResult<()> {
-        unimplemented!("memory.init not yet implemented");
+        let mem_index: MemoryIndex = Converter(clif_mem_index).into();
+        let const_mem_index = pos.ins().iconst(ir::types::I32, mem_index.index() as i64);
+        let const_seg_index = pos.ins().iconst(ir::types::I32, seg_index as i64);
+
+        self.call_vmcall(
+            &mut pos,
+            call_names::LOCAL_NAMESPACE,
+            call_names::MEMORY_INIT,
+            &[const_mem_index, const_seg_index, dst, src, len],
+            &[],
+        );
+        Ok(())
     }
 
     fn translate_data_drop(
         &mut self,
-        _pos: FuncCursor,
-        _seg_index: u32,
+        mut pos: FuncCursor,
+        seg_index: u32,
     ) -> cranelift_wasm::WasmResult<()> {
-        unimplemented!("data.drop not yet implemented");
+        let const_seg_index = pos.ins().iconst(ir::types::I32, seg_index as i64);
+
+        self.call_vmcall(
+            &mut pos,
+            call_names::LOCAL_NAMESPACE,
+            call_names::DATA_DROP,
+            &[const_seg_index],
+            &[],
+        );
+        Ok(())
     }
 
     fn translate_table_size(
         &mut self,
-        _pos: FuncCursor,
-        _index: cranelift_wasm::TableIndex,
+        mut pos: FuncCursor,
+        index: cranelift_wasm::TableIndex,
         _table: ir::Table,
     ) -> cranelift_wasm::WasmResult<ir::Value> {
-        unimplemented!("table.size not yet implemented");
+        let table_index: TableIndex = Converter(index).into();
+        let const_table_index = pos.ins().iconst(ir::types::I32, table_index.index() as i64);
+
+        let call_inst = self.call_vmcall(
+            &mut pos,
+            call_names::LOCAL_NAMESPACE,
+            call_names::TABLE_SIZE,
+            &[const_table_index],
+            &[ir::types::I32],
+        );
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
     }
 
     fn translate_table_copy(
         &mut self,
-        _pos: FuncCursor,
-        _dst_table_index: cranelift_wasm::TableIndex,
+        mut pos: FuncCursor,
+        dst_table_index: cranelift_wasm::TableIndex,
         _dst_table: ir::Table,
-        _src_table_index: cranelift_wasm::TableIndex,
+        src_table_index: cranelift_wasm::TableIndex,
         _src_table: ir::Table,
-        _dst: ir::Value,
-        _src: ir::Value,
-        _len: ir::Value,
+        dst: ir::Value,
+        src: ir::Value,
+        len: ir::Value,
     ) -> cranelift_wasm::WasmResult<()> {
-        unimplemented!("table.copy yet implemented");
+        let dst_table_index: TableIndex = Converter(dst_table_index).into();
+        let src_table_index: TableIndex = Converter(src_table_index).into();
+        let const_dst_table_index = pos
+            .ins()
+            .iconst(ir::types::I32, dst_table_index.index() as i64);
+        let const_src_table_index = pos
+            .ins()
+            .iconst(ir::types::I32, src_table_index.index() as i64);
+
+        self.call_vmcall(
+            &mut pos,
+            call_names::LOCAL_NAMESPACE,
+            call_names::TABLE_COPY,
+            &[const_dst_table_index, const_src_table_index, dst, src, len],
+            &[],
+        );
+        Ok(())
     }
 
     fn translate_table_init(
         &mut self,
-        _pos: FuncCursor,
-        _seg_index: u32,
-        _table_index: cranelift_wasm::TableIndex,
+        mut pos: FuncCursor,
+        seg_index: u32,
+        table_index: cranelift_wasm::TableIndex,
         _table: ir::Table,
-        _dst: ir::Value,
-        _src: ir::Value,
-        _len: ir::Value,
+        dst: ir::Value,
+        src: ir::Value,
+        len: ir::Value,
     ) -> cranelift_wasm::WasmResult<()> {
-        unimplemented!("table.init yet implemented");
+        let table_index: TableIndex = Converter(table_index).into();
+        let const_table_index = pos.ins().iconst(ir::types::I32, table_index.index() as i64);
+        let const_seg_index = pos.ins().iconst(ir::types::I32, seg_index as i64);
+
+        self.call_vmcall(
+            &mut pos,
+            call_names::LOCAL_NAMESPACE,
+            call_names::TABLE_INIT,
+            &[const_table_index, const_seg_index, dst, src, len],
+            &[],
+        );
+        Ok(())
     }
 
     fn translate_elem_drop(
         &mut self,
-        _pos: FuncCursor,
-        _seg_index: u32,
+        mut pos: FuncCursor,
+        seg_index: u32,
     ) -> cranelift_wasm::WasmResult<()> {
-        unimplemented!("elem.drop yet implemented");
+        let const_seg_index = pos.ins().iconst(ir::types::I32, seg_index as i64);
+
+        self.call_vmcall(
+            &mut pos,
+            call_names::LOCAL_NAMESPACE,
+            call_names::ELEM_DROP,
+            &[const_seg_index],
+            &[],
+        );
+        Ok(())
     }
 }
 
 impl FunctionEnvironment {
+    /// Returns the vmcall namespace and the local or imported index of a memory.
+    fn memory_namespace(&self, clif_mem_index: cranelift_wasm::MemoryIndex) -> (u32, usize) {
+        let mem_index: MemoryIndex = Converter(clif_mem_index).into();
+        match mem_index.local_or_import(&self.module_info.read().unwrap()) {
+            LocalOrImport::Local(local_mem_index) => {
+                (call_names::LOCAL_NAMESPACE, local_mem_index.index())
+            }
+            LocalOrImport::Import(import_mem_index) => {
+                (call_names::IMPORT_NAMESPACE, import_mem_index.index())
+            }
+        }
+    }
+
+    /// Lowers the reference types `table.get`, `table.set` and `table.grow` operators,
+    /// which the translator does not handle, and `global.get` and `global.set` on
+    /// globals of reference type, which must keep what they hold alive, to vmcalls
+    /// operating on `stack`.
+    ///
+    /// Returns `false` if `op` is not one of them.
+    fn translate_reference_operator(
+        &self,
+        module_info: &ModuleInfo,
+        op: &Operator,
+        pos: &mut FuncCursor,
+        stack: &mut Vec<ir::Value>,
+    ) -> bool {
+        let is_reference_global = |global_index: u32| {
+            module_info
+                .global_desc(GlobalIndex::new(global_index as usize))
+                .ty
+                .is_reference()
+        };
+
+        let (name_index, arg_count, returns, index) = match *op {
+            Operator::TableGet { table } => {
+                (call_names::TABLE_GET, 1, vec![self.reference_type()], table)
+            }
+            Operator::TableSet { table } => (call_names::TABLE_SET, 2, vec![], table),
+            Operator::TableGrow { table } => {
+                (call_names::TABLE_GROW, 2, vec![ir::types::I32], table)
+            }
+            Operator::GlobalGet { global_index } if is_reference_global(global_index) => (
+                call_names::GLOBAL_GET_REF,
+                0,
+                vec![self.reference_type()],
+                global_index,
+            ),
+            Operator::GlobalSet { global_index } if is_reference_global(global_index) => {
+                (call_names::GLOBAL_SET_REF, 1, vec![], global_index)
+            }
+            _ => return false,
+        };
+
+        let mut args = vec![pos.ins().iconst(ir::types::I32, index as i64)];
+        let args_start = stack.len() - arg_count;
+        args.extend(stack.drain(args_start..));
+
+        let call_inst = self.call_vmcall(
+            pos,
+            call_names::LOCAL_NAMESPACE,
+            name_index,
+            &args,
+            &returns,
+        );
+        stack.extend_from_slice(pos.func.dfg.inst_results(call_inst));
+        true
+    }
+
+    /// Emits a call to a vmcall which takes the vmctx followed by `args`
+    /// and returns values of the `returns` types.
+    fn call_vmcall(
+        &self,
+        pos: &mut FuncCursor,
+        namespace: u32,
+        name_index: u32,
+        args: &[ir::Value],
+        returns: &[ir::Type],
+    ) -> ir::Inst {
+        let mut params = vec![ir::AbiParam::special(
+            self.pointer_type(),
+            ir::ArgumentPurpose::VMContext,
+        )];
+        params.extend(
+            args.iter()
+                .map(|&arg| ir::AbiParam::new(pos.func.dfg.value_type(arg))),
+        );
+
+        let signature = pos.func.import_signature(ir::Signature {
+            call_conv: self.target_config().default_call_conv,
+            params,
+            returns: returns.iter().map(|&ty| ir::AbiParam::new(ty)).collect(),
+        });
+
+        let vmcall_func = pos.func.import_function(ir::ExtFuncData {
+            name: ir::ExternalName::user(namespace, name_index),
+            signature,
+            colocated: false,
+        });
+
+        let vmctx = pos
+            .func
+            .special_param(ir::ArgumentPurpose::VMContext)
+            .expect("missing vmctx parameter");
+
+        let mut call_args = Vec::with_capacity(args.len() + 1);
+        call_args.push(vmctx);
+        call_args.extend_from_slice(args);
+
+        pos.ins().call(vmcall_func, &call_args)
+    }
+
     pub fn get_func_type(
         &self,
         func_index: cranelift_wasm::FuncIndex,
@@ -1088,7 +1337,7 @@ impl FunctionCodeGenerator<CodegenError> for CraneliftFunctionCodeGenerator {
         Ok(())
     }
 
-    fn feed_event(&mut self, event: Event, _module_info: &ModuleInfo) -> Result<(), CodegenError> {
+    fn feed_event(&mut self, event: Event, module_info: &ModuleInfo) -> Result<(), CodegenError> {
         let op = match event {
             Event::Wasm(x) => x,
             Event::WasmOwned(ref x) => x,
@@ -1110,10 +1359,19 @@ impl FunctionCodeGenerator<CodegenError> for CraneliftFunctionCodeGenerator {
             &mut self.func_translator.func_ctx,
             &mut self.position,
         );
-        let module_state = ModuleTranslationState::new();
         let func_state = &mut self.func_translator.state;
+        if func_state.reachable
+            && self.func_env.translate_reference_operator(
+                module_info,
+                op,
+                &mut builder.cursor(),
+                &mut func_state.stack,
+            )
+        {
+            return Ok(());
+        }
         translate_operator(
-            &module_state,
+            &self.module_translation_state,
             op,
             &mut builder,
             func_state,
@@ -1152,6 +1410,8 @@ impl FunctionCodeGenerator<CodegenError> for CraneliftFunctionCodeGenerator {
         state.stack.clear();
 
         self.builder().finalize();
+
+        lower_return_areas(&mut self.func);
         Ok(())
     }
 }
@@ -1236,3 +1496,86 @@ fn declare_wasm_parameters(builder: &mut FunctionBuilder, entry_block: Ebb) -> u
 
     next_local
 }
+
+/// Rewrite functions and calls with several results to pass them through a
+/// return area instead, see `FuncSig::has_return_area`.
+///
+/// The translator builds ordinary multi-value returns and calls, which are
+/// lowered here once the whole function is known.
+fn lower_return_areas(func: &mut Function) {
+    let ptr_type = ir::types::I64;
+    let mut mflags = ir::MemFlags::new();
+    mflags.set_notrap();
+
+    let slot_size = |ty: ir::Type| if ty.bytes() > 8 { 16 } else { 8 };
+
+    let mut returns = vec![];
+    let mut calls = vec![];
+    let mut pos = FuncCursor::new(func);
+    while let Some(_ebb) = pos.next_ebb() {
+        while let Some(inst) = pos.next_inst() {
+            if pos.func.dfg[inst].opcode() == ir::Opcode::Return {
+                returns.push(inst);
+            } else if pos.func.dfg.inst_results(inst).len() > 1
+                && pos.func.dfg.call_signature(inst).is_some()
+            {
+                calls.push(inst);
+            }
+        }
+    }
+
+    // Store the results of this function into the return area.
+    if pos.func.signature.returns.len() > 1 {
+        let entry_ebb = pos.func.layout.entry_block().unwrap();
+        let return_area = pos.func.dfg.append_ebb_param(entry_ebb, ptr_type);
+
+        for inst in returns {
+            pos.goto_inst(inst);
+            let values = pos.func.dfg.inst_args(inst).to_vec();
+            let mut offset = 0;
+            for value in values {
+                pos.ins().store(mflags, value, return_area, offset);
+                offset += slot_size(pos.func.dfg.value_type(value));
+            }
+            pos.func.dfg.replace(inst).return_(&[]);
+        }
+    }
+
+    // Load the results of each call out of a return area on the stack.
+    for inst in calls {
+        let results = pos.func.dfg.inst_results(inst).to_vec();
+        let size = results
+            .iter()
+            .map(|&value| slot_size(pos.func.dfg.value_type(value)))
+            .sum::<i32>();
+        let slot = pos.func.create_stack_slot(ir::StackSlotData::new(
+            ir::StackSlotKind::ExplicitSlot,
+            size as u32,
+        ));
+
+        pos.goto_inst(inst);
+        let return_area = pos.ins().stack_addr(ptr_type, slot, 0);
+        pos.func.dfg.append_inst_arg(inst, return_area);
+        pos.func.dfg.clear_results(inst);
+
+        pos.goto_after_inst(inst);
+        let mut offset = 0;
+        for value in results {
+            let ty = pos.func.dfg.value_type(value);
+            let loaded = pos.ins().load(ty, mflags, return_area, offset);
+            pos.func.dfg.change_to_alias(value, loaded);
+            offset += slot_size(ty);
+        }
+    }
+
+    for sig in pos.func.dfg.signatures.values_mut() {
+        if sig.returns.len() > 1 {
+            sig.returns.clear();
+            sig.params.push(ir::AbiParam::new(ptr_type));
+        }
+    }
+    if pos.func.signature.returns.len() > 1 {
+        pos.func.signature.returns.clear();
+        pos.func.signature.params.push(ir::AbiParam::new(ptr_type));
+    }
+}
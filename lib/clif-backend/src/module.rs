@@ -107,6 +107,7 @@ impl From<Converter<ir::Type>> for Type {
             ir::types::F32 => Type::F32,
             ir::types::F64 => Type::F64,
             ir::types::I32X4 => Type::V128,
+            ir::types::R64 => Type::AnyRef,
             _ => unimplemented!("unsupported wasm type"),
         }
     }
@@ -120,6 +121,7 @@ impl From<Converter<Type>> for ir::Type {
             Type::F32 => ir::types::F32,
             Type::F64 => ir::types::F64,
             Type::V128 => ir::types::I32X4,
+            Type::AnyRef | Type::FuncRef => ir::types::R64,
         }
     }
 }
@@ -132,6 +134,7 @@ impl From<Converter<Type>> for ir::AbiParam {
             Type::F32 => ir::AbiParam::new(ir::types::F32),
             Type::F64 => ir::AbiParam::new(ir::types::F64),
             Type::V128 => ir::AbiParam::new(ir::types::I32X4),
+            Type::AnyRef | Type::FuncRef => ir::AbiParam::new(ir::types::R64),
         }
     }
 }
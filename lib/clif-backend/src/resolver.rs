@@ -266,6 +266,19 @@ impl FuncResolverBuilder {
                             VmCallKind::DynamicMemorySize => {
                                 vmcalls::local_dynamic_memory_size as _
                             }
+                            VmCallKind::MemoryCopy => vmcalls::local_memory_copy as _,
+                            VmCallKind::MemoryFill => vmcalls::local_memory_fill as _,
+                            VmCallKind::MemoryInit => vmcalls::memory_init as _,
+                            VmCallKind::DataDrop => vmcalls::data_drop as _,
+                            VmCallKind::TableCopy => vmcalls::table_copy as _,
+                            VmCallKind::TableInit => vmcalls::table_init as _,
+                            VmCallKind::ElemDrop => vmcalls::elem_drop as _,
+                            VmCallKind::TableGet => vmcalls::table_get as _,
+                            VmCallKind::TableSet => vmcalls::table_set as _,
+                            VmCallKind::TableSize => vmcalls::table_size as _,
+                            VmCallKind::TableGrow => vmcalls::table_grow as _,
+                            VmCallKind::GlobalGetRef => vmcalls::global_get_ref as _,
+                            VmCallKind::GlobalSetRef => vmcalls::global_set_ref as _,
                         },
                         VmCall::Import(kind) => match kind {
                             VmCallKind::StaticMemoryGrow | VmCallKind::SharedStaticMemoryGrow => {
@@ -280,6 +293,19 @@ impl FuncResolverBuilder {
                             VmCallKind::DynamicMemorySize => {
                                 vmcalls::imported_dynamic_memory_size as _
                             }
+                            VmCallKind::MemoryCopy => vmcalls::imported_memory_copy as _,
+                            VmCallKind::MemoryFill => vmcalls::imported_memory_fill as _,
+                            VmCallKind::MemoryInit => vmcalls::memory_init as _,
+                            VmCallKind::DataDrop => vmcalls::data_drop as _,
+                            VmCallKind::TableCopy => vmcalls::table_copy as _,
+                            VmCallKind::TableInit => vmcalls::table_init as _,
+                            VmCallKind::ElemDrop => vmcalls::elem_drop as _,
+                            VmCallKind::TableGet => vmcalls::table_get as _,
+                            VmCallKind::TableSet => vmcalls::table_set as _,
+                            VmCallKind::TableSize => vmcalls::table_size as _,
+                            VmCallKind::TableGrow => vmcalls::table_grow as _,
+                            VmCallKind::GlobalGetRef => vmcalls::global_get_ref as _,
+                            VmCallKind::GlobalSetRef => vmcalls::global_set_ref as _,
                         },
                     },
                     RelocationType::Signature(sig_index) => {
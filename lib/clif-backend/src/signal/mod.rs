@@ -1,5 +1,5 @@
 use crate::{
-    relocation::{TrapData, TrapSink},
+    relocation::{TrapCode, TrapData, TrapSink},
     resolver::FuncResolver,
     trampoline::Trampolines,
 };
@@ -29,6 +29,21 @@ thread_local! {
     pub static TRAP_EARLY_DATA: Cell<Option<Box<dyn Any + Send>>> = Cell::new(None);
 }
 
+/// Maps the trap code of a trapping instruction to the trap reported to the runtime.
+fn trap_info(trapcode: TrapCode) -> WasmTrapInfo {
+    match trapcode {
+        TrapCode::StackOverflow => WasmTrapInfo::StackOverflow,
+        TrapCode::HeapOutOfBounds | TrapCode::OutOfBounds => WasmTrapInfo::MemoryOutOfBounds,
+        TrapCode::TableOutOfBounds | TrapCode::IndirectCallToNull => WasmTrapInfo::CallIndirectOOB,
+        TrapCode::BadSignature => WasmTrapInfo::IncorrectCallIndirectSignature,
+        TrapCode::IntegerOverflow => WasmTrapInfo::IntegerOverflow,
+        TrapCode::IntegerDivisionByZero => WasmTrapInfo::IntegerDivisionByZero,
+        TrapCode::BadConversionToInteger => WasmTrapInfo::BadConversionToInteger,
+        TrapCode::UnreachableCodeReached => WasmTrapInfo::Unreachable,
+        TrapCode::Interrupt | TrapCode::User(_) => WasmTrapInfo::Unknown,
+    }
+}
+
 pub enum CallProtError {
     Trap(WasmTrapInfo),
     Error(Box<dyn Any + Send>),
@@ -108,6 +123,11 @@ impl RunnableModule for Caller {
         })
     }
 
+    #[cfg(unix)]
+    fn swap_trap_state(&self, state: &mut Option<Box<dyn Any>>) {
+        swap_setjmp_buffer(state);
+    }
+
     unsafe fn do_early_trap(&self, data: Box<dyn Any + Send>) -> ! {
         TRAP_EARLY_DATA.with(|cell| cell.set(Some(data)));
         trigger_trap()
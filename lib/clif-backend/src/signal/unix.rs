@@ -9,13 +9,15 @@
 //! are very special, the async signal unsafety of Rust's TLS implementation generally does not affect the correctness here
 //! unless you have memory unsafety elsewhere in your code.
 //!
-use crate::relocation::{TrapCode, TrapData};
+use crate::relocation::TrapData;
 use crate::signal::{CallProtError, HandlerData};
 use libc::{c_int, c_void, siginfo_t};
 use nix::sys::signal::{
     sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal, SIGBUS, SIGFPE, SIGILL, SIGSEGV,
 };
+use std::any::Any;
 use std::cell::{Cell, UnsafeCell};
+use std::mem;
 use std::ptr;
 use std::sync::Once;
 use wasmer_runtime_core::typed_func::WasmTrapInfo;
@@ -56,6 +58,17 @@ thread_local! {
     pub static CURRENT_EXECUTABLE_BUFFER: Cell<*const c_void> = Cell::new(ptr::null());
 }
 
+/// Exchange the setjmp buffer of the current thread with the one kept in `state`.
+///
+/// Each call stack needs its own buffer, so callers that switch stacks swap it on every switch.
+pub fn swap_setjmp_buffer(state: &mut Option<Box<dyn Any>>) {
+    let saved = state
+        .get_or_insert_with(|| Box::new([0 as c_int; SETJMP_BUFFER_LEN]))
+        .downcast_mut::<[c_int; SETJMP_BUFFER_LEN]>()
+        .expect("unexpected trap state");
+    SETJMP_BUFFER.with(|buf| unsafe { mem::swap(&mut *buf.get(), saved) });
+}
+
 pub unsafe fn trigger_trap() -> ! {
     let jmp_buf = SETJMP_BUFFER.with(|buf| buf.get());
 
@@ -89,13 +102,7 @@ pub fn call_protected<T>(
                 }) = handler_data.lookup(inst_ptr)
                 {
                     Err(CallProtError::Trap(match Signal::from_c_int(signum) {
-                        Ok(SIGILL) => match trapcode {
-                            TrapCode::BadSignature => WasmTrapInfo::IncorrectCallIndirectSignature,
-                            TrapCode::IndirectCallToNull => WasmTrapInfo::CallIndirectOOB,
-                            TrapCode::HeapOutOfBounds => WasmTrapInfo::MemoryOutOfBounds,
-                            TrapCode::TableOutOfBounds => WasmTrapInfo::CallIndirectOOB,
-                            _ => WasmTrapInfo::Unknown,
-                        },
+                        Ok(SIGILL) => super::trap_info(trapcode),
                         Ok(SIGSEGV) | Ok(SIGBUS) => WasmTrapInfo::MemoryOutOfBounds,
                         Ok(SIGFPE) => WasmTrapInfo::IllegalArithmetic,
                         _ => unimplemented!(
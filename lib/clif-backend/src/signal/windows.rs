@@ -1,5 +1,5 @@
 use crate::{
-    relocation::{TrapCode, TrapData},
+    relocation::TrapData,
     signal::{CallProtError, HandlerData},
 };
 use std::{
@@ -64,18 +64,10 @@ pub fn call_protected(
     {
         Err(CallProtError::Trap(match code as DWORD {
             EXCEPTION_ACCESS_VIOLATION => WasmTrapInfo::MemoryOutOfBounds,
-            EXCEPTION_ILLEGAL_INSTRUCTION => match trapcode {
-                TrapCode::BadSignature => WasmTrapInfo::IncorrectCallIndirectSignature,
-                TrapCode::IndirectCallToNull => WasmTrapInfo::CallIndirectOOB,
-                TrapCode::HeapOutOfBounds => WasmTrapInfo::MemoryOutOfBounds,
-                TrapCode::TableOutOfBounds => WasmTrapInfo::CallIndirectOOB,
-                TrapCode::UnreachableCodeReached => WasmTrapInfo::Unreachable,
-                _ => WasmTrapInfo::Unknown,
-            },
-            EXCEPTION_STACK_OVERFLOW => WasmTrapInfo::Unknown,
-            EXCEPTION_INT_DIVIDE_BY_ZERO | EXCEPTION_INT_OVERFLOW => {
-                WasmTrapInfo::IllegalArithmetic
-            }
+            EXCEPTION_ILLEGAL_INSTRUCTION => super::trap_info(trapcode),
+            EXCEPTION_STACK_OVERFLOW => WasmTrapInfo::StackOverflow,
+            EXCEPTION_INT_DIVIDE_BY_ZERO => WasmTrapInfo::IntegerDivisionByZero,
+            EXCEPTION_INT_OVERFLOW => WasmTrapInfo::IntegerOverflow,
             _ => WasmTrapInfo::Unknown,
         }))
     } else {
@@ -181,6 +181,11 @@ fn generate_func(func_sig: &FuncSig) -> ir::Function {
         args_vec.push(val);
     }
 
+    // Several results are written straight into the returns array.
+    if func_sig.has_return_area() {
+        args_vec.push(returns_ptr);
+    }
+
     let call_inst = pos.ins().call_indirect(export_sig_ref, func_ptr, &args_vec);
 
     let return_values = pos.func.dfg.inst_results(call_inst).to_vec();
@@ -208,6 +213,7 @@ fn wasm_ty_to_clif(ty: Type) -> ir::types::Type {
         Type::F32 => ir::types::F32,
         Type::F64 => ir::types::F64,
         Type::V128 => ir::types::I32X4,
+        Type::AnyRef | Type::FuncRef => ir::types::R64,
     }
 }
 
@@ -249,16 +255,22 @@ fn generate_export_signature(func_sig: &FuncSig) -> ir::Signature {
     .chain(func_sig_iter)
     .collect();
 
-    export_clif_sig.returns = func_sig
-        .returns()
-        .iter()
-        .map(|wasm_ty| ir::AbiParam {
-            value_type: wasm_ty_to_clif(*wasm_ty),
-            purpose: ir::ArgumentPurpose::Normal,
-            extension: ir::ArgumentExtension::None,
-            location: ir::ArgumentLoc::Unassigned,
-        })
-        .collect();
+    if func_sig.has_return_area() {
+        export_clif_sig
+            .params
+            .push(ir::AbiParam::new(ir::types::I64));
+    } else {
+        export_clif_sig.returns = func_sig
+            .returns()
+            .iter()
+            .map(|wasm_ty| ir::AbiParam {
+                value_type: wasm_ty_to_clif(*wasm_ty),
+                purpose: ir::ArgumentPurpose::Normal,
+                extension: ir::ArgumentExtension::None,
+                location: ir::ArgumentLoc::Unassigned,
+            })
+            .collect();
+    }
 
     export_clif_sig
 }
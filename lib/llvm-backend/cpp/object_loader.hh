@@ -59,6 +59,11 @@ enum WasmTrapType {
   MemoryOutOfBounds = 2,
   CallIndirectOOB = 3,
   IllegalArithmetic = 4,
+  MisalignedAtomicAccess = 5,
+  StackOverflow = 6,
+  IntegerDivisionByZero = 7,
+  IntegerOverflow = 8,
+  BadConversionToInteger = 9,
   Unknown,
 };
 
@@ -232,6 +237,21 @@ private:
     case WasmTrapType::IllegalArithmetic:
       out << "illegal arithmetic operation";
       break;
+    case WasmTrapType::MisalignedAtomicAccess:
+      out << "misaligned atomic access";
+      break;
+    case WasmTrapType::StackOverflow:
+      out << "call stack exhausted";
+      break;
+    case WasmTrapType::IntegerDivisionByZero:
+      out << "integer division by zero";
+      break;
+    case WasmTrapType::IntegerOverflow:
+      out << "integer overflow";
+      break;
+    case WasmTrapType::BadConversionToInteger:
+      out << "invalid conversion to integer";
+      break;
     case WasmTrapType::Unknown:
     default:
       out << "unknown";
@@ -31,7 +31,7 @@ use wasmer_runtime_core::{
     state::ModuleStateMap,
     structures::TypedIndex,
     typed_func::{Trampoline, Wasm, WasmTrapInfo},
-    types::{LocalFuncIndex, SigIndex},
+    types::{FuncIndex, LocalFuncIndex, SigIndex},
     vm, vmcalls,
 };
 
@@ -114,11 +114,34 @@ fn get_callbacks() -> Callbacks {
             fn_name!("vm.memory.size.dynamic.local") => vmcalls::local_dynamic_memory_size as _,
             fn_name!("vm.memory.grow.static.local") => vmcalls::local_static_memory_grow as _,
             fn_name!("vm.memory.size.static.local") => vmcalls::local_static_memory_size as _,
+            fn_name!("vm.memory.grow.shared.local") => vmcalls::local_shared_memory_grow as _,
+            fn_name!("vm.memory.size.shared.local") => vmcalls::local_shared_memory_size as _,
 
             fn_name!("vm.memory.grow.dynamic.import") => vmcalls::imported_dynamic_memory_grow as _,
             fn_name!("vm.memory.size.dynamic.import") => vmcalls::imported_dynamic_memory_size as _,
             fn_name!("vm.memory.grow.static.import") => vmcalls::imported_static_memory_grow as _,
             fn_name!("vm.memory.size.static.import") => vmcalls::imported_static_memory_size as _,
+            fn_name!("vm.memory.grow.shared.import") => vmcalls::imported_shared_memory_grow as _,
+            fn_name!("vm.memory.size.shared.import") => vmcalls::imported_shared_memory_size as _,
+
+            fn_name!("vm.memory.copy.local") => vmcalls::local_memory_copy as _,
+            fn_name!("vm.memory.copy.import") => vmcalls::imported_memory_copy as _,
+            fn_name!("vm.memory.fill.local") => vmcalls::local_memory_fill as _,
+            fn_name!("vm.memory.fill.import") => vmcalls::imported_memory_fill as _,
+            fn_name!("vm.memory.init") => vmcalls::memory_init as _,
+            fn_name!("vm.data.drop") => vmcalls::data_drop as _,
+            fn_name!("vm.table.copy") => vmcalls::table_copy as _,
+            fn_name!("vm.table.init") => vmcalls::table_init as _,
+            fn_name!("vm.elem.drop") => vmcalls::elem_drop as _,
+            fn_name!("vm.table.get") => vmcalls::table_get as _,
+            fn_name!("vm.table.set") => vmcalls::table_set as _,
+            fn_name!("vm.table.size") => vmcalls::table_size as _,
+            fn_name!("vm.table.grow") => vmcalls::table_grow as _,
+            fn_name!("vm.memory.atomic.wait32") => vmcalls::memory_atomic_wait32 as _,
+            fn_name!("vm.memory.atomic.wait64") => vmcalls::memory_atomic_wait64 as _,
+            fn_name!("vm.memory.atomic.notify") => vmcalls::memory_atomic_notify as _,
+            fn_name!("vm.global.get.ref") => vmcalls::global_get_ref as _,
+            fn_name!("vm.global.set.ref") => vmcalls::global_set_ref as _,
 
             fn_name!("vm.exception.trap") => throw_trap as _,
             fn_name!("vm.breakpoint") => throw_breakpoint as _,
@@ -157,6 +180,23 @@ impl Deref for Buffer {
     }
 }
 
+/// The IR name of the function at `index`. When the module names the function, the name
+/// follows the index, so that IR dumps and native profilers show it.
+pub(crate) fn func_symbol(info: &ModuleInfo, index: FuncIndex) -> String {
+    let mut symbol = format!("fn{}", index.index());
+    if let Some(name) = info.func_name(index) {
+        symbol.push('_');
+        symbol.extend(name.chars().map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        }));
+    }
+    symbol
+}
+
 unsafe impl Send for LLVMBackend {}
 unsafe impl Sync for LLVMBackend {}
 
@@ -240,10 +280,11 @@ impl LLVMBackend {
 
                 // All local functions.
                 for index in module_info.imported_functions.len()..module_info.func_assoc.len() {
+                    let symbol = func_symbol(module_info, FuncIndex::new(index));
                     let name = if cfg!(target_os = "macos") {
-                        format!("_fn{}", index)
+                        format!("_{}", symbol)
                     } else {
-                        format!("fn{}", index)
+                        symbol
                     };
 
                     let c_str = CString::new(name).unwrap();
@@ -298,6 +339,15 @@ impl LLVMBackend {
                     }
                 }
 
+                for fsm in msm.local_functions.values_mut() {
+                    let id = fsm.local_function_id;
+                    fsm.wasm_offset_to_binary_offset = stackmaps
+                        .binary_offsets
+                        .range((id, 0)..(id + 1, 0))
+                        .map(|(&(_, opcode_offset), &offset)| (opcode_offset, offset))
+                        .collect();
+                }
+
                 let code_ptr = unsafe { llvm_backend_get_code_ptr(module) } as usize;
                 let code_len = unsafe { llvm_backend_get_code_size(module) } as usize;
 
@@ -375,11 +425,12 @@ impl RunnableModule for LLVMBackend {
         info: &ModuleInfo,
         local_func_index: LocalFuncIndex,
     ) -> Option<NonNull<vm::Func>> {
-        let index = info.imported_functions.len() + local_func_index.index();
+        let index = FuncIndex::new(info.imported_functions.len() + local_func_index.index());
+        let symbol = func_symbol(info, index);
         let name = if cfg!(target_os = "macos") {
-            format!("_fn{}", index)
+            format!("_{}", symbol)
         } else {
-            format!("fn{}", index)
+            symbol
         };
 
         let c_str = CString::new(name).ok()?;
@@ -1,7 +1,7 @@
 use crate::{
-    backend::LLVMBackend,
+    backend::{func_symbol, LLVMBackend},
     intrinsics::{tbaa_label, CtxType, GlobalCache, Intrinsics, MemoryCache},
-    read_info::blocktype_to_type,
+    read_info::blocktype_to_types,
     stackmap::{StackmapEntry, StackmapEntryKind, StackmapRegistry, ValueSemantic},
     state::{ControlFrame, ExtraInfo, IfElseState, State},
     trampolines::generate_trampolines,
@@ -46,30 +46,98 @@ use wasmer_runtime_core::{
 use wasmparser::{BinaryReaderError, MemoryImmediate, Operator, Type as WpType};
 
 fn func_sig_to_llvm<'ctx>(
-    context: &'ctx Context,
     intrinsics: &Intrinsics<'ctx>,
     sig: &FuncSig,
     type_to_llvm: fn(intrinsics: &Intrinsics<'ctx>, ty: Type) -> BasicTypeEnum<'ctx>,
 ) -> FunctionType<'ctx> {
     let user_param_types = sig.params().iter().map(|&ty| type_to_llvm(intrinsics, ty));
 
+    // Several results are written to a return area passed as the last parameter.
+    let return_area_type = if sig.has_return_area() {
+        Some(intrinsics.i64_ptr_ty.as_basic_type_enum())
+    } else {
+        None
+    };
+
     let param_types: Vec<_> = std::iter::once(intrinsics.ctx_ptr_ty.as_basic_type_enum())
         .chain(user_param_types)
+        .chain(return_area_type)
         .collect();
 
     match sig.returns() {
-        &[] => intrinsics.void_ty.fn_type(&param_types, false),
         &[single_value] => type_to_llvm(intrinsics, single_value).fn_type(&param_types, false),
-        returns @ _ => {
-            let basic_types: Vec<_> = returns
-                .iter()
-                .map(|&ty| type_to_llvm(intrinsics, ty))
-                .collect();
-
-            context
-                .struct_type(&basic_types, false)
-                .fn_type(&param_types, false)
-        }
+        _ => intrinsics.void_ty.fn_type(&param_types, false),
+    }
+}
+
+/// Allocates a return area for calls to functions of signature `sig`.
+fn build_return_area<'ctx>(
+    context: &'ctx Context,
+    function: FunctionValue<'ctx>,
+    intrinsics: &Intrinsics<'ctx>,
+    sig: &FuncSig,
+) -> PointerValue<'ctx> {
+    // Allocate it at the start of the function so that it is only done once.
+    let entry_block = function.get_first_basic_block().unwrap();
+    let alloca_builder = context.create_builder();
+    match entry_block.get_first_instruction() {
+        Some(first) => alloca_builder.position_before(&first),
+        None => alloca_builder.position_at_end(&entry_block),
+    }
+
+    let slots = sig
+        .returns()
+        .iter()
+        .map(|&ty| if ty == Type::V128 { 2 } else { 1 })
+        .sum::<u32>();
+    let area = alloca_builder.build_alloca(intrinsics.i64_ty.array_type(slots), "return_area");
+    alloca_builder.build_pointer_cast(area, intrinsics.i64_ptr_ty, "return_area_ptr")
+}
+
+/// Returns a pointer to the `index`-th value in a return area, where each
+/// value takes one 64-bit slot and a `v128` takes two.
+fn return_area_slot<'ctx>(
+    builder: &Builder<'ctx>,
+    intrinsics: &Intrinsics<'ctx>,
+    return_area: PointerValue<'ctx>,
+    returns: &[Type],
+    index: usize,
+) -> PointerValue<'ctx> {
+    let slot = returns[..index]
+        .iter()
+        .map(|&ty| if ty == Type::V128 { 2 } else { 1 })
+        .sum::<u64>();
+    let ptr = unsafe {
+        builder.build_in_bounds_gep(
+            return_area,
+            &[intrinsics.i32_ty.const_int(slot, false)],
+            "return_slot",
+        )
+    };
+    builder.build_pointer_cast(
+        ptr,
+        type_to_llvm(intrinsics, returns[index]).ptr_type(AddressSpace::Generic),
+        "return_slot_ptr",
+    )
+}
+
+/// Pushes the results a call wrote to its return area.
+fn load_return_area<'ctx>(
+    builder: &Builder<'ctx>,
+    intrinsics: &Intrinsics<'ctx>,
+    state: &mut State<'ctx>,
+    return_area: PointerValue<'ctx>,
+    returns: &[Type],
+) {
+    for index in 0..returns.len() {
+        let ptr = return_area_slot(builder, intrinsics, return_area, returns, index);
+        let value = builder.build_load(ptr, &state.var_name());
+        value
+            .as_instruction_value()
+            .unwrap()
+            .set_alignment(8)
+            .unwrap();
+        state.push1(value);
     }
 }
 
@@ -80,6 +148,7 @@ fn type_to_llvm<'ctx>(intrinsics: &Intrinsics<'ctx>, ty: Type) -> BasicTypeEnum<
         Type::F32 => intrinsics.f32_ty.as_basic_type_enum(),
         Type::F64 => intrinsics.f64_ty.as_basic_type_enum(),
         Type::V128 => intrinsics.i128_ty.as_basic_type_enum(),
+        Type::AnyRef | Type::FuncRef => intrinsics.i64_ty.as_basic_type_enum(),
     }
 }
 
@@ -356,7 +425,7 @@ fn trap_if_not_representable_as_int<'ctx>(
     builder.position_at_end(&failure_block);
     builder.build_call(
         intrinsics.throw_trap,
-        &[intrinsics.trap_illegal_arithmetic],
+        &[intrinsics.trap_bad_conversion_to_integer],
         "throw",
     );
     builder.build_unreachable();
@@ -462,7 +531,7 @@ fn trap_if_zero<'ctx>(
     builder.position_at_end(&should_trap_block);
     builder.build_call(
         intrinsics.throw_trap,
-        &[intrinsics.trap_illegal_arithmetic],
+        &[intrinsics.trap_integer_division_by_zero],
         "throw",
     );
     builder.build_unreachable();
@@ -985,6 +1054,7 @@ pub struct LLVMFunctionCodeGenerator<'ctx> {
     stackmaps: Rc<RefCell<StackmapRegistry>>,
     index: usize,
     opcode_offset: usize,
+    binary_offset: Option<usize>,
     track_state: bool,
     module: Rc<RefCell<Module<'ctx>>>,
 }
@@ -1012,6 +1082,7 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
             Type::F32 => intrinsics.f32_zero.as_basic_value_enum(),
             Type::F64 => intrinsics.f64_zero.as_basic_value_enum(),
             Type::V128 => intrinsics.i128_zero.as_basic_value_enum(),
+            Type::AnyRef | Type::FuncRef => intrinsics.i64_zero.as_basic_value_enum(),
         };
 
         let builder = self.builder.as_ref().unwrap();
@@ -1099,6 +1170,10 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
         Ok(())
     }
 
+    fn set_binary_offset(&mut self, offset: Option<usize>) {
+        self.binary_offset = offset;
+    }
+
     fn feed_event(&mut self, event: Event, module_info: &ModuleInfo) -> Result<(), CodegenError> {
         let mut state = &mut self.state;
         let builder = self.builder.as_ref().unwrap();
@@ -1114,6 +1189,14 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
         let op = match event {
             Event::Wasm(x) => {
                 opcode_offset = Some(self.opcode_offset);
+                if self.track_state {
+                    if let Some(offset) = self.binary_offset {
+                        self.stackmaps
+                            .borrow_mut()
+                            .binary_offsets
+                            .insert((self.index, self.opcode_offset), offset);
+                    }
+                }
                 self.opcode_offset += 1;
                 x
             }
@@ -1164,6 +1247,21 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                             );
                         }
                     }
+                    InternalEvent::IncrementCounter(idx) => {
+                        if state.reachable {
+                            let counter_ptr =
+                                ctx.counter(idx as usize, intrinsics, self.module.clone(), builder);
+                            let count = builder
+                                .build_load(counter_ptr, "counter")
+                                .into_int_value();
+                            let count = builder.build_int_add(
+                                count,
+                                intrinsics.i64_ty.const_int(1, false),
+                                &state.var_name(),
+                            );
+                            builder.build_store(counter_ptr, count);
+                        }
+                    }
                 }
                 return Ok(());
             }
@@ -1207,38 +1305,55 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                 let end_block = context.append_basic_block(function, "end");
                 builder.position_at_end(&end_block);
 
-                let phis = if let Ok(wasmer_ty) = blocktype_to_type(ty) {
-                    let llvm_ty = type_to_llvm(intrinsics, wasmer_ty);
-                    [llvm_ty]
-                        .iter()
-                        .map(|&ty| builder.build_phi(ty, &state.var_name()))
-                        .collect()
-                } else {
-                    SmallVec::new()
-                };
+                let (params, results) = blocktype_to_types(info, ty)?;
+                let phis = results
+                    .iter()
+                    .map(|&ty| builder.build_phi(type_to_llvm(intrinsics, ty), &state.var_name()))
+                    .collect();
 
-                state.push_block(end_block, phis);
+                state.push_block(end_block, phis, params.len());
                 builder.position_at_end(&current_block);
             }
             Operator::Loop { ty } => {
+                let current_block = builder.get_insert_block().ok_or(BinaryReaderError {
+                    message: "not currently in a block",
+                    offset: -1isize as usize,
+                })?;
                 let loop_body = context.append_basic_block(function, "loop_body");
                 let loop_next = context.append_basic_block(function, "loop_outer");
 
+                let (params, results) = blocktype_to_types(info, ty)?;
+                let param_values: SmallVec<[_; 1]> = state
+                    .popn_save_extra(params.len())?
+                    .iter()
+                    .map(|&(v, i)| apply_pending_canonicalization(builder, intrinsics, v, i))
+                    .collect();
+
                 builder.build_unconditional_branch(&loop_body);
 
                 builder.position_at_end(&loop_next);
-                let phis = if let Ok(wasmer_ty) = blocktype_to_type(ty) {
-                    let llvm_ty = type_to_llvm(intrinsics, wasmer_ty);
-                    [llvm_ty]
-                        .iter()
-                        .map(|&ty| builder.build_phi(ty, &state.var_name()))
-                        .collect()
-                } else {
-                    SmallVec::new()
-                };
+                let phis = results
+                    .iter()
+                    .map(|&ty| builder.build_phi(type_to_llvm(intrinsics, ty), &state.var_name()))
+                    .collect();
 
                 builder.position_at_end(&loop_body);
 
+                // Branches back to the loop feed its parameters through phis.
+                let loop_body_phis: SmallVec<[PhiValue; 1]> = params
+                    .iter()
+                    .zip(param_values.iter())
+                    .map(|(&ty, value)| {
+                        let phi =
+                            builder.build_phi(type_to_llvm(intrinsics, ty), &state.var_name());
+                        phi.add_incoming(&[(value, &current_block)]);
+                        phi
+                    })
+                    .collect();
+                for phi in loop_body_phis.iter() {
+                    state.push1(phi.as_basic_value());
+                }
+
                 if self.track_state {
                     if let Some(offset) = opcode_offset {
                         let mut stackmaps = self.stackmaps.borrow_mut();
@@ -1270,7 +1385,7 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                     }
                 }
 
-                state.push_loop(loop_body, loop_next, phis);
+                state.push_loop(loop_body, loop_next, phis, loop_body_phis);
             }
             Operator::Br { relative_depth } => {
                 let frame = state.frame_at_depth(relative_depth)?;
@@ -1280,11 +1395,7 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                     offset: -1isize as usize,
                 })?;
 
-                let value_len = if frame.is_loop() {
-                    0
-                } else {
-                    frame.phis().len()
-                };
+                let value_len = frame.br_phis().len();
 
                 let values = state.peekn_extra(value_len)?;
                 let values = values.iter().map(|(v, info)| {
@@ -1294,7 +1405,7 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                 // For each result of the block we're branching to,
                 // pop a value off the value stack and load it into
                 // the corresponding phi.
-                for (phi, value) in frame.phis().iter().zip(values) {
+                for (phi, value) in frame.br_phis().iter().zip(values) {
                     phi.add_incoming(&[(&value, &current_block)]);
                 }
 
@@ -1312,18 +1423,14 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                     offset: -1isize as usize,
                 })?;
 
-                let value_len = if frame.is_loop() {
-                    0
-                } else {
-                    frame.phis().len()
-                };
+                let value_len = frame.br_phis().len();
 
                 let param_stack = state.peekn_extra(value_len)?;
                 let param_stack = param_stack.iter().map(|(v, info)| {
                     apply_pending_canonicalization(builder, intrinsics, *v, *info)
                 });
 
-                for (phi, value) in frame.phis().iter().zip(param_stack) {
+                for (phi, value) in frame.br_phis().iter().zip(param_stack) {
                     phi.add_incoming(&[(&value, &current_block)]);
                 }
 
@@ -1350,14 +1457,10 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
 
                 let default_frame = state.frame_at_depth(default_depth)?;
 
-                let args = if default_frame.is_loop() {
-                    Vec::new()
-                } else {
-                    let res_len = default_frame.phis().len();
-                    state.peekn(res_len)?
-                };
+                let res_len = default_frame.br_phis().len();
+                let args = state.peekn(res_len)?;
 
-                for (phi, value) in default_frame.phis().iter().zip(args.iter()) {
+                for (phi, value) in default_frame.br_phis().iter().zip(args.iter()) {
                     phi.add_incoming(&[(value, &current_block)]);
                 }
 
@@ -1374,7 +1477,7 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                         let case_index_literal =
                             context.i32_type().const_int(case_index as u64, false);
 
-                        for (phi, value) in frame.phis().iter().zip(args.iter()) {
+                        for (phi, value) in frame.br_phis().iter().zip(args.iter()) {
                             phi.add_incoming(&[(value, &current_block)]);
                         }
 
@@ -1393,6 +1496,7 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                     message: "not currently in a block",
                     offset: -1isize as usize,
                 })?;
+                let (params, results) = blocktype_to_types(info, ty)?;
                 let if_then_block = context.append_basic_block(function, "if_then");
                 let if_else_block = context.append_basic_block(function, "if_else");
                 let end_block = context.append_basic_block(function, "if_end");
@@ -1400,21 +1504,20 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                 let end_phis = {
                     builder.position_at_end(&end_block);
 
-                    let phis = if let Ok(wasmer_ty) = blocktype_to_type(ty) {
-                        let llvm_ty = type_to_llvm(intrinsics, wasmer_ty);
-                        [llvm_ty]
-                            .iter()
-                            .map(|&ty| builder.build_phi(ty, &state.var_name()))
-                            .collect()
-                    } else {
-                        SmallVec::new()
-                    };
+                    let phis = results
+                        .iter()
+                        .map(|&ty| {
+                            builder.build_phi(type_to_llvm(intrinsics, ty), &state.var_name())
+                        })
+                        .collect();
 
                     builder.position_at_end(&current_block);
                     phis
                 };
 
                 let cond = state.pop1()?;
+                // The parameters are used again by the else branch.
+                let else_params = state.peekn_extra(params.len())?.iter().cloned().collect();
 
                 let cond_value = builder.build_int_compare(
                     IntPredicate::NE,
@@ -1425,7 +1528,13 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
 
                 builder.build_conditional_branch(cond_value, &if_then_block, &if_else_block);
                 builder.position_at_end(&if_then_block);
-                state.push_if(if_then_block, if_else_block, end_block, end_phis);
+                state.push_if(
+                    if_then_block,
+                    if_else_block,
+                    end_block,
+                    end_phis,
+                    else_params,
+                );
             }
             Operator::Else => {
                 if state.reachable {
@@ -1445,20 +1554,25 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                     builder.build_unconditional_branch(frame.code_after());
                 }
 
-                let (if_else_block, if_else_state) = if let ControlFrame::IfElse {
+                let (stack_size_snapshot, else_params) = if let ControlFrame::IfElse {
                     if_else,
                     if_else_state,
+                    stack_size_snapshot,
+                    else_params,
                     ..
                 } = state.frame_at_depth_mut(0)?
                 {
-                    (if_else, if_else_state)
+                    *if_else_state = IfElseState::Else;
+                    builder.position_at_end(if_else);
+                    (*stack_size_snapshot, else_params.clone())
                 } else {
                     unreachable!()
                 };
 
-                *if_else_state = IfElseState::Else;
-
-                builder.position_at_end(if_else_block);
+                state.stack.truncate(stack_size_snapshot);
+                for (value, info) in else_params {
+                    state.push1_extra(value, info);
+                }
                 state.reachable = true;
             }
 
@@ -1484,11 +1598,19 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                     if_else,
                     next,
                     if_else_state,
+                    phis,
+                    else_params,
                     ..
                 } = &frame
                 {
                     if let IfElseState::If = if_else_state {
+                        // Without an else branch, the parameters are the results.
                         builder.position_at_end(if_else);
+                        for (phi, &(value, info)) in phis.iter().zip(else_params.iter()) {
+                            let value =
+                                apply_pending_canonicalization(builder, intrinsics, value, info);
+                            phi.add_incoming(&[(&value, if_else)]);
+                        }
                         builder.build_unconditional_branch(next);
                     }
                 }
@@ -1773,6 +1895,44 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                 tbaa_label(&self.module, intrinsics, "local", store, Some(local_index));
             }
 
+            Operator::GlobalGet { global_index }
+                if info
+                    .global_desc(GlobalIndex::new(global_index as usize))
+                    .ty
+                    .is_reference() =>
+            {
+                let global_index_const = intrinsics
+                    .i32_ty
+                    .const_int(global_index as u64, false)
+                    .as_basic_value_enum();
+                let res = builder
+                    .build_call(
+                        intrinsics.global_get_ref,
+                        &[ctx.basic(), global_index_const],
+                        &state.var_name(),
+                    )
+                    .try_as_basic_value()
+                    .left()
+                    .unwrap();
+                state.push1(res);
+            }
+            Operator::GlobalSet { global_index }
+                if info
+                    .global_desc(GlobalIndex::new(global_index as usize))
+                    .ty
+                    .is_reference() =>
+            {
+                let global_index_const = intrinsics
+                    .i32_ty
+                    .const_int(global_index as u64, false)
+                    .as_basic_value_enum();
+                let value = state.pop1()?;
+                builder.build_call(
+                    intrinsics.global_set_ref,
+                    &[ctx.basic(), global_index_const, value],
+                    &state.var_name(),
+                );
+            }
             Operator::GlobalGet { global_index } => {
                 let index = GlobalIndex::new(global_index as usize);
                 let global_cache = ctx.global_cache(index, intrinsics, self.module.clone());
@@ -1865,7 +2025,7 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                 let llvm_sig = signatures[sigindex];
                 let func_sig = &info.signatures[sigindex];
 
-                let (params, func_ptr) = match func_index.local_or_import(info) {
+                let (mut params, func_ptr) = match func_index.local_or_import(info) {
                     LocalOrImport::Local(_) => {
                         let params: Vec<_> = std::iter::once(ctx.basic())
                             .chain(
@@ -1945,6 +2105,14 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                 };
 
                 state.popn(func_sig.params().len())?;
+
+                let return_area = if func_sig.has_return_area() {
+                    Some(build_return_area(context, function, intrinsics, func_sig))
+                } else {
+                    None
+                };
+                params.extend(return_area.map(|ptr| ptr.as_basic_value_enum()));
+
                 if self.track_state {
                     if let Some(offset) = opcode_offset {
                         let mut stackmaps = self.stackmaps.borrow_mut();
@@ -1977,20 +2145,10 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                     }
                 }
 
-                if let Some(basic_value) = call_site.try_as_basic_value().left() {
-                    match func_sig.returns().len() {
-                        1 => state.push1(basic_value),
-                        count @ _ => {
-                            // This is a multi-value return.
-                            let struct_value = basic_value.into_struct_value();
-                            for i in 0..(count as u32) {
-                                let value = builder
-                                    .build_extract_value(struct_value, i, &state.var_name())
-                                    .unwrap();
-                                state.push1(value);
-                            }
-                        }
-                    }
+                if let Some(return_area) = return_area {
+                    load_return_area(builder, intrinsics, state, return_area, func_sig.returns());
+                } else if let Some(basic_value) = call_site.try_as_basic_value().left() {
+                    state.push1(basic_value);
                 }
             }
             Operator::CallIndirect { index, table_index } => {
@@ -2134,6 +2292,17 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
 
                 let pushed_args = state.popn_save_extra(wasmer_fn_sig.params().len())?;
 
+                let return_area = if wasmer_fn_sig.has_return_area() {
+                    Some(build_return_area(
+                        context,
+                        function,
+                        intrinsics,
+                        wasmer_fn_sig,
+                    ))
+                } else {
+                    None
+                };
+
                 let args: Vec<_> = std::iter::once(ctx_ptr)
                     .chain(pushed_args.into_iter().enumerate().map(|(i, (v, info))| {
                         match wasmer_fn_sig.params()[i] {
@@ -2153,6 +2322,7 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                             _ => v,
                         }
                     }))
+                    .chain(return_area.map(|ptr| ptr.as_basic_value_enum()))
                     .collect();
 
                 let typed_func_ptr = builder.build_pointer_cast(
@@ -2207,11 +2377,8 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                             _ => value,
                         });
                     }
-                    _ => {
-                        return Err(CodegenError {
-                            message: "Operator::CallIndirect multi-value returns unimplemented"
-                                .to_string(),
-                        });
+                    returns => {
+                        load_return_area(builder, intrinsics, state, return_area.unwrap(), returns);
                     }
                 }
             }
@@ -6041,6 +6208,62 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                 // it would lead to data races that weren't present in the
                 // original source language.
             }
+            Operator::I32AtomicWait { ref memarg } => {
+                let memory_index_const = intrinsics.i32_zero.as_basic_value_enum();
+                let offset_const = intrinsics
+                    .i32_ty
+                    .const_int(memarg.offset as u64, false)
+                    .as_basic_value_enum();
+                let ((addr, _), (expected, _), (timeout, _)) = state.pop3_extra()?;
+                let result = builder.build_call(
+                    intrinsics.memory_atomic_wait32,
+                    &[
+                        ctx.basic(),
+                        memory_index_const,
+                        offset_const,
+                        addr,
+                        expected,
+                        timeout,
+                    ],
+                    &state.var_name(),
+                );
+                state.push1(result.try_as_basic_value().left().unwrap());
+            }
+            Operator::I64AtomicWait { ref memarg } => {
+                let memory_index_const = intrinsics.i32_zero.as_basic_value_enum();
+                let offset_const = intrinsics
+                    .i32_ty
+                    .const_int(memarg.offset as u64, false)
+                    .as_basic_value_enum();
+                let ((addr, _), (expected, _), (timeout, _)) = state.pop3_extra()?;
+                let result = builder.build_call(
+                    intrinsics.memory_atomic_wait64,
+                    &[
+                        ctx.basic(),
+                        memory_index_const,
+                        offset_const,
+                        addr,
+                        expected,
+                        timeout,
+                    ],
+                    &state.var_name(),
+                );
+                state.push1(result.try_as_basic_value().left().unwrap());
+            }
+            Operator::AtomicNotify { ref memarg } => {
+                let memory_index_const = intrinsics.i32_zero.as_basic_value_enum();
+                let offset_const = intrinsics
+                    .i32_ty
+                    .const_int(memarg.offset as u64, false)
+                    .as_basic_value_enum();
+                let ((addr, _), (count, _)) = state.pop2_extra()?;
+                let result = builder.build_call(
+                    intrinsics.memory_atomic_notify,
+                    &[ctx.basic(), memory_index_const, offset_const, addr, count],
+                    &state.var_name(),
+                );
+                state.push1(result.try_as_basic_value().left().unwrap());
+            }
             Operator::I32AtomicLoad { ref memarg } => {
                 let effective_address = resolve_memory_ptr(
                     builder,
@@ -8560,6 +8783,181 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                 );
                 state.push1(result.try_as_basic_value().left().unwrap());
             }
+            Operator::MemoryCopy => {
+                let func_value = match MemoryIndex::new(0).local_or_import(info) {
+                    LocalOrImport::Local(_) => intrinsics.memory_copy_local,
+                    LocalOrImport::Import(_) => intrinsics.memory_copy_import,
+                };
+
+                let memory_index_const = intrinsics.i32_zero.as_basic_value_enum();
+                let ((dst, _), (src, _), (len, _)) = state.pop3_extra()?;
+                builder.build_call(
+                    func_value,
+                    &[ctx.basic(), memory_index_const, dst, src, len],
+                    &state.var_name(),
+                );
+            }
+            Operator::MemoryFill => {
+                let func_value = match MemoryIndex::new(0).local_or_import(info) {
+                    LocalOrImport::Local(_) => intrinsics.memory_fill_local,
+                    LocalOrImport::Import(_) => intrinsics.memory_fill_import,
+                };
+
+                let memory_index_const = intrinsics.i32_zero.as_basic_value_enum();
+                let ((dst, _), (value, _), (len, _)) = state.pop3_extra()?;
+                builder.build_call(
+                    func_value,
+                    &[ctx.basic(), memory_index_const, dst, value, len],
+                    &state.var_name(),
+                );
+            }
+            Operator::MemoryInit { segment } => {
+                let memory_index_const = intrinsics.i32_zero.as_basic_value_enum();
+                let segment_const = intrinsics
+                    .i32_ty
+                    .const_int(segment as u64, false)
+                    .as_basic_value_enum();
+                let ((dst, _), (src, _), (len, _)) = state.pop3_extra()?;
+                builder.build_call(
+                    intrinsics.memory_init,
+                    &[
+                        ctx.basic(),
+                        memory_index_const,
+                        segment_const,
+                        dst,
+                        src,
+                        len,
+                    ],
+                    &state.var_name(),
+                );
+            }
+            Operator::DataDrop { segment } => {
+                let segment_const = intrinsics
+                    .i32_ty
+                    .const_int(segment as u64, false)
+                    .as_basic_value_enum();
+                builder.build_call(
+                    intrinsics.data_drop,
+                    &[ctx.basic(), segment_const],
+                    &state.var_name(),
+                );
+            }
+            Operator::TableCopy => {
+                let table_index_const = intrinsics.i32_zero.as_basic_value_enum();
+                let ((dst, _), (src, _), (len, _)) = state.pop3_extra()?;
+                builder.build_call(
+                    intrinsics.table_copy,
+                    &[
+                        ctx.basic(),
+                        table_index_const,
+                        table_index_const,
+                        dst,
+                        src,
+                        len,
+                    ],
+                    &state.var_name(),
+                );
+            }
+            Operator::TableInit { segment } => {
+                let table_index_const = intrinsics.i32_zero.as_basic_value_enum();
+                let segment_const = intrinsics
+                    .i32_ty
+                    .const_int(segment as u64, false)
+                    .as_basic_value_enum();
+                let ((dst, _), (src, _), (len, _)) = state.pop3_extra()?;
+                builder.build_call(
+                    intrinsics.table_init,
+                    &[ctx.basic(), table_index_const, segment_const, dst, src, len],
+                    &state.var_name(),
+                );
+            }
+            Operator::ElemDrop { segment } => {
+                let segment_const = intrinsics
+                    .i32_ty
+                    .const_int(segment as u64, false)
+                    .as_basic_value_enum();
+                builder.build_call(
+                    intrinsics.elem_drop,
+                    &[ctx.basic(), segment_const],
+                    &state.var_name(),
+                );
+            }
+            Operator::TableGet { table } => {
+                let table_index_const = intrinsics
+                    .i32_ty
+                    .const_int(table as u64, false)
+                    .as_basic_value_enum();
+                let index = state.pop1()?;
+                let res = builder
+                    .build_call(
+                        intrinsics.table_get,
+                        &[ctx.basic(), table_index_const, index],
+                        &state.var_name(),
+                    )
+                    .try_as_basic_value()
+                    .left()
+                    .unwrap();
+                state.push1(res);
+            }
+            Operator::TableSet { table } => {
+                let table_index_const = intrinsics
+                    .i32_ty
+                    .const_int(table as u64, false)
+                    .as_basic_value_enum();
+                let (index, value) = state.pop2()?;
+                builder.build_call(
+                    intrinsics.table_set,
+                    &[ctx.basic(), table_index_const, index, value],
+                    &state.var_name(),
+                );
+            }
+            Operator::TableSize { table } => {
+                let table_index_const = intrinsics
+                    .i32_ty
+                    .const_int(table as u64, false)
+                    .as_basic_value_enum();
+                let res = builder
+                    .build_call(
+                        intrinsics.table_size,
+                        &[ctx.basic(), table_index_const],
+                        &state.var_name(),
+                    )
+                    .try_as_basic_value()
+                    .left()
+                    .unwrap();
+                state.push1(res);
+            }
+            Operator::TableGrow { table } => {
+                let table_index_const = intrinsics
+                    .i32_ty
+                    .const_int(table as u64, false)
+                    .as_basic_value_enum();
+                let (init, delta) = state.pop2()?;
+                let res = builder
+                    .build_call(
+                        intrinsics.table_grow,
+                        &[ctx.basic(), table_index_const, init, delta],
+                        &state.var_name(),
+                    )
+                    .try_as_basic_value()
+                    .left()
+                    .unwrap();
+                state.push1(res);
+            }
+            Operator::RefNull => {
+                state.push1(intrinsics.i64_zero.as_basic_value_enum());
+            }
+            Operator::RefIsNull => {
+                let input = state.pop1()?.into_int_value();
+                let cond = builder.build_int_compare(
+                    IntPredicate::EQ,
+                    input,
+                    intrinsics.i64_zero,
+                    &state.var_name(),
+                );
+                let res = builder.build_int_z_extend(cond, intrinsics.i32_ty, &state.var_name());
+                state.push1(res);
+            }
             _ => {
                 return Err(CodegenError {
                     message: format!("Operator {:?} unimplemented", op),
@@ -8592,10 +8990,22 @@ impl<'ctx> FunctionCodeGenerator<CodegenError> for LLVMFunctionCodeGenerator<'ct
                     "return",
                 )));
             }
-            _ => {
-                return Err(CodegenError {
-                    message: "multi-value returns not yet implemented".to_string(),
-                });
+            results => {
+                let builder = self.builder.as_ref().unwrap();
+                let intrinsics = self.intrinsics.as_ref().unwrap();
+                let return_area = self.function.get_last_param().unwrap().into_pointer_value();
+                let returns = self.func_sig.returns();
+                for (index, &(value, info)) in results.iter().enumerate() {
+                    let value = apply_pending_canonicalization(builder, intrinsics, value, info);
+                    let ptr = return_area_slot(builder, intrinsics, return_area, returns, index);
+                    let value = builder.build_bitcast(
+                        value,
+                        type_to_llvm(intrinsics, returns[index]),
+                        "return",
+                    );
+                    builder.build_store(ptr, value).set_alignment(8).unwrap();
+                }
+                builder.build_return(None);
             }
         }
         Ok(())
@@ -8725,7 +9135,20 @@ impl<'ctx> ModuleCodeGenerator<LLVMFunctionCodeGenerator<'ctx>, LLVMBackend, Cod
         Backend::LLVM
     }
 
-    fn check_precondition(&mut self, _module_info: &ModuleInfo) -> Result<(), CodegenError> {
+    fn check_precondition(&mut self, module_info: &ModuleInfo) -> Result<(), CodegenError> {
+        // Declared here rather than in `feed_function_signatures`, because their names
+        // come from the module info. The `name` section follows the code section, so they
+        // are named again in `finalize`.
+        for (index, sig_id) in &**self.function_signatures.as_ref().unwrap() {
+            if index.index() >= self.func_import_count {
+                let function = self.module.borrow_mut().add_function(
+                    &func_symbol(module_info, index),
+                    self.signatures[*sig_id],
+                    Some(Linkage::External),
+                );
+                self.llvm_functions.borrow_mut().insert(index, function);
+            }
+        }
         Ok(())
     }
 
@@ -8769,7 +9192,7 @@ impl<'ctx> ModuleCodeGenerator<LLVMFunctionCodeGenerator<'ctx>, LLVMBackend, Cod
             .map(|ty| builder.build_phi(ty, &state.var_name()))
             .collect();
 
-        state.push_block(return_block, phis);
+        state.push_block(return_block, phis, 0);
         builder.position_at_end(&entry_block);
 
         let mut locals = Vec::new();
@@ -8777,6 +9200,7 @@ impl<'ctx> ModuleCodeGenerator<LLVMFunctionCodeGenerator<'ctx>, LLVMBackend, Cod
             function
                 .get_param_iter()
                 .skip(1)
+                .take(func_sig.params().len())
                 .enumerate()
                 .map(|(index, param)| {
                     let real_ty = func_sig.params()[index];
@@ -8827,6 +9251,7 @@ impl<'ctx> ModuleCodeGenerator<LLVMFunctionCodeGenerator<'ctx>, LLVMBackend, Cod
             stackmaps: self.stackmaps.clone(),
             index: local_func_index,
             opcode_offset: 0,
+            binary_offset: None,
             track_state: self.track_state,
             module: (*self.module).clone(),
         };
@@ -8866,6 +9291,14 @@ impl<'ctx> ModuleCodeGenerator<LLVMFunctionCodeGenerator<'ctx>, LLVMBackend, Cod
             message: format!("trampolines generation error: {:?}", e),
         })?;
 
+        for (&index, function) in self.llvm_functions.borrow().iter() {
+            // Renaming the pointer to a function renames the function.
+            function
+                .as_global_value()
+                .as_pointer_value()
+                .set_name(&func_symbol(module_info, index));
+        }
+
         if let Some(ref mut callbacks) = self.llvm_callbacks {
             callbacks
                 .borrow_mut()
@@ -8941,14 +9374,7 @@ impl<'ctx> ModuleCodeGenerator<LLVMFunctionCodeGenerator<'ctx>, LLVMBackend, Cod
     fn feed_signatures(&mut self, signatures: Map<SigIndex, FuncSig>) -> Result<(), CodegenError> {
         self.signatures = signatures
             .iter()
-            .map(|(_, sig)| {
-                func_sig_to_llvm(
-                    self.context.as_ref().unwrap(),
-                    self.intrinsics.as_ref().unwrap(),
-                    sig,
-                    type_to_llvm,
-                )
-            })
+            .map(|(_, sig)| func_sig_to_llvm(self.intrinsics.as_ref().unwrap(), sig, type_to_llvm))
             .collect();
         self.signatures_raw = signatures.clone();
         Ok(())
@@ -8958,16 +9384,6 @@ impl<'ctx> ModuleCodeGenerator<LLVMFunctionCodeGenerator<'ctx>, LLVMBackend, Cod
         &mut self,
         assoc: Map<FuncIndex, SigIndex>,
     ) -> Result<(), CodegenError> {
-        for (index, sig_id) in &assoc {
-            if index.index() >= self.func_import_count {
-                let function = self.module.borrow_mut().add_function(
-                    &format!("fn{}", index.index()),
-                    self.signatures[*sig_id],
-                    Some(Linkage::External),
-                );
-                self.llvm_functions.borrow_mut().insert(index, function);
-            }
-        }
         self.function_signatures = Some(Arc::new(assoc));
         Ok(())
     }
@@ -31,6 +31,7 @@ fn type_to_llvm_ptr<'ctx>(intrinsics: &Intrinsics<'ctx>, ty: Type) -> PointerTyp
         Type::F32 => intrinsics.f32_ptr_ty,
         Type::F64 => intrinsics.f64_ptr_ty,
         Type::V128 => intrinsics.i128_ptr_ty,
+        Type::AnyRef | Type::FuncRef => intrinsics.i64_ptr_ty,
     }
 }
 
@@ -126,6 +127,8 @@ pub struct Intrinsics<'ctx> {
     pub trap_memory_oob: BasicValueEnum<'ctx>,
     pub trap_illegal_arithmetic: BasicValueEnum<'ctx>,
     pub trap_misaligned_atomic: BasicValueEnum<'ctx>,
+    pub trap_integer_division_by_zero: BasicValueEnum<'ctx>,
+    pub trap_bad_conversion_to_integer: BasicValueEnum<'ctx>,
 
     // VM intrinsics.
     pub memory_grow_dynamic_local: FunctionValue<'ctx>,
@@ -142,6 +145,25 @@ pub struct Intrinsics<'ctx> {
     pub memory_size_static_import: FunctionValue<'ctx>,
     pub memory_size_shared_import: FunctionValue<'ctx>,
 
+    pub memory_copy_local: FunctionValue<'ctx>,
+    pub memory_copy_import: FunctionValue<'ctx>,
+    pub memory_fill_local: FunctionValue<'ctx>,
+    pub memory_fill_import: FunctionValue<'ctx>,
+    pub memory_init: FunctionValue<'ctx>,
+    pub data_drop: FunctionValue<'ctx>,
+    pub table_copy: FunctionValue<'ctx>,
+    pub table_init: FunctionValue<'ctx>,
+    pub elem_drop: FunctionValue<'ctx>,
+    pub table_get: FunctionValue<'ctx>,
+    pub table_set: FunctionValue<'ctx>,
+    pub table_size: FunctionValue<'ctx>,
+    pub table_grow: FunctionValue<'ctx>,
+    pub memory_atomic_wait32: FunctionValue<'ctx>,
+    pub memory_atomic_wait64: FunctionValue<'ctx>,
+    pub memory_atomic_notify: FunctionValue<'ctx>,
+    pub global_get_ref: FunctionValue<'ctx>,
+    pub global_set_ref: FunctionValue<'ctx>,
+
     pub throw_trap: FunctionValue<'ctx>,
     pub throw_breakpoint: FunctionValue<'ctx>,
 
@@ -220,6 +242,7 @@ impl<'ctx> Intrinsics<'ctx> {
         let memory_bound_ty = i8_ty;
         let internals_ty = i64_ty;
         let interrupt_signal_mem_ty = i8_ty;
+        let counters_ty = i64_ty;
         let local_function_ty = i8_ptr_ty;
 
         let anyfunc_ty = context.struct_type(
@@ -281,6 +304,9 @@ impl<'ctx> Intrinsics<'ctx> {
                 interrupt_signal_mem_ty
                     .ptr_type(AddressSpace::Generic)
                     .as_basic_type_enum(),
+                counters_ty
+                    .ptr_type(AddressSpace::Generic)
+                    .as_basic_type_enum(),
                 local_function_ty
                     .ptr_type(AddressSpace::Generic)
                     .as_basic_type_enum(),
@@ -311,6 +337,89 @@ impl<'ctx> Intrinsics<'ctx> {
         );
         let ret_i32_take_ctx_i32 =
             i32_ty.fn_type(&[ctx_ptr_ty.as_basic_type_enum(), i32_ty_basic], false);
+        let void_take_ctx_i32 =
+            void_ty.fn_type(&[ctx_ptr_ty.as_basic_type_enum(), i32_ty_basic], false);
+        let ret_i64_take_ctx_i32 =
+            i64_ty.fn_type(&[ctx_ptr_ty.as_basic_type_enum(), i32_ty_basic], false);
+        let void_take_ctx_i32_i64 = void_ty.fn_type(
+            &[ctx_ptr_ty.as_basic_type_enum(), i32_ty_basic, i64_ty_basic],
+            false,
+        );
+        let ret_i64_take_ctx_i32_i32 = i64_ty.fn_type(
+            &[ctx_ptr_ty.as_basic_type_enum(), i32_ty_basic, i32_ty_basic],
+            false,
+        );
+        let void_take_ctx_i32_i32_i64 = void_ty.fn_type(
+            &[
+                ctx_ptr_ty.as_basic_type_enum(),
+                i32_ty_basic,
+                i32_ty_basic,
+                i64_ty_basic,
+            ],
+            false,
+        );
+        let ret_i32_take_ctx_i32_i64_i32 = i32_ty.fn_type(
+            &[
+                ctx_ptr_ty.as_basic_type_enum(),
+                i32_ty_basic,
+                i64_ty_basic,
+                i32_ty_basic,
+            ],
+            false,
+        );
+        let void_take_ctx_i32_i32_i32_i32 = void_ty.fn_type(
+            &[
+                ctx_ptr_ty.as_basic_type_enum(),
+                i32_ty_basic,
+                i32_ty_basic,
+                i32_ty_basic,
+                i32_ty_basic,
+            ],
+            false,
+        );
+        let void_take_ctx_i32_i32_i32_i32_i32 = void_ty.fn_type(
+            &[
+                ctx_ptr_ty.as_basic_type_enum(),
+                i32_ty_basic,
+                i32_ty_basic,
+                i32_ty_basic,
+                i32_ty_basic,
+                i32_ty_basic,
+            ],
+            false,
+        );
+        let ret_i32_take_ctx_i32_i32_i32_i32_i64 = i32_ty.fn_type(
+            &[
+                ctx_ptr_ty.as_basic_type_enum(),
+                i32_ty_basic,
+                i32_ty_basic,
+                i32_ty_basic,
+                i32_ty_basic,
+                i64_ty_basic,
+            ],
+            false,
+        );
+        let ret_i32_take_ctx_i32_i32_i32_i64_i64 = i32_ty.fn_type(
+            &[
+                ctx_ptr_ty.as_basic_type_enum(),
+                i32_ty_basic,
+                i32_ty_basic,
+                i32_ty_basic,
+                i64_ty_basic,
+                i64_ty_basic,
+            ],
+            false,
+        );
+        let ret_i32_take_ctx_i32_i32_i32_i32 = i32_ty.fn_type(
+            &[
+                ctx_ptr_ty.as_basic_type_enum(),
+                i32_ty_basic,
+                i32_ty_basic,
+                i32_ty_basic,
+                i32_ty_basic,
+            ],
+            false,
+        );
 
         let ret_i1_take_i1_i1 = i1_ty.fn_type(&[i1_ty_basic, i1_ty_basic], false);
         let intrinsics = Self {
@@ -437,6 +546,8 @@ impl<'ctx> Intrinsics<'ctx> {
             trap_memory_oob: i32_ty.const_int(2, false).as_basic_value_enum(),
             trap_illegal_arithmetic: i32_ty.const_int(4, false).as_basic_value_enum(),
             trap_misaligned_atomic: i32_ty.const_int(5, false).as_basic_value_enum(),
+            trap_integer_division_by_zero: i32_ty.const_int(7, false).as_basic_value_enum(),
+            trap_bad_conversion_to_integer: i32_ty.const_int(9, false).as_basic_value_enum(),
 
             // VM intrinsics.
             memory_grow_dynamic_local: module.add_function(
@@ -500,6 +611,65 @@ impl<'ctx> Intrinsics<'ctx> {
                 ret_i32_take_ctx_i32,
                 None,
             ),
+
+            memory_copy_local: module.add_function(
+                "vm.memory.copy.local",
+                void_take_ctx_i32_i32_i32_i32,
+                None,
+            ),
+            memory_copy_import: module.add_function(
+                "vm.memory.copy.import",
+                void_take_ctx_i32_i32_i32_i32,
+                None,
+            ),
+            memory_fill_local: module.add_function(
+                "vm.memory.fill.local",
+                void_take_ctx_i32_i32_i32_i32,
+                None,
+            ),
+            memory_fill_import: module.add_function(
+                "vm.memory.fill.import",
+                void_take_ctx_i32_i32_i32_i32,
+                None,
+            ),
+            memory_init: module.add_function(
+                "vm.memory.init",
+                void_take_ctx_i32_i32_i32_i32_i32,
+                None,
+            ),
+            data_drop: module.add_function("vm.data.drop", void_take_ctx_i32, None),
+            table_copy: module.add_function(
+                "vm.table.copy",
+                void_take_ctx_i32_i32_i32_i32_i32,
+                None,
+            ),
+            table_init: module.add_function(
+                "vm.table.init",
+                void_take_ctx_i32_i32_i32_i32_i32,
+                None,
+            ),
+            elem_drop: module.add_function("vm.elem.drop", void_take_ctx_i32, None),
+            table_get: module.add_function("vm.table.get", ret_i64_take_ctx_i32_i32, None),
+            table_set: module.add_function("vm.table.set", void_take_ctx_i32_i32_i64, None),
+            table_size: module.add_function("vm.table.size", ret_i32_take_ctx_i32, None),
+            table_grow: module.add_function("vm.table.grow", ret_i32_take_ctx_i32_i64_i32, None),
+            memory_atomic_wait32: module.add_function(
+                "vm.memory.atomic.wait32",
+                ret_i32_take_ctx_i32_i32_i32_i32_i64,
+                None,
+            ),
+            memory_atomic_wait64: module.add_function(
+                "vm.memory.atomic.wait64",
+                ret_i32_take_ctx_i32_i32_i32_i64_i64,
+                None,
+            ),
+            memory_atomic_notify: module.add_function(
+                "vm.memory.atomic.notify",
+                ret_i32_take_ctx_i32_i32_i32_i32,
+                None,
+            ),
+            global_get_ref: module.add_function("vm.global.get.ref", ret_i64_take_ctx_i32, None),
+            global_set_ref: module.add_function("vm.global.set.ref", void_take_ctx_i32_i64, None),
             throw_trap: module.add_function(
                 "vm.exception.trap",
                 void_ty.fn_type(&[i32_ty_basic], false),
@@ -1142,6 +1312,39 @@ impl<'a, 'ctx> CtxType<'a, 'ctx> {
             )
         }
     }
+
+    pub fn counter(
+        &mut self,
+        index: usize,
+        intrinsics: &Intrinsics<'ctx>,
+        module: Rc<RefCell<Module<'ctx>>>,
+        builder: &Builder<'ctx>,
+    ) -> PointerValue<'ctx> {
+        let counters_ptr_ptr = unsafe {
+            builder.build_struct_gep(
+                self.ctx_ptr_value,
+                offset_to_index(Ctx::offset_counters()),
+                "counters_ptr_ptr",
+            )
+        };
+        let counters_ptr = builder
+            .build_load(counters_ptr_ptr, "counters_ptr")
+            .into_pointer_value();
+        tbaa_label(
+            &module,
+            intrinsics,
+            "context_field_ptr_to_counters",
+            counters_ptr.as_instruction_value().unwrap(),
+            None,
+        );
+        unsafe {
+            builder.build_in_bounds_gep(
+                counters_ptr,
+                &[intrinsics.i64_ty.const_int(index as u64, false)],
+                "counter_ptr",
+            )
+        }
+    }
 }
 
 // Given an instruction that operates on memory, mark the access as not aliasing
@@ -1,16 +1,20 @@
+use wasmer_runtime_core::module::ModuleInfo;
 use wasmer_runtime_core::parse::wp_type_to_type;
-use wasmer_runtime_core::types::Type;
-use wasmparser::{BinaryReaderError, TypeOrFuncType as WpTypeOrFuncType};
+use wasmer_runtime_core::structures::TypedIndex;
+use wasmer_runtime_core::types::{SigIndex, Type};
+use wasmparser::{BinaryReaderError, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
 
-pub fn blocktype_to_type(ty: WpTypeOrFuncType) -> Result<Type, BinaryReaderError> {
+/// Returns the parameter and result types of a block.
+pub fn blocktype_to_types(
+    info: &ModuleInfo,
+    ty: WpTypeOrFuncType,
+) -> Result<(Vec<Type>, Vec<Type>), BinaryReaderError> {
     match ty {
-        WpTypeOrFuncType::Type(inner_ty) => wp_type_to_type(inner_ty),
-        _ => {
-            return Err(BinaryReaderError {
-                message:
-                    "the wasmer llvm backend does not yet support the multi-value return extension",
-                offset: -1isize as usize,
-            });
+        WpTypeOrFuncType::Type(WpType::EmptyBlockType) => Ok((vec![], vec![])),
+        WpTypeOrFuncType::Type(inner_ty) => Ok((vec![], vec![wp_type_to_type(inner_ty)?])),
+        WpTypeOrFuncType::FuncType(index) => {
+            let sig = &info.signatures[SigIndex::new(index as usize)];
+            Ok((sig.params().to_vec(), sig.returns().to_vec()))
         }
     }
 }
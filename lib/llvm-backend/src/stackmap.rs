@@ -1,6 +1,7 @@
 // https://llvm.org/docs/StackMaps.html#stackmap-section
 
 use byteorder::{LittleEndian, ReadBytesExt};
+use std::collections::BTreeMap;
 use std::io::{self, Cursor};
 use wasmer_runtime_core::vm::Ctx;
 use wasmer_runtime_core::{
@@ -12,6 +13,8 @@ use wasmer_runtime_core::{
 #[derive(Default, Debug, Clone)]
 pub struct StackmapRegistry {
     pub entries: Vec<StackmapEntry>,
+    /// The offset in the wasm binary of each opcode, by local function id and opcode offset.
+    pub binary_offsets: BTreeMap<(usize, usize), usize>,
 }
 
 #[derive(Debug, Clone)]
@@ -64,7 +67,7 @@ impl StackmapEntry {
         end: Option<(&StackmapEntry, &StkMapRecord)>,
         msm: &mut wasmer_runtime_core::state::ModuleStateMap,
     ) {
-        use std::collections::{BTreeMap, HashMap};
+        use std::collections::HashMap;
         use wasmer_runtime_core::state::{
             x64::{new_machine_state, X64Register, GPR},
             FunctionStateMap, MachineStateDiff, MachineValue, OffsetInfo, RegisterIndex,
@@ -18,6 +18,7 @@ pub enum ControlFrame<'ctx> {
         body: BasicBlock,
         next: BasicBlock,
         phis: SmallVec<[PhiValue<'ctx>; 1]>,
+        loop_body_phis: SmallVec<[PhiValue<'ctx>; 1]>,
         stack_size_snapshot: usize,
     },
     IfElse {
@@ -27,6 +28,7 @@ pub enum ControlFrame<'ctx> {
         phis: SmallVec<[PhiValue<'ctx>; 1]>,
         stack_size_snapshot: usize,
         if_else_state: IfElseState,
+        else_params: SmallVec<[(BasicValueEnum<'ctx>, ExtraInfo); 1]>,
     },
 }
 
@@ -60,6 +62,16 @@ impl<'ctx> ControlFrame<'ctx> {
         }
     }
 
+    /// The phis receiving the values carried by a branch to this frame.
+    pub fn br_phis(&self) -> &[PhiValue<'ctx>] {
+        match self {
+            ControlFrame::Loop {
+                ref loop_body_phis, ..
+            } => loop_body_phis.as_slice(),
+            _ => self.phis(),
+        }
+    }
+
     pub fn is_loop(&self) -> bool {
         match self {
             ControlFrame::Loop { .. } => true,
@@ -377,11 +389,18 @@ impl<'ctx> State<'ctx> {
         Ok(())
     }
 
-    pub fn push_block(&mut self, next: BasicBlock, phis: SmallVec<[PhiValue<'ctx>; 1]>) {
+    /// The parameters of a block, if any, are the top `num_params` values
+    /// of the stack.
+    pub fn push_block(
+        &mut self,
+        next: BasicBlock,
+        phis: SmallVec<[PhiValue<'ctx>; 1]>,
+        num_params: usize,
+    ) {
         self.control_stack.push(ControlFrame::Block {
             next,
             phis,
-            stack_size_snapshot: self.stack.len(),
+            stack_size_snapshot: self.stack.len() - num_params,
         });
     }
 
@@ -390,12 +409,14 @@ impl<'ctx> State<'ctx> {
         body: BasicBlock,
         next: BasicBlock,
         phis: SmallVec<[PhiValue<'ctx>; 1]>,
+        loop_body_phis: SmallVec<[PhiValue<'ctx>; 1]>,
     ) {
         self.control_stack.push(ControlFrame::Loop {
             body,
             next,
             phis,
-            stack_size_snapshot: self.stack.len(),
+            stack_size_snapshot: self.stack.len() - loop_body_phis.len(),
+            loop_body_phis,
         });
     }
 
@@ -405,14 +426,16 @@ impl<'ctx> State<'ctx> {
         if_else: BasicBlock,
         next: BasicBlock,
         phis: SmallVec<[PhiValue<'ctx>; 1]>,
+        else_params: SmallVec<[(BasicValueEnum<'ctx>, ExtraInfo); 1]>,
     ) {
         self.control_stack.push(ControlFrame::IfElse {
             if_then,
             if_else,
             next,
             phis,
-            stack_size_snapshot: self.stack.len(),
+            stack_size_snapshot: self.stack.len() - else_params.len(),
             if_else_state: IfElseState::If,
+            else_params,
         });
     }
 }
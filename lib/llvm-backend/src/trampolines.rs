@@ -4,7 +4,7 @@ use inkwell::{
     context::Context,
     module::{Linkage, Module},
     types::{BasicType, FunctionType},
-    values::FunctionValue,
+    values::{BasicValue, FunctionValue},
     AddressSpace,
 };
 use wasmer_runtime_core::{
@@ -74,6 +74,7 @@ fn generate_trampoline<'ctx>(
         Type::I64 => intrinsics.i64_ptr_ty,
         Type::F64 => intrinsics.f64_ptr_ty,
         Type::V128 => intrinsics.i128_ptr_ty,
+        Type::AnyRef | Type::FuncRef => intrinsics.i64_ptr_ty,
     };
 
     let mut args_vec = Vec::with_capacity(func_sig.params().len() + 1);
@@ -97,6 +98,11 @@ fn generate_trampoline<'ctx>(
         }
     }
 
+    // Functions with several results write them straight into `returns_ptr`.
+    if func_sig.has_return_area() {
+        args_vec.push(returns_ptr.as_basic_value_enum());
+    }
+
     let call_site = builder.build_call(func_ptr, &args_vec, "call");
 
     match func_sig.returns() {
@@ -111,9 +117,7 @@ fn generate_trampoline<'ctx>(
                 call_site.try_as_basic_value().left().unwrap(),
             );
         }
-        _ => {
-            return Err("trampoline function multi-value returns unimplemented".to_string());
-        }
+        _ => {}
     }
 
     builder.build_return(None);
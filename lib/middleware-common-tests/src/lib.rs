@@ -9,28 +9,47 @@ mod tests {
     use wasmer_runtime_core::state::CodeVersion;
     use wasmer_runtime_core::{
         backend::{Backend, Compiler},
-        compile_with, imports, Func,
+        compile_with,
+        error::RuntimeError,
+        func, imports,
+        wasmparser::Operator,
+        Func,
     };
 
+    fn get_compiler() -> (impl Compiler, Backend) {
+        get_compiler_with(|| metering_chain(Metering::new(unit_cost)))
+    }
+
+    fn metering_chain(metering: Metering) -> MiddlewareChain {
+        let mut chain = MiddlewareChain::new();
+        chain.push(metering);
+        chain
+    }
+
+    fn assert_out_of_points(error: RuntimeError) {
+        match error {
+            RuntimeError::ExecutionLimitExceeded => {}
+            error => panic!("expected the execution limit to be exceeded, got {}", error),
+        }
+    }
+
     #[cfg(feature = "llvm")]
-    fn get_compiler(limit: u64) -> (impl Compiler, Backend) {
+    fn get_compiler_with<F>(chain_gen: F) -> (impl Compiler, Backend)
+    where
+        F: Fn() -> MiddlewareChain + 'static,
+    {
         use wasmer_llvm_backend::ModuleCodeGenerator as LLVMMCG;
-        let c: StreamingCompiler<LLVMMCG, _, _, _, _> = StreamingCompiler::new(move || {
-            let mut chain = MiddlewareChain::new();
-            chain.push(Metering::new(limit));
-            chain
-        });
+        let c: StreamingCompiler<LLVMMCG, _, _, _, _> = StreamingCompiler::new(chain_gen);
         (c, Backend::LLVM)
     }
 
     #[cfg(feature = "singlepass")]
-    fn get_compiler(limit: u64) -> (impl Compiler, Backend) {
+    fn get_compiler_with<F>(chain_gen: F) -> (impl Compiler, Backend)
+    where
+        F: Fn() -> MiddlewareChain + 'static,
+    {
         use wasmer_singlepass_backend::ModuleCodeGenerator as SinglePassMCG;
-        let c: StreamingCompiler<SinglePassMCG, _, _, _, _> = StreamingCompiler::new(move || {
-            let mut chain = MiddlewareChain::new();
-            chain.push(Metering::new(limit));
-            chain
-        });
+        let c: StreamingCompiler<SinglePassMCG, _, _, _, _> = StreamingCompiler::new(chain_gen);
         (c, Backend::Singlepass)
     }
 
@@ -38,7 +57,10 @@ mod tests {
     compile_error!("compiler not specified, activate a compiler via features");
 
     #[cfg(feature = "clif")]
-    fn get_compiler(_limit: u64) -> (impl Compiler, Backend) {
+    fn get_compiler_with<F>(_chain_gen: F) -> (impl Compiler, Backend)
+    where
+        F: Fn() -> MiddlewareChain + 'static,
+    {
         compile_error!("cranelift does not implement metering");
         use wasmer_clif_backend::CraneliftCompiler;
         (CraneliftCompiler::new(), Backend::Cranelift)
@@ -109,12 +131,13 @@ mod tests {
 
         let limit = 100u64;
 
-        let (compiler, backend_id) = get_compiler(limit);
+        let (compiler, backend_id) = get_compiler();
         let module = compile_with(&wasm_binary, &compiler).unwrap();
 
         let import_object = imports! {};
         let mut instance = module.instantiate(&import_object).unwrap();
 
+        instance.set_limit(limit);
         set_points_used(&mut instance, 0u64);
 
         let add_to: Func<(i32, i32), i32> = instance.func("add_to").unwrap();
@@ -146,17 +169,17 @@ mod tests {
 
     #[test]
     fn test_traps_after_costly_call() {
-        use wasmer_runtime_core::error::RuntimeError;
         let wasm_binary = wat2wasm(WAT).unwrap();
 
         let limit = 100u64;
 
-        let (compiler, backend_id) = get_compiler(limit);
+        let (compiler, backend_id) = get_compiler();
         let module = compile_with(&wasm_binary, &compiler).unwrap();
 
         let import_object = imports! {};
         let mut instance = module.instantiate(&import_object).unwrap();
 
+        instance.set_limit(limit);
         set_points_used(&mut instance, 0u64);
 
         let add_to: Func<(i32, i32), i32> = instance.func("add_to").unwrap();
@@ -178,15 +201,602 @@ mod tests {
             pop_code_version().unwrap();
         }
 
-        let err = result.unwrap_err();
-        match err {
-            RuntimeError::Error { data } => {
-                assert!(data.downcast_ref::<ExecutionLimitExceededError>().is_some());
-            }
-            _ => unreachable!(),
-        }
+        assert_out_of_points(result.unwrap_err());
 
         // verify it used the correct number of points
         assert_eq!(get_points_used(&instance), 109); // Used points will be slightly more than `limit` because of the way we do gas checking.
     }
+
+    static GROW_WAT: &'static str = r#"
+        (module
+          (import "env" "host" (func $host))
+          (memory 0)
+          (func (export "grow") (param $pages i32) (result i32)
+            call $host
+            get_local $pages
+            memory.grow))
+        "#;
+
+    #[test]
+    fn test_custom_costs() {
+        let wasm_binary = wat2wasm(GROW_WAT).unwrap();
+
+        let (compiler, _) = get_compiler_with(|| {
+            let cost = |op: &Operator| match *op {
+                Operator::MemoryGrow { .. } => 10,
+                _ => 1,
+            };
+            metering_chain(
+                Metering::new(cost)
+                    .with_host_call_cost(100)
+                    .with_memory_grow_cost(20),
+            )
+        });
+        let module = compile_with(&wasm_binary, &compiler).unwrap();
+
+        let import_object = imports! {
+            "env" => {
+                "host" => func!(|| {}),
+            },
+        };
+        let mut instance = module.instantiate(&import_object).unwrap();
+        instance.set_limit(1_000);
+        set_points_used(&mut instance, 0u64);
+
+        let grow: Func<i32, i32> = instance.func("grow").unwrap();
+        assert_eq!(grow.call(3).unwrap(), 0);
+
+        // `call`, `get_local` and `end` cost 1 and `memory.grow` 10, plus 100 for calling the
+        // host function and 20 for each of the 3 pages.
+        assert_eq!(get_points_used(&instance), 1 + 100 + 1 + 10 + 1 + 3 * 20);
+    }
+
+    #[test]
+    fn test_memory_grow_is_charged_before_growing() {
+        let wasm_binary = wat2wasm(GROW_WAT).unwrap();
+
+        for &(cost_per_page, limit) in &[(20, 50), (std::u64::MAX / 2, std::u64::MAX)] {
+            let (compiler, _) = get_compiler_with(move || {
+                metering_chain(Metering::new(unit_cost).with_memory_grow_cost(cost_per_page))
+            });
+            let module = compile_with(&wasm_binary, &compiler).unwrap();
+
+            let import_object = imports! {
+                "env" => {
+                    "host" => func!(|| {}),
+                },
+            };
+            let mut instance = module.instantiate(&import_object).unwrap();
+            instance.set_limit(limit);
+            set_points_used(&mut instance, 0u64);
+
+            // 3 pages cost more than the limit, even when the cost overflows.
+            let grow: Func<i32, i32> = instance.func("grow").unwrap();
+            assert_out_of_points(grow.call(3).unwrap_err());
+            assert_eq!(instance.context().memory(0).size().0, 0);
+        }
+    }
+
+    static CHARGE_WAT: &'static str = r#"
+        (module
+          (import "env" "charge" (func $charge (param i64)))
+          (func (export "run") (param $points i64)
+            get_local $points
+            call $charge))
+        "#;
+
+    #[test]
+    fn test_host_function_charges_points() {
+        use wasmer_runtime_core::vm::Ctx;
+
+        let wasm_binary = wat2wasm(CHARGE_WAT).unwrap();
+
+        let (compiler, _) = get_compiler();
+        let module = compile_with(&wasm_binary, &compiler).unwrap();
+
+        let import_object = imports! {
+            "env" => {
+                "charge" => func!(|ctx: &mut Ctx, points: u64| ctx.add_points(points)),
+            },
+        };
+        let mut instance = module.instantiate(&import_object).unwrap();
+        // Metering fails closed: nothing runs until the limit is set.
+        assert_eq!(instance.limit(), 0);
+        let run: Func<u64> = instance.func("run").unwrap();
+        assert_out_of_points(run.call(0).unwrap_err());
+
+        instance.set_limit(100);
+        set_points_used(&mut instance, 0u64);
+
+        let run: Func<u64> = instance.func("run").unwrap();
+
+        // `get_local`, `call` and `end` cost 1 each, and the host function charges 50 points.
+        run.call(50).unwrap();
+        assert_eq!(get_points_used(&instance), 53);
+        assert_eq!(instance.remaining_points(), 47);
+
+        assert_out_of_points(run.call(50).unwrap_err());
+        assert_eq!(instance.remaining_points(), 0);
+
+        // The host function fails once 105 points are used. Refuelling lets the instance run again.
+        instance.set_limit(1_000);
+        assert!(instance.add_points(10).is_ok());
+        assert_eq!(
+            instance.add_points(std::u64::MAX),
+            Err(ExecutionLimitExceededError)
+        );
+        instance.set_points_used(105 + 10);
+        run.call(50).unwrap();
+        assert_eq!(get_points_used(&instance), 105 + 10 + 53);
+    }
+
+    static INSTRUMENTED_WAT: &'static str = r#"
+        (module
+          (import "env" "double" (func $double (param i32) (result i32)))
+          (table 1 anyfunc)
+          (elem (i32.const 0) $inner)
+          (func $inner (result i32)
+            i32.const 21
+            call $double)
+          (func (export "run") (result i32)
+            i32.const 0
+            call_indirect (result i32)))
+        "#;
+
+    #[test]
+    fn test_module_middleware() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use wasmer_runtime_core::{
+            codegen::{Event, EventSink, FunctionMiddleware, InternalEvent, ModuleMiddleware},
+            module::{ExportIndex, ModuleInfo},
+            structures::TypedIndex,
+            types::{FuncSig, GlobalDescriptor, Initializer, Type, Value},
+        };
+
+        static HITS: AtomicUsize = AtomicUsize::new(0);
+
+        // Adds an exported `calls` global and an `instrument.hit` import.
+        struct AddCounter;
+
+        impl ModuleMiddleware for AddCounter {
+            type Error = String;
+            fn transform_module_info(
+                &mut self,
+                module_info: &mut ModuleInfo,
+            ) -> Result<(), String> {
+                let desc = GlobalDescriptor {
+                    mutable: true,
+                    ty: Type::I32,
+                };
+                let calls = module_info.add_global(desc, Initializer::Const(Value::I32(0)));
+                module_info
+                    .exports
+                    .insert("calls".to_string(), ExportIndex::Global(calls));
+                module_info.add_import_function("instrument", "hit", FuncSig::new(vec![], vec![]));
+                Ok(())
+            }
+        }
+
+        // Increments `calls` and calls `instrument.hit` when entering a function.
+        struct CountCalls;
+
+        impl FunctionMiddleware for CountCalls {
+            type Error = String;
+            fn feed_event<'a, 'b: 'a>(
+                &mut self,
+                op: Event<'a, 'b>,
+                module_info: &ModuleInfo,
+                sink: &mut EventSink<'a, 'b>,
+            ) -> Result<(), String> {
+                if let Event::Internal(InternalEvent::FunctionBegin(_)) = op {
+                    let calls = match module_info.exports.get("calls") {
+                        Some(ExportIndex::Global(index)) => index.index() as u32,
+                        _ => return Err("no `calls` global".to_string()),
+                    };
+                    let hit = module_info
+                        .imported_functions
+                        .iter()
+                        .position(|(_, name)| module_info.name_table.get(name.name_index) == "hit")
+                        .ok_or_else(|| "no `hit` import".to_string())?;
+                    sink.push(Event::WasmOwned(Operator::GetGlobal {
+                        global_index: calls,
+                    }));
+                    sink.push(Event::WasmOwned(Operator::I32Const { value: 1 }));
+                    sink.push(Event::WasmOwned(Operator::I32Add));
+                    sink.push(Event::WasmOwned(Operator::SetGlobal {
+                        global_index: calls,
+                    }));
+                    sink.push(Event::WasmOwned(Operator::Call {
+                        function_index: hit as u32,
+                    }));
+                }
+                sink.push(op);
+                Ok(())
+            }
+        }
+
+        let wasm_binary = wat2wasm(INSTRUMENTED_WAT).unwrap();
+        let (compiler, _) = get_compiler_with(|| {
+            let mut chain = MiddlewareChain::new();
+            chain.push_module(AddCounter);
+            chain.push(CountCalls);
+            chain
+        });
+        let module = compile_with(&wasm_binary, &compiler).unwrap();
+
+        let import_object = imports! {
+            "env" => {
+                "double" => func!(|x: i32| x * 2),
+            },
+            "instrument" => {
+                "hit" => func!(|| {
+                    HITS.fetch_add(1, Ordering::SeqCst);
+                }),
+            },
+        };
+        let instance = module.instantiate(&import_object).unwrap();
+
+        // `run` calls `$inner`, which moved up by one index, through the table.
+        let run: Func<(), i32> = instance.func("run").unwrap();
+        assert_eq!(run.call().unwrap(), 42);
+        assert_eq!(instance.global("calls").unwrap().get(), Value::I32(2));
+        assert_eq!(HITS.load(Ordering::SeqCst), 2);
+    }
+
+    static ABS_WAT: &'static str = r#"
+        (module
+          (func $abs (export "abs") (param i32) (result i32)
+            get_local 0
+            i32.const 0
+            i32.lt_s
+            if (result i32)
+              i32.const 0
+              get_local 0
+              i32.sub
+            else
+              get_local 0
+            end))
+        "#;
+
+    #[test]
+    fn test_coverage() {
+        use wasmer_middleware_common::coverage::Coverage;
+        use wasmer_runtime_core::{structures::TypedIndex, types::FuncIndex};
+
+        let wasm_binary = wat2wasm(ABS_WAT).unwrap();
+        let coverage = Coverage::new();
+        let middlewares = coverage.clone();
+        let (compiler, _) = get_compiler_with(move || {
+            let mut chain = MiddlewareChain::new();
+            chain.push_module(middlewares.clone());
+            chain.push(middlewares.clone());
+            chain
+        });
+        let module = compile_with(&wasm_binary, &compiler).unwrap();
+        let mut instance = module.instantiate(&imports! {}).unwrap();
+
+        {
+            let abs: Func<i32, i32> = instance.func("abs").unwrap();
+            assert_eq!(abs.call(-3).unwrap(), 3);
+        }
+
+        // The function starts a block, so do both branches of the `if` and the code after it.
+        let counters = coverage.counters(&instance).unwrap();
+        let hits = counters.hits();
+        let counts: Vec<u64> = hits.iter().map(|&(_, hits)| hits).collect();
+        assert_eq!(counts, vec![1, 1, 0, 1]);
+        assert!(hits
+            .iter()
+            .all(|(block, _)| block.func_index == FuncIndex::new(0)));
+        assert!(hits.windows(2).all(|w| w[0].0.offset < w[1].0.offset));
+
+        let offsets: Vec<usize> = hits.iter().map(|(block, _)| block.offset).collect();
+        assert_eq!(
+            counters.to_json(),
+            format!(
+                "{{\"0\":{{\"{}\":1,\"{}\":1,\"{}\":0,\"{}\":1}}}}",
+                offsets[0], offsets[1], offsets[2], offsets[3]
+            )
+        );
+
+        {
+            let abs: Func<i32, i32> = instance.func("abs").unwrap();
+            assert_eq!(abs.call(5).unwrap(), 5);
+        }
+        let lcov = coverage
+            .counters(&instance)
+            .unwrap()
+            .to_lcov(&wasm_binary, "abs.wasm");
+        assert!(lcov.starts_with("TN:\nSF:abs.wasm\n"));
+        assert!(lcov.contains(&format!("FN:{},func0\nFNDA:2,func0\n", offsets[0])));
+        assert!(lcov.contains(&format!("DA:{},1\n", offsets[2])));
+        assert!(lcov.ends_with("LF:4\nLH:4\nend_of_record\n"));
+
+        // Each instance counts its own hits.
+        let other = module.instantiate(&imports! {}).unwrap();
+        let counts: Vec<u64> = coverage
+            .counters(&other)
+            .unwrap()
+            .hits()
+            .iter()
+            .map(|&(_, hits)| hits)
+            .collect();
+        assert_eq!(counts, vec![0, 0, 0, 0]);
+
+        coverage.reset(&mut instance);
+        assert!(coverage
+            .counters(&instance)
+            .unwrap()
+            .hits()
+            .iter()
+            .all(|&(_, hits)| hits == 0));
+
+        // Compiling another module keeps the map of the first one.
+        let sum_squares = wat2wasm(SUM_SQUARES_WAT).unwrap();
+        let other_module = compile_with(&sum_squares, &compiler).unwrap();
+        let other = other_module.instantiate(&imports! {}).unwrap();
+        assert!(coverage.counters(&other).is_some());
+        assert_eq!(coverage.counters(&instance).unwrap().hits().len(), 4);
+
+        let (plain_compiler, _) = get_compiler_with(MiddlewareChain::new);
+        let plain_module = compile_with(&wasm_binary, &plain_compiler).unwrap();
+        let plain = plain_module.instantiate(&imports! {}).unwrap();
+        assert!(coverage.counters(&plain).is_none());
+    }
+
+    static SUM_SQUARES_WAT: &'static str = r#"
+        (module
+          (func $square (param i32) (result i32)
+            get_local 0
+            get_local 0
+            i32.mul)
+          (func $sum_squares (export "sum_squares") (param i32) (result i32)
+            (local i32)
+            block
+              loop
+                get_local 0
+                i32.eqz
+                br_if 1
+                get_local 1
+                get_local 0
+                call $square
+                i32.add
+                set_local 1
+                get_local 0
+                i32.const 1
+                i32.sub
+                set_local 0
+                br 0
+              end
+            end
+            get_local 1))
+        "#;
+
+    #[test]
+    fn test_profiler() {
+        use wabt::Wat2Wasm;
+        use wasmer_middleware_common::profiler::{Profiler, PROFILER_NAMESPACE};
+
+        let wasm_binary = Wat2Wasm::new()
+            .write_debug_names(true)
+            .convert(SUM_SQUARES_WAT)
+            .unwrap();
+        let profiler = Profiler::new();
+        let middlewares = profiler.clone();
+        let (compiler, _) = get_compiler_with(move || {
+            let mut chain = MiddlewareChain::new();
+            chain.push_module(middlewares.clone());
+            chain.push(middlewares.clone());
+            chain
+        });
+        let module = compile_with(wasm_binary.as_ref(), &compiler).unwrap();
+
+        let profile = profiler.profile(&module).unwrap();
+        let mut import_object = imports! {};
+        import_object.register(PROFILER_NAMESPACE, profile.namespace());
+        let instance = module.instantiate(&import_object).unwrap();
+
+        let sum_squares: Func<i32, i32> = instance.func("sum_squares").unwrap();
+        assert_eq!(sum_squares.call(3).unwrap(), 14);
+
+        let functions = profile.functions();
+        assert_eq!(functions[0].name, "square");
+        assert_eq!(functions[0].calls, 3);
+        assert_eq!(functions[1].name, "sum_squares");
+        assert_eq!(functions[1].calls, 1);
+        assert!(functions[1].inclusive_time >= functions[0].inclusive_time);
+        assert!(functions
+            .iter()
+            .all(|f| f.exclusive_time <= f.inclusive_time));
+
+        let collapsed = profile.to_collapsed();
+        let stacks: Vec<&str> = collapsed
+            .lines()
+            .map(|line| line.rsplitn(2, ' ').nth(1).unwrap())
+            .collect();
+        assert_eq!(stacks, vec!["sum_squares", "sum_squares;square"]);
+
+        let pprof = profile.to_pprof();
+        assert!(pprof.windows(11).any(|name| name == b"sum_squares"));
+
+        profile.reset();
+        assert!(profile.functions().iter().all(|f| f.calls == 0));
+        assert_eq!(profile.to_collapsed(), "");
+    }
+
+    static EXITS_WAT: &'static str = r#"
+        (module
+          (func $leave_early (export "leave_early") (param i32)
+            get_local 0
+            br_if 0
+            unreachable)
+          (func $trap (export "trap")
+            unreachable)
+          (func $nop (export "nop")))
+        "#;
+
+    #[test]
+    fn test_profiler_leaves_functions_exited_early() {
+        use wabt::Wat2Wasm;
+        use wasmer_middleware_common::profiler::{Profiler, PROFILER_NAMESPACE};
+
+        let wasm_binary = Wat2Wasm::new()
+            .write_debug_names(true)
+            .convert(EXITS_WAT)
+            .unwrap();
+        let profiler = Profiler::new();
+        let middlewares = profiler.clone();
+        let (compiler, _) = get_compiler_with(move || {
+            let mut chain = MiddlewareChain::new();
+            chain.push_module(middlewares.clone());
+            chain.push(middlewares.clone());
+            chain
+        });
+        let module = compile_with(wasm_binary.as_ref(), &compiler).unwrap();
+
+        let profile = profiler.profile(&module).unwrap();
+        let mut import_object = imports! {};
+        import_object.register(PROFILER_NAMESPACE, profile.namespace());
+        let instance = module.instantiate(&import_object).unwrap();
+
+        let leave_early: Func<i32> = instance.func("leave_early").unwrap();
+        let trap: Func = instance.func("trap").unwrap();
+        let nop: Func = instance.func("nop").unwrap();
+        leave_early.call(1).unwrap();
+        nop.call().unwrap();
+        assert!(trap.call().is_err());
+        nop.call().unwrap();
+
+        // Neither `leave_early`, which branched out of its body, nor `trap` is still open
+        // when `nop` is entered.
+        let collapsed = profile.to_collapsed();
+        let stacks: Vec<&str> = collapsed
+            .lines()
+            .map(|line| line.rsplitn(2, ' ').nth(1).unwrap())
+            .collect();
+        assert_eq!(stacks, vec!["leave_early", "trap", "nop"]);
+        assert_eq!(profile.functions()[2].calls, 2);
+    }
+
+    static SPIN_WAT: &'static str = r#"
+        (module
+          (func $spin (param i32)
+            loop
+              get_local 0
+              i32.const 1
+              i32.sub
+              tee_local 0
+              br_if 0
+            end)
+          (func $run (export "run") (param i32)
+            get_local 0
+            call $spin))
+        "#;
+
+    #[cfg(feature = "singlepass")]
+    #[test]
+    fn test_sampling_profiler() {
+        use std::time::Duration;
+        use wabt::Wat2Wasm;
+        use wasmer_middleware_common::profiler::Profiler;
+
+        let wasm_binary = Wat2Wasm::new()
+            .write_debug_names(true)
+            .convert(SPIN_WAT)
+            .unwrap();
+        let profiler = Profiler::sampling();
+        let middlewares = profiler.clone();
+        let (compiler, backend_id) = get_compiler_with(move || {
+            let mut chain = MiddlewareChain::new();
+            chain.push_module(middlewares.clone());
+            chain
+        });
+        let module = compile_with(wasm_binary.as_ref(), &compiler).unwrap();
+        let instance = module.instantiate(&imports! {}).unwrap();
+
+        push_code_version(CodeVersion {
+            baseline: true,
+            msm: instance
+                .module
+                .runnable_module
+                .get_module_state_map()
+                .unwrap(),
+            base: instance.module.runnable_module.get_code().unwrap().as_ptr() as usize,
+            backend: backend_id,
+            runnable_module: instance.module.runnable_module.clone(),
+        });
+        let profile = profiler.profile(&module).unwrap();
+        let run: Func<i32> = instance.func("run").unwrap();
+        let result = profile.sample(&instance, Duration::from_millis(1), || {
+            run.call(100_000_000)
+        });
+        pop_code_version().unwrap();
+        result.unwrap().unwrap();
+
+        // Samples are taken at the header of the loop, or of either function.
+        let collapsed = profile.to_collapsed();
+        assert!(collapsed.lines().all(|line| line.starts_with("run")));
+        assert!(collapsed.lines().any(|line| line.starts_with("run;spin ")));
+        let functions = profile.functions();
+        assert!(functions[0].exclusive_time > Duration::default());
+        assert!(functions[1].inclusive_time >= functions[0].inclusive_time);
+    }
+
+    #[cfg(feature = "singlepass")]
+    #[test]
+    fn test_sampling_profiler_only_samples_its_instance() {
+        use std::time::Duration;
+        use wabt::Wat2Wasm;
+        use wasmer_middleware_common::profiler::Profiler;
+
+        let wasm_binary = Wat2Wasm::new()
+            .write_debug_names(true)
+            .convert(SPIN_WAT)
+            .unwrap();
+        let profiler = Profiler::sampling();
+        let middlewares = profiler.clone();
+        let (compiler, backend_id) = get_compiler_with(move || {
+            let mut chain = MiddlewareChain::new();
+            chain.push_module(middlewares.clone());
+            chain
+        });
+        let module = compile_with(wasm_binary.as_ref(), &compiler).unwrap();
+        let profiled = module.instantiate(&imports! {}).unwrap();
+        let other = module.instantiate(&imports! {}).unwrap();
+        // Instances with an interrupt signal page of their own are not sampled either.
+        let _handle = other.interrupt_handle().unwrap();
+
+        push_code_version(CodeVersion {
+            baseline: true,
+            msm: profiled
+                .module
+                .runnable_module
+                .get_module_state_map()
+                .unwrap(),
+            base: profiled.module.runnable_module.get_code().unwrap().as_ptr() as usize,
+            backend: backend_id,
+            runnable_module: profiled.module.runnable_module.clone(),
+        });
+        let profile = profiler.profile(&module).unwrap();
+        let run_other: Func<i32> = other.func("run").unwrap();
+        let result = profile.sample(&profiled, Duration::from_millis(1), || {
+            run_other.call(100_000_000)
+        });
+        result.unwrap().unwrap();
+        assert_eq!(profile.to_collapsed(), "");
+
+        let run: Func<i32> = profiled.func("run").unwrap();
+        let result = profile.sample(&profiled, Duration::from_millis(1), || {
+            run.call(100_000_000)
+        });
+        pop_code_version().unwrap();
+        result.unwrap().unwrap();
+        assert!(profile
+            .to_collapsed()
+            .lines()
+            .any(|line| line.starts_with("run;spin ")));
+
+        let unprofiled = Profiler::sampling();
+        assert!(unprofiled.profile(&module).is_none());
+    }
 }
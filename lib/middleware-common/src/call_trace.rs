@@ -1,24 +1,51 @@
 use std::sync::{
     atomic::{AtomicU32, Ordering},
-    Arc,
+    Arc, Mutex,
 };
 use wasmer_runtime_core::{
-    codegen::{Event, EventSink, FunctionMiddleware, InternalEvent},
+    codegen::{Event, EventSink, FunctionMiddleware, InternalEvent, ModuleMiddleware},
     module::ModuleInfo,
+    structures::TypedIndex,
+    types::FuncIndex,
 };
 
+/// Prints every call to a local function. Pushed as a module middleware as well, it also
+/// prints the names the module gives to the functions.
+#[derive(Clone)]
 pub struct CallTrace {
     counter: Arc<AtomicU32>,
+    /// The names of the local functions, known once the whole module has been read.
+    names: Arc<Mutex<Vec<Option<String>>>>,
 }
 
 impl CallTrace {
     pub fn new() -> CallTrace {
         CallTrace {
             counter: Arc::new(AtomicU32::new(0)),
+            names: Arc::new(Mutex::new(vec![])),
         }
     }
 }
 
+impl ModuleMiddleware for CallTrace {
+    type Error = String;
+    fn transform_module_info(&mut self, _module_info: &mut ModuleInfo) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn finish_module(&mut self, module_info: &mut ModuleInfo) -> Result<(), Self::Error> {
+        let names = (module_info.imported_functions.len()..module_info.func_assoc.len())
+            .map(|index| {
+                module_info
+                    .func_name(FuncIndex::new(index))
+                    .map(|name| name.to_string())
+            })
+            .collect();
+        *self.names.lock().unwrap() = names;
+        Ok(())
+    }
+}
+
 impl FunctionMiddleware for CallTrace {
     type Error = String;
     fn feed_event<'a, 'b: 'a>(
@@ -28,12 +55,16 @@ impl FunctionMiddleware for CallTrace {
         sink: &mut EventSink<'a, 'b>,
     ) -> Result<(), Self::Error> {
         let counter = self.counter.clone();
+        let names = self.names.clone();
 
         match op {
             Event::Internal(InternalEvent::FunctionBegin(id)) => sink.push(Event::Internal(
                 InternalEvent::Breakpoint(Box::new(move |_| {
                     let idx = counter.fetch_add(1, Ordering::SeqCst);
-                    eprintln!("[{}] func ({})", idx, id);
+                    match names.lock().unwrap().get(id as usize) {
+                        Some(Some(name)) => eprintln!("[{}] func ({} {})", idx, id, name),
+                        _ => eprintln!("[{}] func ({})", idx, id),
+                    }
                     Ok(())
                 })),
             )),
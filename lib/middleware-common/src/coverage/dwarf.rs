@@ -0,0 +1,547 @@
+//! Maps code offsets to source lines with the line programs of the DWARF `.debug_line`
+//! section. Only 32-bit DWARF versions 2 to 5 are read; other units are skipped, as are
+//! version 5 units naming their files with forms other than inline strings, `.debug_str`
+//! and `.debug_line_str` offsets.
+
+use std::cmp::Ordering;
+use wasmer_runtime_core::wasmparser::{ModuleReader, SectionCode};
+
+/// The rows of the line programs of a module, sorted by address.
+pub(super) struct LineMap {
+    /// The offset of the contents of the code section, which DWARF addresses are relative to.
+    code_start: usize,
+    files: Vec<String>,
+    /// The file index and line of the code from each address on, or `None` past the end of
+    /// a sequence.
+    rows: Vec<(u64, Option<(usize, u64)>)>,
+}
+
+impl LineMap {
+    /// Reads the line programs of `wasm`, or returns `None` if it has no `.debug_line`
+    /// section or the section is malformed.
+    pub(super) fn new(wasm: &[u8]) -> Option<LineMap> {
+        let mut reader = ModuleReader::new(wasm).ok()?;
+        let mut code_start = None;
+        let mut debug_line = None;
+        let mut strings = Strings::default();
+        while !reader.eof() {
+            let section = reader.read().ok()?;
+            match section.code {
+                SectionCode::Code => code_start = Some(section.range().start),
+                SectionCode::Custom { name, .. } => {
+                    let mut data = section.get_binary_reader();
+                    let len = data.bytes_remaining();
+                    let contents = data.read_bytes(len).ok()?;
+                    match name {
+                        ".debug_line" => debug_line = Some(contents),
+                        ".debug_str" => strings.debug_str = contents,
+                        ".debug_line_str" => strings.debug_line_str = contents,
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut line_map = LineMap {
+            code_start: code_start?,
+            files: vec![],
+            rows: vec![],
+        };
+        let mut input = Reader { data: debug_line? };
+        while !input.data.is_empty() {
+            line_map.read_unit(&mut input, &strings)?;
+        }
+        // Where a sequence ends at the address another starts, the start wins.
+        line_map
+            .rows
+            .sort_by_key(|&(address, row)| (address, row.is_some()));
+        Some(line_map)
+    }
+
+    /// Returns the source file and line of the code at `offset` in the wasm binary.
+    pub(super) fn find(&self, offset: usize) -> Option<(&str, u64)> {
+        let address = offset.checked_sub(self.code_start)? as u64;
+        let next = match self.rows.binary_search_by(|&(row_address, _)| {
+            if row_address <= address {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        }) {
+            Ok(index) | Err(index) => index,
+        };
+        let (_, row) = self.rows.get(next.checked_sub(1)?)?;
+        let (file, line) = (*row)?;
+        Some((&self.files[file], line))
+    }
+
+    /// Reads the header and runs the line program of one unit.
+    fn read_unit(&mut self, input: &mut Reader, strings: &Strings) -> Option<()> {
+        let unit_length = input.u32()?;
+        if unit_length == 0xffff_ffff {
+            // 64-bit DWARF.
+            return None;
+        }
+        let mut unit = Reader {
+            data: input.take(unit_length as usize)?,
+        };
+        let version = unit.u16()?;
+        if version < 2 || version > 5 {
+            return Some(());
+        }
+        if version >= 5 {
+            // Address and segment selector sizes.
+            unit.u8()?;
+            unit.u8()?;
+        }
+        let header_length = unit.u32()?;
+        let mut header = Reader {
+            data: unit.take(header_length as usize)?,
+        };
+        let min_inst_length = u64::from(header.u8()?);
+        if version >= 4 {
+            // Maximum operations per instruction, only used by VLIW architectures.
+            header.u8()?;
+        }
+        // Whether rows are statements by default.
+        header.u8()?;
+        let line_base = i64::from(header.u8()? as i8);
+        let line_range = u64::from(header.u8()?);
+        let opcode_base = header.u8()?;
+        let standard_opcode_lengths = header.take(usize::from(opcode_base).checked_sub(1)?)?;
+        if line_range == 0 {
+            return None;
+        }
+
+        let first_file = self.files.len();
+        // Versions before 5 number files from 1, leaving 0 for the file of the compilation
+        // unit, which version 5 lists first.
+        let file_base = if version >= 5 {
+            match read_entry_tables(&mut header, strings) {
+                Some(files) => self.files.extend(files),
+                None => return Some(()),
+            }
+            0
+        } else {
+            self.files.extend(read_file_names(&mut header)?);
+            1
+        };
+        let file_count = self.files.len() - first_file;
+
+        let (mut address, mut file, mut line) = (0u64, 1u64, 1i64);
+        while !unit.data.is_empty() {
+            let opcode = unit.u8()?;
+            let emit_row = if opcode >= opcode_base {
+                let adjusted = u64::from(opcode - opcode_base);
+                address = address.wrapping_add(adjusted / line_range * min_inst_length);
+                line = line.wrapping_add(line_base + (adjusted % line_range) as i64);
+                true
+            } else if opcode == 0 {
+                let length = unit.uleb()? as usize;
+                let mut extended = Reader {
+                    data: unit.take(length)?,
+                };
+                match extended.u8()? {
+                    // DW_LNE_end_sequence
+                    1 => {
+                        self.rows.push((address, None));
+                        address = 0;
+                        file = 1;
+                        line = 1;
+                    }
+                    // DW_LNE_set_address
+                    2 => {
+                        address = extended
+                            .data
+                            .iter()
+                            .rev()
+                            .fold(0, |address, &byte| address << 8 | u64::from(byte));
+                    }
+                    _ => {}
+                }
+                false
+            } else {
+                match opcode {
+                    // DW_LNS_copy
+                    1 => true,
+                    // DW_LNS_advance_pc
+                    2 => {
+                        let advance = unit.uleb()?.wrapping_mul(min_inst_length);
+                        address = address.wrapping_add(advance);
+                        false
+                    }
+                    // DW_LNS_advance_line
+                    3 => {
+                        line = line.wrapping_add(unit.sleb()?);
+                        false
+                    }
+                    // DW_LNS_set_file
+                    4 => {
+                        file = unit.uleb()?;
+                        false
+                    }
+                    // DW_LNS_const_add_pc
+                    8 => {
+                        let adjusted = u64::from(255 - opcode_base);
+                        address = address.wrapping_add(adjusted / line_range * min_inst_length);
+                        false
+                    }
+                    // DW_LNS_fixed_advance_pc
+                    9 => {
+                        address = address.wrapping_add(u64::from(unit.u16()?));
+                        false
+                    }
+                    _ => {
+                        let operands = *standard_opcode_lengths.get(usize::from(opcode) - 1)?;
+                        for _ in 0..operands {
+                            unit.uleb()?;
+                        }
+                        false
+                    }
+                }
+            };
+            if emit_row {
+                let row = match (file as usize).checked_sub(file_base) {
+                    Some(index) if index < file_count && line >= 0 => {
+                        Some((first_file + index, line as u64))
+                    }
+                    _ => None,
+                };
+                self.rows.push((address, row));
+            }
+        }
+        Some(())
+    }
+}
+
+/// Reads the include directories and file names of a version 2 to 4 header.
+fn read_file_names(header: &mut Reader) -> Option<Vec<String>> {
+    let mut directories = vec![];
+    loop {
+        let directory = header.cstr()?;
+        if directory.is_empty() {
+            break;
+        }
+        directories.push(directory);
+    }
+    let mut files = vec![];
+    loop {
+        let name = header.cstr()?;
+        if name.is_empty() {
+            break;
+        }
+        let directory = header.uleb()? as usize;
+        // Modification time and length.
+        header.uleb()?;
+        header.uleb()?;
+        // Directory 0 is the directory of the compilation unit, which is left out.
+        let directory = directory.checked_sub(1).and_then(|i| directories.get(i));
+        files.push(join(directory.cloned(), name));
+    }
+    Some(files)
+}
+
+/// Reads the directory and file name tables of a version 5 header, which describe the
+/// format of their entries.
+fn read_entry_tables<'a>(header: &mut Reader<'a>, strings: &Strings<'a>) -> Option<Vec<String>> {
+    let directories = read_entry_table(header, strings)?
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect::<Option<Vec<_>>>()?;
+    read_entry_table(header, strings)?
+        .into_iter()
+        .map(|(name, directory)| {
+            let directory = directories.get(directory as usize).cloned();
+            Some(join(directory, name?))
+        })
+        .collect()
+}
+
+/// Reads the path and directory index of each entry of a version 5 directory or file name
+/// table, or `None` if an entry uses a form which cannot be read.
+fn read_entry_table<'a>(
+    header: &mut Reader<'a>,
+    strings: &Strings<'a>,
+) -> Option<Vec<(Option<&'a str>, u64)>> {
+    // DW_LNCT_path and DW_LNCT_directory_index.
+    const PATH: u64 = 1;
+    const DIRECTORY_INDEX: u64 = 2;
+
+    let format_count = header.u8()?;
+    let mut format = vec![];
+    for _ in 0..format_count {
+        format.push((header.uleb()?, header.uleb()?));
+    }
+    let count = header.uleb()?;
+    let mut entries = vec![];
+    for _ in 0..count {
+        let (mut path, mut directory) = (None, 0);
+        for &(content, form) in &format {
+            match (content, header.form(form, strings)?) {
+                (PATH, Value::Str(s)) => path = Some(s),
+                (DIRECTORY_INDEX, Value::Num(n)) => directory = n,
+                _ => {}
+            }
+        }
+        entries.push((path, directory));
+    }
+    Some(entries)
+}
+
+/// Returns `name` in `directory`, unless it is absolute.
+fn join(directory: Option<&str>, name: &str) -> String {
+    match directory {
+        Some(directory) if !name.starts_with('/') => format!("{}/{}", directory, name),
+        _ => name.to_string(),
+    }
+}
+
+/// The string sections a version 5 header can refer to.
+#[derive(Default)]
+struct Strings<'a> {
+    debug_str: &'a [u8],
+    debug_line_str: &'a [u8],
+}
+
+/// The value of an attribute of a version 5 directory or file name entry.
+enum Value<'a> {
+    Str(&'a str),
+    Num(u64),
+    Other,
+}
+
+/// Reads little-endian DWARF data.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.data.len() {
+            return None;
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let bytes = self.take(2)?;
+        Some(u16::from(bytes[0]) | u16::from(bytes[1]) << 8)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let bytes = self.take(4)?;
+        Some(
+            bytes
+                .iter()
+                .rev()
+                .fold(0, |n, &byte| n << 8 | u32::from(byte)),
+        )
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let low = u64::from(self.u32()?);
+        let high = u64::from(self.u32()?);
+        Some(high << 32 | low)
+    }
+
+    fn uleb(&mut self) -> Option<u64> {
+        let mut result = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                result |= u64::from(byte & 0x7f) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Some(result);
+            }
+        }
+    }
+
+    fn sleb(&mut self) -> Option<i64> {
+        let mut result = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                result |= i64::from(byte & 0x7f) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    result |= -1 << shift;
+                }
+                return Some(result);
+            }
+        }
+    }
+
+    /// Reads a null-terminated string.
+    fn cstr(&mut self) -> Option<&'a str> {
+        let end = self.data.iter().position(|&byte| byte == 0)?;
+        let s = std::str::from_utf8(self.take(end)?).ok()?;
+        self.u8()?;
+        Some(s)
+    }
+
+    /// Reads an attribute in the 32-bit DWARF `form`, or returns `None` if it cannot.
+    fn form(&mut self, form: u64, strings: &Strings<'a>) -> Option<Value<'a>> {
+        let string_at = |section: &'a [u8], offset: u32| {
+            let data = section.get(offset as usize..)?;
+            Reader { data }.cstr()
+        };
+        Some(match form {
+            // DW_FORM_block
+            0x09 => {
+                let len = self.uleb()? as usize;
+                self.take(len)?;
+                Value::Other
+            }
+            // DW_FORM_data1, DW_FORM_data2, DW_FORM_data4, DW_FORM_data8 and DW_FORM_udata
+            0x0b => Value::Num(u64::from(self.u8()?)),
+            0x05 => Value::Num(u64::from(self.u16()?)),
+            0x06 => Value::Num(u64::from(self.u32()?)),
+            0x07 => Value::Num(self.u64()?),
+            0x0f => Value::Num(self.uleb()?),
+            // DW_FORM_data16, used for MD5 digests.
+            0x1e => {
+                self.take(16)?;
+                Value::Other
+            }
+            // DW_FORM_string
+            0x08 => Value::Str(self.cstr()?),
+            // DW_FORM_strp
+            0x0e => Value::Str(string_at(strings.debug_str, self.u32()?)?),
+            // DW_FORM_line_strp
+            0x1f => Value::Str(string_at(strings.debug_line_str, self.u32()?)?),
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LineMap;
+
+    /// Returns the fields of a line program header following the header length, up to its
+    /// directory and file name tables.
+    fn header_start() -> Vec<u8> {
+        vec![
+            1,    // minimum instruction length
+            1,    // maximum operations per instruction
+            1,    // default is_stmt
+            0xfb, // line base: -5
+            14,   // line range
+            13,   // opcode base
+            0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1, // standard opcode lengths
+        ]
+    }
+
+    /// Returns a module with an empty code section, whose contents start at offset 10,
+    /// followed by a `.debug_line` section holding one unit and by the custom `sections`.
+    fn module(
+        version: &[u8],
+        header: &[u8],
+        program: &[u8],
+        sections: &[(&str, &[u8])],
+    ) -> Vec<u8> {
+        let mut unit = version.to_vec();
+        unit.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        unit.extend_from_slice(header);
+        unit.extend_from_slice(program);
+        let mut debug_line = (unit.len() as u32).to_le_bytes().to_vec();
+        debug_line.extend(unit);
+
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        wasm.extend_from_slice(&[10, 1, 0]);
+        let debug_line = [(".debug_line", &debug_line[..])];
+        for (name, contents) in debug_line.iter().chain(sections) {
+            let mut size = 1 + name.len() + contents.len();
+            wasm.push(0);
+            while size >= 0x80 {
+                wasm.push(size as u8 | 0x80);
+                size >>= 7;
+            }
+            wasm.extend_from_slice(&[size as u8, name.len() as u8]);
+            wasm.extend_from_slice(name.as_bytes());
+            wasm.extend_from_slice(contents);
+        }
+        wasm
+    }
+
+    #[test]
+    fn maps_offsets_to_lines() {
+        let mut header = header_start();
+        header.extend_from_slice(b"src\0\0");
+        header.extend_from_slice(b"main.c\0\x01\0\0\0");
+        let program = [
+            0, 5, 2, 0x10, 0, 0, 0, // DW_LNE_set_address 0x10
+            3, 9, // DW_LNS_advance_line 9
+            1, // DW_LNS_copy
+            2, 8, // DW_LNS_advance_pc 8
+            3, 2, // DW_LNS_advance_line 2
+            1, // DW_LNS_copy
+            2, 4, // DW_LNS_advance_pc 4
+            0, 1, 1, // DW_LNE_end_sequence
+        ];
+        let wasm = module(&[4, 0], &header, &program, &[]);
+
+        let line_map = LineMap::new(&wasm).unwrap();
+        assert_eq!(line_map.find(10 + 0x0f), None);
+        assert_eq!(line_map.find(10 + 0x10), Some(("src/main.c", 10)));
+        assert_eq!(line_map.find(10 + 0x17), Some(("src/main.c", 10)));
+        assert_eq!(line_map.find(10 + 0x18), Some(("src/main.c", 12)));
+        assert_eq!(line_map.find(10 + 0x1c), None);
+    }
+
+    #[test]
+    fn reads_version_5_headers() {
+        let mut header = header_start();
+        // Directories: DW_LNCT_path as DW_FORM_line_strp.
+        header.extend_from_slice(&[1, 1, 0x1f, 2, 0, 0, 0, 0, 6, 0, 0, 0]);
+        // Files: DW_LNCT_path as DW_FORM_string, DW_LNCT_directory_index as DW_FORM_udata
+        // and DW_LNCT_MD5 as DW_FORM_data16.
+        header.extend_from_slice(&[3, 1, 0x08, 2, 0x0f, 5, 0x1e, 2]);
+        for (name, directory) in &[(&b"main.c"[..], 1), (&b"/lib/util.c"[..], 0)] {
+            header.extend_from_slice(name);
+            header.push(0);
+            header.push(*directory);
+            header.extend_from_slice(&[0; 16]);
+        }
+        // Version 5 numbers files from 0.
+        let program = [
+            4, 0, // DW_LNS_set_file 0
+            0, 5, 2, 0x10, 0, 0, 0, // DW_LNE_set_address 0x10
+            3, 9, // DW_LNS_advance_line 9
+            1, // DW_LNS_copy
+            2, 8, // DW_LNS_advance_pc 8
+            3, 2, // DW_LNS_advance_line 2
+            1, // DW_LNS_copy
+            2, 4, // DW_LNS_advance_pc 4
+            4, 1, // DW_LNS_set_file 1
+            1, // DW_LNS_copy
+            2, 4, // DW_LNS_advance_pc 4
+            0, 1, 1, // DW_LNE_end_sequence
+        ];
+        let wasm = module(
+            &[5, 0, 4, 0],
+            &header,
+            &program,
+            &[(".debug_line_str", b"/work\0src\0")],
+        );
+
+        let line_map = LineMap::new(&wasm).unwrap();
+        assert_eq!(line_map.find(10 + 0x10), Some(("src/main.c", 10)));
+        assert_eq!(line_map.find(10 + 0x18), Some(("src/main.c", 12)));
+        assert_eq!(line_map.find(10 + 0x1c), Some(("/lib/util.c", 12)));
+        assert_eq!(line_map.find(10 + 0x20), None);
+    }
+}
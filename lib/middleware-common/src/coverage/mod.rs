@@ -0,0 +1,304 @@
+//! Code coverage of WebAssembly code.
+//!
+//! `Coverage` instruments the start of every basic block to increment a counter of the
+//! instance, so counting costs no call out of the instance. Each instance counts its own
+//! hits, which `Coverage::counters` reads:
+//!
+//! ```ignore
+//! let coverage = Coverage::new();
+//! let middlewares = coverage.clone();
+//! let compiler: StreamingCompiler<MCG, _, _, _, _> = StreamingCompiler::new(move || {
+//!     let mut chain = MiddlewareChain::new();
+//!     chain.push_module(middlewares.clone());
+//!     chain.push(middlewares.clone());
+//!     chain
+//! });
+//! let module = compile_with(&wasm, &compiler)?;
+//!
+//! let instance = module.instantiate(&imports! {})?;
+//! // ... run the instance ...
+//! let counters = coverage.counters(&instance).unwrap();
+//! std::fs::write("coverage.info", counters.to_lcov(&wasm, "module.wasm"))?;
+//! ```
+
+mod dwarf;
+
+use self::dwarf::LineMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use wasmer_runtime_core::{
+    codegen::{Event, EventSink, FunctionMiddleware, InternalEvent, ModuleMiddleware},
+    module::ModuleInfo,
+    structures::TypedIndex,
+    types::FuncIndex,
+    wasmparser::Operator,
+    Instance,
+};
+
+/// A basic block of a module instrumented by `Coverage`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    /// The index of the function containing the block.
+    pub func_index: FuncIndex,
+    /// The offset in the wasm binary of the first operator of the block.
+    pub offset: usize,
+}
+
+/// What `Coverage` learned about one module it instrumented.
+#[derive(Default)]
+struct CoverageMap {
+    /// The counter of the first block; the others follow it.
+    first_counter: usize,
+    blocks: Vec<Block>,
+    functions: Vec<(FuncIndex, String)>,
+}
+
+/// A middleware recording which basic blocks of a module are executed, and how often.
+///
+/// The same `Coverage` must be pushed both as a module middleware, which starts a new map
+/// of the blocks for each module, and as a function middleware, which instruments the
+/// blocks. Clones share what they learn about the modules they compile, so `counters` can
+/// be called on any of them.
+#[derive(Clone)]
+pub struct Coverage {
+    maps: Arc<Mutex<HashMap<u64, CoverageMap>>>,
+    func_index: FuncIndex,
+    block_start: bool,
+}
+
+impl Coverage {
+    /// Creates a coverage middleware.
+    pub fn new() -> Coverage {
+        Coverage {
+            maps: Arc::new(Mutex::new(HashMap::new())),
+            func_index: FuncIndex::new(0),
+            block_start: false,
+        }
+    }
+
+    /// Returns the number of times each block of `instance` was entered so far.
+    ///
+    /// Returns `None` if the module of `instance` was not compiled with this middleware.
+    pub fn counters(&self, instance: &Instance) -> Option<CoverageCounters> {
+        let maps = self.maps.lock().unwrap();
+        let map = maps.get(&instance.module().info().id)?;
+        let counters = instance.context().counters();
+        Some(CoverageCounters {
+            blocks: map.blocks.clone(),
+            functions: map.functions.clone(),
+            hits: counters[map.first_counter..map.first_counter + map.blocks.len()].to_vec(),
+        })
+    }
+
+    /// Sets the counters of `instance` back to zero.
+    ///
+    /// Does nothing if the module of `instance` was not compiled with this middleware.
+    pub fn reset(&self, instance: &mut Instance) {
+        let maps = self.maps.lock().unwrap();
+        if let Some(map) = maps.get(&instance.module().info().id) {
+            let counters = instance.context_mut().counters_mut();
+            for hits in &mut counters[map.first_counter..map.first_counter + map.blocks.len()] {
+                *hits = 0;
+            }
+        }
+    }
+}
+
+impl ModuleMiddleware for Coverage {
+    type Error = String;
+    fn transform_module_info(&mut self, module_info: &mut ModuleInfo) -> Result<(), Self::Error> {
+        self.maps.lock().unwrap().insert(
+            module_info.id,
+            CoverageMap {
+                first_counter: module_info.counters,
+                ..CoverageMap::default()
+            },
+        );
+        Ok(())
+    }
+
+    fn finish_module(&mut self, module_info: &mut ModuleInfo) -> Result<(), Self::Error> {
+        let mut maps = self.maps.lock().unwrap();
+        let map = maps.get_mut(&module_info.id).unwrap();
+        for (func_index, name) in map.functions.iter_mut() {
+            if let Some(func_name) = module_info.func_name(*func_index) {
+                *name = func_name.to_string();
+            }
+        }
+        module_info.counters = map.first_counter + map.blocks.len();
+        Ok(())
+    }
+}
+
+impl FunctionMiddleware for Coverage {
+    type Error = String;
+    fn feed_event<'a, 'b: 'a>(
+        &mut self,
+        op: Event<'a, 'b>,
+        module_info: &ModuleInfo,
+        sink: &mut EventSink<'a, 'b>,
+    ) -> Result<(), Self::Error> {
+        match op {
+            Event::Internal(InternalEvent::FunctionBegin(id)) => {
+                self.func_index =
+                    FuncIndex::new(module_info.imported_functions.len() + id as usize);
+                self.block_start = true;
+                let name = match module_info.func_name(self.func_index) {
+                    Some(name) => name.to_string(),
+                    None => format!("func{}", self.func_index.index()),
+                };
+                let mut maps = self.maps.lock().unwrap();
+                let map = maps.get_mut(&module_info.id).ok_or_else(|| {
+                    "`Coverage` must also be pushed as a module middleware".to_string()
+                })?;
+                map.functions.push((self.func_index, name));
+            }
+            Event::Wasm(&ref op) | Event::WasmOwned(ref op) => {
+                if self.block_start {
+                    self.block_start = false;
+                    let mut maps = self.maps.lock().unwrap();
+                    let map = maps.get_mut(&module_info.id).unwrap();
+                    let counter = map.first_counter + map.blocks.len();
+                    sink.push(Event::Internal(InternalEvent::IncrementCounter(
+                        counter as u32,
+                    )));
+                    map.blocks.push(Block {
+                        func_index: self.func_index,
+                        offset: sink.offset().unwrap_or(0),
+                    });
+                }
+                // The code after these is only reached through a branch, or starts a branch.
+                match *op {
+                    Operator::Loop { .. }
+                    | Operator::If { .. }
+                    | Operator::Else
+                    | Operator::End
+                    | Operator::BrIf { .. } => self.block_start = true,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        sink.push(op);
+        Ok(())
+    }
+}
+
+/// The number of times each block of an instance was entered, as read by
+/// `Coverage::counters`.
+#[derive(Clone)]
+pub struct CoverageCounters {
+    blocks: Vec<Block>,
+    functions: Vec<(FuncIndex, String)>,
+    hits: Vec<u64>,
+}
+
+impl CoverageCounters {
+    /// Returns every block with the number of times it was entered.
+    pub fn hits(&self) -> Vec<(Block, u64)> {
+        self.blocks
+            .iter()
+            .cloned()
+            .zip(self.hits.iter().cloned())
+            .collect()
+    }
+
+    /// Returns the hits as a JSON object mapping each function index to an object mapping
+    /// the offsets of the blocks of the function to their hits.
+    pub fn to_json(&self) -> String {
+        let mut functions: BTreeMap<usize, Vec<(usize, u64)>> = BTreeMap::new();
+        for (block, hits) in self.hits() {
+            functions
+                .entry(block.func_index.index())
+                .or_default()
+                .push((block.offset, hits));
+        }
+
+        let mut json = String::from("{");
+        for (i, (func_index, blocks)) in functions.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write!(json, "\"{}\":{{", func_index).unwrap();
+            for (j, (offset, hits)) in blocks.iter().enumerate() {
+                if j > 0 {
+                    json.push(',');
+                }
+                write!(json, "\"{}\":{}", offset, hits).unwrap();
+            }
+            json.push('}');
+        }
+        json.push('}');
+        json
+    }
+
+    /// Returns the hits in the lcov tracefile format.
+    ///
+    /// `wasm` is the binary the module was compiled from. If it has a DWARF `.debug_line`
+    /// section, blocks are reported at their source file and line. Otherwise, and for blocks
+    /// without line information, they are reported in a file named `source`, using their
+    /// offset in the binary as the line number.
+    pub fn to_lcov(&self, wasm: &[u8], source: &str) -> String {
+        #[derive(Default)]
+        struct Record<'a> {
+            functions: Vec<(u64, &'a str, u64)>,
+            lines: BTreeMap<u64, u64>,
+        }
+
+        let line_map = LineMap::new(wasm);
+        let locate = |offset: usize| {
+            line_map
+                .as_ref()
+                .and_then(|line_map| line_map.find(offset))
+                .unwrap_or((source, offset as u64))
+        };
+
+        let hits = self.hits();
+        let mut records: BTreeMap<&str, Record> = BTreeMap::new();
+        for (func_index, name) in self.functions.iter() {
+            // A function is entered through its first block.
+            let entry = hits
+                .iter()
+                .find(|(block, _)| block.func_index == *func_index);
+            if let Some((block, hits)) = entry {
+                let (file, line) = locate(block.offset);
+                let record = records.entry(file).or_default();
+                record.functions.push((line, name.as_str(), *hits));
+            }
+        }
+        for (block, hits) in &hits {
+            let (file, line) = locate(block.offset);
+            let line_hits = records
+                .entry(file)
+                .or_default()
+                .lines
+                .entry(line)
+                .or_insert(0);
+            *line_hits = (*line_hits).max(*hits);
+        }
+
+        let mut lcov = String::new();
+        for (file, record) in &records {
+            writeln!(lcov, "TN:").unwrap();
+            writeln!(lcov, "SF:{}", file).unwrap();
+            for (line, name, _) in &record.functions {
+                writeln!(lcov, "FN:{},{}", line, name).unwrap();
+            }
+            for (_, name, hits) in &record.functions {
+                writeln!(lcov, "FNDA:{},{}", hits, name).unwrap();
+            }
+            let functions_hit = record.functions.iter().filter(|f| f.2 > 0).count();
+            writeln!(lcov, "FNF:{}", record.functions.len()).unwrap();
+            writeln!(lcov, "FNH:{}", functions_hit).unwrap();
+            for (line, hits) in &record.lines {
+                writeln!(lcov, "DA:{},{}", line, hits).unwrap();
+            }
+            let lines_hit = record.lines.values().filter(|&&hits| hits > 0).count();
+            writeln!(lcov, "LF:{}", record.lines.len()).unwrap();
+            writeln!(lcov, "LH:{}", lines_hit).unwrap();
+            writeln!(lcov, "end_of_record").unwrap();
+        }
+        lcov
+    }
+}
@@ -13,4 +13,6 @@
 #[cfg(unix)]
 pub mod block_trace;
 pub mod call_trace;
+pub mod coverage;
 pub mod metering;
+pub mod profiler;
@@ -1,5 +1,7 @@
+pub use wasmer_runtime_core::error::ExecutionLimitExceededError;
 use wasmer_runtime_core::{
     codegen::{Event, EventSink, FunctionMiddleware, InternalEvent},
+    error::RuntimeError,
     module::ModuleInfo,
     vm::{Ctx, InternalField},
     wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType},
@@ -7,42 +9,187 @@ use wasmer_runtime_core::{
 };
 
 static INTERNAL_FIELD: InternalField = InternalField::allocate();
+/// Holds the limit, which is zero until it is set.
+static LIMIT_FIELD: InternalField = InternalField::allocate();
+/// Holds the page delta of a `memory.grow` while its cost is charged.
+static GROW_DELTA_FIELD: InternalField = InternalField::allocate();
+/// Holds the points used plus the cost of a `memory.grow`, before it is saturated.
+static GROW_USED_FIELD: InternalField = InternalField::allocate();
 
 /// Metering is a compiler middleware that calculates the cost of WebAssembly instructions at compile
 /// time and will count the cost of executed instructions at runtime. Within the Metering functionality,
 /// this instruction cost is called `points`.
 ///
-/// The Metering struct takes a `limit` parameter which is the maximum number of points which can be
-/// used by an instance during a function call. If this limit is exceeded, the function call will
-/// trap. Each instance has a `points_used` field which can be used to track points used during
-/// a function call and should be set back to zero after a function call.
+/// Each instance has a limit, set with `Metered::set_limit`, on the number of points which can
+/// be used by the instance. The limit is zero until it is set, so an instance whose limit was
+/// never set cannot run metered code. Once the points used reach the limit, the function call
+/// fails with `RuntimeError::ExecutionLimitExceeded`. Each instance has a `points_used` field
+/// which can be used to track points used during a function call and should be set back to
+/// zero after a function call. Host functions can charge points themselves with
+/// `Metered::add_points` on their `Ctx`.
+///
+/// The cost of each operator is given by the `cost` function passed to `Metering::new`; use
+/// `unit_cost` to charge one point per operator. Calls to imported (host) functions and
+/// `memory.grow` can be charged extra with `with_host_call_cost` and `with_memory_grow_cost`.
 ///
 /// Each compiler backend with Metering enabled should produce the same cost used at runtime for
 /// the same function calls so we can say that the metering is deterministic.
 ///
 pub struct Metering {
-    limit: u64,
     current_block: u64,
+    cost: Box<dyn Fn(&Operator) -> u64>,
+    host_call_cost: u64,
+    memory_grow_cost: u64,
 }
 
 impl Metering {
-    pub fn new(limit: u64) -> Metering {
+    /// Creates a metering middleware where executing an operator costs `cost(operator)`
+    /// points.
+    pub fn new<F>(cost: F) -> Metering
+    where
+        F: Fn(&Operator) -> u64 + 'static,
+    {
         Metering {
-            limit,
             current_block: 0,
+            cost: Box::new(cost),
+            host_call_cost: 0,
+            memory_grow_cost: 0,
+        }
+    }
+
+    /// Charges `cost` points on top of the cost of the `call` operator when calling an
+    /// imported function. Calls through `call_indirect` are not charged, since the callee is
+    /// only known at runtime.
+    pub fn with_host_call_cost(mut self, cost: u64) -> Metering {
+        self.host_call_cost = cost;
+        self
+    }
+
+    /// Charges `cost_per_page` points for each page requested by `memory.grow`, on top of
+    /// the cost of the operator. The pages are charged, and the limit checked, before the
+    /// memory grows, so a call that cannot afford them traps without growing the memory.
+    /// The charge saturates at `u64::MAX` points.
+    pub fn with_memory_grow_cost(mut self, cost_per_page: u64) -> Metering {
+        self.memory_grow_cost = cost_per_page;
+        self
+    }
+}
+
+/// A cost function charging one point for every operator.
+pub fn unit_cost(_: &Operator) -> u64 {
+    1
+}
+
+impl Metering {
+    /// Charges for the pages requested by the `memory.grow` about to run, then checks the
+    /// limit. The page delta on top of the stack is only known at runtime, so it is stashed
+    /// in an internal field while it is charged and then restored.
+    fn push_memory_grow_charge<'a, 'b: 'a>(&mut self, sink: &mut EventSink<'a, 'b>) {
+        // The points of the block so far are charged first, so that the check is exact.
+        push_charge(sink, self.current_block);
+        self.current_block = 0;
+
+        sink.push(Event::WasmOwned(Operator::I64ExtendI32U));
+        sink.push(Event::Internal(InternalEvent::SetInternal(
+            GROW_DELTA_FIELD.index() as _,
+        )));
+
+        // The cost of the pages, `u64::MAX` if `delta * cost_per_page` overflows.
+        let cost_per_page = self.memory_grow_cost;
+        let max_delta = u64::max_value() / cost_per_page;
+        let can_overflow = max_delta < u64::from(u32::max_value());
+        if can_overflow {
+            sink.push(Event::WasmOwned(Operator::I64Const { value: -1 }));
         }
+        sink.push(Event::Internal(InternalEvent::GetInternal(
+            GROW_DELTA_FIELD.index() as _,
+        )));
+        sink.push(Event::WasmOwned(Operator::I64Const {
+            value: cost_per_page as i64,
+        }));
+        sink.push(Event::WasmOwned(Operator::I64Mul));
+        if can_overflow {
+            sink.push(Event::Internal(InternalEvent::GetInternal(
+                GROW_DELTA_FIELD.index() as _,
+            )));
+            sink.push(Event::WasmOwned(Operator::I64Const {
+                value: max_delta as i64,
+            }));
+            sink.push(Event::WasmOwned(Operator::I64GtU));
+            sink.push(Event::WasmOwned(Operator::Select));
+        }
+
+        // The points used plus that cost, `u64::MAX` if the addition overflows.
+        sink.push(Event::Internal(InternalEvent::GetInternal(
+            INTERNAL_FIELD.index() as _,
+        )));
+        sink.push(Event::WasmOwned(Operator::I64Add));
+        sink.push(Event::Internal(InternalEvent::SetInternal(
+            GROW_USED_FIELD.index() as _,
+        )));
+        sink.push(Event::WasmOwned(Operator::I64Const { value: -1 }));
+        sink.push(Event::Internal(InternalEvent::GetInternal(
+            GROW_USED_FIELD.index() as _,
+        )));
+        sink.push(Event::Internal(InternalEvent::GetInternal(
+            GROW_USED_FIELD.index() as _,
+        )));
+        sink.push(Event::Internal(InternalEvent::GetInternal(
+            INTERNAL_FIELD.index() as _,
+        )));
+        sink.push(Event::WasmOwned(Operator::I64LtU));
+        sink.push(Event::WasmOwned(Operator::Select));
+        sink.push(Event::Internal(InternalEvent::SetInternal(
+            INTERNAL_FIELD.index() as _,
+        )));
+
+        push_limit_check(sink);
+
+        sink.push(Event::Internal(InternalEvent::GetInternal(
+            GROW_DELTA_FIELD.index() as _,
+        )));
+        sink.push(Event::WasmOwned(Operator::I32WrapI64));
     }
 }
 
-#[derive(Copy, Clone, Debug)]
-pub struct ExecutionLimitExceededError;
+/// Adds `points` to the points used.
+fn push_charge<'a, 'b: 'a>(sink: &mut EventSink<'a, 'b>, points: u64) {
+    sink.push(Event::Internal(InternalEvent::GetInternal(
+        INTERNAL_FIELD.index() as _,
+    )));
+    sink.push(Event::WasmOwned(Operator::I64Const {
+        value: points as i64,
+    }));
+    sink.push(Event::WasmOwned(Operator::I64Add));
+    sink.push(Event::Internal(InternalEvent::SetInternal(
+        INTERNAL_FIELD.index() as _,
+    )));
+}
+
+/// Traps with `RuntimeError::ExecutionLimitExceeded` if the points used reached the limit.
+fn push_limit_check<'a, 'b: 'a>(sink: &mut EventSink<'a, 'b>) {
+    sink.push(Event::Internal(InternalEvent::GetInternal(
+        INTERNAL_FIELD.index() as _,
+    )));
+    sink.push(Event::Internal(InternalEvent::GetInternal(
+        LIMIT_FIELD.index() as _,
+    )));
+    sink.push(Event::WasmOwned(Operator::I64GeU));
+    sink.push(Event::WasmOwned(Operator::If {
+        ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+    }));
+    sink.push(Event::Internal(InternalEvent::Breakpoint(Box::new(|_| {
+        Err(Box::new(RuntimeError::ExecutionLimitExceeded))
+    }))));
+    sink.push(Event::WasmOwned(Operator::End));
+}
 
 impl FunctionMiddleware for Metering {
     type Error = String;
     fn feed_event<'a, 'b: 'a>(
         &mut self,
         op: Event<'a, 'b>,
-        _module_info: &ModuleInfo,
+        module_info: &ModuleInfo,
         sink: &mut EventSink<'a, 'b>,
     ) -> Result<(), Self::Error> {
         match op {
@@ -50,7 +197,18 @@ impl FunctionMiddleware for Metering {
                 self.current_block = 0;
             }
             Event::Wasm(&ref op) | Event::WasmOwned(ref op) => {
-                self.current_block += 1;
+                self.current_block += (self.cost)(op);
+                match *op {
+                    Operator::Call { function_index }
+                        if (function_index as usize) < module_info.imported_functions.len() =>
+                    {
+                        self.current_block += self.host_call_cost;
+                    }
+                    Operator::MemoryGrow { .. } if self.memory_grow_cost != 0 => {
+                        self.push_memory_grow_charge(sink);
+                    }
+                    _ => {}
+                }
                 match *op {
                     Operator::Loop { .. }
                     | Operator::Block { .. }
@@ -64,16 +222,7 @@ impl FunctionMiddleware for Metering {
                     | Operator::Call { .. }
                     | Operator::CallIndirect { .. }
                     | Operator::Return => {
-                        sink.push(Event::Internal(InternalEvent::GetInternal(
-                            INTERNAL_FIELD.index() as _,
-                        )));
-                        sink.push(Event::WasmOwned(Operator::I64Const {
-                            value: self.current_block as i64,
-                        }));
-                        sink.push(Event::WasmOwned(Operator::I64Add));
-                        sink.push(Event::Internal(InternalEvent::SetInternal(
-                            INTERNAL_FIELD.index() as _,
-                        )));
+                        push_charge(sink, self.current_block);
                         self.current_block = 0;
                     }
                     _ => {}
@@ -83,22 +232,7 @@ impl FunctionMiddleware for Metering {
                     | Operator::BrTable { .. }
                     | Operator::BrIf { .. }
                     | Operator::Call { .. }
-                    | Operator::CallIndirect { .. } => {
-                        sink.push(Event::Internal(InternalEvent::GetInternal(
-                            INTERNAL_FIELD.index() as _,
-                        )));
-                        sink.push(Event::WasmOwned(Operator::I64Const {
-                            value: self.limit as i64,
-                        }));
-                        sink.push(Event::WasmOwned(Operator::I64GeU));
-                        sink.push(Event::WasmOwned(Operator::If {
-                            ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
-                        }));
-                        sink.push(Event::Internal(InternalEvent::Breakpoint(Box::new(|_| {
-                            Err(Box::new(ExecutionLimitExceededError))
-                        }))));
-                        sink.push(Event::WasmOwned(Operator::End));
-                    }
+                    | Operator::CallIndirect { .. } => push_limit_check(sink),
                     _ => {}
                 }
             }
@@ -128,3 +262,75 @@ pub fn get_points_used_ctx(ctx: &Ctx) -> u64 {
 pub fn set_points_used_ctx(ctx: &mut Ctx, value: u64) {
     ctx.set_internal(&INTERNAL_FIELD, value);
 }
+
+/// Gas accounting on an `Instance`, or on the `Ctx` a host function is called with.
+pub trait Metered {
+    /// Returns the number of points used.
+    fn points_used(&self) -> u64;
+
+    /// Sets the number of points used.
+    fn set_points_used(&mut self, value: u64);
+
+    /// Returns the limit on the points used, zero unless it was set.
+    fn limit(&self) -> u64;
+
+    /// Sets the limit on the points used. Raising it lets an instance which ran out of points
+    /// run again.
+    fn set_limit(&mut self, limit: u64);
+
+    /// Returns the number of points which can still be used.
+    fn remaining_points(&self) -> u64 {
+        self.limit().saturating_sub(self.points_used())
+    }
+
+    /// Charges `points`, failing once the points used reach the limit.
+    ///
+    /// A host function can return the error to make the call fail with
+    /// `RuntimeError::ExecutionLimitExceeded`, the same way WebAssembly code running out of
+    /// points does.
+    fn add_points(&mut self, points: u64) -> Result<(), ExecutionLimitExceededError> {
+        let used = self.points_used().saturating_add(points);
+        self.set_points_used(used);
+        if used >= self.limit() {
+            Err(ExecutionLimitExceededError)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Metered for Instance {
+    fn points_used(&self) -> u64 {
+        self.get_internal(&INTERNAL_FIELD)
+    }
+
+    fn set_points_used(&mut self, value: u64) {
+        self.set_internal(&INTERNAL_FIELD, value);
+    }
+
+    fn limit(&self) -> u64 {
+        self.get_internal(&LIMIT_FIELD)
+    }
+
+    fn set_limit(&mut self, limit: u64) {
+        self.set_internal(&LIMIT_FIELD, limit);
+    }
+}
+
+impl Metered for Ctx {
+    fn points_used(&self) -> u64 {
+        self.get_internal(&INTERNAL_FIELD)
+    }
+
+    fn set_points_used(&mut self, value: u64) {
+        self.set_internal(&INTERNAL_FIELD, value);
+    }
+
+    fn limit(&self) -> u64 {
+        self.get_internal(&LIMIT_FIELD)
+    }
+
+    fn set_limit(&mut self, limit: u64) {
+        self.set_internal(&LIMIT_FIELD, limit);
+    }
+}
@@ -0,0 +1,581 @@
+//! Profiling of WebAssembly code.
+//!
+//! `Profiler::new` instruments the entry and exit of every function with calls to functions
+//! imported from `PROFILER_NAMESPACE`. Each instance gets its own `Profile`, which provides
+//! these functions and counts the calls and the time spent in each function:
+//!
+//! ```ignore
+//! let profiler = Profiler::new();
+//! let middlewares = profiler.clone();
+//! let compiler: StreamingCompiler<MCG, _, _, _, _> = StreamingCompiler::new(move || {
+//!     let mut chain = MiddlewareChain::new();
+//!     chain.push_module(middlewares.clone());
+//!     chain.push(middlewares.clone());
+//!     chain
+//! });
+//! let module = compile_with(&wasm, &compiler)?;
+//!
+//! let profile = profiler.profile(&module).unwrap();
+//! let mut import_object = imports! {};
+//! import_object.register(PROFILER_NAMESPACE, profile.namespace());
+//! let instance = module.instantiate(&import_object)?;
+//! // ... run the instance ...
+//! std::fs::write("module.folded", profile.to_collapsed())?;
+//! std::fs::write("module.pb", profile.to_pprof())?;
+//! ```
+//!
+//! `Profiler::sampling` leaves the code as it is and only learns the names of the functions.
+//! Its profiles are filled by `Profile::sample`, which reads the WebAssembly stack of one
+//! instance at a regular interval instead.
+
+mod pprof;
+
+use self::pprof::Pprof;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+#[cfg(unix)]
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use wasmer_runtime_core::{
+    codegen::{Event, EventSink, FunctionMiddleware, InternalEvent, ModuleMiddleware},
+    func,
+    import::Namespace,
+    module::ModuleInfo,
+    parse::type_to_wp_type,
+    structures::TypedIndex,
+    types::{FuncIndex, FuncSig, Type},
+    vm::{Ctx, InternalField},
+    wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType},
+    Module,
+};
+#[cfg(unix)]
+use wasmer_runtime_core::{
+    fault::{self, FaultInfo},
+    interrupt::InterruptHandle,
+    Instance,
+};
+
+/// The namespace of the functions that instrumented code calls when entering and leaving
+/// functions.
+pub const PROFILER_NAMESPACE: &str = "__wasmer_profiler";
+
+/// One more than the local index of the function calling a local function or through a
+/// table, until the callee is entered.
+static CALLER_FIELD: InternalField = InternalField::allocate();
+/// One more than the local index of the function calling an imported function, until the
+/// call returns.
+static IMPORT_CALLER_FIELD: InternalField = InternalField::allocate();
+
+/// The functions imported by instrumented code.
+#[derive(Copy, Clone)]
+struct Hooks {
+    enter: FuncIndex,
+    leave: FuncIndex,
+}
+
+/// What `Profiler` learned about one module it compiled.
+#[derive(Default)]
+struct ProfilerMap {
+    hooks: Option<Hooks>,
+    /// The type of the block wrapping the body of each local function, which has its
+    /// results.
+    block_types: Vec<WpTypeOrFuncType>,
+    /// The names of the local functions.
+    names: Vec<String>,
+}
+
+/// A middleware profiling the functions of a module.
+///
+/// The same `Profiler` must be pushed both as a module middleware, which adds the imports
+/// called when entering and leaving a function, and as a function middleware, which
+/// instruments the functions. Clones share what they learn about the modules they compile,
+/// so `profile` can be called on any of them.
+///
+/// Each function body is wrapped in a block, so that a function is left when it returns or
+/// branches out of its body. Functions that trap are left when the instance enters another
+/// function from the function that called them, or from the host. When the trap comes from
+/// an imported function, the function that called it looks like it is still calling it,
+/// so the functions below are only left once a call to an imported function returns.
+#[derive(Clone)]
+pub struct Profiler {
+    maps: Arc<Mutex<HashMap<u64, ProfilerMap>>>,
+    sampling: bool,
+    hooks: Option<Hooks>,
+    local_index: u32,
+    block_type: WpTypeOrFuncType,
+    depth: usize,
+    function_start: bool,
+}
+
+impl Profiler {
+    /// Creates a profiler instrumenting the functions.
+    pub fn new() -> Profiler {
+        Profiler {
+            maps: Arc::new(Mutex::new(HashMap::new())),
+            sampling: false,
+            hooks: None,
+            local_index: 0,
+            block_type: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+            depth: 0,
+            function_start: false,
+        }
+    }
+
+    /// Creates a profiler for `Profile::sample`, which only names the functions. It only
+    /// needs to be pushed as a module middleware.
+    pub fn sampling() -> Profiler {
+        Profiler {
+            sampling: true,
+            ..Profiler::new()
+        }
+    }
+
+    /// Creates an empty profile for an instance of `module`.
+    ///
+    /// Returns `None` if `module` was not compiled with this middleware.
+    pub fn profile(&self, module: &Module) -> Option<Profile> {
+        let maps = self.maps.lock().unwrap();
+        let map = maps.get(&module.info().id)?;
+        let functions = map
+            .names
+            .iter()
+            .map(|name| FunctionProfile {
+                name: name.clone(),
+                calls: 0,
+                inclusive_time: Duration::default(),
+                exclusive_time: Duration::default(),
+            })
+            .collect();
+        Some(Profile {
+            data: Arc::new(Mutex::new(ProfileData {
+                sampled: self.sampling,
+                interval: None,
+                functions,
+                stacks: BTreeMap::new(),
+                open: vec![],
+            })),
+        })
+    }
+
+    fn call_hook<'a, 'b: 'a>(&self, hook: FuncIndex, sink: &mut EventSink<'a, 'b>) {
+        sink.push(Event::WasmOwned(Operator::I32Const {
+            value: self.local_index as i32,
+        }));
+        sink.push(Event::WasmOwned(Operator::Call {
+            function_index: hook.index() as u32,
+        }));
+    }
+}
+
+impl ModuleMiddleware for Profiler {
+    type Error = String;
+    fn transform_module_info(&mut self, module_info: &mut ModuleInfo) -> Result<(), Self::Error> {
+        let mut hooks = None;
+        let mut block_types = vec![];
+        if !self.sampling {
+            let mut add_hook = |name| {
+                let signature = FuncSig::new(vec![Type::I32], vec![]);
+                module_info.add_import_function(PROFILER_NAMESPACE, name, signature)
+            };
+            hooks = Some(Hooks {
+                enter: add_hook("enter"),
+                leave: add_hook("leave"),
+            });
+
+            let imported = module_info.imported_functions.len();
+            for index in imported..module_info.func_assoc.len() {
+                let sig_index = module_info.func_assoc[FuncIndex::new(index)];
+                let returns = module_info.signatures[sig_index].returns().to_vec();
+                block_types.push(match returns[..] {
+                    [] => WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+                    [ty] => WpTypeOrFuncType::Type(type_to_wp_type(ty)),
+                    _ => {
+                        let signature = FuncSig::new(vec![], returns);
+                        let existing = module_info
+                            .signatures
+                            .iter()
+                            .find(|&(_, sig)| *sig == signature)
+                            .map(|(sig_index, _)| sig_index);
+                        let sig_index = match existing {
+                            Some(sig_index) => sig_index,
+                            None => module_info.signatures.push(signature),
+                        };
+                        WpTypeOrFuncType::FuncType(sig_index.index() as u32)
+                    }
+                });
+            }
+        }
+        self.maps.lock().unwrap().insert(
+            module_info.id,
+            ProfilerMap {
+                hooks,
+                block_types,
+                ..ProfilerMap::default()
+            },
+        );
+        Ok(())
+    }
+
+    fn finish_module(&mut self, module_info: &mut ModuleInfo) -> Result<(), Self::Error> {
+        let names = (module_info.imported_functions.len()..module_info.func_assoc.len())
+            .map(|index| match module_info.func_name(FuncIndex::new(index)) {
+                Some(name) => name.to_string(),
+                None => format!("func{}", index),
+            })
+            .collect();
+        let mut maps = self.maps.lock().unwrap();
+        maps.get_mut(&module_info.id).unwrap().names = names;
+        Ok(())
+    }
+}
+
+impl FunctionMiddleware for Profiler {
+    type Error = String;
+    fn feed_event<'a, 'b: 'a>(
+        &mut self,
+        op: Event<'a, 'b>,
+        module_info: &ModuleInfo,
+        sink: &mut EventSink<'a, 'b>,
+    ) -> Result<(), Self::Error> {
+        let mut after_call = None;
+        match op {
+            Event::Internal(InternalEvent::FunctionBegin(id)) => {
+                let maps = self.maps.lock().unwrap();
+                let map = maps.get(&module_info.id);
+                self.hooks = map.and_then(|map| map.hooks);
+                if let Some(block_type) = map.and_then(|map| map.block_types.get(id as usize)) {
+                    self.block_type = *block_type;
+                }
+                drop(maps);
+                self.local_index = id;
+                self.depth = 0;
+                self.function_start = !self.sampling;
+            }
+            Event::Wasm(&ref op) | Event::WasmOwned(ref op) if !self.sampling => {
+                let hooks = self.hooks.ok_or_else(|| {
+                    "`Profiler` must also be pushed as a module middleware".to_string()
+                })?;
+                if self.function_start {
+                    self.function_start = false;
+                    self.call_hook(hooks.enter, sink);
+                    // Branches out of the body land at the end of this block, where the
+                    // function is left.
+                    sink.push(Event::WasmOwned(Operator::Block {
+                        ty: self.block_type,
+                    }));
+                }
+                // `depth` counts the blocks around `op` inside the body.
+                match *op {
+                    Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } => {
+                        self.depth += 1;
+                    }
+                    Operator::End if self.depth > 0 => self.depth -= 1,
+                    Operator::End => {
+                        sink.push(Event::WasmOwned(Operator::End));
+                        self.call_hook(hooks.leave, sink);
+                    }
+                    Operator::Return => self.call_hook(hooks.leave, sink),
+                    _ => {}
+                }
+                // Tell the callee who called it. The callee takes the caller of a local
+                // function as it is entered, while functions entered by an imported one
+                // are all called from the importing function until it returns.
+                let imported = module_info.imported_functions.len();
+                let caller = match *op {
+                    Operator::Call { function_index } if (function_index as usize) < imported => {
+                        after_call = Some(&IMPORT_CALLER_FIELD);
+                        Some(&IMPORT_CALLER_FIELD)
+                    }
+                    Operator::Call { .. } => Some(&CALLER_FIELD),
+                    Operator::CallIndirect { .. } => {
+                        after_call = Some(&CALLER_FIELD);
+                        Some(&CALLER_FIELD)
+                    }
+                    _ => None,
+                };
+                if let Some(field) = caller {
+                    set_field(sink, field, i64::from(self.local_index) + 1);
+                }
+            }
+            _ => {}
+        }
+        sink.push(op);
+        if let Some(field) = after_call {
+            set_field(sink, field, 0);
+        }
+        Ok(())
+    }
+}
+
+/// Sets the internal `field` of the instance to `value`.
+fn set_field<'a, 'b: 'a>(sink: &mut EventSink<'a, 'b>, field: &InternalField, value: i64) {
+    sink.push(Event::WasmOwned(Operator::I64Const { value }));
+    sink.push(Event::Internal(InternalEvent::SetInternal(
+        field.index() as _,
+    )));
+}
+
+/// What a profile measured of a function.
+///
+/// Sampled profiles do not count calls, and estimate times from the number of samples.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionProfile {
+    /// The name of the function from the `name` section, or `func{index}`.
+    pub name: String,
+    /// The number of calls to the function.
+    pub calls: u64,
+    /// The time spent in the function, including the functions it called.
+    pub inclusive_time: Duration,
+    /// The time spent in the function itself.
+    pub exclusive_time: Duration,
+}
+
+/// A function that was entered and has not been left yet.
+struct OpenFrame {
+    function: usize,
+    start: Instant,
+    children: Duration,
+}
+
+struct ProfileData {
+    sampled: bool,
+    interval: Option<Duration>,
+    functions: Vec<FunctionProfile>,
+    /// The number of calls or samples and the exclusive time of each stack, from the root.
+    stacks: BTreeMap<Vec<usize>, (u64, Duration)>,
+    open: Vec<OpenFrame>,
+}
+
+impl ProfileData {
+    /// Enters `function`, called by the local function one less than `caller`, or by the
+    /// host if it is zero. Whatever was entered above the caller was left, even if it
+    /// trapped.
+    fn enter(&mut self, function: usize, caller: u64) {
+        let depth = match caller.checked_sub(1) {
+            Some(caller) => self
+                .open
+                .iter()
+                .rposition(|f| f.function as u64 == caller)
+                .map_or(0, |depth| depth + 1),
+            None => 0,
+        };
+        self.close(depth);
+        if let Some(profile) = self.functions.get_mut(function) {
+            profile.calls += 1;
+            self.open.push(OpenFrame {
+                function,
+                start: Instant::now(),
+                children: Duration::default(),
+            });
+            let stack = self.open.iter().map(|frame| frame.function).collect();
+            self.stacks.entry(stack).or_default().0 += 1;
+        }
+    }
+
+    fn leave(&mut self, function: usize) {
+        if let Some(depth) = self.open.iter().rposition(|f| f.function == function) {
+            self.close(depth);
+        }
+    }
+
+    /// Leaves the functions entered above the first `depth` open frames.
+    fn close(&mut self, depth: usize) {
+        let now = Instant::now();
+        while self.open.len() > depth {
+            let stack: Vec<usize> = self.open.iter().map(|frame| frame.function).collect();
+            let frame = self.open.pop().unwrap();
+            let elapsed = now.duration_since(frame.start);
+            let exclusive = elapsed.checked_sub(frame.children).unwrap_or_default();
+
+            let profile = &mut self.functions[frame.function];
+            profile.exclusive_time += exclusive;
+            // Recursive calls are already timed by the outermost one.
+            if self.open.iter().all(|f| f.function != frame.function) {
+                profile.inclusive_time += elapsed;
+            }
+            if let Some(parent) = self.open.last_mut() {
+                parent.children += elapsed;
+            }
+            self.stacks.entry(stack).or_default().1 += exclusive;
+        }
+    }
+
+    fn sample(&mut self, stack: Vec<usize>, interval: Duration) {
+        if stack
+            .iter()
+            .any(|&function| function >= self.functions.len())
+        {
+            return;
+        }
+        for (i, &function) in stack.iter().enumerate() {
+            if !stack[..i].contains(&function) {
+                self.functions[function].inclusive_time += interval;
+            }
+        }
+        self.functions[*stack.last().unwrap()].exclusive_time += interval;
+        let entry = self.stacks.entry(stack).or_default();
+        entry.0 += 1;
+        entry.1 += interval;
+    }
+}
+
+/// The profile of an instance.
+#[derive(Clone)]
+pub struct Profile {
+    data: Arc<Mutex<ProfileData>>,
+}
+
+impl Profile {
+    /// Returns the namespace to register as `PROFILER_NAMESPACE` in the imports of an
+    /// instrumented instance, which records the calls.
+    pub fn namespace(&self) -> Namespace {
+        let mut namespace = Namespace::new();
+        let data = Arc::clone(&self.data);
+        namespace.insert(
+            "enter",
+            func!(move |ctx: &mut Ctx, function: u32| {
+                let caller = match ctx.get_internal(&CALLER_FIELD) {
+                    0 => ctx.get_internal(&IMPORT_CALLER_FIELD),
+                    caller => {
+                        ctx.set_internal(&CALLER_FIELD, 0);
+                        caller
+                    }
+                };
+                data.lock().unwrap().enter(function as usize, caller)
+            }),
+        );
+        let data = Arc::clone(&self.data);
+        namespace.insert(
+            "leave",
+            func!(move |function: u32| data.lock().unwrap().leave(function as usize)),
+        );
+        namespace
+    }
+
+    /// Runs `f`, sampling the WebAssembly stack of `instance` every `interval` of CPU time
+    /// used by the process. Other instances run undisturbed, even on the same thread.
+    ///
+    /// The module must be compiled by the singlepass backend, and its code version pushed
+    /// with `fault::push_code_version` while `f` runs on the current thread. The timer is
+    /// shared by the whole process, as described by `fault::start_sampling_timer`, which
+    /// returns the error if it cannot be started; `f` is not run then, nor if `instance`
+    /// cannot be interrupted.
+    #[cfg(unix)]
+    pub fn sample<R, F: FnOnce() -> R>(
+        &self,
+        instance: &Instance,
+        interval: Duration,
+        f: F,
+    ) -> io::Result<R> {
+        /// Stops sampling when dropped, even if `f` panics.
+        struct Sampling(InterruptHandle, Option<Box<dyn FnMut(&FaultInfo)>>);
+
+        impl Drop for Sampling {
+            fn drop(&mut self) {
+                self.0.stop_sampling();
+                // Stopping sets the same timer starting did, so it does not fail.
+                let _ = fault::stop_sampling_timer();
+                fault::set_sampler(self.1.take());
+            }
+        }
+
+        let handle = instance
+            .interrupt_handle()
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        fault::start_sampling_timer(interval)?;
+        self.data.lock().unwrap().interval = Some(interval);
+        let data = Arc::clone(&self.data);
+        let previous = fault::set_sampler(Some(Box::new(move |fault: &FaultInfo| {
+            let frames = match unsafe { fault.read_stack(None) } {
+                Some(image) => image.frames,
+                None => return,
+            };
+            // The stack cannot be read outside of function and loop headers.
+            if frames.is_empty() {
+                return;
+            }
+            let stack = frames.iter().rev().map(|f| f.local_function_id).collect();
+            if let Ok(mut data) = data.lock() {
+                data.sample(stack, interval);
+            }
+        })));
+        handle.start_sampling();
+        let _sampling = Sampling(handle, previous);
+        Ok(f())
+    }
+
+    /// Returns what was measured of each local function, in the order of the module.
+    pub fn functions(&self) -> Vec<FunctionProfile> {
+        self.data.lock().unwrap().functions.clone()
+    }
+
+    /// Clears the profile, including the functions not left yet.
+    pub fn reset(&self) {
+        let mut data = self.data.lock().unwrap();
+        for profile in data.functions.iter_mut() {
+            profile.calls = 0;
+            profile.inclusive_time = Duration::default();
+            profile.exclusive_time = Duration::default();
+        }
+        data.stacks.clear();
+        data.open.clear();
+    }
+
+    /// Returns the profile in the collapsed stack format read by flamegraph tools.
+    ///
+    /// Each line lists the names of the functions of a stack from the root, separated by
+    /// `;`, followed by the number of samples of the stack in a sampled profile, or by the
+    /// time spent in its last function in nanoseconds otherwise.
+    pub fn to_collapsed(&self) -> String {
+        let data = self.data.lock().unwrap();
+        let mut collapsed = String::new();
+        for (stack, &(count, time)) in &data.stacks {
+            let value = if data.sampled {
+                u128::from(count)
+            } else {
+                time.as_nanos()
+            };
+            if value == 0 {
+                continue;
+            }
+            for (i, &function) in stack.iter().enumerate() {
+                if i > 0 {
+                    collapsed.push(';');
+                }
+                collapsed.push_str(&data.functions[function].name);
+            }
+            writeln!(collapsed, " {}", value).unwrap();
+        }
+        collapsed
+    }
+
+    /// Returns the profile as an uncompressed pprof protocol buffer.
+    ///
+    /// Sampled profiles have the number of samples and the CPU time of each stack, others the
+    /// number of calls and the wall time.
+    pub fn to_pprof(&self) -> Vec<u8> {
+        let data = self.data.lock().unwrap();
+        let names: Vec<String> = data.functions.iter().map(|f| f.name.clone()).collect();
+        let (sample_types, period) = if data.sampled {
+            let interval = data.interval.unwrap_or_default();
+            (
+                [("samples", "count"), ("cpu", "nanoseconds")],
+                Some((("cpu", "nanoseconds"), interval.as_nanos() as i64)),
+            )
+        } else {
+            ([("calls", "count"), ("wall", "nanoseconds")], None)
+        };
+        Pprof {
+            sample_types,
+            period,
+            functions: &names,
+            samples: data
+                .stacks
+                .iter()
+                .map(|(stack, &(count, time))| (&stack[..], [count as i64, time.as_nanos() as i64]))
+                .collect(),
+        }
+        .encode()
+    }
+}
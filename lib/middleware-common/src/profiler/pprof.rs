@@ -0,0 +1,173 @@
+//! Encodes profiles in the protocol buffer format of pprof, described by
+//! https://github.com/google/pprof/blob/master/proto/profile.proto.
+
+use std::collections::HashMap;
+
+/// A profile to encode, with `(type, unit)` names for each of the values of the samples.
+pub(super) struct Pprof<'a> {
+    pub(super) sample_types: [(&'a str, &'a str); 2],
+    /// The sampling period as a `(type, unit)` name and a value, if the profile was sampled.
+    pub(super) period: Option<((&'a str, &'a str), i64)>,
+    pub(super) functions: &'a [String],
+    /// The stacks, from the root to the leaf, as indexes into `functions`, with their values.
+    pub(super) samples: Vec<(&'a [usize], [i64; 2])>,
+}
+
+impl<'a> Pprof<'a> {
+    pub(super) fn encode(&self) -> Vec<u8> {
+        let mut strings = StringTable::default();
+        let mut profile = Message::default();
+
+        for &(ty, unit) in &self.sample_types {
+            profile.message(1, &value_type(&mut strings, ty, unit));
+        }
+        for (stack, values) in &self.samples {
+            // Locations are listed from the leaf, and numbered from 1.
+            let locations: Vec<u64> = stack.iter().rev().map(|&f| f as u64 + 1).collect();
+            let mut sample = Message::default();
+            sample.packed(1, locations.iter().cloned());
+            sample.packed(2, values.iter().map(|&value| value as u64));
+            profile.message(2, &sample);
+        }
+        // Each function gets a location of its own, with the same id.
+        for id in 1..=self.functions.len() as u64 {
+            let mut line = Message::default();
+            line.uint(1, id);
+            let mut location = Message::default();
+            location.uint(1, id);
+            location.message(4, &line);
+            profile.message(4, &location);
+        }
+        for (id, name) in self.functions.iter().enumerate() {
+            let name = strings.index(name);
+            let mut function = Message::default();
+            function.uint(1, id as u64 + 1);
+            function.uint(2, name);
+            function.uint(3, name);
+            profile.message(5, &function);
+        }
+        if let Some(((ty, unit), period)) = self.period {
+            profile.message(11, &value_type(&mut strings, ty, unit));
+            profile.uint(12, period as u64);
+        }
+        for s in &strings.strings {
+            profile.bytes(6, s.as_bytes());
+        }
+        profile.data
+    }
+}
+
+fn value_type(strings: &mut StringTable, ty: &str, unit: &str) -> Message {
+    let mut value_type = Message::default();
+    value_type.uint(1, strings.index(ty));
+    value_type.uint(2, strings.index(unit));
+    value_type
+}
+
+/// The strings of a profile, which start with the empty string.
+struct StringTable {
+    strings: Vec<String>,
+    indexes: HashMap<String, u64>,
+}
+
+impl Default for StringTable {
+    fn default() -> StringTable {
+        let mut table = StringTable {
+            strings: vec![],
+            indexes: HashMap::new(),
+        };
+        table.index("");
+        table
+    }
+}
+
+impl StringTable {
+    fn index(&mut self, s: &str) -> u64 {
+        if let Some(&index) = self.indexes.get(s) {
+            return index;
+        }
+        let index = self.strings.len() as u64;
+        self.strings.push(s.to_string());
+        self.indexes.insert(s.to_string(), index);
+        index
+    }
+}
+
+/// An encoded protocol buffer message.
+#[derive(Default)]
+struct Message {
+    data: Vec<u8>,
+}
+
+impl Message {
+    fn varint(&mut self, mut n: u64) {
+        while n >= 0x80 {
+            self.data.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+        self.data.push(n as u8);
+    }
+
+    fn uint(&mut self, field: u32, value: u64) {
+        if value != 0 {
+            self.varint(u64::from(field) << 3);
+            self.varint(value);
+        }
+    }
+
+    fn bytes(&mut self, field: u32, bytes: &[u8]) {
+        self.varint(u64::from(field) << 3 | 2);
+        self.varint(bytes.len() as u64);
+        self.data.extend_from_slice(bytes);
+    }
+
+    fn message(&mut self, field: u32, message: &Message) {
+        self.bytes(field, &message.data);
+    }
+
+    fn packed<I: Iterator<Item = u64>>(&mut self, field: u32, values: I) {
+        let mut packed = Message::default();
+        for value in values {
+            packed.varint(value);
+        }
+        self.bytes(field, &packed.data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pprof;
+
+    #[test]
+    fn encodes_profile() {
+        let functions = vec!["main".to_string(), "work".to_string()];
+        let stack = [0, 1];
+        let pprof = Pprof {
+            sample_types: [("samples", "count"), ("cpu", "nanoseconds")],
+            period: Some((("cpu", "nanoseconds"), 1000)),
+            functions: &functions,
+            samples: vec![(&stack[..], [3, 3000])],
+        };
+
+        let mut expected = vec![];
+        // sample_type { type: 1 unit: 2 }, sample_type { type: 3 unit: 4 }
+        expected.extend_from_slice(&[0x0a, 4, 0x08, 1, 0x10, 2]);
+        expected.extend_from_slice(&[0x0a, 4, 0x08, 3, 0x10, 4]);
+        // sample { location_id: [2, 1] value: [3, 3000] }
+        expected.extend_from_slice(&[0x12, 9, 0x0a, 2, 2, 1, 0x12, 3, 3, 0xb8, 0x17]);
+        // location { id: 1 line { function_id: 1 } }, and the same for 2
+        expected.extend_from_slice(&[0x22, 6, 0x08, 1, 0x22, 2, 0x08, 1]);
+        expected.extend_from_slice(&[0x22, 6, 0x08, 2, 0x22, 2, 0x08, 2]);
+        // function { id: 1 name: 5 system_name: 5 }, and the same for 2 with name 6
+        expected.extend_from_slice(&[0x2a, 6, 0x08, 1, 0x10, 5, 0x18, 5]);
+        expected.extend_from_slice(&[0x2a, 6, 0x08, 2, 0x10, 6, 0x18, 6]);
+        // period_type { type: 3 unit: 4 } period: 1000
+        expected.extend_from_slice(&[0x5a, 4, 0x08, 3, 0x10, 4, 0x60, 0xe8, 0x07]);
+        for s in &["", "samples", "count", "cpu", "nanoseconds", "main", "work"] {
+            expected.push(0x32);
+            expected.push(s.len() as u8);
+            expected.extend_from_slice(s.as_bytes());
+        }
+        assert_eq!(pprof.encode(), expected);
+    }
+}
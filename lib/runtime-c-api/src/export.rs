@@ -477,6 +477,7 @@ pub unsafe extern "C" fn wasmer_export_func_call(
                         value: wasmer_value { F64: x },
                     },
                     Value::V128(_) => unimplemented!("returning V128 type"),
+                    Value::AnyRef(_) => unimplemented!("returning AnyRef type"),
                 };
                 results[0] = ret;
             }
@@ -218,6 +218,7 @@ pub unsafe extern "C" fn wasmer_instance_call(
                         value: wasmer_value { F64: x },
                     },
                     Value::V128(_) => unimplemented!("calling function with V128 parameter"),
+                    Value::AnyRef(_) => unimplemented!("calling function with AnyRef parameter"),
                 };
                 results[0] = ret;
             }
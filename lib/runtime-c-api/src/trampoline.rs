@@ -3,6 +3,7 @@
 use std::ffi::c_void;
 use std::mem;
 use wasmer_runtime_core::trampoline::*;
+use wasmer_runtime_core::types::Type;
 
 #[repr(C)]
 pub struct wasmer_trampoline_buffer_builder_t;
@@ -43,7 +44,11 @@ pub unsafe extern "C" fn wasmer_trampoline_buffer_builder_add_callinfo_trampolin
     num_params: u32,
 ) -> usize {
     let builder = &mut *(builder as *mut TrampolineBufferBuilder);
-    builder.add_callinfo_trampoline(mem::transmute(func), ctx as *const CallContext, num_params)
+    builder.add_callinfo_trampoline(
+        mem::transmute(func),
+        ctx as *const CallContext,
+        &vec![Type::I64; num_params as usize],
+    )
 }
 
 /// Finalizes the trampoline builder into an executable buffer.
@@ -77,6 +77,7 @@ impl From<Value> for wasmer_value_t {
                 value: wasmer_value { F64: x },
             },
             Value::V128(_) => unimplemented!("V128 not supported in C API"),
+            Value::AnyRef(_) => unimplemented!("AnyRef not supported in C API"),
         }
     }
 }
@@ -90,6 +91,7 @@ impl From<Type> for wasmer_value_tag {
             Type::F32 => wasmer_value_tag::WASM_F32,
             Type::F64 => wasmer_value_tag::WASM_F64,
             Type::V128 => unreachable!("V128 not supported in C API"),
+            Type::AnyRef | Type::FuncRef => unreachable!("AnyRef not supported in C API"),
         }
     }
 }
@@ -115,6 +117,7 @@ impl From<&wasmer_runtime::wasm::Type> for wasmer_value_tag {
             Type::F32 => wasmer_value_tag::WASM_F32,
             Type::F64 => wasmer_value_tag::WASM_F64,
             Type::V128 => unimplemented!("V128 not supported in C API"),
+            Type::AnyRef | Type::FuncRef => unimplemented!("AnyRef not supported in C API"),
         }
     }
 }
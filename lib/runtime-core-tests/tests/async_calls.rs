@@ -0,0 +1,151 @@
+#![cfg(all(unix, target_arch = "x86_64"))]
+
+use std::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    ptr,
+    rc::Rc,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+    thread,
+};
+use wasmer_runtime_core::{
+    compile_with,
+    fiber::{self, WaitError},
+    func, imports,
+    types::Value,
+    vm,
+};
+use wasmer_runtime_core_tests::{get_compiler, wat2wasm};
+
+const MODULE: &str = r#"
+(module
+  (import "env" "sleep" (func $sleep (param i32) (result i32)))
+  (func (export "run") (param i32) (result i32)
+    (i32.add (call $sleep (local.get 0)) (i32.const 1))))
+"#;
+
+/// A future that stays pending for a number of polls.
+struct Sleep(i32);
+
+impl Future for Sleep {
+    type Output = i32;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<i32> {
+        if self.0 == 0 {
+            Poll::Ready(42)
+        } else {
+            self.0 -= 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+/// Sets its flag when dropped by an unwinding.
+struct UnwindFlag(Rc<Cell<bool>>);
+
+impl Drop for UnwindFlag {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.0.set(true);
+        }
+    }
+}
+
+fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+    unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) }
+}
+
+fn instantiate(unwound: Rc<Cell<bool>>) -> wasmer_runtime_core::Instance {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let import_object = imports! {
+        "env" => {
+            "sleep" => func!(move |_ctx: &mut vm::Ctx, polls: i32| -> Result<i32, WaitError> {
+                let _flag = UnwindFlag(Rc::clone(&unwound));
+                fiber::wait(Sleep(polls))
+            }),
+        },
+    };
+    module.instantiate(&import_object).unwrap()
+}
+
+#[test]
+fn async_call_suspends_while_waiting() {
+    let mut instance = instantiate(Rc::new(Cell::new(false)));
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let mut call = instance.call_async("run", &[Value::I32(3)]);
+    let mut pending = 0;
+    let result = loop {
+        match Pin::new(&mut call).poll(&mut cx) {
+            Poll::Ready(result) => break result,
+            Poll::Pending => pending += 1,
+        }
+    };
+
+    assert_eq!(pending, 3);
+    assert_eq!(result.unwrap(), vec![Value::I32(43)]);
+}
+
+#[test]
+fn async_calls_run_concurrently_on_one_thread() {
+    let mut slow = instantiate(Rc::new(Cell::new(false)));
+    let mut fast = instantiate(Rc::new(Cell::new(false)));
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    // Poll each pending call in turn, like a single-threaded executor does.
+    let mut calls = vec![
+        (0, slow.call_async("run", &[Value::I32(3)])),
+        (1, fast.call_async("run", &[Value::I32(1)])),
+    ];
+    let mut finished = vec![];
+    while !calls.is_empty() {
+        let mut i = 0;
+        while i < calls.len() {
+            match Pin::new(&mut calls[i].1).poll(&mut cx) {
+                Poll::Ready(result) => {
+                    assert_eq!(result.unwrap(), vec![Value::I32(43)]);
+                    finished.push(calls.remove(i).0);
+                }
+                Poll::Pending => i += 1,
+            }
+        }
+    }
+
+    // The fast call completed while the slow one was suspended.
+    assert_eq!(finished, vec![1, 0]);
+}
+
+#[test]
+fn dropped_async_call_is_cancelled() {
+    let unwound = Rc::new(Cell::new(false));
+    let mut instance = instantiate(Rc::clone(&unwound));
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let mut call = instance.call_async("run", &[Value::I32(3)]);
+    assert!(Pin::new(&mut call).poll(&mut cx).is_pending());
+    drop(call);
+    // The host function was unwound from the `wait` it was suspended in.
+    assert!(unwound.get());
+
+    // The instance is still usable once the call unwound.
+    let result = instance.call("run", &[Value::I32(0)]).unwrap();
+    assert_eq!(result, vec![Value::I32(43)]);
+}
+
+#[test]
+fn wait_outside_async_call_fails() {
+    let instance = instantiate(Rc::new(Cell::new(false)));
+    assert!(instance.call("run", &[Value::I32(1)]).is_err());
+}
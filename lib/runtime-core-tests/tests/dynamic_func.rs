@@ -0,0 +1,165 @@
+#![cfg(all(unix, target_arch = "x86_64"))]
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use wasmer_runtime_core::{
+    compile_with,
+    error::RuntimeError,
+    import::Namespace,
+    imports,
+    typed_func::{DynamicFunc, Func},
+    types::{FuncSig, Type, Value},
+    vm,
+};
+use wasmer_runtime_core_tests::{get_compiler, wat2wasm};
+
+const MODULE: &str = r#"
+(module
+  (import "env" "sum" (func $sum (param i32 f64 i64 f32) (result f64)))
+  (import "env" "fail" (func $fail))
+  (func (export "call_sum") (result f64)
+    (call $sum (i32.const 1) (f64.const 2.5) (i64.const 3) (f32.const 4.5)))
+  (func (export "call_fail")
+    (call $fail)))
+"#;
+
+fn sum(_: &mut vm::Ctx, params: &[Value]) -> Result<Vec<Value>, RuntimeError> {
+    let sum = params
+        .iter()
+        .map(|param| match *param {
+            Value::I32(x) => x as f64,
+            Value::I64(x) => x as f64,
+            Value::F32(x) => x as f64,
+            Value::F64(x) => x,
+            _ => unreachable!(),
+        })
+        .sum();
+    Ok(vec![Value::F64(sum)])
+}
+
+#[test]
+fn dynamic_func_is_called() {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let sum_sig = FuncSig::new(
+        vec![Type::I32, Type::F64, Type::I64, Type::F32],
+        vec![Type::F64],
+    );
+    let fail_sig = FuncSig::new(vec![], vec![]);
+    let import_object = imports! {
+        "env" => {
+            "sum" => DynamicFunc::new(Arc::new(sum_sig), sum),
+            "fail" => DynamicFunc::new(Arc::new(fail_sig), |_, _| {
+                Err(RuntimeError::Error { data: Box::new("fail") })
+            }),
+        },
+    };
+    let instance = module.instantiate(&import_object).unwrap();
+
+    let call_sum: Func<(), f64> = instance.func("call_sum").unwrap();
+    assert_eq!(call_sum.call().unwrap(), 11.0);
+
+    let call_fail: Func<(), ()> = instance.func("call_fail").unwrap();
+    match call_fail.call() {
+        Err(RuntimeError::Error { data }) => assert_eq!(data.downcast_ref(), Some(&"fail")),
+        _ => panic!("expected the error of the dynamic function"),
+    }
+}
+
+#[test]
+fn dynamic_func_checks_results() {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let sum_sig = FuncSig::new(
+        vec![Type::I32, Type::F64, Type::I64, Type::F32],
+        vec![Type::F64],
+    );
+    let import_object = imports! {
+        "env" => {
+            "sum" => DynamicFunc::new(Arc::new(sum_sig), |_, _| Ok(vec![Value::I32(0)])),
+            "fail" => DynamicFunc::new(Arc::new(FuncSig::new(vec![], vec![])), |_, _| Ok(vec![])),
+        },
+    };
+    let instance = module.instantiate(&import_object).unwrap();
+
+    let call_sum: Func<(), f64> = instance.func("call_sum").unwrap();
+    assert!(call_sum.call().is_err());
+}
+
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+static DROPPED_AFTER_REPLACING: AtomicUsize = AtomicUsize::new(0);
+
+struct DropCounter(&'static AtomicUsize);
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn dynamic_func_is_freed_with_its_instances() {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let sum_sig = FuncSig::new(
+        vec![Type::I32, Type::F64, Type::I64, Type::F32],
+        vec![Type::F64],
+    );
+    let counter = DropCounter(&DROPPED);
+    let import_object = imports! {
+        "env" => {
+            "sum" => DynamicFunc::new(Arc::new(sum_sig), move |ctx, params| {
+                let _ = &counter;
+                sum(ctx, params)
+            }),
+            "fail" => DynamicFunc::new(Arc::new(FuncSig::new(vec![], vec![])), |_, _| Ok(vec![])),
+        },
+    };
+    let instance = module.instantiate(&import_object).unwrap();
+
+    // The instance keeps the functions of its imports alive.
+    drop(import_object);
+    {
+        let call_sum: Func<(), f64> = instance.func("call_sum").unwrap();
+        assert_eq!(call_sum.call().unwrap(), 11.0);
+    }
+    assert_eq!(DROPPED.load(Ordering::SeqCst), 0);
+
+    drop(instance);
+    assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn dynamic_func_outlives_its_namespace() {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let sum_sig = FuncSig::new(
+        vec![Type::I32, Type::F64, Type::I64, Type::F32],
+        vec![Type::F64],
+    );
+    let counter = DropCounter(&DROPPED_AFTER_REPLACING);
+    let mut import_object = imports! {
+        "env" => {
+            "sum" => DynamicFunc::new(Arc::new(sum_sig), move |ctx, params| {
+                let _ = &counter;
+                sum(ctx, params)
+            }),
+            "fail" => DynamicFunc::new(Arc::new(FuncSig::new(vec![], vec![])), |_, _| Ok(vec![])),
+        },
+    };
+    let instance = module.instantiate(&import_object).unwrap();
+
+    // The instance shares the import object, whose namespace is dropped here. The instance
+    // holds on to the functions it imported.
+    drop(import_object.register("env", Namespace::new()));
+    {
+        let call_sum: Func<(), f64> = instance.func("call_sum").unwrap();
+        assert_eq!(call_sum.call().unwrap(), 11.0);
+    }
+    assert_eq!(DROPPED_AFTER_REPLACING.load(Ordering::SeqCst), 0);
+
+    drop(instance);
+    assert_eq!(DROPPED_AFTER_REPLACING.load(Ordering::SeqCst), 1);
+}
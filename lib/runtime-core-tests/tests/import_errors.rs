@@ -0,0 +1,55 @@
+//! Checks that failing to link an instance frees what linking allocated. This is the only
+//! test of its binary, so that the allocations it counts are its own.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicIsize, Ordering},
+};
+use wasmer_runtime_core::{compile_with, func, imports, vm};
+use wasmer_runtime_core_tests::{get_compiler, wat2wasm};
+
+struct CountingAllocator;
+
+static LIVE: AtomicIsize = AtomicIsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(1, Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const MODULE: &str = r#"
+(module
+  (import "env" "found" (func))
+  (import "env" "missing" (func)))
+"#;
+
+fn found(_: &mut vm::Ctx) {}
+
+#[test]
+fn failed_link_frees_imported_functions() {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let import_object = imports! {
+        "env" => {
+            "found" => func!(found),
+        },
+    };
+
+    // The first attempt fills the caches that live as long as the process.
+    assert!(module.instantiate(&import_object).is_err());
+    let live = LIVE.load(Ordering::SeqCst);
+    for _ in 0..10 {
+        assert!(module.instantiate(&import_object).is_err());
+    }
+    assert_eq!(LIVE.load(Ordering::SeqCst), live);
+}
@@ -1,6 +1,15 @@
+use std::sync::{Arc, Mutex};
 use wasmer_runtime_core::{
-    compile_with, error::RuntimeError, imports, memory::Memory, typed_func::Func,
-    types::MemoryDescriptor, units::Pages, vm, Instance,
+    compile_with,
+    error::{Error, RuntimeError},
+    global::Global,
+    import::{ImportType, IsExport},
+    imports,
+    memory::Memory,
+    typed_func::Func,
+    types::{MemoryDescriptor, Value},
+    units::Pages,
+    vm, Instance,
 };
 use wasmer_runtime_core_tests::{get_compiler, wat2wasm};
 
@@ -292,3 +301,59 @@ test!(
         data: Box::new(format!("! {}", 2 + shift + SHIFT))
     })
 );
+
+#[test]
+fn import_resolver() {
+    static WAT: &'static str = r#"
+        (module
+          (import "env" "stub" (func $stub (param i32) (result i32)))
+          (import "env" "memory" (memory 1))
+          (import "env" "global" (global i32))
+          (func (export "call_stub") (param i32) (result i32)
+            get_local 0
+            call $stub))
+    "#;
+    let wasm_binary = wat2wasm(WAT.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+
+    let missing = Arc::new(Mutex::new(vec![]));
+    let mut import_object = imports! {};
+    {
+        let missing = Arc::clone(&missing);
+        import_object.set_import_resolver(move |namespace, name, import_type| {
+            missing
+                .lock()
+                .unwrap()
+                .push(format!("{}.{}", namespace, name));
+            match import_type {
+                ImportType::Function(_) => Some(Func::new(|n: i32| n * 2).to_export()),
+                _ => None,
+            }
+        });
+    }
+
+    // The memory and global are reported together.
+    match module.instantiate(&import_object) {
+        Err(Error::LinkError(errors)) => assert_eq!(errors.len(), 2),
+        _ => panic!("expected the memory and global to be missing"),
+    }
+    assert_eq!(
+        *missing.lock().unwrap(),
+        vec!["env.stub", "env.memory", "env.global"]
+    );
+
+    let memory_descriptor = MemoryDescriptor::new(Pages(1), None, false).unwrap();
+    let mut import_object = imports! {
+        "env" => {
+            "memory" => Memory::new(memory_descriptor).unwrap(),
+            "global" => Global::new(Value::I32(0)),
+        },
+    };
+    import_object.set_import_resolver(|_, _, import_type| match import_type {
+        ImportType::Function(_) => Some(Func::new(|n: i32| n * 2).to_export()),
+        _ => None,
+    });
+    let instance = module.instantiate(&import_object).unwrap();
+    let call_stub: Func<i32, i32> = instance.func("call_stub").unwrap();
+    assert_eq!(call_stub.call(21), Ok(42));
+}
@@ -0,0 +1,56 @@
+use wasmer_runtime_core::{
+    compile_with,
+    error::ResolveError,
+    imports,
+    table::Element,
+    types::{FuncSig, Type, Value},
+};
+use wasmer_runtime_core_tests::{get_compiler, wat2wasm};
+
+static WAT: &'static str = r#"
+    (module
+      (type $t (func (param i32) (result i32)))
+      (memory (export "data") 2)
+      (global (export "answer") i32 (i32.const 42))
+      (table (export "funcs") 2 anyfunc)
+      (func $double (type $t)
+        get_local 0
+        i32.const 2
+        i32.mul)
+      (elem (i32.const 0) $double)
+      (export "double" (func $double)))
+"#;
+
+#[test]
+fn typed_export_accessors() {
+    let wasm_binary = wat2wasm(WAT.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let instance = module.instantiate(&imports! {}).unwrap();
+
+    assert_eq!(instance.global("answer").unwrap().get(), Value::I32(42));
+    assert_eq!(instance.memory("data").unwrap().size().0, 2);
+
+    let funcs = instance.table("funcs").unwrap();
+    match funcs.get(0) {
+        Some(Element::Anyfunc(anyfunc)) => {
+            assert!(!anyfunc.is_null());
+            let signature = FuncSig::new(vec![Type::I32], vec![Type::I32]);
+            assert_eq!(*anyfunc.signature().unwrap(), signature);
+        }
+        _ => panic!("expected a function in the table"),
+    }
+    match funcs.get(1) {
+        Some(Element::Anyfunc(anyfunc)) => assert!(anyfunc.is_null()),
+        _ => panic!("expected a null function in the table"),
+    }
+    assert!(funcs.get(2).is_none());
+
+    match instance.global("double") {
+        Err(ResolveError::ExportWrongType { name }) => assert_eq!(name, "double"),
+        _ => panic!("expected a function, not a global"),
+    }
+    match instance.memory("missing") {
+        Err(ResolveError::ExportNotFound { name }) => assert_eq!(name, "missing"),
+        _ => panic!("expected no such export"),
+    }
+}
@@ -0,0 +1,97 @@
+use wasmer_runtime_core::{
+    compile_with,
+    module::{ExportDescriptor, ImportDescriptor},
+    parse::{read_module_interface, LoadError},
+    types::{ExternType, FuncSig, GlobalDescriptor, MemoryDescriptor, Type},
+    units::Pages,
+};
+use wasmer_runtime_core_tests::{get_compiler, wat2wasm};
+
+static WAT: &'static str = r#"
+    (module
+      (import "env" "log" (func $log (param i32 i64)))
+      (import "env" "memory" (memory 1 2))
+      (global $counter i32 (i32.const 7))
+      (table 3 anyfunc)
+      (func $add (param i32 i32) (result i32)
+        get_local 0
+        get_local 1
+        i32.add)
+      (export "add" (func $add))
+      (export "log" (func $log))
+      (export "counter" (global $counter))
+      (export "memory" (memory 0)))
+"#;
+
+#[test]
+fn module_imports_and_exports() {
+    let wasm_binary = wat2wasm(WAT.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+
+    let memory = MemoryDescriptor::new(Pages(1), Some(Pages(2)), false).unwrap();
+    let log = FuncSig::new(vec![Type::I32, Type::I64], vec![]);
+    assert_eq!(
+        module.imports(),
+        vec![
+            ImportDescriptor {
+                namespace: "env".to_string(),
+                name: "log".to_string(),
+                ty: ExternType::Function(log.clone()),
+            },
+            ImportDescriptor {
+                namespace: "env".to_string(),
+                name: "memory".to_string(),
+                ty: ExternType::Memory(memory),
+            },
+        ]
+    );
+
+    assert_eq!(
+        module.exports(),
+        vec![
+            ExportDescriptor {
+                name: "add".to_string(),
+                ty: ExternType::Function(FuncSig::new(vec![Type::I32, Type::I32], vec![Type::I32])),
+            },
+            ExportDescriptor {
+                name: "log".to_string(),
+                ty: ExternType::Function(log),
+            },
+            ExportDescriptor {
+                name: "counter".to_string(),
+                ty: ExternType::Global(GlobalDescriptor {
+                    mutable: false,
+                    ty: Type::I32,
+                }),
+            },
+            ExportDescriptor {
+                name: "memory".to_string(),
+                ty: ExternType::Memory(memory),
+            },
+        ]
+    );
+
+    // Reading the interface without compiling gives the same result.
+    let interface = read_module_interface(&wasm_binary).unwrap();
+    assert_eq!(interface.imports, module.imports());
+    assert_eq!(interface.exports, module.exports());
+}
+
+#[test]
+fn interface_rejects_out_of_bounds_indices() {
+    const HEADER: &[u8] = b"\0asm\x01\0\0\0";
+
+    // A function of type 0, without a type section.
+    let wasm_binary = [HEADER, &[3, 2, 1, 0][..]].concat();
+    match read_module_interface(&wasm_binary) {
+        Err(LoadError::Parse(_)) => {}
+        _ => panic!("expected the type index to be rejected"),
+    }
+
+    // An export of function 0, without functions.
+    let wasm_binary = [HEADER, &[7, 5, 1, 1, b'f', 0, 0][..]].concat();
+    match read_module_interface(&wasm_binary) {
+        Err(LoadError::Parse(_)) => {}
+        _ => panic!("expected the export index to be rejected"),
+    }
+}
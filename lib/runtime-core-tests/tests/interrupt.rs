@@ -0,0 +1,76 @@
+#![cfg(unix)]
+
+use std::time::Duration;
+use wasmer_runtime_core::{
+    compile_with,
+    error::{CallError, InterruptHandleError, RuntimeError},
+    imports,
+    interrupt::InterruptHandle,
+    types::Value,
+    Instance,
+};
+use wasmer_runtime_core_tests::{get_compiler, wat2wasm};
+
+const MODULE: &str = r#"
+(module
+  (func (export "spin")
+    (loop $continue
+      (br $continue)))
+  (func (export "answer") (result i32)
+    (i32.const 42)))
+"#;
+
+/// Returns the interrupt handle of `instance`, or `None` on the backends that
+/// do not support interrupts, after checking that they say so.
+fn interrupt_handle(instance: &Instance) -> Option<InterruptHandle> {
+    let handle = instance.interrupt_handle();
+    if cfg!(feature = "backend-singlepass") {
+        Some(handle.unwrap())
+    } else {
+        assert_eq!(handle.err(), Some(InterruptHandleError::Unsupported));
+        None
+    }
+}
+
+fn assert_interrupted<T: std::fmt::Debug>(result: Result<T, CallError>) {
+    match result {
+        Err(CallError::Runtime(RuntimeError::Interrupted)) => {}
+        other => panic!("expected an interrupt, got {:?}", other),
+    }
+}
+
+#[test]
+fn deadline_interrupts_a_running_instance() {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let instance = module.instantiate(&imports! {}).unwrap();
+    let other = module.instantiate(&imports! {}).unwrap();
+
+    let handle = match interrupt_handle(&instance) {
+        Some(handle) => handle,
+        None => return,
+    };
+    handle.set_timeout(Duration::from_millis(10));
+    assert_interrupted(instance.call("spin", &[]));
+
+    // The interrupt is consumed, and other instances are not affected.
+    assert_eq!(instance.call("answer", &[]).unwrap(), vec![Value::I32(42)]);
+    assert_eq!(other.call("answer", &[]).unwrap(), vec![Value::I32(42)]);
+}
+
+#[test]
+fn interrupt_before_a_call_stops_it() {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let instance = module.instantiate(&imports! {}).unwrap();
+
+    let handle = match interrupt_handle(&instance) {
+        Some(handle) => handle,
+        None => return,
+    };
+    handle.set_timeout(Duration::from_secs(3600));
+    handle.clear_deadline();
+    handle.interrupt();
+    assert_interrupted(instance.call("answer", &[]));
+    assert_eq!(instance.call("answer", &[]).unwrap(), vec![Value::I32(42)]);
+}
@@ -0,0 +1,75 @@
+use std::sync::{Arc, Mutex};
+use wasmer_runtime_core::{
+    compile_with, imports, limits::ResourceLimiter, typed_func::Func, units::Pages,
+};
+use wasmer_runtime_core_tests::{get_compiler, wat2wasm};
+
+const MODULE: &str = r#"
+(module
+  (memory 1)
+  (func (export "grow") (param i32) (result i32)
+    (memory.grow (local.get 0))))
+"#;
+
+struct Budget {
+    pages: Pages,
+    instances: Mutex<usize>,
+}
+
+impl ResourceLimiter for Budget {
+    fn memory_growing(&self, _current: Pages, desired: Pages) -> bool {
+        desired <= self.pages
+    }
+
+    fn instance_creating(&self) -> bool {
+        let mut instances = self.instances.lock().unwrap();
+        if *instances == 1 {
+            return false;
+        }
+        *instances += 1;
+        true
+    }
+
+    fn instance_dropped(&self) {
+        *self.instances.lock().unwrap() -= 1;
+    }
+}
+
+fn budget(pages: u32) -> Arc<Budget> {
+    Arc::new(Budget {
+        pages: Pages(pages),
+        instances: Mutex::new(0),
+    })
+}
+
+#[test]
+fn limiter_denies_memory_growth() {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let mut import_object = imports! {};
+    import_object.set_resource_limiter(budget(2));
+    let instance = module.instantiate(&import_object).unwrap();
+
+    let grow: Func<i32, i32> = instance.func("grow").unwrap();
+    assert_eq!(grow.call(1).unwrap(), 1);
+    assert_eq!(grow.call(1).unwrap(), -1);
+    assert_eq!(grow.call(0).unwrap(), 2);
+}
+
+#[test]
+fn limiter_denies_instances() {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let mut import_object = imports! {};
+    import_object.set_resource_limiter(budget(1));
+
+    let instance = module.instantiate(&import_object).unwrap();
+    assert!(module.instantiate(&import_object).is_err());
+    drop(instance);
+    assert!(module.instantiate(&import_object).is_ok());
+
+    // The minimum of a memory counts against the budget too.
+    let mut import_object = imports! {};
+    import_object.set_resource_limiter(budget(0));
+    assert!(module.instantiate(&import_object).is_err());
+}
@@ -0,0 +1,91 @@
+use wabt::Wat2Wasm;
+use wasmer_runtime_core::{compile_with, types::FuncIndex};
+use wasmer_runtime_core_tests::get_compiler;
+
+const MODULE: &str = r#"
+(module
+  (import "env" "log" (func $log (param i32)))
+  (func $add (export "add") (param $lhs i32) (param $rhs i32) (result i32)
+    (local $sum i32)
+    (local.set $sum (i32.add (local.get $lhs) (local.get $rhs)))
+    (local.get $sum))
+  (func (export "anonymous")))
+"#;
+
+#[test]
+fn name_section_is_decoded() {
+    let wasm_binary = Wat2Wasm::new()
+        .write_debug_names(true)
+        .convert(MODULE)
+        .expect("WAST not valid or malformed");
+    let module = compile_with(wasm_binary.as_ref(), &get_compiler()).unwrap();
+    let info = module.info();
+
+    assert_eq!(info.func_name(FuncIndex::new(0)), Some("log"));
+    assert_eq!(info.func_name(FuncIndex::new(1)), Some("add"));
+    assert_eq!(info.func_name(FuncIndex::new(2)), None);
+
+    assert_eq!(info.local_name(FuncIndex::new(1), 0), Some("lhs"));
+    assert_eq!(info.local_name(FuncIndex::new(1), 1), Some("rhs"));
+    assert_eq!(info.local_name(FuncIndex::new(1), 2), Some("sum"));
+}
+
+#[test]
+fn malformed_name_section_is_ignored() {
+    let mut wasm_binary = Wat2Wasm::new()
+        .write_debug_names(true)
+        .convert(MODULE)
+        .expect("WAST not valid or malformed")
+        .as_ref()
+        .to_vec();
+    // A `name` section whose function names subsection claims more bytes than it has.
+    wasm_binary.extend_from_slice(&[0, 8, 4, b'n', b'a', b'm', b'e', 1, 16, 1]);
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let info = module.info();
+
+    assert_eq!(info.func_name(FuncIndex::new(1)), None);
+    assert_eq!(info.local_name(FuncIndex::new(1), 0), None);
+}
+
+/// Returns `wasm_binary` with `section` inserted before its first section.
+fn with_first_section(wasm_binary: &[u8], section: &[u8]) -> Vec<u8> {
+    let mut wasm = wasm_binary[..8].to_vec();
+    wasm.extend_from_slice(section);
+    wasm.extend_from_slice(&wasm_binary[8..]);
+    wasm
+}
+
+#[test]
+fn early_name_section_is_decoded() {
+    let wasm_binary = Wat2Wasm::new()
+        .convert(MODULE)
+        .expect("WAST not valid or malformed");
+    // A `name` section naming function 1 `add`, ahead of the type section.
+    let section = [
+        0, 13, 4, b'n', b'a', b'm', b'e', 1, 6, 1, 1, 3, b'a', b'd', b'd',
+    ];
+    let wasm_binary = with_first_section(wasm_binary.as_ref(), &section);
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let info = module.info();
+
+    assert_eq!(info.func_name(FuncIndex::new(1)), Some("add"));
+    assert_eq!(info.func_name(FuncIndex::new(0)), None);
+}
+
+#[test]
+fn early_malformed_name_section_is_skipped() {
+    let wasm_binary = Wat2Wasm::new()
+        .write_debug_names(true)
+        .convert(MODULE)
+        .expect("WAST not valid or malformed");
+    let section = [0, 8, 4, b'n', b'a', b'm', b'e', 1, 16, 1];
+    let wasm_binary = with_first_section(wasm_binary.as_ref(), &section);
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let info = module.info();
+
+    // The sections after the malformed one are all read.
+    assert!(info.exports.contains_key("add"));
+    assert!(info.exports.contains_key("anonymous"));
+    assert_eq!(info.func_assoc.len(), 3);
+    assert_eq!(info.func_name(FuncIndex::new(1)), None);
+}
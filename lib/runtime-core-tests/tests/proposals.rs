@@ -0,0 +1,150 @@
+//! Runs the `.wast` scripts in `tests/proposals`, which cover WebAssembly proposals that
+//! the upstream spec tests copied into `lib/spectests` don't include yet.
+
+use wabt::script::{Action, CommandKind, ScriptParser, Value as WabtValue};
+use wasmer_runtime_core::{
+    backend::{CompilerConfig, Features},
+    compile_with_config, imports,
+    types::Value,
+    Instance,
+};
+use wasmer_runtime_core_tests::get_compiler;
+
+fn convert_value(value: WabtValue<f32, f64>) -> Value {
+    match value {
+        WabtValue::I32(v) => Value::I32(v),
+        WabtValue::I64(v) => Value::I64(v),
+        WabtValue::F32(v) => Value::F32(v),
+        WabtValue::F64(v) => Value::F64(v),
+        WabtValue::V128(v) => Value::V128(v),
+    }
+}
+
+/// Returns the bits of a numeric value, so that NaNs compare equal to themselves.
+fn value_bits(value: &Value) -> Option<u128> {
+    match *value {
+        Value::I32(v) => Some(v as u32 as u128),
+        Value::I64(v) => Some(v as u64 as u128),
+        Value::F32(v) => Some(v.to_bits() as u128),
+        Value::F64(v) => Some(v.to_bits() as u128),
+        Value::V128(v) => Some(v),
+        _ => None,
+    }
+}
+
+fn run_wast(filename: &str, source: &str) {
+    let mut features = wabt::Features::new();
+    features.enable_threads();
+    features.enable_bulk_memory();
+    features.enable_reference_types();
+    features.enable_multi_value();
+    let mut parser = ScriptParser::<f32, f64>::from_source_and_name_with_features(
+        source.as_bytes(),
+        filename,
+        features,
+    )
+    .unwrap();
+    let config = || CompilerConfig {
+        features: Features {
+            threads: true,
+            bulk_memory: true,
+            reference_types: true,
+            multi_value: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let mut instance: Option<Instance> = None;
+    let invoke = |instance: &Option<Instance>, action: Action<f32, f64>, line: u64| match action {
+        Action::Invoke { field, args, .. } => {
+            let args: Vec<Value> = args.into_iter().map(convert_value).collect();
+            let instance = instance
+                .as_ref()
+                .unwrap_or_else(|| panic!("{}:{}: no module to invoke", filename, line));
+            instance.call(&field, &args)
+        }
+        Action::Get { .. } => panic!("{}:{}: `get` is not supported", filename, line),
+    };
+
+    while let Some(command) = parser.next().unwrap() {
+        let line = command.line;
+        match command.kind {
+            CommandKind::Module { module, .. } => {
+                let module = compile_with_config(&module.into_vec(), &get_compiler(), config())
+                    .unwrap_or_else(|e| panic!("{}:{}: {:?}", filename, line, e));
+                instance = Some(
+                    module
+                        .instantiate(&imports! {})
+                        .unwrap_or_else(|e| panic!("{}:{}: {:?}", filename, line, e)),
+                );
+            }
+            CommandKind::AssertReturn { action, expected } => {
+                let results = invoke(&instance, action, line)
+                    .unwrap_or_else(|e| panic!("{}:{}: {:?}", filename, line, e));
+                let results: Vec<Option<u128>> = results.iter().map(value_bits).collect();
+                let expected: Vec<Option<u128>> = expected
+                    .into_iter()
+                    .map(|v| value_bits(&convert_value(v)))
+                    .collect();
+                assert_eq!(results, expected, "{}:{}", filename, line);
+            }
+            CommandKind::AssertTrap { action, .. } => {
+                assert!(
+                    invoke(&instance, action, line).is_err(),
+                    "{}:{}: expected a trap",
+                    filename,
+                    line
+                );
+            }
+            CommandKind::AssertInvalid { module, .. } => {
+                let result = compile_with_config(&module.into_vec(), &get_compiler(), config());
+                assert!(
+                    result.is_err(),
+                    "{}:{}: expected an invalid module",
+                    filename,
+                    line
+                );
+            }
+            CommandKind::PerformAction(action) => {
+                invoke(&instance, action, line)
+                    .unwrap_or_else(|e| panic!("{}:{}: {:?}", filename, line, e));
+            }
+            _ => panic!("{}:{}: unsupported command", filename, line),
+        }
+    }
+}
+
+// Threads are not implemented by Cranelift, nor by singlepass on aarch64.
+#[cfg(not(any(
+    feature = "backend-cranelift",
+    all(feature = "backend-singlepass", target_arch = "aarch64")
+)))]
+#[test]
+fn atomic_wait_notify() {
+    run_wast(
+        "atomic_wait_notify.wast",
+        include_str!("proposals/atomic_wait_notify.wast"),
+    );
+}
+
+#[test]
+fn reference_types() {
+    run_wast("ref_types.wast", include_str!("proposals/ref_types.wast"));
+}
+
+#[test]
+fn multi_value() {
+    run_wast(
+        "multi_value.wast",
+        include_str!("proposals/multi_value.wast"),
+    );
+}
+
+#[test]
+fn multi_value_blocks() {
+    run_wast(
+        "multi_value_blocks.wast",
+        include_str!("proposals/multi_value_blocks.wast"),
+    );
+}
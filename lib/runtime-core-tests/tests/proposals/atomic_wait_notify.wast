@@ -0,0 +1,57 @@
+;; Threads: memory.atomic.wait and atomic.notify within a single thread
+(module
+  (memory 1 1 shared)
+
+  (func (export "init") (param i32 i64)
+    (i64.store (local.get 0) (local.get 1)))
+
+  (func (export "notify") (param i32 i32) (result i32)
+    (atomic.notify (local.get 0) (local.get 1)))
+
+  (func (export "notify-offset") (param i32 i32) (result i32)
+    (atomic.notify offset=4 (local.get 0) (local.get 1)))
+
+  (func (export "wait32") (param i32 i32 i64) (result i32)
+    (i32.atomic.wait (local.get 0) (local.get 1) (local.get 2)))
+
+  (func (export "wait64") (param i32 i64 i64) (result i32)
+    (i64.atomic.wait (local.get 0) (local.get 1) (local.get 2)))
+)
+
+(invoke "init" (i32.const 0) (i64.const 0x0000000200000001))
+
+;; Nobody is waiting.
+(assert_return (invoke "notify" (i32.const 0) (i32.const 1)) (i32.const 0))
+(assert_return (invoke "notify-offset" (i32.const 0) (i32.const 1)) (i32.const 0))
+
+;; "not-equal" when the value differs from the expected one.
+(assert_return (invoke "wait32" (i32.const 0) (i32.const 0) (i64.const -1)) (i32.const 1))
+(assert_return (invoke "wait32" (i32.const 4) (i32.const 1) (i64.const -1)) (i32.const 1))
+(assert_return (invoke "wait64" (i32.const 0) (i64.const 0) (i64.const -1)) (i32.const 1))
+
+;; "timed-out" when the value matches but nobody notifies.
+(assert_return (invoke "wait32" (i32.const 0) (i32.const 1) (i64.const 0)) (i32.const 2))
+(assert_return (invoke "wait32" (i32.const 4) (i32.const 2) (i64.const 1000)) (i32.const 2))
+(assert_return (invoke "wait64" (i32.const 0) (i64.const 0x0000000200000001) (i64.const 0)) (i32.const 2))
+
+(assert_trap (invoke "notify" (i32.const 1) (i32.const 1)) "misaligned atomic access")
+(assert_trap (invoke "wait32" (i32.const 2) (i32.const 0) (i64.const 0)) "misaligned atomic access")
+(assert_trap (invoke "wait64" (i32.const 4) (i64.const 0) (i64.const 0)) "misaligned atomic access")
+(assert_trap (invoke "notify" (i32.const 65536) (i32.const 1)) "out of bounds")
+(assert_trap (invoke "wait32" (i32.const 65536) (i32.const 0) (i64.const 0)) "out of bounds")
+(assert_trap (invoke "wait64" (i32.const 65536) (i64.const 0) (i64.const 0)) "out of bounds")
+(assert_return (invoke "wait64" (i32.const 65520) (i64.const 1) (i64.const 0)) (i32.const 1))
+
+;; Waiting on an unshared memory traps, while notifying it wakes nobody.
+(module
+  (memory 1)
+
+  (func (export "notify") (param i32) (result i32)
+    (atomic.notify (local.get 0) (i32.const 1)))
+
+  (func (export "wait32") (param i32) (result i32)
+    (i32.atomic.wait (local.get 0) (i32.const 0) (i64.const 0)))
+)
+
+(assert_return (invoke "notify" (i32.const 0)) (i32.const 0))
+(assert_trap (invoke "wait32" (i32.const 0)) "expected shared memory")
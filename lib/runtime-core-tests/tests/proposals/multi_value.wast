@@ -0,0 +1,64 @@
+;; Multi-value: functions returning several values, and calls to them
+(module
+  (type $pair (func (param i32 i64) (result i64 i32)))
+
+  (table 1 funcref)
+  (elem (i32.const 0) $swap)
+
+  (func $swap (type $pair)
+    (local.get 1) (local.get 0))
+
+  (func (export "swap") (param i32 i64) (result i64 i32)
+    (call $swap (local.get 0) (local.get 1)))
+
+  (func (export "swap-indirect") (param i32 i64) (result i64 i32)
+    (call_indirect (type $pair) (local.get 0) (local.get 1) (i32.const 0)))
+
+  (func $all (result i32 i64 f32 f64)
+    (i32.const 1) (i64.const 2) (f32.const 3.5) (f64.const 4.25))
+
+  (func (export "all") (result i32 i64 f32 f64)
+    (call $all))
+
+  ;; The results of a call are pushed in order.
+  (func (export "swap-sub") (result i64)
+    (call $swap (i32.const 10) (i64.const 3))
+    (i64.extend_i32_s)
+    (i64.sub))
+
+  (func (export "sum-all") (result f64)
+    (local i32 i64 f32 f64)
+    (call $all)
+    (local.set 3) (local.set 2) (local.set 1) (local.set 0)
+    (f64.add
+      (f64.add (f64.convert_i32_s (local.get 0)) (f64.convert_i64_s (local.get 1)))
+      (f64.add (f64.promote_f32 (local.get 2)) (local.get 3))))
+
+  (func (export "return") (param i32) (result i32 i32)
+    (if (local.get 0)
+      (then (return (i32.const 1) (i32.const 2))))
+    (i32.const 3) (i32.const 4))
+
+  (func $many (result i64 i64 i64 i64 i64 i64 i64 i64)
+    (i64.const 1) (i64.const 2) (i64.const 3) (i64.const 4)
+    (i64.const 5) (i64.const 6) (i64.const 7) (i64.const 8))
+
+  (func (export "many-sum") (result i64)
+    (call $many)
+    (i64.add) (i64.add) (i64.add) (i64.add) (i64.add) (i64.add) (i64.add))
+
+  (func (export "many") (result i64 i64 i64 i64 i64 i64 i64 i64)
+    (call $many))
+)
+
+(assert_return (invoke "swap" (i32.const 1) (i64.const 2)) (i64.const 2) (i32.const 1))
+(assert_return (invoke "swap-indirect" (i32.const 1) (i64.const 2)) (i64.const 2) (i32.const 1))
+(assert_return (invoke "all") (i32.const 1) (i64.const 2) (f32.const 3.5) (f64.const 4.25))
+(assert_return (invoke "swap-sub") (i64.const -7))
+(assert_return (invoke "sum-all") (f64.const 10.75))
+(assert_return (invoke "many-sum") (i64.const 36))
+(assert_return (invoke "many")
+  (i64.const 1) (i64.const 2) (i64.const 3) (i64.const 4)
+  (i64.const 5) (i64.const 6) (i64.const 7) (i64.const 8))
+(assert_return (invoke "return" (i32.const 1)) (i32.const 1) (i32.const 2))
+(assert_return (invoke "return" (i32.const 0)) (i32.const 3) (i32.const 4))
@@ -0,0 +1,61 @@
+;; Multi-value: blocks with parameters and several results
+(module
+  (func (export "block") (result i32 i32)
+    (block (result i32 i32)
+      (i32.const 1) (i32.const 2)))
+
+  (func (export "block-param") (param i32) (result i32)
+    (local.get 0)
+    (block (param i32) (result i32)
+      (i32.const 10)
+      (i32.add)))
+
+  (func (export "block-br") (param i32) (result i32 i64)
+    (block (result i32 i64)
+      (i32.const 1) (i64.const 2)
+      (br_if 0 (local.get 0))
+      (drop) (drop)
+      (i32.const 3) (i64.const 4)))
+
+  (func (export "if") (param i32) (result i32 i32)
+    (i32.const 7)
+    (if (param i32) (result i32 i32) (local.get 0)
+      (then (i32.const 1))
+      (else (i32.const 2))))
+
+  ;; Without an else branch the parameters are passed through.
+  (func (export "if-no-else") (param i32) (result i32)
+    (i32.const 5)
+    (if (param i32) (result i32) (local.get 0)
+      (then (i32.const 1) (i32.add))))
+
+  ;; Sums the numbers from 1 to n, passing the running values as loop parameters.
+  (func (export "loop-sum") (param i32) (result i32)
+    (i32.const 0) (local.get 0)
+    (loop $l (param i32 i32) (result i32)
+      (local.set 0)
+      (i32.add (local.get 0))
+      (local.tee 0 (i32.sub (local.get 0) (i32.const 1)))
+      (br_if $l (local.get 0))
+      (drop)))
+
+  (func (export "br-table") (param i32) (result i32 i32)
+    (block (result i32 i32)
+      (block (result i32 i32)
+        (i32.const 1) (i32.const 2)
+        (br_table 0 1 (local.get 0)))
+      (drop) (drop)
+      (i32.const 3) (i32.const 4)))
+)
+
+(assert_return (invoke "block") (i32.const 1) (i32.const 2))
+(assert_return (invoke "block-param" (i32.const 5)) (i32.const 15))
+(assert_return (invoke "block-br" (i32.const 1)) (i32.const 1) (i64.const 2))
+(assert_return (invoke "block-br" (i32.const 0)) (i32.const 3) (i64.const 4))
+(assert_return (invoke "if" (i32.const 1)) (i32.const 7) (i32.const 1))
+(assert_return (invoke "if" (i32.const 0)) (i32.const 7) (i32.const 2))
+(assert_return (invoke "if-no-else" (i32.const 1)) (i32.const 6))
+(assert_return (invoke "if-no-else" (i32.const 0)) (i32.const 5))
+(assert_return (invoke "loop-sum" (i32.const 4)) (i32.const 10))
+(assert_return (invoke "br-table" (i32.const 0)) (i32.const 3) (i32.const 4))
+(assert_return (invoke "br-table" (i32.const 1)) (i32.const 1) (i32.const 2))
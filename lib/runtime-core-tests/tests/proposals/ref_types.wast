@@ -0,0 +1,85 @@
+;; Reference types: anyref values, anyref tables and the table.* instructions
+(module
+  (type $ret-i32 (func (result i32)))
+
+  (table $f 2 funcref)
+  (table $r 1 anyref)
+  (elem (i32.const 0) $one)
+
+  (global $g (mut anyref) (ref.null))
+
+  (func $one (result i32) (i32.const 1))
+
+  (func (export "null-is-null") (result i32)
+    (ref.is_null (ref.null)))
+
+  (func (export "default-is-null") (result i32)
+    (ref.is_null (table.get $r (i32.const 0))))
+
+  (func (export "local-is-null") (result i32)
+    (local anyref)
+    (ref.is_null (local.get 0)))
+
+  (func (export "size-f") (result i32)
+    (table.size $f))
+
+  (func (export "size-r") (result i32)
+    (table.size $r))
+
+  (func (export "grow-r") (param i32) (result i32)
+    (table.grow $r (ref.null) (local.get 0)))
+
+  ;; Moves a function between slots of the funcref table.
+  (func (export "copy-func") (result i32)
+    (table.set $f (i32.const 1) (table.get $f (i32.const 0)))
+    (call_indirect (type $ret-i32) (i32.const 1)))
+
+  ;; funcref is a subtype of anyref.
+  (func (export "func-in-anyref") (result i32)
+    (table.set $r (i32.const 0) (table.get $f (i32.const 0)))
+    (ref.is_null (table.get $r (i32.const 0))))
+
+  (func (export "global-roundtrip") (result i32)
+    (global.set $g (table.get $r (i32.const 0)))
+    (ref.is_null (global.get $g)))
+
+  (func (export "clear-r") (param i32)
+    (table.set $r (local.get 0) (ref.null)))
+
+  (func (export "is-null-r") (param i32) (result i32)
+    (ref.is_null (table.get $r (local.get 0))))
+)
+
+(assert_return (invoke "null-is-null") (i32.const 1))
+(assert_return (invoke "default-is-null") (i32.const 1))
+(assert_return (invoke "local-is-null") (i32.const 1))
+(assert_return (invoke "size-f") (i32.const 2))
+(assert_return (invoke "size-r") (i32.const 1))
+(assert_return (invoke "copy-func") (i32.const 1))
+(assert_return (invoke "func-in-anyref") (i32.const 0))
+(assert_return (invoke "global-roundtrip") (i32.const 0))
+(assert_return (invoke "grow-r" (i32.const 3)) (i32.const 1))
+(assert_return (invoke "size-r") (i32.const 4))
+(assert_return (invoke "is-null-r" (i32.const 3)) (i32.const 1))
+(invoke "clear-r" (i32.const 0))
+(assert_return (invoke "is-null-r" (i32.const 0)) (i32.const 1))
+(assert_trap (invoke "is-null-r" (i32.const 4)) "out of bounds")
+(assert_trap (invoke "clear-r" (i32.const 4)) "out of bounds")
+
+;; A table can only grow up to its maximum.
+(module
+  (table $t 1 2 anyref)
+  (func (export "grow") (param i32) (result i32)
+    (table.grow $t (ref.null) (local.get 0)))
+)
+
+(assert_return (invoke "grow" (i32.const 1)) (i32.const 1))
+(assert_return (invoke "grow" (i32.const 1)) (i32.const -1))
+
+;; anyref values can't flow where a funcref is expected.
+(assert_invalid
+  (module
+    (table $f 1 funcref)
+    (func (param anyref)
+      (table.set $f (i32.const 0) (local.get 0))))
+  "type mismatch")
@@ -0,0 +1,83 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use wasmer_runtime_core::{
+    backend::{CompilerConfig, Features},
+    compile_with_config, imports,
+    table::AnyRef,
+    types::Value,
+    Instance,
+};
+use wasmer_runtime_core_tests::get_compiler;
+
+const MODULE: &str = r#"
+(module
+  (global $g (export "g") (mut anyref) (ref.null))
+  (func (export "store") (param anyref)
+    (global.set $g (local.get 0)))
+  (func (export "load") (result anyref)
+    (global.get $g)))
+"#;
+
+/// Sets its flag when dropped.
+struct DropFlag(Arc<AtomicBool>);
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+fn instantiate() -> Instance {
+    let mut features = wabt::Features::new();
+    features.enable_reference_types();
+    let wasm_binary = wabt::wat2wasm_with_features(MODULE.as_bytes(), features)
+        .expect("WAST not valid or malformed");
+    let config = CompilerConfig {
+        features: Features {
+            reference_types: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let module = compile_with_config(&wasm_binary, &get_compiler(), config).unwrap();
+    module.instantiate(&imports! {}).unwrap()
+}
+
+#[test]
+fn global_owns_the_reference_stored_by_wasm() {
+    let instance = instantiate();
+    let global = instance.global("g").unwrap();
+    instance
+        .call("store", &[Value::AnyRef(AnyRef::new(String::from("kept")))])
+        .unwrap();
+    drop(instance);
+
+    match global.get() {
+        Value::AnyRef(r) => assert_eq!(r.downcast_ref::<String>().unwrap(), "kept"),
+        value => panic!("expected a reference, got {:?}", value),
+    }
+}
+
+#[test]
+fn overwritten_references_are_released_after_the_call() {
+    let instance = instantiate();
+    let dropped = Arc::new(AtomicBool::new(false));
+    instance
+        .call(
+            "store",
+            &[Value::AnyRef(AnyRef::new(DropFlag(dropped.clone())))],
+        )
+        .unwrap();
+    match instance.call("load", &[]).unwrap().as_slice() {
+        [Value::AnyRef(r)] => assert!(r.downcast_ref::<DropFlag>().is_some()),
+        values => panic!("expected a reference, got {:?}", values),
+    }
+    assert!(!dropped.load(Ordering::SeqCst));
+
+    instance
+        .call("store", &[Value::AnyRef(AnyRef::null())])
+        .unwrap();
+    assert!(dropped.load(Ordering::SeqCst));
+}
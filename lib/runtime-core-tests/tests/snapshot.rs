@@ -0,0 +1,112 @@
+use wasmer_runtime_core::{
+    compile_with, error::RestoreError, global::Global, imports, state::InstanceImage,
+    typed_func::Func, types::Value,
+};
+use wasmer_runtime_core_tests::{get_compiler, wat2wasm};
+
+const MODULE: &str = r#"
+(module
+  (memory 1)
+  (table 2 funcref)
+  (elem (i32.const 0) $one $two)
+  (global $counter (mut i32) (i32.const 0))
+  (type $ret_i32 (func (result i32)))
+  (func $one (result i32) (i32.const 1))
+  (func $two (result i32) (i32.const 2))
+  (func (export "step") (result i32)
+    (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+    (i32.store (i32.const 8) (i32.mul (global.get $counter) (i32.const 10)))
+    (drop (memory.grow (i32.const 1)))
+    (global.get $counter))
+  (func (export "state") (result i32)
+    (i32.add
+      (i32.add (global.get $counter) (i32.load (i32.const 8)))
+      (i32.mul (memory.size) (i32.const 1000))))
+  (func (export "call") (param i32) (result i32)
+    (call_indirect (type $ret_i32) (local.get 0))))
+"#;
+
+#[test]
+fn snapshot_restores_into_fresh_instance() {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let import_object = imports! {};
+
+    let instance = module.instantiate(&import_object).unwrap();
+    let step: Func<(), i32> = instance.func("step").unwrap();
+    step.call().unwrap();
+    step.call().unwrap();
+    let state: Func<(), i32> = instance.func("state").unwrap();
+    assert_eq!(state.call().unwrap(), 3022);
+
+    let bytes = instance.snapshot().to_bytes();
+    let image = InstanceImage::from_bytes(&bytes).unwrap();
+    let mut restored = module.instantiate(&import_object).unwrap();
+    restored.restore(&image).unwrap();
+
+    let state: Func<(), i32> = restored.func("state").unwrap();
+    assert_eq!(state.call().unwrap(), 3022);
+    let call: Func<i32, i32> = restored.func("call").unwrap();
+    assert_eq!(call.call(0).unwrap(), 1);
+    assert_eq!(call.call(1).unwrap(), 2);
+}
+
+#[test]
+fn snapshot_version_is_checked() {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let mut instance = module.instantiate(&imports! {}).unwrap();
+
+    let mut image = instance.snapshot();
+    image.version += 1;
+    assert!(InstanceImage::from_bytes(&image.to_bytes()).is_none());
+    match instance.restore(&image) {
+        Err(RestoreError::IncompatibleVersion(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+const IMPORTED_GLOBALS_MODULE: &str = r#"
+(module
+  (import "env" "counter" (global $counter (mut i32)))
+  (import "env" "base" (global $base i32))
+  (func (export "step")
+    (global.set $counter (i32.add (global.get $counter) (global.get $base)))))
+"#;
+
+#[test]
+fn snapshot_restores_imported_mutable_globals() {
+    let wasm_binary =
+        wat2wasm(IMPORTED_GLOBALS_MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+
+    let counter = Global::new_mutable(Value::I32(0));
+    let import_object = imports! {
+        "env" => {
+            "counter" => counter.clone(),
+            "base" => Global::new(Value::I32(5)),
+        },
+    };
+    let instance = module.instantiate(&import_object).unwrap();
+    let step: Func = instance.func("step").unwrap();
+    step.call().unwrap();
+    step.call().unwrap();
+    let image = instance.snapshot();
+    assert_eq!(image.imported_globals.len(), 2);
+
+    let fresh_counter = Global::new_mutable(Value::I32(0));
+    let import_object = imports! {
+        "env" => {
+            "counter" => fresh_counter.clone(),
+            "base" => Global::new(Value::I32(7)),
+        },
+    };
+    let mut restored = module.instantiate(&import_object).unwrap();
+    restored.restore(&image).unwrap();
+    assert_eq!(fresh_counter.get(), Value::I32(10));
+
+    // The immutable import keeps its own value.
+    let step: Func = restored.func("step").unwrap();
+    step.call().unwrap();
+    assert_eq!(fresh_counter.get(), Value::I32(17));
+}
@@ -0,0 +1,114 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use wasmer_runtime_core::{
+    compile_with,
+    error::MemoryCreationError,
+    imports,
+    memory::{DefaultMemoryCreator, MemoryCreator, MemoryRegion},
+    template::{InstanceTemplate, TemplateConfig},
+    typed_func::Func,
+    types::MemoryDescriptor,
+};
+use wasmer_runtime_core_tests::{get_compiler, wat2wasm};
+
+const MODULE: &str = r#"
+(module
+  (memory 1)
+  (data (i32.const 0) "\01")
+  (global $started (mut i32) (i32.const 0))
+  (func $start
+    (global.set $started (i32.add (global.get $started) (i32.const 1))))
+  (start $start)
+  (func (export "initialize")
+    (i32.store8 (i32.const 1) (i32.const 2))
+    (drop (memory.grow (i32.const 1)))
+    (i32.store8 (i32.const 65536) (i32.const 3)))
+  (func (export "poke") (param i32)
+    (i32.store8 (i32.const 0) (local.get 0)))
+  (func (export "state") (result i32)
+    (i32.add
+      (i32.add
+        (i32.mul (global.get $started) (i32.const 1000))
+        (i32.mul (memory.size) (i32.const 100)))
+      (i32.add
+        (i32.add (i32.load8_u (i32.const 0)) (i32.load8_u (i32.const 1)))
+        (if (result i32) (i32.gt_u (memory.size) (i32.const 1))
+          (then (i32.load8_u (i32.const 65536)))
+          (else (i32.const 0)))))))
+"#;
+
+#[test]
+fn instances_start_from_template_state() {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let config = TemplateConfig {
+        run_start: true,
+        init: Some("initialize".to_string()),
+    };
+    let template = InstanceTemplate::new(&module, &imports! {}, config).unwrap();
+
+    let first = template.instantiate(&imports! {}).unwrap();
+    let second = template.instantiate(&imports! {}).unwrap();
+    let state: Func<(), i32> = first.func("state").unwrap();
+    assert_eq!(state.call().unwrap(), 1206);
+
+    // Writes stay private to the instance that makes them.
+    let poke: Func<i32> = first.func("poke").unwrap();
+    poke.call(50).unwrap();
+    assert_eq!(state.call().unwrap(), 1255);
+    let state: Func<(), i32> = second.func("state").unwrap();
+    assert_eq!(state.call().unwrap(), 1206);
+    let third = template.instantiate(&imports! {}).unwrap();
+    let state: Func<(), i32> = third.func("state").unwrap();
+    assert_eq!(state.call().unwrap(), 1206);
+}
+
+#[test]
+fn instances_run_start_when_template_did_not() {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let template = InstanceTemplate::new(&module, &imports! {}, TemplateConfig::default()).unwrap();
+
+    let instance = template.instantiate(&imports! {}).unwrap();
+    let state: Func<(), i32> = instance.func("state").unwrap();
+    assert_eq!(state.call().unwrap(), 1101);
+}
+
+#[derive(Default)]
+struct CountingCreator {
+    reserved: AtomicUsize,
+}
+
+impl MemoryCreator for CountingCreator {
+    fn reserve(
+        &self,
+        desc: &MemoryDescriptor,
+        size: usize,
+    ) -> Result<Box<dyn MemoryRegion>, MemoryCreationError> {
+        self.reserved.fetch_add(1, Ordering::SeqCst);
+        DefaultMemoryCreator.reserve(desc, size)
+    }
+}
+
+#[test]
+fn instances_use_the_memory_creator_of_their_imports() {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let config = TemplateConfig {
+        run_start: true,
+        init: Some("initialize".to_string()),
+    };
+    let template = InstanceTemplate::new(&module, &imports! {}, config).unwrap();
+
+    let creator = Arc::new(CountingCreator::default());
+    let mut import_object = imports! {};
+    import_object.set_memory_creator(creator.clone());
+    let instance = template.instantiate(&import_object).unwrap();
+    assert_eq!(creator.reserved.load(Ordering::SeqCst), 1);
+
+    // The contents of the template are copied into the memory of the creator.
+    let state: Func<(), i32> = instance.func("state").unwrap();
+    assert_eq!(state.call().unwrap(), 1206);
+}
@@ -0,0 +1,112 @@
+use std::sync::{Arc, Barrier};
+use std::thread;
+use wasmer_runtime_core::{
+    backend::{CompilerConfig, Features, MemoryBoundCheckMode},
+    compile_with_config, imports,
+    typed_func::Func,
+};
+use wasmer_runtime_core_tests::{get_compiler, wat2wasm};
+
+const MODULE: &str = r#"
+(module
+  (memory 1 1 shared)
+  (data (i32.const 0) "\2a")
+  (func (export "load") (param i32) (result i32)
+    (i32.load (local.get 0)))
+  (func (export "store") (param i32 i32)
+    (i32.store (local.get 0) (local.get 1))))
+"#;
+
+#[test]
+fn spawned_instances_share_memory() {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let config = CompilerConfig {
+        features: Features {
+            threads: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let module = compile_with_config(&wasm_binary, &get_compiler(), config).unwrap();
+    let instance = module.instantiate(&imports! {}).unwrap();
+    let store: Func<(i32, i32)> = instance.func("store").unwrap();
+    store.call(0, 7).unwrap();
+
+    let spawner = instance.spawner().unwrap();
+    let handle = thread::spawn(move || {
+        let instance = spawner.spawn(&imports! {}).unwrap();
+        let load: Func<i32, i32> = instance.func("load").unwrap();
+        let store: Func<(i32, i32)> = instance.func("store").unwrap();
+        store.call(4, 11).unwrap();
+        // The data segment was not copied in again.
+        load.call(0).unwrap()
+    });
+    assert_eq!(handle.join().unwrap(), 7);
+
+    let load: Func<i32, i32> = instance.func("load").unwrap();
+    assert_eq!(load.call(4).unwrap(), 11);
+}
+
+#[test]
+fn pages_grown_by_one_instance_are_accessible_from_another() {
+    let wasm_binary = wat2wasm(
+        r#"
+(module
+  (memory 1 2 shared)
+  (func (export "grow") (param i32) (result i32)
+    (memory.grow (local.get 0)))
+  (func (export "load") (param i32) (result i32)
+    (i32.load (local.get 0)))
+  (func (export "store") (param i32 i32)
+    (i32.store (local.get 0) (local.get 1))))
+"#
+        .as_bytes(),
+    )
+    .expect("WAST not valid or malformed");
+    // Explicit bound checks are where a bound cached before the growth would show.
+    let config = CompilerConfig {
+        features: Features {
+            threads: true,
+            ..Default::default()
+        },
+        memory_bound_check_mode: MemoryBoundCheckMode::Enable,
+        ..Default::default()
+    };
+    let module = compile_with_config(&wasm_binary, &get_compiler(), config).unwrap();
+    let instance = module.instantiate(&imports! {}).unwrap();
+
+    let spawner = instance.spawner().unwrap();
+    let grown = Arc::new(Barrier::new(2));
+    let spawned = Arc::new(Barrier::new(2));
+    let handle = {
+        let (grown, spawned) = (grown.clone(), spawned.clone());
+        thread::spawn(move || {
+            let instance = spawner.spawn(&imports! {}).unwrap();
+            let load: Func<i32, i32> = instance.func("load").unwrap();
+            let store: Func<(i32, i32)> = instance.func("store").unwrap();
+            spawned.wait();
+            grown.wait();
+            store.call(65536, 13).unwrap();
+            load.call(65536).unwrap()
+        })
+    };
+
+    // The spawned instance cached the bound of its memory before it grows.
+    spawned.wait();
+    let grow: Func<i32, i32> = instance.func("grow").unwrap();
+    assert_eq!(grow.call(1).unwrap(), 1);
+    grown.wait();
+    assert_eq!(handle.join().unwrap(), 13);
+
+    let load: Func<i32, i32> = instance.func("load").unwrap();
+    assert_eq!(load.call(65536).unwrap(), 13);
+}
+
+#[test]
+fn unshared_instances_cannot_be_spawned() {
+    let wasm_binary = wat2wasm(r#"(module (memory 1))"#.as_bytes()).unwrap();
+    let module =
+        compile_with_config(&wasm_binary, &get_compiler(), CompilerConfig::default()).unwrap();
+    let instance = module.instantiate(&imports! {}).unwrap();
+    assert!(instance.spawner().is_err());
+}
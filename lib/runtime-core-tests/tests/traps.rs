@@ -0,0 +1,88 @@
+use wasmer_runtime_core::{
+    compile_with,
+    error::{CallError, RuntimeError, TrapCode},
+    imports,
+    types::Value,
+    Instance,
+};
+use wasmer_runtime_core_tests::{get_compiler, wat2wasm};
+
+const MODULE: &str = r#"
+(module
+  (memory 1)
+  (func (export "unreachable")
+    (unreachable))
+  (func (export "div") (param i32 i32) (result i32)
+    (i32.div_u (local.get 0) (local.get 1)))
+  (func (export "load") (param i32) (result i32)
+    (i32.load (local.get 0))))
+"#;
+
+fn instantiate() -> Instance {
+    let wasm_binary = wat2wasm(MODULE.as_bytes()).expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    module.instantiate(&imports! {}).unwrap()
+}
+
+fn trap_code(instance: &Instance, name: &str, params: &[Value]) -> TrapCode {
+    match instance.call(name, params) {
+        Err(CallError::Runtime(RuntimeError::Trap { code, .. })) => code,
+        other => panic!("expected a trap from `{}`, got {:?}", name, other),
+    }
+}
+
+#[test]
+fn traps_report_their_code() {
+    let instance = instantiate();
+
+    assert_eq!(
+        trap_code(&instance, "unreachable", &[]),
+        TrapCode::Unreachable
+    );
+    assert_eq!(
+        trap_code(&instance, "load", &[Value::I32(65536)]),
+        TrapCode::MemoryOutOfBounds
+    );
+
+    // Some backends only know that an arithmetic instruction faulted.
+    let code = trap_code(&instance, "div", &[Value::I32(1), Value::I32(0)]);
+    assert!(
+        code == TrapCode::IntegerDivisionByZero || code == TrapCode::IllegalArithmetic,
+        "unexpected trap code {:?}",
+        code
+    );
+}
+
+#[cfg(all(unix, feature = "backend-singlepass"))]
+#[test]
+fn backtraces_report_binary_offsets() {
+    use wasmer_runtime_core::{
+        backend::Backend,
+        fault::{pop_code_version, push_code_version},
+        state::CodeVersion,
+    };
+
+    let wasm_binary = wat2wasm(&br#"(module (func (export "trap") (unreachable)))"#[..])
+        .expect("WAST not valid or malformed");
+    let module = compile_with(&wasm_binary, &get_compiler()).unwrap();
+    let instance = module.instantiate(&imports! {}).unwrap();
+
+    let runnable_module = &instance.module.runnable_module;
+    push_code_version(CodeVersion {
+        baseline: true,
+        msm: runnable_module.get_module_state_map().unwrap(),
+        base: runnable_module.get_code().unwrap().as_ptr() as usize,
+        backend: Backend::Singlepass,
+        runnable_module: runnable_module.clone(),
+    });
+    let result = instance.call("trap", &[]);
+    pop_code_version().unwrap();
+
+    let backtrace = match result {
+        Err(CallError::Runtime(RuntimeError::Trap { backtrace, .. })) => backtrace,
+        other => panic!("expected a trap, got {:?}", other),
+    };
+    // The body of the function is `unreachable` followed by `end`, at the end of the binary.
+    assert_eq!(backtrace.len(), 1);
+    assert_eq!(backtrace[0].offset, Some(wasm_binary.len() - 2));
+}
@@ -125,3 +125,30 @@ popq %rax
 addq $8, %rsp
 
 jmpq *%rax
+
+# For switching between the stack of a suspended call and the stack resuming it.
+.globl _swap_stack
+_swap_stack:
+# (save_sp, new_sp)
+pushq %rbp
+pushq %rbx
+pushq %r12
+pushq %r13
+pushq %r14
+pushq %r15
+movq %rsp, (%rdi)
+movq %rsi, %rsp
+popq %r15
+popq %r14
+popq %r13
+popq %r12
+popq %rbx
+popq %rbp
+retq
+
+# Entry point of a new stack. Keep this consistent with the initial frame built in `fiber.rs`.
+.globl _fiber_start
+_fiber_start:
+movq %rbx, %rdi
+callq *%r12
+ud2
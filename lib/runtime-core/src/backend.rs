@@ -150,6 +150,9 @@ impl Default for MemoryBoundCheckMode {
 pub struct Features {
     pub simd: bool,
     pub threads: bool,
+    pub bulk_memory: bool,
+    pub reference_types: bool,
+    pub multi_value: bool,
 }
 
 /// Use this to point to a compiler config struct provided by the backend.
@@ -214,6 +217,12 @@ pub trait RunnableModule: Send + Sync {
         None
     }
 
+    /// Returns whether the code reads `InternalCtx::interrupt_signal_mem` at every
+    /// function entry and loop header, so that an `InterruptHandle` can stop it.
+    fn supports_interrupts(&self) -> bool {
+        false
+    }
+
     unsafe fn patch_local_function(&self, _idx: usize, _target_address: usize) -> bool {
         false
     }
@@ -225,6 +234,12 @@ pub trait RunnableModule: Send + Sync {
 
     unsafe fn do_early_trap(&self, data: Box<dyn Any + Send>) -> !;
 
+    /// Exchanges the backend's thread-local trap handling state with `state`.
+    ///
+    /// An asynchronous call does this whenever it switches stacks, so that a
+    /// trap is caught by the protected call on the stack it happened on.
+    fn swap_trap_state(&self, _state: &mut Option<Box<dyn Any>>) {}
+
     /// Returns the machine code associated with this module.
     fn get_code(&self) -> Option<&[u8]> {
         None
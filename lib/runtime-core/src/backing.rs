@@ -2,23 +2,27 @@ use crate::{
     error::{CreationError, LinkError, LinkResult},
     export::{Context, Export},
     global::Global,
-    import::ImportObject,
-    memory::Memory,
+    import::{ImportObject, ImportType},
+    limits::{Limiters, ResourceLimiter},
+    memory::{DefaultMemoryCreator, Memory, MemoryCreator},
     module::{ImportName, ModuleInfo, ModuleInner},
     sig_registry::SigRegistry,
     structures::{BoxedMap, Map, SliceMap, TypedIndex},
-    table::Table,
+    table::{AnyRef, Table},
     types::{
-        ImportedFuncIndex, ImportedGlobalIndex, ImportedMemoryIndex, ImportedTableIndex,
-        Initializer, LocalFuncIndex, LocalGlobalIndex, LocalMemoryIndex, LocalOrImport,
-        LocalTableIndex, SigIndex, Value,
+        DataIndex, ElemIndex, ImportedFuncIndex, ImportedGlobalIndex, ImportedMemoryIndex,
+        ImportedTableIndex, Initializer, LocalFuncIndex, LocalGlobalIndex, LocalMemoryIndex,
+        LocalOrImport, LocalTableIndex, SigIndex, Value,
     },
     vm,
 };
 use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
     fmt::Debug,
     ptr::{self, NonNull},
     slice,
+    sync::Arc,
 };
 
 /// Size of the array for internal instance usage
@@ -56,7 +60,16 @@ pub struct LocalBacking {
     pub(crate) dynamic_sigindices: BoxedMap<SigIndex, vm::SigId>,
     pub(crate) local_functions: BoxedMap<LocalFuncIndex, *const vm::Func>,
 
+    /// Passive segments that have been dropped with `data.drop` or `elem.drop`.
+    pub(crate) dropped_data: HashSet<DataIndex>,
+    pub(crate) dropped_elems: HashSet<ElemIndex>,
+
+    /// Handles for functions read out of `anyfunc` tables, keyed by function and context.
+    pub(crate) funcrefs: HashMap<(usize, usize), AnyRef>,
+
     pub(crate) internals: Internals,
+    /// The counters middlewares instrumented the module with, see `ModuleInfo::counters`.
+    pub(crate) counters: Box<[u64]>,
 }
 
 // Manually implemented because LocalBacking contains raw pointers directly
@@ -67,23 +80,38 @@ impl LocalBacking {
         module: &ModuleInner,
         imports: &ImportBacking,
         vmctx: *mut vm::Ctx,
+        existing_memories: Option<&BoxedMap<LocalMemoryIndex, Memory>>,
+        resource_limiter: Option<Arc<dyn ResourceLimiter>>,
+        memory_creator: Option<Arc<dyn MemoryCreator>>,
     ) -> LinkResult<Self> {
-        let mut memories = match Self::generate_memories(module) {
-            Ok(m) => m,
+        let limiters = Limiters::new(resource_limiter.as_ref());
+        let memory_creator = memory_creator
+            .unwrap_or_else(|| Arc::new(DefaultMemoryCreator) as Arc<dyn MemoryCreator>);
+        let mut memories =
+            match Self::generate_memories(module, existing_memories, &limiters, &memory_creator) {
+                Ok(m) => m,
+                Err(e) => {
+                    return Err(vec![LinkError::Generic {
+                        message: format!("unable to create memory: {:?}", e),
+                    }]);
+                }
+            };
+        let mut tables = match Self::generate_tables(module, &limiters) {
+            Ok(t) => t,
             Err(e) => {
                 return Err(vec![LinkError::Generic {
-                    message: format!("unable to create memory: {:?}", e),
+                    message: format!("unable to create table: {:?}", e),
                 }]);
             }
         };
-        let mut tables = Self::generate_tables(module);
         let mut globals = Self::generate_globals(module, imports)?;
 
         // Ensure all initializers are valid before running finalizers
         Self::validate_memories(module, imports)?;
         Self::validate_tables(module, imports, &mut tables)?;
 
-        let vm_memories = Self::finalize_memories(module, imports, &mut memories)?;
+        let vm_memories =
+            Self::finalize_memories(module, imports, &mut memories, existing_memories.is_some())?;
         let vm_tables = Self::finalize_tables(module, imports, &mut tables, vmctx)?;
         let vm_globals = Self::finalize_globals(&mut globals);
 
@@ -102,10 +130,17 @@ impl LocalBacking {
             dynamic_sigindices,
             local_functions,
 
+            dropped_data: HashSet::new(),
+            dropped_elems: HashSet::new(),
+
+            funcrefs: HashMap::new(),
+
             internals: Internals([0; INTERNALS_SIZE]),
+            counters: vec![0; module.info.counters].into_boxed_slice(),
         })
     }
 
+
     fn generate_local_functions(module: &ModuleInner) -> BoxedMap<LocalFuncIndex, *const vm::Func> {
         (0..module.info.func_assoc.len() - module.info.imported_functions.len())
             .map(|index| {
@@ -133,10 +168,17 @@ impl LocalBacking {
 
     fn generate_memories(
         module: &ModuleInner,
+        existing_memories: Option<&BoxedMap<LocalMemoryIndex, Memory>>,
+        limiters: &Limiters,
+        memory_creator: &Arc<dyn MemoryCreator>,
     ) -> Result<BoxedMap<LocalMemoryIndex, Memory>, CreationError> {
+        if let Some(existing_memories) = existing_memories {
+            return Ok(existing_memories.clone());
+        }
+
         let mut memories = Map::with_capacity(module.info.memories.len());
         for (_, &desc) in &module.info.memories {
-            let memory = Memory::new(desc)?;
+            let memory = Memory::new_limited(desc, limiters, Arc::clone(memory_creator))?;
             memories.push(memory);
         }
 
@@ -201,11 +243,13 @@ impl LocalBacking {
 
     /// Initialize each locally-defined memory in the Module.
     ///
-    /// This involves copying in the data initializers.
+    /// This involves copying in the data initializers, except into memories
+    /// that were initialized before, such as those shared with an earlier instance.
     fn finalize_memories(
         module: &ModuleInner,
         imports: &ImportBacking,
         memories: &mut SliceMap<LocalMemoryIndex, Memory>,
+        initialized: bool,
     ) -> LinkResult<BoxedMap<LocalMemoryIndex, *mut vm::LocalMemory>> {
         // For each init that has some data...
         // Initialize data
@@ -234,6 +278,7 @@ impl LocalBacking {
             } as usize;
 
             match init.memory_index.local_or_import(&module.info) {
+                LocalOrImport::Local(_) if initialized => {}
                 LocalOrImport::Local(local_memory_index) => {
                     let mem = &memories[local_memory_index];
                     for (mem_byte, data_byte) in mem.view()[init_base..init_base + init.data.len()]
@@ -264,15 +309,18 @@ impl LocalBacking {
             .into_boxed_map())
     }
 
-    fn generate_tables(module: &ModuleInner) -> BoxedMap<LocalTableIndex, Table> {
+    fn generate_tables(
+        module: &ModuleInner,
+        limiters: &Limiters,
+    ) -> Result<BoxedMap<LocalTableIndex, Table>, CreationError> {
         let mut tables = Map::with_capacity(module.info.tables.len());
 
         for (_, &table_desc) in module.info.tables.iter() {
-            let table = Table::new(table_desc).unwrap();
+            let table = Table::new_limited(table_desc, limiters)?;
             tables.push(table);
         }
 
-        tables.into_boxed_map()
+        Ok(tables.into_boxed_map())
     }
 
     /// This validates all of the locally-defined tables in the Module.
@@ -498,6 +546,10 @@ pub struct ImportBacking {
     pub(crate) vm_memories: BoxedMap<ImportedMemoryIndex, *mut vm::LocalMemory>,
     pub(crate) vm_tables: BoxedMap<ImportedTableIndex, *mut vm::LocalTable>,
     pub(crate) vm_globals: BoxedMap<ImportedGlobalIndex, *mut vm::LocalGlobal>,
+
+    /// Keeps the code of imported host functions which is not owned by an instance, such as
+    /// the trampoline of a `DynamicFunc`, alive.
+    pub(crate) function_owners: Vec<Arc<dyn Any + Send + Sync>>,
 }
 
 // manually implemented because ImportBacking contains raw pointers directly
@@ -510,46 +562,49 @@ impl ImportBacking {
         imports: &ImportObject,
         vmctx: *mut vm::Ctx,
     ) -> LinkResult<Self> {
-        let mut failed = false;
+        // Every kind of import is resolved even if another one failed, so that all of the
+        // unresolved imports are reported together.
         let mut link_errors = vec![];
 
-        let vm_functions = import_functions(module, imports, vmctx).unwrap_or_else(|le| {
-            failed = true;
-            link_errors.extend(le);
-            Map::new().into_boxed_map()
-        });
+        let (vm_functions, function_owners) = import_functions(module, imports, vmctx)
+            .unwrap_or_else(|le| {
+                link_errors.extend(le);
+                (Map::new().into_boxed_map(), vec![])
+            });
 
         let (memories, vm_memories) = import_memories(module, imports).unwrap_or_else(|le| {
-            failed = true;
             link_errors.extend(le);
             (Map::new().into_boxed_map(), Map::new().into_boxed_map())
         });
 
         let (tables, vm_tables) = import_tables(module, imports).unwrap_or_else(|le| {
-            failed = true;
             link_errors.extend(le);
             (Map::new().into_boxed_map(), Map::new().into_boxed_map())
         });
 
         let (globals, vm_globals) = import_globals(module, imports).unwrap_or_else(|le| {
-            failed = true;
             link_errors.extend(le);
             (Map::new().into_boxed_map(), Map::new().into_boxed_map())
         });
 
-        if failed {
-            Err(link_errors)
+        // Built even if linking failed, so that dropping it releases the function contexts.
+        let import_backing = ImportBacking {
+            memories,
+            tables,
+            globals,
+
+            vm_functions,
+            vm_memories,
+            vm_tables,
+            vm_globals,
+
+            function_owners,
+        };
+
+        if link_errors.is_empty() {
+            Ok(import_backing)
         } else {
-            Ok(ImportBacking {
-                memories,
-                tables,
-                globals,
-
-                vm_functions,
-                vm_memories,
-                vm_tables,
-                vm_globals,
-            })
+            Err(link_errors)
         }
     }
 
@@ -561,9 +616,16 @@ impl ImportBacking {
 
 impl Drop for ImportBacking {
     fn drop(&mut self) {
-        // Properly drop the `vm::FuncCtx` in `vm::ImportedFunc`.
-        for (_imported_func_index, imported_func) in (*self.vm_functions).iter_mut() {
-            let _: Box<vm::FuncCtx> = unsafe { Box::from_raw(imported_func.func_ctx.as_ptr()) };
+        unsafe { drop_func_ctxs(self.vm_functions.iter().map(|(_, f)| f)) };
+    }
+}
+
+/// Properly drop the `vm::FuncCtx` in each `vm::ImportedFunc`. Missing functions have none.
+unsafe fn drop_func_ctxs<'a>(functions: impl Iterator<Item = &'a vm::ImportedFunc>) {
+    for imported_func in functions {
+        let func_ctx = imported_func.func_ctx.as_ptr();
+        if !func_ctx.is_null() {
+            let _: Box<vm::FuncCtx> = Box::from_raw(func_ctx);
         }
     }
 }
@@ -572,9 +634,13 @@ fn import_functions(
     module: &ModuleInner,
     imports: &ImportObject,
     vmctx: *mut vm::Ctx,
-) -> LinkResult<BoxedMap<ImportedFuncIndex, vm::ImportedFunc>> {
+) -> LinkResult<(
+    BoxedMap<ImportedFuncIndex, vm::ImportedFunc>,
+    Vec<Arc<dyn Any + Send + Sync>>,
+)> {
     let mut link_errors = vec![];
     let mut functions = Map::with_capacity(module.info.imported_functions.len());
+    let mut owners = vec![];
     for (
         index,
         ImportName {
@@ -589,8 +655,9 @@ fn import_functions(
         let namespace = module.info.namespace_table.get(*namespace_index);
         let name = module.info.name_table.get(*name_index);
 
-        let import =
-            imports.maybe_with_namespace(namespace, |namespace| namespace.get_export(name));
+        let import = imports.resolve(namespace, name, || {
+            ImportType::Function(expected_sig.clone())
+        });
 
         match import {
             Some(Export::Function {
@@ -599,6 +666,7 @@ fn import_functions(
                 signature,
             }) => {
                 if *expected_sig == *signature {
+                    owners.extend(func.owner().cloned());
                     functions.push(vm::ImportedFunc {
                         func: func.inner(),
                         func_ctx: NonNull::new(Box::into_raw(Box::new(vm::FuncCtx {
@@ -665,9 +733,10 @@ fn import_functions(
     }
 
     if !link_errors.is_empty() {
+        unsafe { drop_func_ctxs(functions.iter().map(|(_, f)| f)) };
         Err(link_errors)
     } else {
-        Ok(functions.into_boxed_map())
+        Ok((functions.into_boxed_map(), owners))
     }
 }
 
@@ -695,8 +764,9 @@ fn import_memories(
         let namespace = module.info.namespace_table.get(*namespace_index);
         let name = module.info.name_table.get(*name_index);
 
-        let memory_import =
-            imports.maybe_with_namespace(namespace, |namespace| namespace.get_export(name));
+        let memory_import = imports.resolve(namespace, name, || {
+            ImportType::Memory(*expected_memory_desc)
+        });
         match memory_import {
             Some(Export::Memory(memory)) => {
                 if expected_memory_desc.fits_in_imported(memory.descriptor()) {
@@ -767,7 +837,7 @@ fn import_tables(
         let name = module.info.name_table.get(*name_index);
 
         let table_import =
-            imports.maybe_with_namespace(namespace, |namespace| namespace.get_export(name));
+            imports.resolve(namespace, name, || ImportType::Table(*expected_table_desc));
         match table_import {
             Some(Export::Table(mut table)) => {
                 if expected_table_desc.fits_in_imported(table.descriptor()) {
@@ -836,8 +906,9 @@ fn import_globals(
     {
         let namespace = module.info.namespace_table.get(*namespace_index);
         let name = module.info.name_table.get(*name_index);
-        let import =
-            imports.maybe_with_namespace(namespace, |namespace| namespace.get_export(name));
+        let import = imports.resolve(namespace, name, || {
+            ImportType::Global(*imported_global_desc)
+        });
         match import {
             Some(Export::Global(mut global)) => {
                 if global.descriptor() == *imported_global_desc {
@@ -51,6 +51,8 @@ pub enum InternalEvent {
     SetInternal(u32),
     /// Indicates getting an internal field.
     GetInternal(u32),
+    /// Increments the counter of the instance at this index, see `ModuleInfo::counters`.
+    IncrementCounter(u32),
 }
 
 impl fmt::Debug for InternalEvent {
@@ -61,6 +63,7 @@ impl fmt::Debug for InternalEvent {
             InternalEvent::Breakpoint(_) => write!(f, "Breakpoint"),
             InternalEvent::SetInternal(_) => write!(f, "SetInternal"),
             InternalEvent::GetInternal(_) => write!(f, "GetInternal"),
+            InternalEvent::IncrementCounter(_) => write!(f, "IncrementCounter"),
         }
     }
 }
@@ -182,10 +185,10 @@ pub fn validating_parser_config(features: &Features) -> wasmparser::ValidatingPa
     wasmparser::ValidatingParserConfig {
         operator_config: wasmparser::OperatorValidatorConfig {
             enable_threads: features.threads,
-            enable_reference_types: false,
+            enable_reference_types: features.reference_types,
             enable_simd: features.simd,
-            enable_bulk_memory: false,
-            enable_multi_value: false,
+            enable_bulk_memory: features.bulk_memory,
+            enable_multi_value: features.multi_value,
 
             #[cfg(feature = "deterministic-execution")]
             deterministic_only: true,
@@ -194,15 +197,30 @@ pub fn validating_parser_config(features: &Features) -> wasmparser::ValidatingPa
 }
 
 fn validate_with_features(bytes: &[u8], features: &Features) -> CompileResult<()> {
+    use wasmparser::{CustomSectionKind, ParserInput, ParserState, SectionCode};
+
     let mut parser =
         wasmparser::ValidatingParser::new(bytes, Some(validating_parser_config(features)));
+    let mut input = ParserInput::Default;
     loop {
-        let state = parser.read();
+        let state = parser.read_with_input(input);
+        input = ParserInput::Default;
         match *state {
-            wasmparser::ParserState::EndWasm => break Ok(()),
-            wasmparser::ParserState::Error(err) => Err(CompileError::ValidationError {
+            ParserState::EndWasm => break Ok(()),
+            ParserState::Error(err) => Err(CompileError::ValidationError {
                 msg: err.message.to_string(),
             })?,
+            ParserState::BeginSection {
+                code:
+                    SectionCode::Custom {
+                        kind: CustomSectionKind::Name,
+                        ..
+                    },
+                ..
+            } => {
+                // `read_module` ignores a malformed name section.
+                input = ParserInput::SkipSection;
+            }
             _ => {}
         }
     }
@@ -275,6 +293,7 @@ fn requires_pre_validation(backend: Backend) -> bool {
 /// A sink for parse events.
 pub struct EventSink<'a, 'b> {
     buffer: SmallVec<[Event<'a, 'b>; 2]>,
+    offset: Option<usize>,
 }
 
 impl<'a, 'b> EventSink<'a, 'b> {
@@ -282,17 +301,27 @@ impl<'a, 'b> EventSink<'a, 'b> {
     pub fn push(&mut self, ev: Event<'a, 'b>) {
         self.buffer.push(ev);
     }
+
+    /// Returns the offset in the wasm binary of the operator the events come from, or `None`
+    /// for internal events such as `FunctionBegin`.
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
 }
 
 /// A container for a chain of middlewares.
 pub struct MiddlewareChain {
     chain: Vec<Box<dyn GenericFunctionMiddleware>>,
+    module_chain: Vec<Box<dyn GenericModuleMiddleware>>,
 }
 
 impl MiddlewareChain {
     /// Create a new empty `MiddlewareChain`.
     pub fn new() -> MiddlewareChain {
-        MiddlewareChain { chain: vec![] }
+        MiddlewareChain {
+            chain: vec![],
+            module_chain: vec![],
+        }
     }
 
     /// Push a new `FunctionMiddleware` to this `MiddlewareChain`.
@@ -300,15 +329,40 @@ impl MiddlewareChain {
         self.chain.push(Box::new(m));
     }
 
-    /// Run this chain with the provided function code generator, event and module info.
+    /// Push a new `ModuleMiddleware` to this `MiddlewareChain`. Module middlewares run in the
+    /// order they were pushed, before any function middleware.
+    pub fn push_module<M: ModuleMiddleware + 'static>(&mut self, m: M) {
+        self.module_chain.push(Box::new(m));
+    }
+
+    /// Run the module middlewares of this chain on the module info.
+    pub(crate) fn run_module(&mut self, module_info: &mut ModuleInfo) -> Result<(), String> {
+        for m in &mut self.module_chain {
+            m.transform_module_info(module_info)?;
+        }
+        Ok(())
+    }
+
+    /// Run the module middlewares of this chain on the module info of the whole module.
+    pub(crate) fn finish_module(&mut self, module_info: &mut ModuleInfo) -> Result<(), String> {
+        for m in &mut self.module_chain {
+            m.finish_module(module_info)?;
+        }
+        Ok(())
+    }
+
+    /// Run this chain with the provided function code generator, event, offset of the event in
+    /// the wasm binary and module info.
     pub(crate) fn run<E: Debug, FCG: FunctionCodeGenerator<E>>(
         &mut self,
         fcg: Option<&mut FCG>,
         ev: Event,
+        offset: Option<usize>,
         module_info: &ModuleInfo,
     ) -> Result<(), String> {
         let mut sink = EventSink {
             buffer: SmallVec::new(),
+            offset,
         };
         sink.push(ev);
         for m in &mut self.chain {
@@ -318,6 +372,7 @@ impl MiddlewareChain {
             }
         }
         if let Some(fcg) = fcg {
+            fcg.set_binary_offset(sink.offset);
             for ev in sink.buffer {
                 fcg.feed_event(ev, module_info)
                     .map_err(|x| format!("{:?}", x))?;
@@ -362,6 +417,41 @@ impl<E: Debug, T: FunctionMiddleware<Error = E>> GenericFunctionMiddleware for T
     }
 }
 
+/// A middleware rewriting the `ModuleInfo` of a module before its functions are compiled, e.g.
+/// to add a global or an imported function with `ModuleInfo::add_global` and
+/// `ModuleInfo::add_import_function`. Function middlewares are given the rewritten
+/// `ModuleInfo`, so they can find what was added, for instance through its `exports`.
+pub trait ModuleMiddleware {
+    /// The error type for this middleware's functions.
+    type Error: Debug;
+    /// Rewrites the module info. The types, imports, functions, tables, memories, globals,
+    /// exports, start function and table initializers of the module have been read, but not
+    /// its data initializers.
+    fn transform_module_info(&mut self, module_info: &mut ModuleInfo) -> Result<(), Self::Error>;
+
+    /// Called once the whole module has been read, with the names of the `name` section,
+    /// which follows the code section.
+    fn finish_module(&mut self, _module_info: &mut ModuleInfo) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+pub(crate) trait GenericModuleMiddleware {
+    fn transform_module_info(&mut self, module_info: &mut ModuleInfo) -> Result<(), String>;
+    fn finish_module(&mut self, module_info: &mut ModuleInfo) -> Result<(), String>;
+}
+
+impl<E: Debug, T: ModuleMiddleware<Error = E>> GenericModuleMiddleware for T {
+    fn transform_module_info(&mut self, module_info: &mut ModuleInfo) -> Result<(), String> {
+        <Self as ModuleMiddleware>::transform_module_info(self, module_info)
+            .map_err(|x| format!("{:?}", x))
+    }
+
+    fn finish_module(&mut self, module_info: &mut ModuleInfo) -> Result<(), String> {
+        <Self as ModuleMiddleware>::finish_module(self, module_info).map_err(|x| format!("{:?}", x))
+    }
+}
+
 /// The function-scope code generator trait.
 pub trait FunctionCodeGenerator<E: Debug> {
     /// Sets the return type.
@@ -376,6 +466,11 @@ pub trait FunctionCodeGenerator<E: Debug> {
     /// Called before the first call to `feed_opcode`.
     fn begin_body(&mut self, module_info: &ModuleInfo) -> Result<(), E>;
 
+    /// Called before the events of each operator with the offset of the operator in the wasm
+    /// binary, and with `None` before internal events such as `FunctionBegin`. Backends that
+    /// track execution state record it in `FunctionStateMap::wasm_offset_to_binary_offset`.
+    fn set_binary_offset(&mut self, _offset: Option<usize>) {}
+
     /// Called for each operator.
     fn feed_event(&mut self, op: Event, module_info: &ModuleInfo) -> Result<(), E>;
 
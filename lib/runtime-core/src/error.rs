@@ -1,6 +1,9 @@
 //! The error module contains the data structures and helper functions used to implement errors that
 //! are produced and returned from the wasmer runtime core.
-use crate::types::{FuncSig, GlobalDescriptor, MemoryDescriptor, TableDescriptor, Type};
+use crate::module::ModuleInfo;
+use crate::state::ExecutionStateImage;
+use crate::typed_func::WasmTrapInfo;
+use crate::types::{FuncIndex, FuncSig, GlobalDescriptor, MemoryDescriptor, TableDescriptor, Type};
 use core::borrow::Borrow;
 use std::any::Any;
 
@@ -172,6 +175,110 @@ impl std::fmt::Display for LinkError {
 
 impl std::error::Error for LinkError {}
 
+/// The reason a WebAssembly trap occurred.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TrapCode {
+    /// An `unreachable` instruction was executed.
+    Unreachable,
+    /// A `call_indirect` went through a table element of the wrong signature.
+    IncorrectCallIndirectSignature,
+    /// A memory access was out of bounds.
+    MemoryOutOfBounds,
+    /// A table access, including the one made by `call_indirect`, was out of bounds.
+    TableOutOfBounds,
+    /// An integer was divided by zero.
+    IntegerDivisionByZero,
+    /// An integer operation overflowed, e.g. `i32.div_s` of `i32::MIN` by -1.
+    IntegerOverflow,
+    /// A float could not be converted to an integer.
+    BadConversionToInteger,
+    /// An arithmetic operation trapped, and the backend cannot tell why.
+    IllegalArithmetic,
+    /// An atomic memory access was not naturally aligned.
+    MisalignedAtomicAccess,
+    /// The call stack was exhausted.
+    StackOverflow,
+    /// The trap was raised by a runtime function with a message of its own.
+    Other,
+    /// The backend cannot tell why the trap occurred.
+    Unknown,
+}
+
+impl From<WasmTrapInfo> for TrapCode {
+    fn from(info: WasmTrapInfo) -> TrapCode {
+        match info {
+            WasmTrapInfo::Unreachable => TrapCode::Unreachable,
+            WasmTrapInfo::IncorrectCallIndirectSignature => {
+                TrapCode::IncorrectCallIndirectSignature
+            }
+            WasmTrapInfo::MemoryOutOfBounds => TrapCode::MemoryOutOfBounds,
+            WasmTrapInfo::CallIndirectOOB => TrapCode::TableOutOfBounds,
+            WasmTrapInfo::IllegalArithmetic => TrapCode::IllegalArithmetic,
+            WasmTrapInfo::MisalignedAtomicAccess => TrapCode::MisalignedAtomicAccess,
+            WasmTrapInfo::StackOverflow => TrapCode::StackOverflow,
+            WasmTrapInfo::IntegerDivisionByZero => TrapCode::IntegerDivisionByZero,
+            WasmTrapInfo::IntegerOverflow => TrapCode::IntegerOverflow,
+            WasmTrapInfo::BadConversionToInteger => TrapCode::BadConversionToInteger,
+            WasmTrapInfo::Unknown => TrapCode::Unknown,
+        }
+    }
+}
+
+impl std::fmt::Display for TrapCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                TrapCode::Unreachable => "unreachable",
+                TrapCode::IncorrectCallIndirectSignature => "incorrect `call_indirect` signature",
+                TrapCode::MemoryOutOfBounds => "memory out-of-bounds access",
+                TrapCode::TableOutOfBounds => "table out-of-bounds access",
+                TrapCode::IntegerDivisionByZero => "integer division by zero",
+                TrapCode::IntegerOverflow => "integer overflow",
+                TrapCode::BadConversionToInteger => "invalid conversion to integer",
+                TrapCode::IllegalArithmetic => "illegal arithmetic operation",
+                TrapCode::MisalignedAtomicAccess => "misaligned atomic access",
+                TrapCode::StackOverflow => "call stack exhausted",
+                TrapCode::Other => "runtime trap",
+                TrapCode::Unknown => "unknown",
+            }
+        )
+    }
+}
+
+/// A WebAssembly function that was on the stack when a trap occurred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WasmFrame {
+    /// The index of the function.
+    pub func_index: FuncIndex,
+    /// The name of the function, if the module gives it one.
+    pub name: Option<String>,
+    /// The offset in the wasm binary of the instruction being executed, if the backend
+    /// recorded it.
+    pub offset: Option<usize>,
+}
+
+impl std::fmt::Display for WasmFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.name {
+            Some(ref name) => write!(f, "{}", name)?,
+            None => write!(f, "<func {}>", self.func_index.index())?,
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " @ {:#x}", offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// A trap caught by the fault handler, whose stack has not been matched
+/// against a module yet.
+pub(crate) struct UnresolvedTrap {
+    pub(crate) code: TrapCode,
+    pub(crate) image: ExecutionStateImage,
+}
+
 /// This is the error type returned when calling
 /// a WebAssembly function.
 ///
@@ -181,14 +288,63 @@ impl std::error::Error for LinkError {}
 pub enum RuntimeError {
     /// Trap.
     Trap {
+        /// Why the trap occurred.
+        code: TrapCode,
         /// Trap message.
         msg: Box<str>,
+        /// The WebAssembly frames on the stack, innermost first.
+        ///
+        /// This is only available when the backend tracks execution state, and
+        /// the code was registered with `fault::push_code_version`.
+        backtrace: Vec<WasmFrame>,
     },
     /// Error.
     Error {
         /// Error data.
         data: Box<dyn Any + Send>,
     },
+    /// Execution was stopped through an `InterruptHandle`.
+    Interrupted,
+    /// The instance used all the points it was allowed by the metering middleware.
+    ExecutionLimitExceeded,
+}
+
+impl RuntimeError {
+    /// Creates a trap without a backtrace.
+    pub fn trap(code: TrapCode, msg: impl Into<Box<str>>) -> Self {
+        RuntimeError::Trap {
+            code,
+            msg: msg.into(),
+            backtrace: vec![],
+        }
+    }
+
+    /// Converts the payload of an early trap into a `RuntimeError`. Payloads that
+    /// already are a `RuntimeError` (e.g. traps raised by vmcalls) are passed through,
+    /// an `ExecutionLimitExceededError` becomes `RuntimeError::ExecutionLimitExceeded`,
+    /// and traps caught by the fault handler get their backtrace resolved against `info`.
+    pub(crate) fn from_user_error(data: Box<dyn Any + Send>, info: &ModuleInfo) -> Self {
+        let data = match data.downcast::<RuntimeError>() {
+            Ok(err) => return *err,
+            Err(data) => data,
+        };
+        if data.is::<ExecutionLimitExceededError>() {
+            return RuntimeError::ExecutionLimitExceeded;
+        }
+        match data.downcast::<UnresolvedTrap>() {
+            Ok(trap) => RuntimeError::Trap {
+                code: trap.code,
+                msg: trap.code.to_string().into(),
+                backtrace: trap.image.backtrace(info),
+            },
+            Err(data) => RuntimeError::Error { data },
+        }
+    }
+
+    /// Converts the trap reported by a backend into a `RuntimeError`.
+    pub(crate) fn from_trap_info(info: WasmTrapInfo) -> Self {
+        RuntimeError::trap(info.into(), info.to_string())
+    }
 }
 
 impl PartialEq for RuntimeError {
@@ -200,8 +356,16 @@ impl PartialEq for RuntimeError {
 impl std::fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            RuntimeError::Trap { ref msg } => {
-                write!(f, "WebAssembly trap occurred during runtime: {}", msg)
+            RuntimeError::Trap {
+                ref msg,
+                ref backtrace,
+                ..
+            } => {
+                write!(f, "WebAssembly trap occurred during runtime: {}", msg)?;
+                for frame in backtrace {
+                    write!(f, "\n    at {}", frame)?;
+                }
+                Ok(())
             }
             RuntimeError::Error { data } => {
                 if let Some(s) = data.downcast_ref::<String>() {
@@ -212,6 +376,8 @@ impl std::fmt::Display for RuntimeError {
                     write!(f, "unknown error")
                 }
             }
+            RuntimeError::Interrupted => write!(f, "WebAssembly execution was interrupted"),
+            RuntimeError::ExecutionLimitExceeded => write!(f, "execution limit exceeded"),
         }
     }
 }
@@ -330,6 +496,8 @@ pub enum CreationError {
     UnableToCreateTable,
     /// Invalid descriptor error with message.
     InvalidDescriptor(String),
+    /// A resource limiter denied the creation.
+    ResourceLimitExceeded,
 }
 
 impl PartialEq for CreationError {
@@ -348,6 +516,9 @@ impl std::fmt::Display for CreationError {
                 "Unable to create because the supplied descriptor is invalid: \"{}\"",
                 msg
             ),
+            CreationError::ResourceLimitExceeded => {
+                write!(f, "Unable to create because a resource limit was exceeded")
+            }
         }
     }
 }
@@ -373,6 +544,8 @@ pub enum Error {
     CallError(CallError),
     /// Creation error.
     CreationError(CreationError),
+    /// Restore error.
+    RestoreError(RestoreError),
 }
 
 impl PartialEq for Error {
@@ -411,6 +584,12 @@ impl From<CreationError> for Error {
     }
 }
 
+impl From<RestoreError> for Error {
+    fn from(restore_err: RestoreError) -> Self {
+        Error::RestoreError(restore_err)
+    }
+}
+
 impl From<Vec<LinkError>> for Error {
     fn from(link_errs: Vec<LinkError>) -> Self {
         Error::LinkError(link_errs)
@@ -448,6 +627,7 @@ impl std::fmt::Display for Error {
             Error::ResolveError(err) => write!(f, "resolve error: {}", err),
             Error::CallError(err) => write!(f, "call error: {}", err),
             Error::CreationError(err) => write!(f, "creation error: {}", err),
+            Error::RestoreError(err) => write!(f, "restore error: {}", err),
         }
     }
 }
@@ -469,6 +649,8 @@ pub enum GrowError {
     CouldNotProtectMemory(MemoryProtectionError),
     /// Error creating memory.
     CouldNotCreateMemory(MemoryCreationError),
+    /// A resource limiter denied the growth.
+    ResourceLimitExceeded,
 }
 
 impl std::fmt::Display for GrowError {
@@ -480,12 +662,93 @@ impl std::fmt::Display for GrowError {
             GrowError::ExceededMaxPagesForMemory(left, added) => write!(f, "Failed to add pages because would exceed maximum number of pages for the memory. Left: {}, Added: {}", left, added),
             GrowError::CouldNotCreateMemory(e) => write!(f, "Grow Error: {}", e),
             GrowError::CouldNotProtectMemory(e) => write!(f, "Grow Error: {}", e),
+            GrowError::ResourceLimitExceeded => write!(f, "Grow Error: a resource limit was exceeded"),
         }
     }
 }
 
 impl std::error::Error for GrowError {}
 
+/// An instance used all the points it was allowed by the metering middleware.
+///
+/// A host function returning it makes the call fail with
+/// `RuntimeError::ExecutionLimitExceeded`, like WebAssembly code running out of points.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ExecutionLimitExceededError;
+
+impl std::fmt::Display for ExecutionLimitExceededError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "execution limit exceeded")
+    }
+}
+
+impl std::error::Error for ExecutionLimitExceededError {}
+
+impl From<ExecutionLimitExceededError> for RuntimeError {
+    fn from(_: ExecutionLimitExceededError) -> Self {
+        RuntimeError::ExecutionLimitExceeded
+    }
+}
+
+/// An error getting an `InterruptHandle` for an instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptHandleError {
+    /// The backend that compiled the instance does not check for interrupts, so
+    /// it cannot be stopped through an `InterruptHandle`.
+    Unsupported,
+    /// Every interrupt signal page is taken by a live instance.
+    TooManyHandles,
+}
+
+impl std::fmt::Display for InterruptHandleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            InterruptHandleError::Unsupported => {
+                write!(f, "the backend of this instance cannot be interrupted")
+            }
+            InterruptHandleError::TooManyHandles => {
+                write!(f, "too many instances have an interrupt handle")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InterruptHandleError {}
+
+/// An error occurred while restoring an `InstanceImage` into an instance.
+#[derive(Debug)]
+pub enum RestoreError {
+    /// The image was made with another version of the image format.
+    IncompatibleVersion(u32),
+    /// The image was not made from an instance of the same module.
+    Mismatch(String),
+    /// The image holds a reference to something outside of the instance.
+    Unrestorable(String),
+    /// A memory or table could not grow to its size in the image.
+    CouldNotGrow(GrowError),
+}
+
+impl std::fmt::Display for RestoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RestoreError::IncompatibleVersion(version) => {
+                write!(f, "Restore Error: unsupported image version {}", version)
+            }
+            RestoreError::Mismatch(msg) => write!(f, "Restore Error: {}", msg),
+            RestoreError::Unrestorable(msg) => write!(f, "Restore Error: {}", msg),
+            RestoreError::CouldNotGrow(e) => write!(f, "Restore Error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RestoreError {}
+
+impl From<GrowError> for RestoreError {
+    fn from(grow_error: GrowError) -> Self {
+        RestoreError::CouldNotGrow(grow_error)
+    }
+}
+
 /// A kind of page error.
 #[derive(Debug)]
 pub enum PageError {
@@ -6,7 +6,7 @@ use crate::{
     module::ModuleInner, table::Table, types::FuncSig, vm,
 };
 use indexmap::map::Iter as IndexMapIter;
-use std::{ptr::NonNull, sync::Arc};
+use std::{any::Any, ptr::NonNull, sync::Arc};
 
 /// A kind of Context.
 #[derive(Debug, Copy, Clone)]
@@ -45,9 +45,9 @@ pub enum Export {
     Global(Global),
 }
 
-/// Const pointer to a `Func`.
+/// Const pointer to a `Func`, along with whatever must outlive the code it points to.
 #[derive(Debug, Clone)]
-pub struct FuncPointer(*const vm::Func);
+pub struct FuncPointer(*const vm::Func, Option<Arc<dyn Any + Send + Sync>>);
 
 // Manually implemented because FuncPointer contains a raw pointer to Ctx
 unsafe impl Send for FuncPointer {}
@@ -57,12 +57,22 @@ impl FuncPointer {
     /// no way to check whether the passed function
     /// is valid and has the right signature.
     pub unsafe fn new(f: *const vm::Func) -> Self {
-        FuncPointer(f)
+        FuncPointer(f, None)
+    }
+
+    /// A pointer to code that lives as long as `owner`. Instances importing the function
+    /// keep a clone of `owner`.
+    pub(crate) unsafe fn with_owner(f: *const vm::Func, owner: Arc<dyn Any + Send + Sync>) -> Self {
+        FuncPointer(f, Some(owner))
     }
 
     pub(crate) fn inner(&self) -> *const vm::Func {
         self.0
     }
+
+    pub(crate) fn owner(&self) -> Option<&Arc<dyn Any + Send + Sync>> {
+        self.1.as_ref()
+    }
 }
 
 /// An iterator to an instance's exports.
@@ -11,6 +11,10 @@ pub mod raw {
         pub fn run_on_alternative_stack(stack_end: *mut u64, stack_begin: *mut u64) -> u64;
         /// Internal routine for switching into a backend without information about where registers are preserved.
         pub fn register_preservation_trampoline(); // NOT safe to call directly
+        /// Save callee-saved registers and the stack pointer to `save_sp`, then continue on the stack at `new_sp`.
+        pub fn swap_stack(save_sp: *mut *mut u64, new_sp: *mut u64);
+        /// Internal entry point of a new stack for `swap_stack`.
+        pub fn fiber_start(); // NOT safe to call directly
     }
 
     /// Internal routine for switching into a backend without information about where registers are preserved.
@@ -28,20 +32,28 @@ pub mod raw {
 }
 
 use crate::codegen::{BreakpointInfo, BreakpointMap};
+use crate::error::{RuntimeError, TrapCode, UnresolvedTrap};
+use crate::interrupt;
 use crate::state::x64::{build_instance_image, read_stack, X64Register, GPR};
 use crate::state::{CodeVersion, ExecutionStateImage};
+use crate::typed_func::WasmTrapInfo;
 use crate::vm;
-use libc::{mmap, mprotect, siginfo_t, MAP_ANON, MAP_PRIVATE, PROT_NONE, PROT_READ, PROT_WRITE};
+use libc::{
+    itimerval, mmap, mprotect, setitimer, siginfo_t, timeval, ITIMER_PROF, MAP_ANON, MAP_PRIVATE,
+    PROT_NONE, PROT_READ, PROT_WRITE,
+};
 use nix::sys::signal::{
     sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal, SIGBUS, SIGFPE, SIGILL, SIGINT,
-    SIGSEGV, SIGTRAP,
+    SIGPROF, SIGSEGV, SIGTRAP,
 };
 use std::any::Any;
 use std::cell::{Cell, RefCell, UnsafeCell};
 use std::ffi::c_void;
+use std::io;
 use std::process;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Once;
+use std::sync::{Mutex, Once};
+use std::time::Duration;
 
 #[cfg(target_arch = "x86_64")]
 pub(crate) unsafe fn run_on_alternative_stack(stack_end: *mut u64, stack_begin: *mut u64) -> u64 {
@@ -55,6 +67,10 @@ pub(crate) unsafe fn run_on_alternative_stack(_stack_end: *mut u64, _stack_begin
 
 const TRAP_STACK_SIZE: usize = 1048576; // 1MB
 
+/// Backends that trap with `ud2` can follow it with this byte and a `WasmTrapInfo` byte,
+/// to tell the fault handler why the trap occurred.
+pub const TRAP_CODE_MARKER: u8 = 0xcc; // int3
+
 const SETJMP_BUFFER_LEN: usize = 128;
 type SetJmpBuffer = [i32; SETJMP_BUFFER_LEN];
 
@@ -85,9 +101,38 @@ thread_local! {
     static CURRENT_CTX: UnsafeCell<*mut vm::Ctx> = UnsafeCell::new(::std::ptr::null_mut());
     static CURRENT_CODE_VERSIONS: RefCell<Vec<CodeVersion>> = RefCell::new(vec![]);
     static WAS_SIGINT_TRIGGERED: Cell<bool> = Cell::new(false);
+    static SAMPLER: RefCell<Option<Box<dyn FnMut(&FaultInfo)>>> = RefCell::new(None);
     static BOUNDARY_REGISTER_PRESERVATION: UnsafeCell<BoundaryRegisterPreservation> = UnsafeCell::new(BoundaryRegisterPreservation::default());
 }
 
+/// The per-stack part of the trap handling state.
+///
+/// Each stack that runs WebAssembly code needs its own unwind target, so
+/// switching between stacks swaps this state in and out of the thread.
+#[cfg(target_arch = "x86_64")]
+pub(crate) struct TrapState {
+    unwind: Option<UnwindInfo>,
+    ctx: *mut vm::Ctx,
+}
+
+#[cfg(target_arch = "x86_64")]
+impl TrapState {
+    /// The state of a stack that has not entered WebAssembly code yet.
+    pub(crate) fn new() -> TrapState {
+        TrapState {
+            unwind: None,
+            ctx: ::std::ptr::null_mut(),
+        }
+    }
+}
+
+/// Exchanges the trap handling state of the current thread with `state`.
+#[cfg(target_arch = "x86_64")]
+pub(crate) unsafe fn swap_trap_state(state: &mut TrapState) {
+    UNWIND.with(|x| ::std::mem::swap(&mut *x.get(), &mut state.unwind));
+    CURRENT_CTX.with(|x| ::std::mem::swap(&mut *x.get(), &mut state.ctx));
+}
+
 /// Gets a mutable pointer to the `BoundaryRegisterPreservation`.
 #[no_mangle]
 pub unsafe extern "C" fn get_boundary_register_preservation() -> *mut BoundaryRegisterPreservation {
@@ -98,7 +143,7 @@ struct InterruptSignalMem(*mut u8);
 unsafe impl Send for InterruptSignalMem {}
 unsafe impl Sync for InterruptSignalMem {}
 
-const INTERRUPT_SIGNAL_MEM_SIZE: usize = 4096;
+pub(crate) const INTERRUPT_SIGNAL_MEM_SIZE: usize = 4096;
 
 lazy_static! {
     static ref INTERRUPT_SIGNAL_MEM: InterruptSignalMem = {
@@ -120,6 +165,11 @@ lazy_static! {
 }
 static INTERRUPT_SIGNAL_DELIVERED: AtomicBool = AtomicBool::new(false);
 
+lazy_static! {
+    /// The number of `start_sampling_timer` calls not matched by a `stop_sampling_timer` yet.
+    static ref SAMPLING_TIMER_USERS: Mutex<usize> = Mutex::new(0);
+}
+
 /// Returns a boolean indicating if SIGINT triggered the fault.
 pub fn was_sigint_triggered_fault() -> bool {
     WAS_SIGINT_TRIGGERED.with(|x| x.get())
@@ -178,6 +228,77 @@ pub unsafe fn clear_wasm_interrupt() {
     }
 }
 
+/// Sets the function called with the fault info of the current thread when its WebAssembly
+/// code stops for a sample, and returns the previous one.
+pub fn set_sampler(
+    sampler: Option<Box<dyn FnMut(&FaultInfo)>>,
+) -> Option<Box<dyn FnMut(&FaultInfo)>> {
+    SAMPLER.with(|x| ::std::mem::replace(&mut *x.borrow_mut(), sampler))
+}
+
+/// Requests a sample: the WebAssembly code of each instance sampled through its
+/// `InterruptHandle` stops at the next function or loop header, calls the sampler of its
+/// thread and goes on. Other instances are not disturbed.
+///
+/// Only code that checks the wasm interrupt signal mem, as generated by the singlepass
+/// backend, stops. This is safe to call from a signal handler.
+pub unsafe fn request_sample() {
+    interrupt::request_samples();
+}
+
+/// Starts requesting a sample every `interval` of CPU time used by the process.
+///
+/// There is a single timer for the whole process, and each tick requests a sample from every
+/// instance sampled through its `InterruptHandle`, on whichever thread it runs. Calls made
+/// while the timer runs keep its interval and only count one more user; each successful call
+/// must be matched by a `stop_sampling_timer`, and the timer stops with the last one.
+pub fn start_sampling_timer(interval: Duration) -> io::Result<()> {
+    let mut users = SAMPLING_TIMER_USERS.lock().unwrap();
+    if *users == 0 {
+        INSTALL_SAMPLING_HANDLER.call_once(|| unsafe {
+            let sa_sample = SigAction::new(
+                SigHandler::SigAction(sigprof_handler),
+                SaFlags::SA_ONSTACK | SaFlags::SA_RESTART,
+                SigSet::empty(),
+            );
+            sigaction(SIGPROF, &sa_sample).unwrap();
+        });
+        ensure_sighandler();
+        set_sampling_timer(timeval {
+            tv_sec: interval.as_secs() as _,
+            tv_usec: interval.subsec_micros().max(1) as _,
+        })?;
+    }
+    *users += 1;
+    Ok(())
+}
+
+/// Matches a call to `start_sampling_timer`. The last one stops the timer.
+pub fn stop_sampling_timer() -> io::Result<()> {
+    let mut users = SAMPLING_TIMER_USERS.lock().unwrap();
+    if *users != 1 {
+        *users = users.saturating_sub(1);
+        return Ok(());
+    }
+    set_sampling_timer(timeval {
+        tv_sec: 0,
+        tv_usec: 0,
+    })?;
+    *users = 0;
+    Ok(())
+}
+
+fn set_sampling_timer(interval: timeval) -> io::Result<()> {
+    let value = itimerval {
+        it_interval: interval,
+        it_value: interval,
+    };
+    if unsafe { setitimer(ITIMER_PROF, &value, ::std::ptr::null_mut()) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
 /// Catches an unsafe unwind with the given functions and breakpoints.
 pub unsafe fn catch_unsafe_unwind<R, F: FnOnce() -> R>(
     f: F,
@@ -338,6 +459,7 @@ extern "C" fn signal_trap_handler(
 
         should_unwind = allocate_and_run(TRAP_STACK_SIZE, || {
             let mut is_suspend_signal = false;
+            let mut trap_code = TrapCode::Unknown;
 
             WAS_SIGINT_TRIGGERED.with(|x| x.set(false));
 
@@ -363,13 +485,38 @@ extern "C" fn signal_trap_handler(
                         None => {}
                     }
                 }
+                Ok(SIGILL) => {
+                    trap_code = read_trap_code(fault.ip.get());
+                }
+                Ok(SIGFPE) => {
+                    trap_code = TrapCode::IllegalArithmetic;
+                }
                 Ok(SIGSEGV) | Ok(SIGBUS) => {
+                    trap_code = TrapCode::MemoryOutOfBounds;
                     if fault.faulting_addr as usize == get_wasm_interrupt_signal_mem() as usize {
                         is_suspend_signal = true;
                         clear_wasm_interrupt();
                         if INTERRUPT_SIGNAL_DELIVERED.swap(false, Ordering::SeqCst) {
                             WAS_SIGINT_TRIGGERED.with(|x| x.set(true));
                         }
+                    } else if interrupt::is_interrupt_signal_mem(fault.faulting_addr as _) {
+                        let (interrupted, sampled) =
+                            interrupt::take_requests(fault.faulting_addr as _);
+                        if sampled {
+                            SAMPLER.with(|x| {
+                                if let Some(sampler) = x.borrow_mut().as_mut() {
+                                    sampler(&fault);
+                                }
+                            });
+                        }
+                        if !interrupted {
+                            // Only a sample was requested, or the request was dropped after
+                            // the fault, so carry on.
+                            return false;
+                        }
+                        // An `InterruptHandle` stopped this instance.
+                        unwind_result = Box::new(RuntimeError::Interrupted);
+                        return true;
                     }
                 }
                 _ => {}
@@ -389,9 +536,12 @@ extern "C" fn signal_trap_handler(
                         "\n{}",
                         "Wasmer encountered an error while running your WebAssembly program."
                     );
-                    es_image.print_backtrace_if_needed();
+                    es_image.print_backtrace_if_needed(&(*ctx.module).info);
                 }
-                // Just let the error propagate otherwise
+                unwind_result = Box::new(UnresolvedTrap {
+                    code: trap_code,
+                    image: es_image,
+                });
             }
 
             true
@@ -403,6 +553,20 @@ extern "C" fn signal_trap_handler(
     }
 }
 
+/// Reads the trap code tagged onto the `ud2` at `ip`, if any.
+unsafe fn read_trap_code(ip: usize) -> TrapCode {
+    let ip = ip as *const u8;
+    if cfg!(target_arch = "x86_64")
+        && *ip == 0x0f
+        && *ip.add(1) == 0x0b
+        && *ip.add(2) == TRAP_CODE_MARKER
+    {
+        WasmTrapInfo::from_u8(*ip.add(3)).into()
+    } else {
+        TrapCode::Unknown
+    }
+}
+
 extern "C" fn sigint_handler(
     _signum: ::nix::libc::c_int,
     _siginfo: *mut siginfo_t,
@@ -417,6 +581,16 @@ extern "C" fn sigint_handler(
     }
 }
 
+extern "C" fn sigprof_handler(
+    _signum: ::nix::libc::c_int,
+    _siginfo: *mut siginfo_t,
+    _ucontext: *mut c_void,
+) {
+    unsafe {
+        request_sample();
+    }
+}
+
 /// Ensure the signal handler is installed.
 pub fn ensure_sighandler() {
     INSTALL_SIGHANDLER.call_once(|| unsafe {
@@ -425,6 +599,7 @@ pub fn ensure_sighandler() {
 }
 
 static INSTALL_SIGHANDLER: Once = Once::new();
+static INSTALL_SAMPLING_HANDLER: Once = Once::new();
 
 unsafe fn install_sighandler() {
     let sa_trap = SigAction::new(
@@ -0,0 +1,313 @@
+//! Fibers let a WebAssembly call suspend while one of its host functions
+//! waits on a future, and later resume on the same stack.
+//!
+//! A call started with [`Instance::call_async`] runs on a fiber. Host
+//! functions reached from it use [`wait`] to await a future; the call is
+//! suspended until the future completes, and the thread is free to run other
+//! tasks in the meantime.
+//!
+//! ```
+//! # use wasmer_runtime_core::{fiber, vm};
+//! # use std::future::Future;
+//! # fn read_socket() -> impl Future<Output = i32> { async { 0 } }
+//! fn read(_ctx: &mut vm::Ctx) -> Result<i32, fiber::WaitError> {
+//!     fiber::wait(read_socket())
+//! }
+//! ```
+//!
+//! The future returned by `call_async` is not `Send`: the call keeps its
+//! trap handling state and references in thread-local storage while it runs,
+//! so it must be polled on the thread that created it. Run it on a
+//! single-threaded executor, such as a tokio `LocalSet`.
+//!
+//! [`Instance::call_async`]: ../struct.Instance.html#method.call_async
+//! [`wait`]: fn.wait.html
+
+use crate::{
+    backend::RunnableModule,
+    error::CallResult,
+    fault::{self, raw, TrapState},
+    sys::{self, Protect},
+    table::{self, RefRoots},
+    types::Value,
+};
+use std::{
+    any::Any,
+    cell::Cell,
+    fmt,
+    future::Future,
+    marker::PhantomData,
+    mem,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    ptr,
+    sync::Arc,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+/// Usable stack size of a fiber.
+const FIBER_STACK_SIZE: usize = 4 * 1024 * 1024; // 4MB
+
+thread_local! {
+    static CURRENT_FIBER: Cell<*mut Fiber> = Cell::new(ptr::null_mut());
+}
+
+/// The reasons a host function cannot wait on a future.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WaitError {
+    /// The host function was not called from an asynchronous call.
+    NotAsync,
+    /// The asynchronous call was dropped before it completed.
+    Cancelled,
+}
+
+impl fmt::Display for WaitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WaitError::NotAsync => write!(f, "not running inside an asynchronous call"),
+            WaitError::Cancelled => write!(f, "the asynchronous call was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for WaitError {}
+
+/// Wait for `future` to complete, suspending the asynchronous call that
+/// reached the current host function.
+///
+/// When the call is dropped before the future completes, this does not
+/// return: the future is dropped and the call unwinds from here with a
+/// `WaitError::Cancelled` error, as if the host function had trapped. Host
+/// functions must not catch that unwinding.
+pub fn wait<F: Future>(future: F) -> Result<F::Output, WaitError> {
+    let fiber = CURRENT_FIBER.with(|x| x.get());
+    if fiber.is_null() {
+        return Err(WaitError::NotAsync);
+    }
+
+    let mut future = future;
+    // The future lives on the fiber stack, which never moves.
+    let mut future = unsafe { Pin::new_unchecked(&mut future) };
+    unsafe {
+        loop {
+            if (*fiber).cancelled {
+                // Unwinds the host function, whose wrapper turns this into a trap of the call.
+                panic::resume_unwind(Box::new(WaitError::Cancelled));
+            }
+            if let Poll::Ready(output) = future.as_mut().poll(&mut *(*fiber).cx) {
+                return Ok(output);
+            }
+            (*fiber).suspend();
+        }
+    }
+}
+
+struct Fiber {
+    stack: sys::Memory,
+    /// The stack pointer of the fiber while it is suspended.
+    fiber_sp: *mut u64,
+    /// The stack pointer of the poller while the fiber runs.
+    caller_sp: *mut u64,
+    entry: Option<Box<dyn FnOnce()>>,
+    /// The context of the poll in progress.
+    cx: *mut Context<'static>,
+    runnable_module: Arc<Box<dyn RunnableModule>>,
+    trap_state: TrapState,
+    backend_trap_state: Option<Box<dyn Any>>,
+    /// The references rooted by the call running on the fiber.
+    ref_roots: RefRoots,
+    panic: Option<Box<dyn Any + Send>>,
+    finished: bool,
+    cancelled: bool,
+}
+
+impl Fiber {
+    /// Create a fiber that will run `entry` on its own stack.
+    ///
+    /// The caller must make sure the fiber finishes before anything `entry` borrows goes away.
+    unsafe fn new(
+        runnable_module: Arc<Box<dyn RunnableModule>>,
+        entry: Box<dyn FnOnce() + '_>,
+    ) -> Box<Fiber> {
+        let guard_size = page_size::get();
+        let mut stack = sys::Memory::with_size(guard_size + FIBER_STACK_SIZE)
+            .expect("cannot allocate a fiber stack");
+        // Leave the lowest page inaccessible, so that an overflow faults.
+        stack
+            .protect(guard_size.., Protect::ReadWrite)
+            .expect("cannot protect a fiber stack");
+
+        let entry: Box<dyn FnOnce()> = mem::transmute(entry);
+        let mut fiber = Box::new(Fiber {
+            stack,
+            fiber_sp: ptr::null_mut(),
+            caller_sp: ptr::null_mut(),
+            entry: Some(entry),
+            cx: ptr::null_mut(),
+            runnable_module,
+            trap_state: TrapState::new(),
+            backend_trap_state: None,
+            ref_roots: RefRoots::default(),
+            panic: None,
+            finished: false,
+            cancelled: false,
+        });
+
+        // NOTE: Keep this consistent with `swap_stack` and `fiber_start` in `image-loading-*.s`.
+        // The first switch pops r15, r14, r13, r12, rbx and rbp, then returns to `fiber_start`,
+        // which calls r12 with rbx as its argument on a 16-byte aligned stack.
+        let top = fiber.stack.as_ptr().add(fiber.stack.size()) as *mut u64;
+        let frame = top.offset(-2 - 7);
+        *frame.offset(0) = 0; // r15
+        *frame.offset(1) = 0; // r14
+        *frame.offset(2) = 0; // r13
+        *frame.offset(3) = fiber_main as usize as u64; // r12
+        *frame.offset(4) = &mut *fiber as *mut Fiber as usize as u64; // rbx
+        *frame.offset(5) = 0; // rbp
+        *frame.offset(6) = raw::fiber_start as usize as u64;
+        fiber.fiber_sp = frame;
+
+        fiber
+    }
+
+    /// Run the fiber until it suspends or finishes.
+    ///
+    /// Returns the payload of a panic that escaped the entry point.
+    unsafe fn resume(&mut self, cx: &mut Context) -> Option<Box<dyn Any + Send>> {
+        let this: *mut Fiber = self;
+        self.cx = cx as *mut Context as *mut Context<'static>;
+        let previous = CURRENT_FIBER.with(|x| x.replace(this));
+
+        self.swap_stack_states();
+        raw::swap_stack(&mut self.caller_sp, self.fiber_sp);
+        self.swap_stack_states();
+
+        CURRENT_FIBER.with(|x| x.set(previous));
+        self.cx = ptr::null_mut();
+
+        self.panic.take()
+    }
+
+    /// Switch back to the poller, from the fiber stack.
+    unsafe fn suspend(&mut self) {
+        raw::swap_stack(&mut self.fiber_sp, self.caller_sp);
+    }
+
+    unsafe fn swap_stack_states(&mut self) {
+        fault::swap_trap_state(&mut self.trap_state);
+        self.runnable_module
+            .swap_trap_state(&mut self.backend_trap_state);
+        table::swap_ref_roots(&mut self.ref_roots);
+    }
+}
+
+extern "C" fn fiber_main(fiber: *mut Fiber) {
+    unsafe {
+        let entry = (*fiber).entry.take().unwrap();
+        // Unwinding must not cross `fiber_start`.
+        if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(entry)) {
+            (*fiber).panic = Some(payload);
+        }
+        (*fiber).finished = true;
+        (*fiber).suspend();
+        unreachable!("a finished fiber was resumed");
+    }
+}
+
+/// A WebAssembly call that can suspend while its host functions wait on futures.
+///
+/// Created by [`Instance::call_async`]. Dropping it before it completes
+/// resumes it one last time, to unwind the call from the [`wait`] it is
+/// suspended in, before the instance is released.
+///
+/// `AsyncCall` is not `Send`, see the [module documentation](index.html):
+///
+/// ```compile_fail
+/// # use wasmer_runtime_core::fiber::AsyncCall;
+/// fn assert_send<T: Send>() {}
+/// assert_send::<AsyncCall>();
+/// ```
+///
+/// [`Instance::call_async`]: ../struct.Instance.html#method.call_async
+/// [`wait`]: fn.wait.html
+pub struct AsyncCall<'a> {
+    fiber: Box<Fiber>,
+    result: Box<Option<CallResult<Vec<Value>>>>,
+    _borrow: PhantomData<&'a mut ()>,
+}
+
+impl<'a> AsyncCall<'a> {
+    pub(crate) fn new<F>(runnable_module: Arc<Box<dyn RunnableModule>>, call: F) -> AsyncCall<'a>
+    where
+        F: FnOnce() -> CallResult<Vec<Value>> + 'a,
+    {
+        let mut result = Box::new(None);
+        let slot: *mut Option<CallResult<Vec<Value>>> = &mut *result;
+        let entry = Box::new(move || unsafe { *slot = Some(call()) });
+
+        AsyncCall {
+            // `Drop` runs the fiber to completion, so it never outlives `'a`.
+            fiber: unsafe { Fiber::new(runnable_module, entry) },
+            result,
+            _borrow: PhantomData,
+        }
+    }
+}
+
+impl<'a> Future for AsyncCall<'a> {
+    type Output = CallResult<Vec<Value>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if !this.fiber.finished {
+            if let Some(payload) = unsafe { this.fiber.resume(cx) } {
+                panic::resume_unwind(payload);
+            }
+        }
+
+        if this.fiber.finished {
+            Poll::Ready(
+                this.result
+                    .take()
+                    .expect("`AsyncCall` polled after completion"),
+            )
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<'a> Drop for AsyncCall<'a> {
+    fn drop(&mut self) {
+        if self.fiber.finished || self.fiber.entry.is_some() {
+            return;
+        }
+
+        // `wait` unwinds the call down to the entry point of the fiber, which then finishes.
+        // Whatever the unwinding did not reach is abandoned with the stack.
+        self.fiber.cancelled = true;
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        drop(unsafe { self.fiber.resume(&mut cx) });
+    }
+}
+
+fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+    unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{wait, WaitError};
+
+    #[test]
+    fn test_wait_outside_fiber() {
+        assert_eq!(wait(async { 1 }), Err(WaitError::NotAsync));
+    }
+}
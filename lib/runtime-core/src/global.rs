@@ -3,11 +3,12 @@
 use crate::{
     export::Export,
     import::IsExport,
+    table::AnyRef,
     types::{GlobalDescriptor, Type, Value},
     vm,
 };
 use std::{
-    fmt,
+    fmt, mem,
     sync::{Arc, Mutex},
 };
 
@@ -15,6 +16,8 @@ use std::{
 pub struct Global {
     desc: GlobalDescriptor,
     storage: Arc<Mutex<vm::LocalGlobal>>,
+    /// The reference held by a global of reference type; `storage` holds its raw value.
+    reference: Arc<Mutex<AnyRef>>,
 }
 
 impl Global {
@@ -51,18 +54,17 @@ impl Global {
         };
 
         let local_global = vm::LocalGlobal {
-            data: match value {
-                Value::I32(x) => x as u128,
-                Value::I64(x) => x as u128,
-                Value::F32(x) => x.to_bits() as u128,
-                Value::F64(x) => x.to_bits() as u128,
-                Value::V128(x) => x,
-            },
+            data: value.to_u128(),
+        };
+        let reference = match value {
+            Value::AnyRef(r) => r,
+            _ => AnyRef::null(),
         };
 
         Self {
             desc,
             storage: Arc::new(Mutex::new(local_global)),
+            reference: Arc::new(Mutex::new(reference)),
         }
     }
 
@@ -79,18 +81,17 @@ impl Global {
     /// the wrong type.
     pub fn set(&self, value: Value) {
         if self.desc.mutable {
-            if self.desc.ty == value.ty() {
-                let local_global = vm::LocalGlobal {
-                    data: match value {
-                        Value::I32(x) => x as u128,
-                        Value::I64(x) => x as u128,
-                        Value::F32(x) => x.to_bits() as u128,
-                        Value::F64(x) => x.to_bits() as u128,
-                        Value::V128(x) => x,
-                    },
-                };
-                let mut storage = self.storage.lock().unwrap();
-                *storage = local_global;
+            if value.is_of_type(self.desc.ty) {
+                match value {
+                    Value::AnyRef(r) => self.set_ref(r),
+                    value => {
+                        let local_global = vm::LocalGlobal {
+                            data: value.to_u128(),
+                        };
+                        let mut storage = self.storage.lock().unwrap();
+                        *storage = local_global;
+                    }
+                }
             } else {
                 panic!("Wrong type for setting this global")
             }
@@ -101,6 +102,10 @@ impl Global {
 
     /// Get the value held by this global.
     pub fn get(&self) -> Value {
+        if let Type::AnyRef | Type::FuncRef = self.desc.ty {
+            return Value::AnyRef(self.get_ref());
+        }
+
         let storage = self.storage.lock().unwrap();
         let data = storage.data;
 
@@ -110,9 +115,28 @@ impl Global {
             Type::F32 => Value::F32(f32::from_bits(data as u32)),
             Type::F64 => Value::F64(f64::from_bits(data as u64)),
             Type::V128 => Value::V128(data),
+            Type::AnyRef | Type::FuncRef => unreachable!(),
         }
     }
 
+    /// The reference held by a global of reference type.
+    pub(crate) fn get_ref(&self) -> AnyRef {
+        self.reference.lock().unwrap().clone()
+    }
+
+    /// Store `r` into a global of reference type, without checking its mutability.
+    ///
+    /// WebAssembly stores references through this, so that the global owns what it holds.
+    pub(crate) fn set_ref(&self, r: AnyRef) {
+        let previous = {
+            let mut reference = self.reference.lock().unwrap();
+            self.storage.lock().unwrap().data = r.as_raw() as u128;
+            mem::replace(&mut *reference, r)
+        };
+        // Host values may run arbitrary code when dropped, so release it outside of the locks.
+        drop(previous);
+    }
+
     // TODO: think about this and if this should now be unsafe
     pub(crate) fn vm_local_global(&mut self) -> *mut vm::LocalGlobal {
         let mut storage = self.storage.lock().unwrap();
@@ -131,6 +155,7 @@ impl Clone for Global {
         Self {
             desc: self.desc,
             storage: Arc::clone(&self.storage),
+            reference: Arc::clone(&self.reference),
         }
     }
 }
@@ -1,7 +1,7 @@
 //! The import module contains the implementation data structures and helper functions used to
 //! manipulate and access a wasm module's imports including memories, tables, globals, and
 //! functions.
-use crate::export::Export;
+use crate::{export::Export, limits::ResourceLimiter, memory::MemoryCreator, types::ExternType};
 use std::collections::VecDeque;
 use std::collections::{hash_map::Entry, HashMap};
 use std::{
@@ -21,6 +21,9 @@ pub trait LikeNamespace {
     fn maybe_insert(&mut self, name: &str, export: Export) -> Option<()>;
 }
 
+/// The type an import is expected to have, as given to an import resolver.
+pub type ImportType = ExternType;
+
 /// A trait that represents `Export` values.
 pub trait IsExport {
     /// Gets self as `Export`.
@@ -58,6 +61,10 @@ pub struct ImportObject {
     map: Arc<Mutex<HashMap<String, Box<dyn LikeNamespace + Send>>>>,
     pub(crate) state_creator:
         Option<Arc<dyn Fn() -> (*mut c_void, fn(*mut c_void)) + Send + Sync + 'static>>,
+    pub(crate) resource_limiter: Option<Arc<dyn ResourceLimiter>>,
+    pub(crate) memory_creator: Option<Arc<dyn MemoryCreator>>,
+    import_resolver:
+        Option<Arc<dyn Fn(&str, &str, &ImportType) -> Option<Export> + Send + Sync + 'static>>,
     /// Allow missing functions to be generated and instantiation to continue when required
     /// functions are not provided.
     pub allow_missing_functions: bool,
@@ -69,6 +76,9 @@ impl ImportObject {
         Self {
             map: Arc::new(Mutex::new(HashMap::new())),
             state_creator: None,
+            resource_limiter: None,
+            memory_creator: None,
+            import_resolver: None,
             allow_missing_functions: false,
         }
     }
@@ -81,6 +91,9 @@ impl ImportObject {
         Self {
             map: Arc::new(Mutex::new(HashMap::new())),
             state_creator: Some(Arc::new(state_creator)),
+            resource_limiter: None,
+            memory_creator: None,
+            import_resolver: None,
             allow_missing_functions: false,
         }
     }
@@ -89,6 +102,46 @@ impl ImportObject {
         self.state_creator.as_ref().map(|state_gen| state_gen())
     }
 
+    /// Limit the memories, tables and instance count of the instances created with this
+    /// `ImportObject`, in addition to the process-wide limiter.
+    ///
+    /// Instances created with the same limiter share its budget. The memories and tables
+    /// that these instances define stay charged to it until they are dropped.
+    pub fn set_resource_limiter(&mut self, limiter: Arc<dyn ResourceLimiter>) {
+        self.resource_limiter = Some(limiter);
+    }
+
+    /// Allocate the local memories of the instances created with this `ImportObject`
+    /// through `creator`, rather than mapping fresh memory for each of them.
+    pub fn set_memory_creator(&mut self, creator: Arc<dyn MemoryCreator>) {
+        self.memory_creator = Some(creator);
+    }
+
+    /// Consult `resolver` for the imports that are not found in the registered namespaces.
+    /// It is given the namespace and name of the import and the type it should have, and
+    /// may return an export created on demand, e.g. a stub that traps when called.
+    ///
+    /// Returning `None` leaves the import unresolved. The resolver is consulted for every
+    /// such import before instantiation fails, so it also sees all of the missing imports.
+    pub fn set_import_resolver<F>(&mut self, resolver: F)
+    where
+        F: Fn(&str, &str, &ImportType) -> Option<Export> + Send + Sync + 'static,
+    {
+        self.import_resolver = Some(Arc::new(resolver));
+    }
+
+    /// Looks up an import in the registered namespaces, then with the import resolver.
+    pub(crate) fn resolve<T>(&self, namespace: &str, name: &str, import_type: T) -> Option<Export>
+    where
+        T: FnOnce() -> ImportType,
+    {
+        self.maybe_with_namespace(namespace, |namespace| namespace.get_export(name))
+            .or_else(|| {
+                let resolver = self.import_resolver.as_ref()?;
+                resolver(namespace, name, &import_type())
+            })
+    }
+
     /// Register anything that implements `LikeNamespace` as a namespace.
     ///
     /// # Usage:
@@ -163,6 +216,9 @@ impl ImportObject {
         Self {
             map: Arc::clone(&self.map),
             state_creator: self.state_creator.clone(),
+            resource_limiter: self.resource_limiter.clone(),
+            memory_creator: self.memory_creator.clone(),
+            import_resolver: self.import_resolver.clone(),
             allow_missing_functions: false,
         }
     }
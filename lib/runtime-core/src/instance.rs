@@ -1,22 +1,37 @@
 //! The instance module contains the implementation data structures and helper functions used to
 //! manipulate and access wasm instances.
+#[cfg(all(unix, target_arch = "x86_64"))]
+use crate::fiber::AsyncCall;
 use crate::{
     backend::RunnableModule,
     backing::{ImportBacking, LocalBacking},
-    error::{CallError, CallResult, ResolveError, ResolveResult, Result, RuntimeError},
+    error::{
+        CallError, CallResult, CreationError, ResolveError, ResolveResult, RestoreError, Result,
+        RuntimeError,
+    },
     export::{Context, Export, ExportIter, FuncPointer},
     global::Global,
     import::{ImportObject, LikeNamespace},
+    limits::{InstanceCount, Limiters},
     loader::Loader,
     memory::Memory,
     module::{ExportIndex, Module, ModuleInfo, ModuleInner},
     sig_registry::SigRegistry,
-    structures::TypedIndex,
-    table::Table,
+    state::{ExecutionStateImage, InstanceImage},
+    structures::{BoxedMap, TypedIndex},
+    table::{root_ref, AnyRef, CallScope, Table},
     typed_func::{Func, Wasm, WasmTrapInfo, WasmTypeList},
-    types::{FuncIndex, FuncSig, GlobalIndex, LocalOrImport, MemoryIndex, TableIndex, Type, Value},
+    types::{
+        FuncIndex, FuncSig, GlobalIndex, LocalMemoryIndex, LocalOrImport, MemoryIndex, TableIndex,
+        Type, Value,
+    },
     vm::{self, InternalField},
 };
+#[cfg(unix)]
+use crate::{
+    error::InterruptHandleError,
+    interrupt::{InterruptHandle, InterruptSignal},
+};
 use smallvec::{smallvec, SmallVec};
 use std::{
     mem,
@@ -30,6 +45,9 @@ pub(crate) struct InstanceInner {
     pub(crate) backing: LocalBacking,
     import_backing: ImportBacking,
     pub(crate) vmctx: *mut vm::Ctx,
+    #[cfg(unix)]
+    interrupt_signal: Mutex<Option<Arc<InterruptSignal>>>,
+    _instance_count: InstanceCount,
 }
 
 // manually implemented because InstanceInner contains a raw pointer to Ctx
@@ -58,20 +76,42 @@ pub struct Instance {
 }
 
 impl Instance {
-    pub(crate) fn new(module: Arc<ModuleInner>, imports: &ImportObject) -> Result<Instance> {
+    /// Instantiates `module`.
+    ///
+    /// `memories`, if given, are used as the local memories instead of creating new ones;
+    /// they are already initialized, so data segments are not copied into them. The start
+    /// function only runs if `run_start` is true.
+    pub(crate) fn new(
+        module: Arc<ModuleInner>,
+        imports: &ImportObject,
+        memories: Option<&BoxedMap<LocalMemoryIndex, Memory>>,
+        run_start: bool,
+    ) -> Result<Instance> {
         // We need the backing and import_backing to create a vm::Ctx, but we need
         // a vm::Ctx to create a backing and an import_backing. The solution is to create an
         // uninitialized vm::Ctx and then initialize it in-place.
+        let instance_count = InstanceCount::new(Limiters::new(imports.resource_limiter.as_ref()))?;
+
         let mut vmctx: Box<mem::MaybeUninit<vm::Ctx>> =
             Box::new(mem::MaybeUninit::<vm::Ctx>::zeroed());
 
         let import_backing = ImportBacking::new(&module, &imports, vmctx.as_mut_ptr())?;
-        let backing = LocalBacking::new(&module, &import_backing, vmctx.as_mut_ptr())?;
+        let backing = LocalBacking::new(
+            &module,
+            &import_backing,
+            vmctx.as_mut_ptr(),
+            memories,
+            imports.resource_limiter.clone(),
+            imports.memory_creator.clone(),
+        )?;
 
         let mut inner = Box::pin(InstanceInner {
             backing,
             import_backing,
             vmctx: vmctx.as_mut_ptr(),
+            #[cfg(unix)]
+            interrupt_signal: Mutex::new(None),
+            _instance_count: instance_count,
         });
 
         // Initialize the vm::Ctx in-place after the backing
@@ -95,26 +135,35 @@ impl Instance {
             import_object: imports.clone_ref(),
         };
 
-        if let Some(start_index) = instance.module.info.start_func {
+        if run_start {
+            instance.call_start_func()?;
+        }
+
+        Ok(instance)
+    }
+
+    /// Runs the start function of the module, if it has one.
+    pub(crate) fn call_start_func(&self) -> Result<()> {
+        if let Some(start_index) = self.module.info.start_func {
             // We know that the start function takes no arguments and returns no values.
             // Therefore, we can call it without doing any signature checking, etc.
 
-            let func_ptr = match start_index.local_or_import(&instance.module.info) {
-                LocalOrImport::Local(local_func_index) => instance
+            let func_ptr = match start_index.local_or_import(&self.module.info) {
+                LocalOrImport::Local(local_func_index) => self
                     .module
                     .runnable_module
-                    .get_func(&instance.module.info, local_func_index)
+                    .get_func(&self.module.info, local_func_index)
                     .unwrap(),
                 LocalOrImport::Import(import_func_index) => NonNull::new(
-                    instance.inner.import_backing.vm_functions[import_func_index].func as *mut _,
+                    self.inner.import_backing.vm_functions[import_func_index].func as *mut _,
                 )
                 .unwrap(),
             };
 
-            let ctx_ptr = match start_index.local_or_import(&instance.module.info) {
-                LocalOrImport::Local(_) => instance.inner.vmctx,
+            let ctx_ptr = match start_index.local_or_import(&self.module.info) {
+                LocalOrImport::Local(_) => self.inner.vmctx,
                 LocalOrImport::Import(imported_func_index) => unsafe {
-                    instance.inner.import_backing.vm_functions[imported_func_index]
+                    self.inner.import_backing.vm_functions[imported_func_index]
                         .func_ctx
                         .as_ref()
                 }
@@ -122,17 +171,17 @@ impl Instance {
                 .as_ptr(),
             };
 
-            let sig_index = *instance
+            let sig_index = *self
                 .module
                 .info
                 .func_assoc
                 .get(start_index)
                 .expect("broken invariant, incorrect func index");
 
-            let wasm_trampoline = instance
+            let wasm_trampoline = self
                 .module
                 .runnable_module
-                .get_trampoline(&instance.module.info, sig_index)
+                .get_trampoline(&self.module.info, sig_index)
                 .expect("wasm trampoline");
 
             let start_func: Func<(), (), Wasm> =
@@ -141,7 +190,7 @@ impl Instance {
             start_func.call()?;
         }
 
-        Ok(instance)
+        Ok(())
     }
 
     /// Load an `Instance` using the given loader.
@@ -324,8 +373,8 @@ impl Instance {
     /// [`Value`]: enum.Value.html
     ///
     /// # Note:
-    /// This returns `CallResult<Vec<Value>>` in order to support
-    /// the future multi-value returns WebAssembly feature.
+    /// This returns `CallResult<Vec<Value>>` since a function may
+    /// return several values with the multi-value feature.
     ///
     /// # Usage:
     /// ```
@@ -373,6 +422,131 @@ impl Instance {
         Ok(results)
     }
 
+    /// Gets an exported global by name.
+    ///
+    /// # Usage:
+    /// ```
+    /// # use wasmer_runtime_core::error::ResolveResult;
+    /// # use wasmer_runtime_core::Instance;
+    /// # fn get_global(instance: &Instance) -> ResolveResult<()> {
+    /// let counter = instance.global("counter")?;
+    /// let value = counter.get();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn global(&self, name: &str) -> ResolveResult<Global> {
+        match self.export(name)? {
+            Export::Global(global) => Ok(global),
+            _ => Err(ResolveError::ExportWrongType {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// Gets an exported table by name.
+    pub fn table(&self, name: &str) -> ResolveResult<Table> {
+        match self.export(name)? {
+            Export::Table(table) => Ok(table),
+            _ => Err(ResolveError::ExportWrongType {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// Gets an exported memory by name, which need not be the default memory.
+    pub fn memory(&self, name: &str) -> ResolveResult<Memory> {
+        match self.export(name)? {
+            Export::Memory(memory) => Ok(memory),
+            _ => Err(ResolveError::ExportWrongType {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    fn export(&self, name: &str) -> ResolveResult<Export> {
+        let export_index =
+            self.module
+                .info
+                .exports
+                .get(name)
+                .ok_or_else(|| ResolveError::ExportNotFound {
+                    name: name.to_string(),
+                })?;
+        Ok(self.inner.get_export_from_index(&self.module, export_index))
+    }
+
+    /// Call an exported WebAssembly function on its own stack, so that the
+    /// host functions it reaches can wait on futures with [`fiber::wait`].
+    ///
+    /// The returned future resolves to the same results as [`call`]. While a
+    /// host function waits, the call is suspended and the future is pending.
+    ///
+    /// [`fiber::wait`]: fiber/fn.wait.html
+    /// [`call`]: struct.Instance.html#method.call
+    ///
+    /// # Usage:
+    /// ```
+    /// # use wasmer_runtime_core::types::Value;
+    /// # use wasmer_runtime_core::error::CallResult;
+    /// # use wasmer_runtime_core::Instance;
+    /// # async fn call_foo(instance: &mut Instance) -> CallResult<()> {
+    /// let results = instance.call_async("foo", &[Value::I32(42)]).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(all(unix, target_arch = "x86_64"))]
+    pub fn call_async(&mut self, name: &str, params: &[Value]) -> AsyncCall<'_> {
+        let name = name.to_string();
+        let params = params.to_vec();
+        let runnable_module = Arc::clone(&self.module.runnable_module);
+        let instance: &Instance = self;
+
+        AsyncCall::new(runnable_module, move || instance.call(&name, &params))
+    }
+
+    /// Returns a handle that stops this instance from another thread, without
+    /// affecting any other instance.
+    ///
+    /// The first call gives the instance an interrupt signal of its own; every
+    /// call after that returns a handle to the same signal.
+    ///
+    /// Fails with [`InterruptHandleError`]`::Unsupported` if the backend that compiled
+    /// the module does not check for interrupts; only singlepass does. Fails with
+    /// `TooManyHandles` if every interrupt signal page is taken; the page of an
+    /// instance is freed once the instance and all of its handles are dropped.
+    ///
+    /// # Usage:
+    /// ```
+    /// # use std::time::Duration;
+    /// # use wasmer_runtime_core::Instance;
+    /// # fn run_with_timeout(instance: &Instance) {
+    /// let handle = instance.interrupt_handle().unwrap();
+    /// handle.set_timeout(Duration::from_secs(1));
+    /// // Returns `Err(CallError::Runtime(RuntimeError::Interrupted))` if it runs for too long.
+    /// let result = instance.call("main", &[]);
+    /// handle.clear_deadline();
+    /// # }
+    /// ```
+    ///
+    /// [`InterruptHandleError`]: error/enum.InterruptHandleError.html
+    #[cfg(unix)]
+    pub fn interrupt_handle(&self) -> std::result::Result<InterruptHandle, InterruptHandleError> {
+        if !self.module.runnable_module.supports_interrupts() {
+            return Err(InterruptHandleError::Unsupported);
+        }
+        let mut interrupt_signal = self.inner.interrupt_signal.lock().unwrap();
+        if interrupt_signal.is_none() {
+            let signal = InterruptSignal::new().ok_or(InterruptHandleError::TooManyHandles)?;
+            unsafe {
+                (*self.inner.vmctx).internal.interrupt_signal_mem = signal.mem();
+            }
+            *interrupt_signal = Some(signal);
+        }
+        Ok(InterruptHandle::new(Arc::clone(
+            interrupt_signal.as_ref().unwrap(),
+        )))
+    }
+
     /// Returns an immutable reference to the
     /// [`Ctx`] used by this Instance.
     ///
@@ -389,6 +563,33 @@ impl Instance {
         unsafe { &mut *self.inner.vmctx }
     }
 
+    /// Captures the state of this instance between calls: the contents of memory 0, the
+    /// values of the local globals and the elements of the local tables.
+    ///
+    /// The image can be persisted with [`InstanceImage::to_bytes`] and loaded into a fresh
+    /// instance of the same module, possibly on another host, with [`Instance::restore`].
+    /// Table elements that refer to functions of other instances or to host references are
+    /// recorded as [`TableElementImage::Foreign`] and cannot be restored.
+    ///
+    /// [`InstanceImage::to_bytes`]: state/struct.InstanceImage.html#method.to_bytes
+    /// [`Instance::restore`]: struct.Instance.html#method.restore
+    /// [`TableElementImage::Foreign`]: state/enum.TableElementImage.html#variant.Foreign
+    pub fn snapshot(&self) -> InstanceImage {
+        let execution_state = ExecutionStateImage { frames: vec![] };
+        unsafe { InstanceImage::capture(&*self.inner.vmctx, execution_state) }
+    }
+
+    /// Writes the memory, globals and tables captured by [`Instance::snapshot`] into this
+    /// instance, which must be an instance of the same module.
+    ///
+    /// Memories and tables grow to their size in the image. The execution state of the image
+    /// is ignored, so it should be restored between calls only.
+    ///
+    /// [`Instance::snapshot`]: struct.Instance.html#method.snapshot
+    pub fn restore(&mut self, image: &InstanceImage) -> ::std::result::Result<(), RestoreError> {
+        unsafe { image.restore(&mut *self.inner.vmctx) }
+    }
+
     /// Returns an iterator over all of the items
     /// exported from this instance.
     pub fn exports(&self) -> ExportIter {
@@ -409,6 +610,55 @@ impl Instance {
     pub fn set_internal(&mut self, field: &InternalField, value: u64) {
         self.inner.backing.internals.0[field.index()] = value;
     }
+
+    /// Returns a handle for creating more instances of this module that
+    /// share its memories, such as one instance per thread of a threaded program.
+    ///
+    /// Every memory defined by the module must be shared. Imported memories
+    /// are shared by passing the same [`Memory`] in each instance's imports.
+    ///
+    /// [`Memory`]: memory/struct.Memory.html
+    pub fn spawner(&self) -> Result<InstanceSpawner> {
+        let memories = &self.inner.backing.memories;
+        if memories.iter().any(|(_, memory)| !memory.is_shared()) {
+            return Err(CreationError::InvalidDescriptor(
+                "only instances whose memories are all shared can be spawned".to_string(),
+            )
+            .into());
+        }
+
+        Ok(InstanceSpawner {
+            module: Arc::clone(&self.module),
+            memories: memories.clone(),
+        })
+    }
+}
+
+/// Creates instances that share the memories of the instance it was made from.
+///
+/// A spawner can be sent to another thread to create an instance there.
+/// See [`Instance::spawner`].
+///
+/// [`Instance::spawner`]: struct.Instance.html#method.spawner
+#[derive(Clone)]
+pub struct InstanceSpawner {
+    module: Arc<ModuleInner>,
+    memories: BoxedMap<LocalMemoryIndex, Memory>,
+}
+
+impl InstanceSpawner {
+    /// Instantiate the module again with the given imports.
+    ///
+    /// The shared memories have already been initialized, so data segments
+    /// are not copied into them again; the start function does run.
+    pub fn spawn(&self, import_object: &ImportObject) -> Result<Instance> {
+        Instance::new(
+            Arc::clone(&self.module),
+            import_object,
+            Some(&self.memories),
+            true,
+        )
+    }
 }
 
 impl InstanceInner {
@@ -623,6 +873,9 @@ pub(crate) fn call_func_with_index_inner(
 ) -> CallResult<()> {
     rets.clear();
 
+    // Keeps the references passed to and returned by WebAssembly alive until the call ends.
+    let _scope = CallScope::enter();
+
     let num_results = signature.returns().len();
     let num_results = num_results
         + signature
@@ -663,6 +916,9 @@ pub(crate) fn call_func_with_index_inner(
                 hi.clone_from_slice(&bytes[8..16]);
                 raw_args.push(u64::from_le_bytes(hi));
             }
+            Value::AnyRef(r) => {
+                raw_args.push(root_ref(r));
+            }
         }
     }
 
@@ -691,11 +947,12 @@ pub(crate) fn call_func_with_index_inner(
             Ok(())
         } else {
             if let Some(data) = user_error {
-                Err(RuntimeError::Error { data })
+                Err(RuntimeError::from_user_error(
+                    data,
+                    &(*(*ctx_ptr).module).info,
+                ))
             } else {
-                Err(RuntimeError::Trap {
-                    msg: trap_info.to_string().into(),
-                })
+                Err(RuntimeError::from_trap_info(trap_info))
             }
         }
     };
@@ -706,6 +963,7 @@ pub(crate) fn call_func_with_index_inner(
         Type::F32 => Value::F32(f32::from_bits(raw as u32)),
         Type::F64 => Value::F64(f64::from_bits(raw)),
         Type::V128 => unreachable!("V128 does not map to any single value"),
+        Type::AnyRef | Type::FuncRef => Value::AnyRef(unsafe { AnyRef::from_raw(raw) }),
     };
 
     match signature.returns() {
@@ -742,12 +1000,17 @@ pub(crate) fn call_func_with_index_inner(
 
             run_wasm(results.as_mut_ptr())?;
 
-            rets.extend(
-                results
-                    .iter()
-                    .zip(result_tys.iter())
-                    .map(|(&raw, &ty)| raw_to_value(raw, ty)),
-            );
+            let mut slots = results.iter().cloned();
+            for &ty in result_tys {
+                let raw = slots.next().unwrap();
+                rets.push(match ty {
+                    Type::V128 => {
+                        let hi = slots.next().unwrap();
+                        Value::V128(((hi as u128) << 64) | raw as u128)
+                    }
+                    _ => raw_to_value(raw, ty),
+                });
+            }
 
             Ok(())
         }
@@ -771,8 +1034,8 @@ impl<'a> DynFunc<'a> {
     /// [`Value`]: enum.Value.html
     ///
     /// # Note:
-    /// This returns `CallResult<Vec<Value>>` in order to support
-    /// the future multi-value returns WebAssembly feature.
+    /// This returns `CallResult<Vec<Value>>` since a function may
+    /// return several values with the multi-value feature.
     ///
     /// # Usage:
     /// ```
@@ -832,4 +1095,9 @@ mod test {
     fn test_instance_is_send() {
         is_send::<Instance>();
     }
+
+    #[test]
+    fn test_instance_spawner_is_send() {
+        is_send::<InstanceSpawner>();
+    }
 }
@@ -0,0 +1,396 @@
+//! Interrupting the WebAssembly code of a single instance from another thread.
+//!
+//! Compiled code reads the interrupt signal page of its `vm::Ctx` at every
+//! function entry and loop header. By default all instances share the page that
+//! `fault::set_wasm_interrupt` revokes on SIGINT. An instance that hands out an
+//! [`InterruptHandle`] gets a page of its own instead, so revoking access to it
+//! only stops that instance: the next read faults, and the fault handler turns
+//! it into `RuntimeError::Interrupted`.
+//!
+//! The same page stops an instance for a sample while it is profiled: the
+//! sampling timer of `fault` revokes the pages of the instances that called
+//! [`InterruptHandle::start_sampling`], and only those instances fault on a tick.
+//!
+//! Only the singlepass backend emits these reads; on modules compiled by the
+//! other backends, `Instance::interrupt_handle` fails with
+//! `InterruptHandleError::Unsupported`.
+//!
+//! [`InterruptHandle`]: struct.InterruptHandle.html
+//! [`InterruptHandle::start_sampling`]: struct.InterruptHandle.html#method.start_sampling
+
+use crate::fault::INTERRUPT_SIGNAL_MEM_SIZE;
+use libc::{mmap, mprotect, MAP_ANON, MAP_PRIVATE, PROT_NONE, PROT_READ, PROT_WRITE};
+use parking_lot::{Condvar, Mutex};
+use std::{
+    cmp::Ordering as CmpOrdering,
+    collections::BinaryHeap,
+    ptr,
+    sync::{
+        atomic::{AtomicPtr, AtomicU8, AtomicUsize, Ordering},
+        Arc, Once, Weak,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The number of interrupt signal pages that can be live at once.
+const MAX_SIGNAL_PAGES: usize = 65536;
+
+/// The start of the region that interrupt signal pages are carved from, or
+/// zero until it is reserved. Read from the signal handler, so it cannot sit
+/// behind a lock.
+static SIGNAL_REGION: AtomicUsize = AtomicUsize::new(0);
+
+/// The requests pending on each page, indexed like the pages. Leaked by
+/// `SignalPages::reserve` and read from the signal handlers, like `SIGNAL_REGION`.
+static PAGE_STATES: AtomicPtr<AtomicU8> = AtomicPtr::new(ptr::null_mut());
+/// One more than the highest index of a page handed out so far.
+static PAGES_USED: AtomicUsize = AtomicUsize::new(0);
+/// The number of pages whose instance is being sampled.
+static SAMPLED_PAGES: AtomicUsize = AtomicUsize::new(0);
+
+/// An `InterruptHandle` interrupted the instance.
+const INTERRUPT_REQUESTED: u8 = 1;
+/// The sampling timer asked the instance for a sample.
+const SAMPLE_REQUESTED: u8 = 2;
+/// The instance is sampled on each tick of the sampling timer.
+const SAMPLED: u8 = 4;
+
+lazy_static! {
+    static ref SIGNAL_PAGES: Mutex<SignalPages> = Mutex::new(SignalPages::reserve());
+    static ref DEADLINES: Deadlines = Deadlines {
+        queue: Mutex::new(BinaryHeap::new()),
+        condvar: Condvar::new(),
+    };
+}
+
+static START_WATCHDOG: Once = Once::new();
+
+struct SignalPages {
+    next: usize,
+    free: Vec<usize>,
+}
+
+impl SignalPages {
+    /// Reserve address space for every page up front, so that telling an
+    /// interrupt signal page apart from any other faulting address is a range check.
+    fn reserve() -> SignalPages {
+        let ptr = unsafe {
+            mmap(
+                ptr::null_mut(),
+                MAX_SIGNAL_PAGES * INTERRUPT_SIGNAL_MEM_SIZE,
+                PROT_NONE,
+                MAP_PRIVATE | MAP_ANON,
+                -1,
+                0,
+            )
+        };
+        if ptr as isize == -1 {
+            panic!("cannot reserve interrupt signal memory");
+        }
+        let states: Vec<AtomicU8> = (0..MAX_SIGNAL_PAGES).map(|_| AtomicU8::new(0)).collect();
+        let states = Box::leak(states.into_boxed_slice());
+        PAGE_STATES.store(states.as_mut_ptr(), Ordering::SeqCst);
+        SIGNAL_REGION.store(ptr as usize, Ordering::SeqCst);
+
+        SignalPages {
+            next: 0,
+            free: vec![],
+        }
+    }
+
+    /// Returns `None` once every page is taken; released pages are handed out again first.
+    fn allocate(&mut self) -> Option<usize> {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                if self.next == MAX_SIGNAL_PAGES {
+                    return None;
+                }
+                self.next += 1;
+                PAGES_USED.store(self.next, Ordering::SeqCst);
+                self.next - 1
+            }
+        };
+        page_state(index).store(0, Ordering::SeqCst);
+        let page = SIGNAL_REGION.load(Ordering::SeqCst) + index * INTERRUPT_SIGNAL_MEM_SIZE;
+        unsafe { protect(page as _, PROT_READ | PROT_WRITE) };
+        Some(page)
+    }
+
+    fn release(&mut self, page: usize) {
+        let index = page_index(page as _);
+        if page_state(index).swap(0, Ordering::SeqCst) & SAMPLED != 0 {
+            SAMPLED_PAGES.fetch_sub(1, Ordering::SeqCst);
+        }
+        unsafe { protect(page as _, PROT_NONE) };
+        self.free.push(index);
+    }
+}
+
+fn page_index(page: *const u8) -> usize {
+    (page as usize - SIGNAL_REGION.load(Ordering::SeqCst)) / INTERRUPT_SIGNAL_MEM_SIZE
+}
+
+/// The requests pending on the page at `index`, which was reserved already.
+fn page_state(index: usize) -> &'static AtomicU8 {
+    unsafe { &*PAGE_STATES.load(Ordering::SeqCst).add(index) }
+}
+
+unsafe fn protect(page: *mut u8, prot: i32) {
+    if mprotect(page as _, INTERRUPT_SIGNAL_MEM_SIZE, prot) < 0 {
+        panic!("cannot change the protection of an interrupt signal page");
+    }
+}
+
+/// Returns whether `addr` is the interrupt signal page of some instance.
+///
+/// Called from the signal handler.
+pub(crate) fn is_interrupt_signal_mem(addr: *const u8) -> bool {
+    let start = SIGNAL_REGION.load(Ordering::SeqCst);
+    let addr = addr as usize;
+    start != 0
+        && addr >= start
+        && addr < start + MAX_SIGNAL_PAGES * INTERRUPT_SIGNAL_MEM_SIZE
+        && (addr - start) % INTERRUPT_SIGNAL_MEM_SIZE == 0
+}
+
+/// Makes the interrupt signal page at `page` readable again, and returns whether its
+/// instance was interrupted and whether it was asked for a sample.
+///
+/// Called from the signal handler. A page can fault with nothing pending, when a request
+/// was dropped after revoking it; the instance just goes on then.
+pub(crate) unsafe fn take_requests(page: *mut u8) -> (bool, bool) {
+    protect(page, PROT_READ | PROT_WRITE);
+    // Requests made from here on revoke the page again.
+    let state = page_state(page_index(page)).fetch_and(SAMPLED, Ordering::SeqCst);
+    (
+        state & INTERRUPT_REQUESTED != 0,
+        state & SAMPLE_REQUESTED != 0,
+    )
+}
+
+/// Asks every sampled instance for a sample, by revoking its page unless a sample is
+/// pending already.
+///
+/// Called from the SIGPROF handler, so it neither locks, allocates nor panics.
+pub(crate) unsafe fn request_samples() {
+    if SAMPLED_PAGES.load(Ordering::SeqCst) == 0 {
+        return;
+    }
+    let start = SIGNAL_REGION.load(Ordering::SeqCst);
+    for index in 0..PAGES_USED.load(Ordering::SeqCst) {
+        let state = page_state(index);
+        if state.load(Ordering::SeqCst) & SAMPLED != 0
+            && state.fetch_or(SAMPLE_REQUESTED, Ordering::SeqCst) & SAMPLE_REQUESTED == 0
+        {
+            let page = start + index * INTERRUPT_SIGNAL_MEM_SIZE;
+            mprotect(page as _, INTERRUPT_SIGNAL_MEM_SIZE, PROT_NONE);
+        }
+    }
+}
+
+/// The interrupt signal page of one instance, along with its deadline.
+pub(crate) struct InterruptSignal {
+    page: usize,
+    deadline: Mutex<Option<Instant>>,
+}
+
+impl InterruptSignal {
+    /// Returns `None` if every interrupt signal page is in use.
+    pub(crate) fn new() -> Option<Arc<InterruptSignal>> {
+        let page = SIGNAL_PAGES.lock().allocate()?;
+        Some(Arc::new(InterruptSignal {
+            page,
+            deadline: Mutex::new(None),
+        }))
+    }
+
+    /// The page to store in `InternalCtx::interrupt_signal_mem`.
+    pub(crate) fn mem(&self) -> *mut u8 {
+        self.page as _
+    }
+
+    fn state(&self) -> &'static AtomicU8 {
+        page_state(page_index(self.mem()))
+    }
+
+    fn raise(&self) {
+        self.state().fetch_or(INTERRUPT_REQUESTED, Ordering::SeqCst);
+        unsafe { protect(self.mem(), PROT_NONE) };
+    }
+}
+
+impl Drop for InterruptSignal {
+    fn drop(&mut self) {
+        SIGNAL_PAGES.lock().release(self.page);
+    }
+}
+
+/// A handle that stops the WebAssembly code of one instance, from any thread.
+///
+/// Created by [`Instance::interrupt_handle`]. Once interrupted, the instance
+/// returns `RuntimeError::Interrupted` from the call in progress at its next
+/// function entry or loop iteration. If the instance is not running, its
+/// next call is the one that gets interrupted.
+///
+/// [`Instance::interrupt_handle`]: ../struct.Instance.html#method.interrupt_handle
+#[derive(Clone)]
+pub struct InterruptHandle {
+    signal: Arc<InterruptSignal>,
+}
+
+impl InterruptHandle {
+    pub(crate) fn new(signal: Arc<InterruptSignal>) -> InterruptHandle {
+        InterruptHandle { signal }
+    }
+
+    /// Interrupt the instance now.
+    pub fn interrupt(&self) {
+        self.signal.raise();
+    }
+
+    /// Interrupt the instance once `deadline` has passed.
+    ///
+    /// This replaces any deadline set before.
+    pub fn set_deadline(&self, deadline: Instant) {
+        *self.signal.deadline.lock() = Some(deadline);
+
+        START_WATCHDOG.call_once(|| {
+            thread::Builder::new()
+                .name("wasmer-interrupt-watchdog".to_string())
+                .spawn(watchdog)
+                .expect("cannot start the interrupt watchdog");
+        });
+        DEADLINES.queue.lock().push(Deadline {
+            at: deadline,
+            signal: Arc::downgrade(&self.signal),
+        });
+        DEADLINES.condvar.notify_one();
+    }
+
+    /// Interrupt the instance once `timeout` has elapsed from now.
+    pub fn set_timeout(&self, timeout: Duration) {
+        self.set_deadline(Instant::now() + timeout);
+    }
+
+    /// Cancel the deadline, if it has not passed yet.
+    pub fn clear_deadline(&self) {
+        *self.signal.deadline.lock() = None;
+    }
+
+    /// Stop the instance for a sample on each tick of the sampling timer, as started by
+    /// `fault::start_sampling_timer`. Instances that did not call this run undisturbed.
+    pub fn start_sampling(&self) {
+        if self.signal.state().fetch_or(SAMPLED, Ordering::SeqCst) & SAMPLED == 0 {
+            SAMPLED_PAGES.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Stop sampling the instance, and drop a sample requested but not taken yet.
+    pub fn stop_sampling(&self) {
+        let state = self
+            .signal
+            .state()
+            .fetch_and(!(SAMPLED | SAMPLE_REQUESTED), Ordering::SeqCst);
+        if state & SAMPLED != 0 {
+            SAMPLED_PAGES.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+struct Deadlines {
+    queue: Mutex<BinaryHeap<Deadline>>,
+    condvar: Condvar,
+}
+
+struct Deadline {
+    at: Instant,
+    signal: Weak<InterruptSignal>,
+}
+
+// Ordered so that the earliest deadline is at the top of the heap.
+impl Ord for Deadline {
+    fn cmp(&self, other: &Deadline) -> CmpOrdering {
+        other.at.cmp(&self.at)
+    }
+}
+
+impl PartialOrd for Deadline {
+    fn partial_cmp(&self, other: &Deadline) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Deadline {
+    fn eq(&self, other: &Deadline) -> bool {
+        self.at == other.at
+    }
+}
+
+impl Eq for Deadline {}
+
+/// Raises the interrupts whose deadlines pass. Deadlines that were replaced
+/// or cleared stay queued and are skipped when they come up.
+fn watchdog() {
+    let mut queue = DEADLINES.queue.lock();
+    loop {
+        let at = match queue.peek() {
+            Some(deadline) => deadline.at,
+            None => {
+                DEADLINES.condvar.wait(&mut queue);
+                continue;
+            }
+        };
+        if at > Instant::now() {
+            DEADLINES.condvar.wait_until(&mut queue, at);
+            continue;
+        }
+
+        let deadline = queue.pop().unwrap();
+        if let Some(signal) = deadline.signal.upgrade() {
+            let mut current = signal.deadline.lock();
+            if *current == Some(deadline.at) {
+                *current = None;
+                signal.raise();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_interrupt_signal_mem, request_samples, take_requests, InterruptHandle, InterruptSignal,
+    };
+
+    #[test]
+    fn test_signal_pages_are_recognized() {
+        let signal = InterruptSignal::new().unwrap();
+        let mem = signal.mem();
+        assert!(is_interrupt_signal_mem(mem));
+        assert!(!is_interrupt_signal_mem(unsafe { mem.add(1) }));
+        assert!(!is_interrupt_signal_mem(unsafe {
+            crate::fault::get_wasm_interrupt_signal_mem()
+        }));
+
+        // Reading the page must not fault until the interrupt is raised.
+        assert_eq!(unsafe { *mem }, 0);
+    }
+
+    #[test]
+    fn test_only_sampled_pages_are_revoked() {
+        let sampled = InterruptHandle::new(InterruptSignal::new().unwrap());
+        let other = InterruptSignal::new().unwrap();
+        sampled.start_sampling();
+        unsafe { request_samples() };
+
+        assert_eq!(unsafe { *other.mem() }, 0);
+        assert_eq!(
+            unsafe { take_requests(sampled.signal.mem()) },
+            (false, true)
+        );
+        assert_eq!(unsafe { *sampled.signal.mem() }, 0);
+        sampled.stop_sampling();
+    }
+}
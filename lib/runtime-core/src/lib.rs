@@ -43,9 +43,12 @@ pub mod cache;
 pub mod codegen;
 pub mod error;
 pub mod export;
+#[cfg(all(unix, target_arch = "x86_64"))]
+pub mod fiber;
 pub mod global;
 pub mod import;
 pub mod instance;
+pub mod limits;
 pub mod loader;
 pub mod memory;
 pub mod module;
@@ -54,6 +57,7 @@ mod sig_registry;
 pub mod structures;
 mod sys;
 pub mod table;
+pub mod template;
 #[cfg(all(unix, target_arch = "x86_64"))]
 pub mod trampoline_x64;
 pub mod typed_func;
@@ -66,6 +70,8 @@ pub mod vmcalls;
 pub use trampoline_x64 as trampoline;
 #[cfg(unix)]
 pub mod fault;
+#[cfg(unix)]
+pub mod interrupt;
 pub mod state;
 #[cfg(feature = "managed")]
 pub mod tiering;
@@ -76,9 +82,12 @@ pub use self::error::Result;
 #[doc(inline)]
 pub use self::import::IsExport;
 #[doc(inline)]
-pub use self::instance::{DynFunc, Instance};
+pub use self::instance::{DynFunc, Instance, InstanceSpawner};
 #[doc(inline)]
 pub use self::module::Module;
+#[cfg(all(unix, target_arch = "x86_64"))]
+#[doc(inline)]
+pub use self::typed_func::DynamicFunc;
 #[doc(inline)]
 pub use self::typed_func::Func;
 use std::sync::Arc;
@@ -153,9 +162,9 @@ pub fn validate_and_report_errors_with_features(
     let config = wasmparser::ValidatingParserConfig {
         operator_config: wasmparser::OperatorValidatorConfig {
             enable_simd: features.simd,
-            enable_bulk_memory: false,
-            enable_multi_value: false,
-            enable_reference_types: false,
+            enable_bulk_memory: features.bulk_memory,
+            enable_multi_value: features.multi_value,
+            enable_reference_types: features.reference_types,
             enable_threads: features.threads,
 
             #[cfg(feature = "deterministic-execution")]
@@ -0,0 +1,419 @@
+//! Host-imposed limits on the memories, tables and instances that WebAssembly code uses.
+//!
+//! The maximum in a memory or table descriptor is chosen by the module, so a host running
+//! untrusted code cannot rely on it. A [`ResourceLimiter`] enforces a budget of the host's
+//! choosing instead. One limiter can be installed for the whole process with
+//! [`set_resource_limiter`], and one per instance with
+//! [`ImportObject::set_resource_limiter`]; both are consulted and either can deny.
+//!
+//! A memory or table is governed by the limiters in place when it is created, those of the
+//! instance defining it and of the process, for as long as it lives: growing it from another
+//! instance that imports it still charges the limiters of its creator.
+//!
+//! [`ResourceLimiter`]: trait.ResourceLimiter.html
+//! [`set_resource_limiter`]: fn.set_resource_limiter.html
+//! [`ImportObject::set_resource_limiter`]: ../import/struct.ImportObject.html#method.set_resource_limiter
+
+use crate::{
+    error::{CreationError, GrowError},
+    types::{MemoryDescriptor, TableDescriptor},
+    units::Pages,
+};
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+/// Decides whether memories and tables may grow and whether instances may be created.
+///
+/// Every method allows by default. Returning `true` does not guarantee that the resource
+/// is used: another limiter, or the host running out of memory, can still refuse it, in
+/// which case the matching `*_grow_failed` method is called. A limiter that keeps a running
+/// total can therefore add on `*_growing` and subtract on `*_grow_failed` and `*_released`.
+pub trait ResourceLimiter: Send + Sync {
+    /// Whether a memory may grow from `current` to `desired` pages.
+    ///
+    /// Creating a memory asks to grow it from zero pages to its minimum.
+    fn memory_growing(&self, _current: Pages, _desired: Pages) -> bool {
+        true
+    }
+
+    /// Called when a memory that this limiter allowed to grow from `current` to `desired`
+    /// pages stayed at `current` pages after all.
+    fn memory_grow_failed(&self, _current: Pages, _desired: Pages) {}
+
+    /// Called when a memory of `pages` pages is freed.
+    fn memory_released(&self, _pages: Pages) {}
+
+    /// Whether a table may grow from `current` to `desired` elements.
+    ///
+    /// Creating a table asks to grow it from zero elements to its minimum.
+    fn table_growing(&self, _current: u32, _desired: u32) -> bool {
+        true
+    }
+
+    /// Called when a table that this limiter allowed to grow from `current` to `desired`
+    /// elements stayed at `current` elements after all.
+    fn table_grow_failed(&self, _current: u32, _desired: u32) {}
+
+    /// Called when a table of `elements` elements is freed.
+    fn table_released(&self, _elements: u32) {}
+
+    /// Whether an instance may be created.
+    fn instance_creating(&self) -> bool {
+        true
+    }
+
+    /// Called when an instance that this limiter allowed is dropped.
+    fn instance_dropped(&self) {}
+}
+
+lazy_static! {
+    static ref PROCESS_LIMITER: RwLock<Option<Arc<dyn ResourceLimiter>>> = RwLock::new(None);
+}
+
+/// Install `limiter` for every memory, table and instance of the process.
+///
+/// This replaces the limiter installed before, if any.
+pub fn set_resource_limiter(limiter: Arc<dyn ResourceLimiter>) {
+    *PROCESS_LIMITER.write() = Some(limiter);
+}
+
+/// Remove the limiter installed with `set_resource_limiter`.
+pub fn clear_resource_limiter() {
+    *PROCESS_LIMITER.write() = None;
+}
+
+/// The limiters that govern a resource: those of the instance owning it and of the process.
+#[derive(Clone)]
+pub(crate) struct Limiters {
+    limiters: Vec<Arc<dyn ResourceLimiter>>,
+}
+
+impl Limiters {
+    pub(crate) fn new(instance: Option<&Arc<dyn ResourceLimiter>>) -> Limiters {
+        let mut limiters = vec![];
+        limiters.extend(instance.cloned());
+        limiters.extend(PROCESS_LIMITER.read().clone());
+        Limiters { limiters }
+    }
+
+    /// The limiters of a resource that no instance owns.
+    pub(crate) fn process() -> Limiters {
+        Limiters::new(None)
+    }
+
+    /// Whether every limiter `allows`. When one denies, `undo` is called on those that
+    /// allowed before it.
+    fn all(
+        &self,
+        allows: impl Fn(&dyn ResourceLimiter) -> bool,
+        undo: impl Fn(&dyn ResourceLimiter),
+    ) -> bool {
+        for (i, limiter) in self.limiters.iter().enumerate() {
+            if !allows(&**limiter) {
+                for limiter in &self.limiters[..i] {
+                    undo(&**limiter);
+                }
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Checks growing a memory of `current` pages by `delta`. Growth beyond `maximum` is
+    /// left for the memory itself to refuse.
+    pub(crate) fn memory_growth(
+        &self,
+        current: Pages,
+        delta: Pages,
+        maximum: Option<Pages>,
+    ) -> Result<(), GrowError> {
+        let desired = match current.checked_add(delta) {
+            Ok(desired) => desired,
+            Err(_) => return Ok(()),
+        };
+        if delta == Pages(0) || maximum.map_or(false, |maximum| desired > maximum) {
+            return Ok(());
+        }
+
+        if self.all(
+            |limiter| limiter.memory_growing(current, desired),
+            |limiter| limiter.memory_grow_failed(current, desired),
+        ) {
+            Ok(())
+        } else {
+            Err(GrowError::ResourceLimitExceeded)
+        }
+    }
+
+    /// Checks growing a table of `current` elements by `delta`. Growth beyond `maximum` is
+    /// left for the table itself to refuse.
+    pub(crate) fn table_growth(
+        &self,
+        current: u32,
+        delta: u32,
+        maximum: Option<u32>,
+    ) -> Result<(), GrowError> {
+        let desired = match current.checked_add(delta) {
+            Some(desired) => desired,
+            None => return Ok(()),
+        };
+        if delta == 0 || maximum.map_or(false, |maximum| desired > maximum) {
+            return Ok(());
+        }
+
+        if self.all(
+            |limiter| limiter.table_growing(current, desired),
+            |limiter| limiter.table_grow_failed(current, desired),
+        ) {
+            Ok(())
+        } else {
+            Err(GrowError::ResourceLimitExceeded)
+        }
+    }
+
+    /// Reports that a memory allowed to grow from `current` to `desired` pages did not.
+    pub(crate) fn memory_grow_failed(&self, current: Pages, desired: Pages) {
+        if current != desired {
+            for limiter in &self.limiters {
+                limiter.memory_grow_failed(current, desired);
+            }
+        }
+    }
+
+    /// Reports that a table allowed to grow from `current` to `desired` elements did not.
+    pub(crate) fn table_grow_failed(&self, current: u32, desired: u32) {
+        if current != desired {
+            for limiter in &self.limiters {
+                limiter.table_grow_failed(current, desired);
+            }
+        }
+    }
+
+    pub(crate) fn memory_creation(&self, desc: &MemoryDescriptor) -> Result<(), CreationError> {
+        self.memory_growth(Pages(0), desc.minimum, None)
+            .map_err(|_| CreationError::ResourceLimitExceeded)
+    }
+
+    pub(crate) fn table_creation(&self, desc: &TableDescriptor) -> Result<(), CreationError> {
+        self.table_growth(0, desc.minimum, None)
+            .map_err(|_| CreationError::ResourceLimitExceeded)
+    }
+
+    pub(crate) fn memory_released(&self, pages: Pages) {
+        if pages != Pages(0) {
+            for limiter in &self.limiters {
+                limiter.memory_released(pages);
+            }
+        }
+    }
+
+    pub(crate) fn table_released(&self, elements: u32) {
+        if elements != 0 {
+            for limiter in &self.limiters {
+                limiter.table_released(elements);
+            }
+        }
+    }
+}
+
+/// Counts an instance against its limiters for as long as it lives.
+pub(crate) struct InstanceCount {
+    limiters: Limiters,
+}
+
+impl InstanceCount {
+    pub(crate) fn new(limiters: Limiters) -> Result<InstanceCount, CreationError> {
+        // The limiters asked before a denying one counted an instance that never exists.
+        if limiters.all(
+            |limiter| limiter.instance_creating(),
+            |limiter| limiter.instance_dropped(),
+        ) {
+            Ok(InstanceCount { limiters })
+        } else {
+            Err(CreationError::ResourceLimitExceeded)
+        }
+    }
+}
+
+impl Drop for InstanceCount {
+    fn drop(&mut self) {
+        for limiter in &self.limiters.limiters {
+            limiter.instance_dropped();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InstanceCount, Limiters, ResourceLimiter};
+    use crate::{
+        error::GrowError,
+        memory::{DefaultMemoryCreator, Memory},
+        table::Table,
+        types::{ElementType, MemoryDescriptor, TableDescriptor},
+        units::Pages,
+    };
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    struct Budget {
+        pages: Pages,
+        instances: Mutex<usize>,
+    }
+
+    impl ResourceLimiter for Budget {
+        fn memory_growing(&self, _current: Pages, desired: Pages) -> bool {
+            desired <= self.pages
+        }
+
+        fn instance_creating(&self) -> bool {
+            let mut instances = self.instances.lock();
+            if *instances == 1 {
+                return false;
+            }
+            *instances += 1;
+            true
+        }
+
+        fn instance_dropped(&self) {
+            *self.instances.lock() -= 1;
+        }
+
+        // Tables get as many elements as memories get pages.
+        fn table_growing(&self, _current: u32, desired: u32) -> bool {
+            desired <= self.pages.0
+        }
+    }
+
+    /// Keeps a running total of the pages and elements in use.
+    #[derive(Default)]
+    struct Tally {
+        pages: Mutex<u32>,
+        elements: Mutex<u32>,
+    }
+
+    impl ResourceLimiter for Tally {
+        fn memory_growing(&self, current: Pages, desired: Pages) -> bool {
+            *self.pages.lock() += desired.0 - current.0;
+            true
+        }
+
+        fn memory_grow_failed(&self, current: Pages, desired: Pages) {
+            *self.pages.lock() -= desired.0 - current.0;
+        }
+
+        fn memory_released(&self, pages: Pages) {
+            *self.pages.lock() -= pages.0;
+        }
+
+        fn table_growing(&self, current: u32, desired: u32) -> bool {
+            *self.elements.lock() += desired - current;
+            true
+        }
+
+        fn table_grow_failed(&self, current: u32, desired: u32) {
+            *self.elements.lock() -= desired - current;
+        }
+
+        fn table_released(&self, elements: u32) {
+            *self.elements.lock() -= elements;
+        }
+    }
+
+    fn tally_and_budget(pages: Pages) -> (Arc<Tally>, Limiters) {
+        let tally = Arc::new(Tally::default());
+        let budget = Arc::new(Budget {
+            pages,
+            instances: Mutex::new(0),
+        });
+        let limiters = Limiters {
+            limiters: vec![tally.clone() as Arc<dyn ResourceLimiter>, budget],
+        };
+        (tally, limiters)
+    }
+
+    #[test]
+    fn test_limiter_denies_growth() {
+        let budget: Arc<dyn ResourceLimiter> = Arc::new(Budget {
+            pages: Pages(2),
+            instances: Mutex::new(0),
+        });
+        let limiters = Limiters::new(Some(&budget));
+
+        assert!(limiters.memory_growth(Pages(1), Pages(1), None).is_ok());
+        match limiters.memory_growth(Pages(1), Pages(2), None) {
+            Err(GrowError::ResourceLimitExceeded) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        // The descriptor maximum is left for the memory to enforce.
+        assert!(limiters
+            .memory_growth(Pages(1), Pages(2), Some(Pages(2)))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_limiter_counts_instances() {
+        let budget: Arc<dyn ResourceLimiter> = Arc::new(Budget {
+            pages: Pages(0),
+            instances: Mutex::new(0),
+        });
+        let limiters = Limiters::new(Some(&budget));
+
+        let first = InstanceCount::new(limiters.clone()).unwrap();
+        assert!(InstanceCount::new(limiters.clone()).is_err());
+        drop(first);
+        assert!(InstanceCount::new(limiters).is_ok());
+    }
+
+    #[test]
+    fn test_denied_growth_is_reported() {
+        let (tally, limiters) = tally_and_budget(Pages(2));
+
+        assert!(limiters.memory_growth(Pages(1), Pages(1), None).is_ok());
+        assert_eq!(*tally.pages.lock(), 1);
+        // The budget denies after the tally counted the growth.
+        assert!(limiters.memory_growth(Pages(2), Pages(1), None).is_err());
+        assert_eq!(*tally.pages.lock(), 1);
+        assert!(limiters.table_growth(0, 3, None).is_err());
+        assert_eq!(*tally.elements.lock(), 0);
+    }
+
+    #[test]
+    fn test_resources_are_released() {
+        let (tally, limiters) = tally_and_budget(Pages(4));
+
+        let desc = MemoryDescriptor::new(Pages(1), Some(Pages(4)), false).unwrap();
+        let creator = Arc::new(DefaultMemoryCreator);
+        let memory = Memory::new_limited(desc, &limiters, creator).unwrap();
+        memory.grow(Pages(2)).unwrap();
+        assert!(memory.grow(Pages(2)).is_err());
+        assert_eq!(*tally.pages.lock(), 3);
+
+        let desc = TableDescriptor {
+            element: ElementType::Anyfunc,
+            minimum: 2,
+            maximum: None,
+        };
+        let table = Table::new_limited(desc, &limiters).unwrap();
+        table.grow(1).unwrap();
+        assert_eq!(*tally.elements.lock(), 3);
+
+        drop(memory);
+        drop(table);
+        assert_eq!(*tally.pages.lock(), 0);
+        assert_eq!(*tally.elements.lock(), 0);
+    }
+
+    #[test]
+    fn test_table_minimum_is_limited() {
+        let (tally, limiters) = tally_and_budget(Pages(4));
+
+        let desc = TableDescriptor {
+            element: ElementType::AnyRef,
+            minimum: 5,
+            maximum: None,
+        };
+        assert!(Table::new_limited(desc, &limiters).is_err());
+        assert_eq!(*tally.elements.lock(), 0);
+    }
+}
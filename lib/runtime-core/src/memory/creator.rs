@@ -0,0 +1,101 @@
+//! Pluggable allocation of the virtual memory behind linear memories.
+//!
+//! By default every linear memory maps fresh memory from the system when it is created or,
+//! for dynamic memories, when it grows. An embedder can pass its own [`MemoryCreator`] at
+//! instantiation, with [`ImportObject::set_memory_creator`], to take regions from a pool,
+//! back them with a memfd or a file, or place them in huge pages. Either way the runtime
+//! decides how large a region is and which parts of it are accessible, so guard pages and
+//! bounds work the same as with the default allocator.
+//!
+//! [`MemoryCreator`]: trait.MemoryCreator.html
+//! [`ImportObject::set_memory_creator`]: ../import/struct.ImportObject.html#method.set_memory_creator
+
+use crate::{
+    error::{MemoryCreationError, MemoryProtectionError},
+    sys,
+    types::MemoryDescriptor,
+};
+use std::{ops::Range, slice};
+
+/// A region of virtual memory that backs a linear memory.
+///
+/// The region is released when it is dropped.
+///
+/// # Safety
+///
+/// `as_ptr` must point to `size` bytes that stay mapped at the same address for as long
+/// as the region lives.
+pub unsafe trait MemoryRegion: Send + Sync {
+    /// The start of the region.
+    fn as_ptr(&self) -> *mut u8;
+
+    /// The size of the region in bytes.
+    fn size(&self) -> usize;
+
+    /// Change the protection of `range`, in bytes from the start of the region.
+    ///
+    /// The runtime only passes ranges that start on a page boundary and only asks for
+    /// `Protect::None` and `Protect::ReadWrite`.
+    unsafe fn protect(
+        &mut self,
+        range: Range<usize>,
+        protection: sys::Protect,
+    ) -> Result<(), MemoryProtectionError>;
+}
+
+impl dyn MemoryRegion {
+    pub(crate) unsafe fn as_slice(&self) -> &[u8] {
+        slice::from_raw_parts(self.as_ptr(), self.size())
+    }
+
+    pub(crate) unsafe fn as_slice_mut(&mut self) -> &mut [u8] {
+        slice::from_raw_parts_mut(self.as_ptr(), self.size())
+    }
+}
+
+unsafe impl MemoryRegion for sys::Memory {
+    fn as_ptr(&self) -> *mut u8 {
+        sys::Memory::as_ptr(self)
+    }
+
+    fn size(&self) -> usize {
+        sys::Memory::size(self)
+    }
+
+    unsafe fn protect(
+        &mut self,
+        range: Range<usize>,
+        protection: sys::Protect,
+    ) -> Result<(), MemoryProtectionError> {
+        sys::Memory::protect(self, range, protection)
+    }
+}
+
+/// Allocates the regions behind linear memories.
+pub trait MemoryCreator: Send + Sync {
+    /// Reserve a region of `size` bytes for a memory described by `desc`.
+    ///
+    /// The whole region must be inaccessible and read as zeroes once the runtime makes
+    /// parts of it accessible. `size` includes the guard pages of the memory, and is the
+    /// same for every static memory.
+    fn reserve(
+        &self,
+        desc: &MemoryDescriptor,
+        size: usize,
+    ) -> Result<Box<dyn MemoryRegion>, MemoryCreationError>;
+}
+
+/// The allocator used when the embedder does not provide one: it maps fresh memory from
+/// the system for every region.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultMemoryCreator;
+
+impl MemoryCreator for DefaultMemoryCreator {
+    fn reserve(
+        &self,
+        _desc: &MemoryDescriptor,
+        size: usize,
+    ) -> Result<Box<dyn MemoryRegion>, MemoryCreationError> {
+        Ok(Box::new(sys::Memory::with_size(size)?))
+    }
+}
@@ -1,11 +1,14 @@
 use crate::error::GrowError;
 use crate::{
     error::CreationError,
+    limits::Limiters,
+    memory::{MemoryCreator, MemoryRegion},
     sys,
     types::MemoryDescriptor,
     units::{Bytes, Pages},
     vm,
 };
+use std::sync::Arc;
 
 pub const DYNAMIC_GUARD_SIZE: usize = 4096;
 
@@ -23,35 +26,36 @@ pub const DYNAMIC_GUARD_SIZE: usize = 4096;
 /// backing memory, we use mmap (or the platform-equivalent) to allow
 /// us to add a guard-page at the end to help elide some bounds-checks.
 pub struct DynamicMemory {
-    memory: sys::Memory,
+    memory: Box<dyn MemoryRegion>,
     current: Pages,
     max: Option<Pages>,
+    desc: MemoryDescriptor,
+    creator: Arc<dyn MemoryCreator>,
+    limiters: Limiters,
 }
 
 impl DynamicMemory {
     pub(super) fn new(
         desc: MemoryDescriptor,
         local: &mut vm::LocalMemory,
+        creator: Arc<dyn MemoryCreator>,
+        limiters: Limiters,
     ) -> Result<Box<Self>, CreationError> {
-        let min_bytes: Bytes = desc.minimum.into();
-        let memory = {
-            let mut memory = sys::Memory::with_size(min_bytes.0 + DYNAMIC_GUARD_SIZE)
-                .map_err(|_| CreationError::UnableToCreateMemory)?;
-            if desc.minimum != Pages(0) {
-                unsafe {
-                    memory
-                        .protect(0..min_bytes.0, sys::Protect::ReadWrite)
-                        .map_err(|_| CreationError::UnableToCreateMemory)?;
-                }
-            }
+        limiters.memory_creation(&desc)?;
 
-            memory
-        };
+        let min_bytes: Bytes = desc.minimum.into();
+        let memory = Self::reserve(&*creator, &desc, desc.minimum).map_err(|_| {
+            limiters.memory_grow_failed(Pages(0), desc.minimum);
+            CreationError::UnableToCreateMemory
+        })?;
 
         let mut storage = Box::new(DynamicMemory {
             memory,
             current: desc.minimum,
             max: desc.maximum,
+            desc,
+            creator,
+            limiters,
         });
         let storage_ptr: *mut DynamicMemory = &mut *storage;
 
@@ -62,6 +66,25 @@ impl DynamicMemory {
         Ok(storage)
     }
 
+    /// Reserves a region whose first `pages` pages are accessible.
+    fn reserve(
+        creator: &dyn MemoryCreator,
+        desc: &MemoryDescriptor,
+        pages: Pages,
+    ) -> Result<Box<dyn MemoryRegion>, GrowError> {
+        let mut memory = creator
+            .reserve(desc, pages.bytes().0 + DYNAMIC_GUARD_SIZE)
+            .map_err(|e| e.into())?;
+        if pages != Pages(0) {
+            unsafe {
+                memory
+                    .protect(0..pages.bytes().0, sys::Protect::ReadWrite)
+                    .map_err(|e| e.into())?;
+            }
+        }
+        Ok(memory)
+    }
+
     /// The size of this memory in `Pages`.
     pub fn size(&self) -> Pages {
         self.current
@@ -84,14 +107,17 @@ impl DynamicMemory {
             }
         }
 
-        let mut new_memory = sys::Memory::with_size(new_pages.bytes().0 + DYNAMIC_GUARD_SIZE)
-            .map_err(|e| e.into())?;
+        self.limiters.memory_growth(self.current, delta, self.max)?;
 
-        unsafe {
-            new_memory
-                .protect(0..new_pages.bytes().0, sys::Protect::ReadWrite)
-                .map_err(|e| e.into())?;
+        let mut new_memory = match Self::reserve(&*self.creator, &self.desc, new_pages) {
+            Ok(new_memory) => new_memory,
+            Err(e) => {
+                self.limiters.memory_grow_failed(self.current, new_pages);
+                return Err(e);
+            }
+        };
 
+        unsafe {
             new_memory.as_slice_mut()[..self.current.bytes().0]
                 .copy_from_slice(&self.memory.as_slice()[..self.current.bytes().0]);
         }
@@ -116,3 +142,9 @@ impl DynamicMemory {
         unsafe { &mut self.memory.as_slice_mut()[0..self.current.bytes().0] }
     }
 }
+
+impl Drop for DynamicMemory {
+    fn drop(&mut self) {
+        self.limiters.memory_released(self.current);
+    }
+}
@@ -4,6 +4,7 @@ use crate::{
     error::{CreationError, GrowError},
     export::Export,
     import::IsExport,
+    limits::Limiters,
     memory::dynamic::DYNAMIC_GUARD_SIZE,
     memory::static_::{SAFE_STATIC_GUARD_SIZE, SAFE_STATIC_HEAP_SIZE},
     types::{MemoryDescriptor, ValueType},
@@ -14,16 +15,19 @@ use std::{cell::Cell, fmt, mem, sync::Arc};
 
 use std::sync::Mutex as StdMutex;
 
+pub use self::creator::{DefaultMemoryCreator, MemoryCreator, MemoryRegion};
 pub use self::dynamic::DynamicMemory;
 pub use self::static_::StaticMemory;
 pub use self::view::{Atomically, MemoryView};
 
 use parking_lot::Mutex;
 
+mod creator;
 mod dynamic;
 pub mod ptr;
 mod static_;
 mod view;
+pub(crate) mod wait;
 
 #[derive(Clone)]
 enum MemoryVariant {
@@ -60,6 +64,24 @@ impl Memory {
     /// }
     /// ```
     pub fn new(desc: MemoryDescriptor) -> Result<Self, CreationError> {
+        Memory::with_creator(desc, Arc::new(DefaultMemoryCreator))
+    }
+
+    /// Create a new `Memory` whose backing is allocated by `creator`.
+    pub fn with_creator(
+        desc: MemoryDescriptor,
+        creator: Arc<dyn MemoryCreator>,
+    ) -> Result<Self, CreationError> {
+        Memory::new_limited(desc, &Limiters::process(), creator)
+    }
+
+    /// Create a new `Memory`, if `limiters` allow its minimum size. The memory is charged
+    /// to `limiters` for as long as it lives.
+    pub(crate) fn new_limited(
+        desc: MemoryDescriptor,
+        limiters: &Limiters,
+        creator: Arc<dyn MemoryCreator>,
+    ) -> Result<Self, CreationError> {
         if let Some(max) = desc.maximum {
             if max < desc.minimum {
                 return Err(CreationError::InvalidDescriptor(
@@ -75,10 +97,11 @@ impl Memory {
             ));
         }
 
+        let limiters = limiters.clone();
         let variant = if !desc.shared {
-            MemoryVariant::Unshared(UnsharedMemory::new(desc)?)
+            MemoryVariant::Unshared(UnsharedMemory::new_limited(desc, creator, limiters)?)
         } else {
-            MemoryVariant::Shared(SharedMemory::new(desc)?)
+            MemoryVariant::Shared(SharedMemory::new(desc, &*creator, limiters)?)
         };
 
         Ok(Memory { desc, variant })
@@ -92,6 +115,11 @@ impl Memory {
         self.desc
     }
 
+    /// Returns true if this memory can be shared between instances on different threads.
+    pub fn is_shared(&self) -> bool {
+        self.desc.shared
+    }
+
     /// Grow this memory by the specified number of pages.
     pub fn grow(&self, delta: Pages) -> Result<Pages, GrowError> {
         match &self.variant {
@@ -223,6 +251,22 @@ unsafe impl Sync for UnsharedMemoryInternal {}
 impl UnsharedMemory {
     /// Create a new `UnsharedMemory` from the given memory descriptor.
     pub fn new(desc: MemoryDescriptor) -> Result<Self, CreationError> {
+        UnsharedMemory::with_creator(desc, Arc::new(DefaultMemoryCreator))
+    }
+
+    /// Create a new `UnsharedMemory` whose backing is allocated by `creator`.
+    pub fn with_creator(
+        desc: MemoryDescriptor,
+        creator: Arc<dyn MemoryCreator>,
+    ) -> Result<Self, CreationError> {
+        UnsharedMemory::new_limited(desc, creator, Limiters::process())
+    }
+
+    fn new_limited(
+        desc: MemoryDescriptor,
+        creator: Arc<dyn MemoryCreator>,
+        limiters: Limiters,
+    ) -> Result<Self, CreationError> {
         let mut local = vm::LocalMemory {
             base: std::ptr::null_mut(),
             bound: 0,
@@ -230,12 +274,12 @@ impl UnsharedMemory {
         };
 
         let storage = match desc.memory_type() {
-            MemoryType::Dynamic => {
-                UnsharedMemoryStorage::Dynamic(DynamicMemory::new(desc, &mut local)?)
-            }
-            MemoryType::Static => {
-                UnsharedMemoryStorage::Static(StaticMemory::new(desc, &mut local)?)
-            }
+            MemoryType::Dynamic => UnsharedMemoryStorage::Dynamic(DynamicMemory::new(
+                desc, &mut local, creator, limiters,
+            )?),
+            MemoryType::Static => UnsharedMemoryStorage::Static(StaticMemory::new(
+                desc, &mut local, &*creator, limiters,
+            )?),
             MemoryType::SharedStatic => {
                 return Err(CreationError::InvalidDescriptor(
                     "attempting to create shared unshared memory".to_string(),
@@ -308,39 +352,59 @@ pub struct SharedMemoryInternal {
 // this is safe because of `lock`; accesing `local` without locking `lock` is not safe (Maybe we could put the lock on Local then?)
 unsafe impl Sync for SharedMemoryInternal {}
 
+impl SharedMemoryInternal {
+    pub(crate) fn grow(&self, delta: Pages) -> Result<Pages, GrowError> {
+        let _guard = self.lock.lock();
+        let mut local = self.local.get();
+        let mut memory = self.memory.lock().unwrap();
+        let pages = memory.grow(delta, &mut local);
+        self.local.set(local);
+        pages
+    }
+
+    pub(crate) fn size(&self) -> Pages {
+        let _guard = self.lock.lock();
+        let memory = self.memory.lock().unwrap();
+        memory.size()
+    }
+}
+
 impl SharedMemory {
-    fn new(desc: MemoryDescriptor) -> Result<Self, CreationError> {
+    fn new(
+        desc: MemoryDescriptor,
+        creator: &dyn MemoryCreator,
+        limiters: Limiters,
+    ) -> Result<Self, CreationError> {
         let mut local = vm::LocalMemory {
             base: std::ptr::null_mut(),
             bound: 0,
             memory: std::ptr::null_mut(),
         };
 
-        let memory = StaticMemory::new(desc, &mut local)?;
+        let memory = StaticMemory::new(desc, &mut local, creator, limiters)?;
 
-        Ok(Self {
-            internal: Arc::new(SharedMemoryInternal {
-                memory: StdMutex::new(memory),
-                local: Cell::new(local),
-                lock: Mutex::new(()),
-            }),
-        })
+        let internal = Arc::new(SharedMemoryInternal {
+            memory: StdMutex::new(memory),
+            local: Cell::new(local),
+            lock: Mutex::new(()),
+        });
+
+        // The shared memory intrinsics find the memory through `local.memory`,
+        // so point it at the lock-protected internals rather than the storage.
+        local.memory = &*internal as *const SharedMemoryInternal as *mut ();
+        internal.local.set(local);
+
+        Ok(Self { internal })
     }
 
     /// Try to grow this memory by the given number of delta pages.
     pub fn grow(&self, delta: Pages) -> Result<Pages, GrowError> {
-        let _guard = self.internal.lock.lock();
-        let mut local = self.internal.local.get();
-        let mut memory = self.internal.memory.lock().unwrap();
-        let pages = memory.grow(delta, &mut local);
-        pages
+        self.internal.grow(delta)
     }
 
     /// Size of this memory in pages.
     pub fn size(&self) -> Pages {
-        let _guard = self.internal.lock.lock();
-        let memory = self.internal.memory.lock().unwrap();
-        memory.size()
+        self.internal.size()
     }
 
     /// Gets a mutable pointer to the `LocalMemory`.
@@ -361,7 +425,14 @@ impl Clone for SharedMemory {
 #[cfg(test)]
 mod memory_tests {
 
-    use super::{Memory, MemoryDescriptor, Pages};
+    use super::{
+        DefaultMemoryCreator, Memory, MemoryCreator, MemoryDescriptor, MemoryRegion, Pages,
+    };
+    use crate::error::MemoryCreationError;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
 
     #[test]
     fn test_initial_memory_size() {
@@ -370,6 +441,47 @@ mod memory_tests {
         assert_eq!(unshared_memory.size(), Pages(10));
     }
 
+    #[test]
+    fn test_shared_memory_grow() {
+        let memory_desc = MemoryDescriptor::new(Pages(1), Some(Pages(4)), true).unwrap();
+        let memory = Memory::new(memory_desc).unwrap();
+        let clone = memory.clone();
+        assert_eq!(memory.grow(Pages(2)).unwrap(), Pages(1));
+        assert_eq!(clone.size(), Pages(3));
+        let bound = unsafe { (*clone.vm_local_memory()).bound };
+        assert_eq!(bound, Pages(3).bytes().0);
+    }
+
+    #[derive(Default)]
+    struct CountingCreator {
+        reserved: AtomicUsize,
+    }
+
+    impl MemoryCreator for CountingCreator {
+        fn reserve(
+            &self,
+            desc: &MemoryDescriptor,
+            size: usize,
+        ) -> Result<Box<dyn MemoryRegion>, MemoryCreationError> {
+            self.reserved.fetch_add(1, Ordering::SeqCst);
+            DefaultMemoryCreator.reserve(desc, size)
+        }
+    }
+
+    #[test]
+    fn test_memory_creator_backs_memory() {
+        let creator = Arc::new(CountingCreator::default());
+        let memory_desc = MemoryDescriptor::new(Pages(1), None, false).unwrap();
+        let memory = Memory::with_creator(memory_desc, creator.clone()).unwrap();
+        assert_eq!(creator.reserved.load(Ordering::SeqCst), 1);
+
+        memory.view::<u8>()[42].set(7);
+        // Dynamic memories move to a new region when they grow.
+        assert_eq!(memory.grow(Pages(1)).unwrap(), Pages(1));
+        assert_eq!(creator.reserved.load(Ordering::SeqCst), 2);
+        assert_eq!(memory.view::<u8>()[42].get(), 7);
+    }
+
     #[test]
     fn test_invalid_descriptor_returns_error() {
         let memory_desc = MemoryDescriptor::new(Pages(10), None, true);
@@ -1,5 +1,13 @@
 use crate::error::GrowError;
-use crate::{error::CreationError, sys, types::MemoryDescriptor, units::Pages, vm};
+use crate::{
+    error::CreationError,
+    limits::Limiters,
+    memory::{MemoryCreator, MemoryRegion},
+    sys,
+    types::MemoryDescriptor,
+    units::Pages,
+    vm,
+};
 
 #[doc(hidden)]
 pub const SAFE_STATIC_HEAP_SIZE: usize = 1 << 32; // 4 GiB
@@ -18,34 +26,31 @@ pub const SAFE_STATIC_GUARD_SIZE: usize = 1 << 31; // 2 GiB
 /// it's recommended that a dynamic memory is used. There is currently no user-facing api that
 /// allows them to select the type of memory used however.
 pub struct StaticMemory {
-    memory: sys::Memory,
+    memory: Box<dyn MemoryRegion>,
     current: Pages,
     max: Option<Pages>,
+    limiters: Limiters,
 }
 
 impl StaticMemory {
     pub(in crate::memory) fn new(
         desc: MemoryDescriptor,
         local: &mut vm::LocalMemory,
+        creator: &dyn MemoryCreator,
+        limiters: Limiters,
     ) -> Result<Box<Self>, CreationError> {
-        let memory = {
-            let mut memory = sys::Memory::with_size(SAFE_STATIC_HEAP_SIZE + SAFE_STATIC_GUARD_SIZE)
-                .map_err(|_| CreationError::UnableToCreateMemory)?;
-            if desc.minimum != Pages(0) {
-                unsafe {
-                    memory
-                        .protect(0..desc.minimum.bytes().0, sys::Protect::ReadWrite)
-                        .map_err(|_| CreationError::UnableToCreateMemory)?;
-                }
-            }
+        limiters.memory_creation(&desc)?;
 
-            memory
-        };
+        let memory = Self::reserve(&desc, creator).map_err(|_| {
+            limiters.memory_grow_failed(Pages(0), desc.minimum);
+            CreationError::UnableToCreateMemory
+        })?;
 
         let mut storage = Box::new(StaticMemory {
             memory,
             current: desc.minimum,
             max: desc.maximum,
+            limiters,
         });
         let storage_ptr: *mut StaticMemory = &mut *storage;
 
@@ -56,6 +61,24 @@ impl StaticMemory {
         Ok(storage)
     }
 
+    /// Reserves the whole heap of a memory, with its first `desc.minimum` pages accessible.
+    fn reserve(
+        desc: &MemoryDescriptor,
+        creator: &dyn MemoryCreator,
+    ) -> Result<Box<dyn MemoryRegion>, GrowError> {
+        let mut memory = creator
+            .reserve(desc, SAFE_STATIC_HEAP_SIZE + SAFE_STATIC_GUARD_SIZE)
+            .map_err(|e| e.into())?;
+        if desc.minimum != Pages(0) {
+            unsafe {
+                memory
+                    .protect(0..desc.minimum.bytes().0, sys::Protect::ReadWrite)
+                    .map_err(|e| e.into())?;
+            }
+        }
+        Ok(memory)
+    }
+
     /// The size of this memory in `Pages`.
     pub fn size(&self) -> Pages {
         self.current
@@ -78,14 +101,18 @@ impl StaticMemory {
             }
         }
 
-        let _ = unsafe {
-            self.memory
-                .protect(
-                    self.current.bytes().0..new_pages.bytes().0,
-                    sys::Protect::ReadWrite,
-                )
-                .map_err(|e| e.into())
-        }?;
+        self.limiters.memory_growth(self.current, delta, self.max)?;
+
+        let protected = unsafe {
+            self.memory.protect(
+                self.current.bytes().0..new_pages.bytes().0,
+                sys::Protect::ReadWrite,
+            )
+        };
+        if let Err(e) = protected {
+            self.limiters.memory_grow_failed(self.current, new_pages);
+            return Err(e.into());
+        }
 
         local.bound = new_pages.bytes().0;
 
@@ -106,3 +133,9 @@ impl StaticMemory {
         unsafe { &mut self.memory.as_slice_mut()[0..self.current.bytes().0] }
     }
 }
+
+impl Drop for StaticMemory {
+    fn drop(&mut self) {
+        self.limiters.memory_released(self.current);
+    }
+}
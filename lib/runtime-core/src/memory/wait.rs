@@ -0,0 +1,174 @@
+//! The waiter queues behind `memory.atomic.wait` and `atomic.notify`.
+//!
+//! Waiters are keyed on the host address of the location they wait on, so
+//! that instances sharing a memory on different threads meet in the same
+//! queue no matter which instance they run in.
+use parking_lot::{Condvar, Mutex};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// The outcome of a wait, encoded the way `memory.atomic.wait` returns it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum WaitResult {
+    /// Woken up by a notify.
+    Ok = 0,
+    /// The location did not hold the expected value.
+    NotEqual = 1,
+    /// The timeout expired before a notify arrived.
+    TimedOut = 2,
+}
+
+struct Waiter {
+    woken: Mutex<bool>,
+    condvar: Condvar,
+}
+
+lazy_static! {
+    static ref QUEUES: Mutex<HashMap<usize, VecDeque<Arc<Waiter>>>> = Mutex::new(HashMap::new());
+}
+
+/// Park the current thread on `address` until notified or until `timeout` expires.
+///
+/// `validate` runs with the queues locked, so a notify cannot slip in between
+/// checking the value and starting to wait. If it returns false the thread
+/// does not wait.
+pub(crate) fn wait(
+    address: usize,
+    validate: impl FnOnce() -> bool,
+    timeout: Option<Duration>,
+) -> WaitResult {
+    let waiter = Arc::new(Waiter {
+        woken: Mutex::new(false),
+        condvar: Condvar::new(),
+    });
+
+    {
+        let mut queues = QUEUES.lock();
+        if !validate() {
+            return WaitResult::NotEqual;
+        }
+        queues
+            .entry(address)
+            .or_insert_with(VecDeque::new)
+            .push_back(Arc::clone(&waiter));
+    }
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    {
+        let mut woken = waiter.woken.lock();
+        while !*woken {
+            match deadline {
+                Some(deadline) => {
+                    if waiter.condvar.wait_until(&mut woken, deadline).timed_out() {
+                        break;
+                    }
+                }
+                None => waiter.condvar.wait(&mut woken),
+            }
+        }
+        if *woken {
+            return WaitResult::Ok;
+        }
+    }
+
+    // We timed out, but a notify may have dequeued us in the meantime; if so
+    // it counted us as woken.
+    let mut queues = QUEUES.lock();
+    if let Some(queue) = queues.get_mut(&address) {
+        if let Some(position) = queue.iter().position(|w| Arc::ptr_eq(w, &waiter)) {
+            queue.remove(position);
+            if queue.is_empty() {
+                queues.remove(&address);
+            }
+            return WaitResult::TimedOut;
+        }
+    }
+    WaitResult::Ok
+}
+
+/// Wake up to `count` threads waiting on `address`, oldest first.
+///
+/// Returns the number of threads woken.
+pub(crate) fn notify(address: usize, count: u32) -> u32 {
+    let mut queues = QUEUES.lock();
+    let queue = match queues.get_mut(&address) {
+        Some(queue) => queue,
+        None => return 0,
+    };
+
+    let mut woken = 0;
+    while woken < count {
+        let waiter = match queue.pop_front() {
+            Some(waiter) => waiter,
+            None => break,
+        };
+        *waiter.woken.lock() = true;
+        waiter.condvar.notify_one();
+        woken += 1;
+    }
+
+    if queue.is_empty() {
+        queues.remove(&address);
+    }
+    woken
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{notify, wait, WaitResult};
+    use std::{
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    #[test]
+    fn test_wait_not_equal() {
+        let value = AtomicU32::new(1);
+        let address = &value as *const _ as usize;
+        let result = wait(address, || value.load(Ordering::SeqCst) == 0, None);
+        assert_eq!(result, WaitResult::NotEqual);
+    }
+
+    #[test]
+    fn test_wait_timeout() {
+        let value = AtomicU32::new(0);
+        let address = &value as *const _ as usize;
+        let result = wait(
+            address,
+            || value.load(Ordering::SeqCst) == 0,
+            Some(Duration::from_millis(10)),
+        );
+        assert_eq!(result, WaitResult::TimedOut);
+        assert_eq!(notify(address, 1), 0);
+    }
+
+    #[test]
+    fn test_notify_wakes_waiters() {
+        let value = Arc::new(AtomicU32::new(0));
+        let address = &*value as *const _ as usize;
+
+        let waiters: Vec<_> = (0..2)
+            .map(|_| {
+                let value = Arc::clone(&value);
+                thread::spawn(move || wait(address, || value.load(Ordering::SeqCst) == 0, None))
+            })
+            .collect();
+
+        let mut woken = 0;
+        while woken < 2 {
+            woken += notify(address, 2 - woken);
+            thread::yield_now();
+        }
+
+        for waiter in waiters {
+            assert_eq!(waiter.join().unwrap(), WaitResult::Ok);
+        }
+    }
+}
@@ -7,10 +7,10 @@ use crate::{
     import::ImportObject,
     structures::{Map, TypedIndex},
     types::{
-        FuncIndex, FuncSig, GlobalDescriptor, GlobalIndex, GlobalInit, ImportedFuncIndex,
-        ImportedGlobalIndex, ImportedMemoryIndex, ImportedTableIndex, Initializer,
-        LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryDescriptor, MemoryIndex,
-        SigIndex, TableDescriptor, TableIndex,
+        DataIndex, ElemIndex, ExternType, FuncIndex, FuncSig, FuncType, GlobalDescriptor,
+        GlobalIndex, GlobalInit, ImportedFuncIndex, ImportedGlobalIndex, ImportedMemoryIndex,
+        ImportedTableIndex, Initializer, LocalGlobalIndex, LocalMemoryIndex, LocalOrImport,
+        LocalTableIndex, MemoryDescriptor, MemoryIndex, SigIndex, TableDescriptor, TableIndex,
     },
     Instance,
 };
@@ -18,7 +18,11 @@ use crate::{
 use crate::backend::CacheGen;
 use indexmap::IndexMap;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::mem;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 
 /// This is used to instantiate a new WebAssembly module.
 #[doc(hidden)]
@@ -56,6 +60,10 @@ pub struct ModuleInfo {
     pub data_initializers: Vec<DataInitializer>,
     /// Vector of table initializers.
     pub elem_initializers: Vec<TableInitializer>,
+    /// Map of passive data segment index to its contents.
+    pub passive_data: HashMap<DataIndex, Vec<u8>>,
+    /// Map of passive element segment index to its function indices.
+    pub passive_elements: HashMap<ElemIndex, Vec<FuncIndex>>,
 
     /// Index of optional start function.
     pub start_func: Option<FuncIndex>,
@@ -75,11 +83,197 @@ pub struct ModuleInfo {
     /// Symbol information from emscripten.
     pub em_symbol_map: Option<HashMap<u32, String>>,
 
+    /// Module name from the `name` custom section.
+    pub module_name: Option<String>,
+    /// Map of function index to function name, from the `name` custom section.
+    pub func_names: HashMap<FuncIndex, String>,
+    /// Map of function index to the names of its locals, from the `name` custom section.
+    pub local_names: HashMap<FuncIndex, HashMap<u32, String>>,
+
     /// Custom sections.
     pub custom_sections: HashMap<String, Vec<u8>>,
+
+    /// Tells apart the modules compiled or loaded by this process, so that middlewares can
+    /// keep what they learn about each module they instrument.
+    #[serde(skip, default = "next_module_id")]
+    pub id: u64,
+    /// The number of `u64` counters middlewares instrumented the module with.
+    ///
+    /// Each instance gets its own zeroed counters, which the code increments with
+    /// `InternalEvent::IncrementCounter` and the host reads with `Ctx::counters`. A
+    /// middleware sets this in `ModuleMiddleware::finish_module`.
+    pub counters: usize,
+}
+
+/// Returns a module id that no other module of this process has.
+pub(crate) fn next_module_id() -> u64 {
+    static NEXT_MODULE_ID: AtomicU64 = AtomicU64::new(0);
+    NEXT_MODULE_ID.fetch_add(1, Ordering::Relaxed)
 }
 
 impl ModuleInfo {
+    /// Returns the name of the function at `func_index`, if the module gives it one.
+    pub fn func_name(&self, func_index: FuncIndex) -> Option<&str> {
+        if let Some(name) = self.func_names.get(&func_index) {
+            return Some(name);
+        }
+        self.em_symbol_map
+            .as_ref()?
+            .get(&(func_index.index() as u32))
+            .map(|name| name.as_str())
+    }
+
+    /// Returns the name of local `local_index` of the function at `func_index`, if the
+    /// module gives it one.
+    pub fn local_name(&self, func_index: FuncIndex, local_index: u32) -> Option<&str> {
+        self.local_names
+            .get(&func_index)?
+            .get(&local_index)
+            .map(|name| name.as_str())
+    }
+
+    /// Returns the descriptor of the global at `global_index`.
+    pub fn global_desc(&self, global_index: GlobalIndex) -> GlobalDescriptor {
+        match global_index.local_or_import(self) {
+            LocalOrImport::Local(local_index) => self.globals[local_index].desc,
+            LocalOrImport::Import(import_index) => self.imported_globals[import_index].1,
+        }
+    }
+
+    /// Adds a global defined by the module and returns its index.
+    pub fn add_global(&mut self, desc: GlobalDescriptor, init: Initializer) -> GlobalIndex {
+        let local_index = self.globals.push(GlobalInit { desc, init });
+        local_index.convert_up(self)
+    }
+
+    /// Adds an imported function after the other imported functions and returns its index.
+    ///
+    /// The functions defined by the module each move up by one index. Their references in
+    /// exports, table initializers, the start function and function names are updated here,
+    /// and `read_module` updates the calls in function bodies read after this.
+    pub fn add_import_function(
+        &mut self,
+        namespace: &str,
+        name: &str,
+        signature: FuncSig,
+    ) -> FuncIndex {
+        let func_index = FuncIndex::new(self.imported_functions.len());
+        let sig_index = match self.signatures.iter().find(|&(_, sig)| *sig == signature) {
+            Some((sig_index, _)) => sig_index,
+            None => self.signatures.push(signature),
+        };
+        self.imported_functions.push(ImportName {
+            namespace_index: self.namespace_table.register(namespace),
+            name_index: self.name_table.register(name),
+        });
+        let mut func_assoc = mem::replace(&mut self.func_assoc, Map::new()).into_vec();
+        func_assoc.insert(func_index.index(), sig_index);
+        self.func_assoc = func_assoc.into_iter().collect();
+
+        let shift = |index: FuncIndex| {
+            if index.index() >= func_index.index() {
+                FuncIndex::new(index.index() + 1)
+            } else {
+                index
+            }
+        };
+        for export in self.exports.values_mut() {
+            if let ExportIndex::Func(index) = export {
+                *index = shift(*index);
+            }
+        }
+        let elements = self
+            .elem_initializers
+            .iter_mut()
+            .map(|init| &mut init.elements)
+            .chain(self.passive_elements.values_mut());
+        for elements in elements {
+            for index in elements.iter_mut() {
+                *index = shift(*index);
+            }
+        }
+        self.start_func = self.start_func.map(shift);
+        self.func_names = self
+            .func_names
+            .drain()
+            .map(|(index, name)| (shift(index), name))
+            .collect();
+        self.local_names = self
+            .local_names
+            .drain()
+            .map(|(index, names)| (shift(index), names))
+            .collect();
+        if let Some(symbol_map) = self.em_symbol_map.as_mut() {
+            *symbol_map = symbol_map
+                .drain()
+                .map(|(index, name)| (shift(FuncIndex::new(index as usize)).index() as u32, name))
+                .collect();
+        }
+        func_index
+    }
+
+    /// Lists the imports of the module with their types: the functions first, then the
+    /// memories, tables and globals.
+    pub fn imports(&self) -> Vec<ImportDescriptor> {
+        let import = |import_name: &ImportName, ty| ImportDescriptor {
+            namespace: self
+                .namespace_table
+                .get(import_name.namespace_index)
+                .to_string(),
+            name: self.name_table.get(import_name.name_index).to_string(),
+            ty,
+        };
+
+        let mut imports = Vec::new();
+        for (index, import_name) in self.imported_functions.iter() {
+            let ty = ExternType::Function(self.func_type(index.convert_up(self)));
+            imports.push(import(import_name, ty));
+        }
+        for (_, (import_name, desc)) in self.imported_memories.iter() {
+            imports.push(import(import_name, ExternType::Memory(*desc)));
+        }
+        for (_, (import_name, desc)) in self.imported_tables.iter() {
+            imports.push(import(import_name, ExternType::Table(*desc)));
+        }
+        for (_, (import_name, desc)) in self.imported_globals.iter() {
+            imports.push(import(import_name, ExternType::Global(*desc)));
+        }
+        imports
+    }
+
+    /// Lists the exports of the module with their types, in the order they are declared.
+    pub fn exports(&self) -> Vec<ExportDescriptor> {
+        self.exports
+            .iter()
+            .map(|(name, export_index)| ExportDescriptor {
+                name: name.clone(),
+                ty: self.export_type(*export_index),
+            })
+            .collect()
+    }
+
+    fn func_type(&self, func_index: FuncIndex) -> FuncType {
+        self.signatures[self.func_assoc[func_index]].clone()
+    }
+
+    fn export_type(&self, export_index: ExportIndex) -> ExternType {
+        match export_index {
+            ExportIndex::Func(func_index) => ExternType::Function(self.func_type(func_index)),
+            ExportIndex::Memory(memory_index) => match memory_index.local_or_import(self) {
+                LocalOrImport::Local(index) => ExternType::Memory(self.memories[index]),
+                LocalOrImport::Import(index) => ExternType::Memory(self.imported_memories[index].1),
+            },
+            ExportIndex::Table(table_index) => match table_index.local_or_import(self) {
+                LocalOrImport::Local(index) => ExternType::Table(self.tables[index]),
+                LocalOrImport::Import(index) => ExternType::Table(self.imported_tables[index].1),
+            },
+            ExportIndex::Global(global_index) => match global_index.local_or_import(self) {
+                LocalOrImport::Local(index) => ExternType::Global(self.globals[index].desc),
+                LocalOrImport::Import(index) => ExternType::Global(self.imported_globals[index].1),
+            },
+        }
+    }
+
     /// Creates custom section info from the given wasm file.
     pub fn import_custom_sections(&mut self, wasm: &[u8]) -> crate::error::ParseResult<()> {
         let mut parser = wasmparser::ModuleReader::new(wasm)?;
@@ -104,7 +298,7 @@ impl ModuleInfo {
 ///
 /// [`compile_with`]: crate::compile_with
 pub struct Module {
-    inner: Arc<ModuleInner>,
+    pub(crate) inner: Arc<ModuleInner>,
 }
 
 impl Module {
@@ -135,7 +329,7 @@ impl Module {
     /// # }
     /// ```
     pub fn instantiate(&self, import_object: &ImportObject) -> error::Result<Instance> {
-        Instance::new(Arc::clone(&self.inner), import_object)
+        Instance::new(Arc::clone(&self.inner), import_object, None, true)
     }
 
     /// Create a cache artifact from this module.
@@ -152,6 +346,20 @@ impl Module {
     pub fn info(&self) -> &ModuleInfo {
         &self.inner.info
     }
+
+    /// Lists the imports of this module with their types.
+    ///
+    /// To list them without compiling the module, see [`read_module_interface`].
+    ///
+    /// [`read_module_interface`]: ../parse/fn.read_module_interface.html
+    pub fn imports(&self) -> Vec<ImportDescriptor> {
+        self.inner.info.imports()
+    }
+
+    /// Lists the exports of this module with their types.
+    pub fn exports(&self) -> Vec<ExportDescriptor> {
+        self.inner.info.exports()
+    }
 }
 
 impl Clone for Module {
@@ -164,6 +372,42 @@ impl Clone for Module {
 
 impl ModuleInner {}
 
+/// An import of a module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportDescriptor {
+    /// The namespace, i.e. the module name, of the import.
+    pub namespace: String,
+    /// The name, i.e. the field, of the import.
+    pub name: String,
+    /// The type of the import.
+    pub ty: ExternType,
+}
+
+/// An export of a module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportDescriptor {
+    /// The name of the export.
+    pub name: String,
+    /// The type of the export.
+    pub ty: ExternType,
+}
+
+/// The imports and exports of a module, as read by [`read_module_interface`] without
+/// compiling it.
+///
+/// [`read_module_interface`]: ../parse/fn.read_module_interface.html
+#[derive(Debug, Clone)]
+pub struct ModuleInterface {
+    /// The imports of the module, as listed by [`ModuleInfo::imports`].
+    ///
+    /// [`ModuleInfo::imports`]: struct.ModuleInfo.html#method.imports
+    pub imports: Vec<ImportDescriptor>,
+    /// The exports of the module, as listed by [`ModuleInfo::exports`].
+    ///
+    /// [`ModuleInfo::exports`]: struct.ModuleInfo.html#method.exports
+    pub exports: Vec<ExportDescriptor>,
+}
+
 #[doc(hidden)]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ImportName {
@@ -295,6 +539,16 @@ impl<K: TypedIndex> StringTable<K> {
 
         &self.buffer[offset..offset + length]
     }
+
+    /// Returns the index of `s`, adding it to the table if it is not there yet.
+    fn register(&mut self, s: &str) -> K {
+        let (offset, length) = (self.buffer.len() as u32, s.len() as u32);
+        if let Some((index, _)) = self.table.iter().find(|&(index, _)| self.get(index) == s) {
+            return index;
+        }
+        self.buffer.push_str(s);
+        self.table.push((offset, length))
+    }
 }
 
 /// A type-safe handle referring to a module namespace.
@@ -6,14 +6,15 @@ use crate::{
     backend::{Backend, CompilerConfig, RunnableModule},
     error::CompileError,
     module::{
-        DataInitializer, ExportIndex, ImportName, ModuleInfo, StringTable, StringTableBuilder,
-        TableInitializer,
+        next_module_id, DataInitializer, ExportIndex, ImportName, ModuleInfo, ModuleInterface,
+        StringTable, StringTableBuilder, TableInitializer,
     },
     structures::{Map, TypedIndex},
+    table::AnyRef,
     types::{
-        ElementType, FuncIndex, FuncSig, GlobalDescriptor, GlobalIndex, GlobalInit,
-        ImportedGlobalIndex, Initializer, MemoryDescriptor, MemoryIndex, SigIndex, TableDescriptor,
-        TableIndex, Type, Value,
+        DataIndex, ElemIndex, ElementType, FuncIndex, FuncSig, GlobalDescriptor, GlobalIndex,
+        GlobalInit, ImportedGlobalIndex, Initializer, MemoryDescriptor, MemoryIndex, SigIndex,
+        TableDescriptor, TableIndex, Type, Value,
     },
     units::Pages,
 };
@@ -21,8 +22,8 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::{Arc, RwLock};
 use wasmparser::{
-    BinaryReaderError, ExternalKind, FuncType, ImportSectionEntryType, Operator, Type as WpType,
-    WasmDecoder,
+    BinaryReaderError, ExternalKind, FuncType, GlobalType, ImportSectionEntryType, MemoryType,
+    Operator, ParserInput, TableType, Type as WpType, WasmDecoder,
 };
 
 /// Kind of load error.
@@ -64,34 +65,10 @@ pub fn read_module<
 ) -> Result<Arc<RwLock<ModuleInfo>>, LoadError> {
     mcg.feed_compiler_config(compiler_config)
         .map_err(|x| LoadError::Codegen(format!("{:?}", x)))?;
-    let info = Arc::new(RwLock::new(ModuleInfo {
-        memories: Map::new(),
-        globals: Map::new(),
-        tables: Map::new(),
-
-        imported_functions: Map::new(),
-        imported_memories: Map::new(),
-        imported_tables: Map::new(),
-        imported_globals: Map::new(),
-
-        exports: Default::default(),
-
-        data_initializers: Vec::new(),
-        elem_initializers: Vec::new(),
-
-        start_func: None,
-
-        func_assoc: Map::new(),
-        signatures: Map::new(),
-        backend: backend,
-
-        namespace_table: StringTable::new(),
-        name_table: StringTable::new(),
-
-        em_symbol_map: compiler_config.symbol_map.clone(),
-
-        custom_sections: HashMap::new(),
-    }));
+    let info = Arc::new(RwLock::new(empty_module_info(
+        backend,
+        compiler_config.symbol_map.clone(),
+    )));
 
     let mut parser = wasmparser::ValidatingParser::new(
         wasm,
@@ -101,11 +78,21 @@ pub fn read_module<
     let mut namespace_builder = Some(StringTableBuilder::new());
     let mut name_builder = Some(StringTableBuilder::new());
     let mut func_count: usize = 0;
+    let mut elem_count: usize = 0;
+    let mut data_count: usize = 0;
     let mut mcg_info_fed = false;
+    // Module middlewares may add imported functions, which shifts the indices of the
+    // functions defined by the module.
+    let mut declared_func_imports: usize = 0;
+    let mut func_shift: u32 = 0;
+    // The contents of the name sections, decoded once the function indices are final.
+    let mut name_sections = vec![];
+    let mut input = ParserInput::Default;
 
     loop {
-        use wasmparser::ParserState;
-        let state = parser.read();
+        use wasmparser::{CustomSectionKind, ParserState, SectionCode};
+        let state = parser.read_with_input(input);
+        input = ParserInput::Default;
         match *state {
             ParserState::Error(err) => Err(LoadError::Parse(err))?,
             ParserState::TypeSectionEntry(ref ty) => {
@@ -131,36 +118,21 @@ pub fn read_module<
                             .map_err(|x| LoadError::Codegen(format!("{:?}", x)))?;
                     }
                     ImportSectionEntryType::Table(table_ty) => {
-                        assert_eq!(table_ty.element_type, WpType::AnyFunc);
-                        let table_desc = TableDescriptor {
-                            element: ElementType::Anyfunc,
-                            minimum: table_ty.limits.initial,
-                            maximum: table_ty.limits.maximum,
-                        };
-
+                        let table_desc = table_type_to_table_desc(&table_ty)?;
                         info.write()
                             .unwrap()
                             .imported_tables
                             .push((import_name, table_desc));
                     }
                     ImportSectionEntryType::Memory(memory_ty) => {
-                        let mem_desc = MemoryDescriptor::new(
-                            Pages(memory_ty.limits.initial),
-                            memory_ty.limits.maximum.map(|max| Pages(max)),
-                            memory_ty.shared,
-                        )
-                        .map_err(|x| LoadError::Codegen(format!("{:?}", x)))?;
-
+                        let mem_desc = memory_type_to_memory_desc(&memory_ty)?;
                         info.write()
                             .unwrap()
                             .imported_memories
                             .push((import_name, mem_desc));
                     }
                     ImportSectionEntryType::Global(global_ty) => {
-                        let global_desc = GlobalDescriptor {
-                            mutable: global_ty.mutable,
-                            ty: wp_type_to_type(global_ty.content_type)?,
-                        };
+                        let global_desc = global_type_to_global_desc(&global_ty)?;
                         info.write()
                             .unwrap()
                             .imported_globals
@@ -173,36 +145,18 @@ pub fn read_module<
                 info.write().unwrap().func_assoc.push(sigindex);
             }
             ParserState::TableSectionEntry(table_ty) => {
-                let table_desc = TableDescriptor {
-                    element: ElementType::Anyfunc,
-                    minimum: table_ty.limits.initial,
-                    maximum: table_ty.limits.maximum,
-                };
-
+                let table_desc = table_type_to_table_desc(&table_ty)?;
                 info.write().unwrap().tables.push(table_desc);
             }
             ParserState::MemorySectionEntry(memory_ty) => {
-                let mem_desc = MemoryDescriptor::new(
-                    Pages(memory_ty.limits.initial),
-                    memory_ty.limits.maximum.map(|max| Pages(max)),
-                    memory_ty.shared,
-                )
-                .map_err(|x| LoadError::Codegen(format!("{:?}", x)))?;
-
+                let mem_desc = memory_type_to_memory_desc(&memory_ty)?;
                 info.write().unwrap().memories.push(mem_desc);
             }
             ParserState::ExportSectionEntry { field, kind, index } => {
-                let export_index = match kind {
-                    ExternalKind::Function => ExportIndex::Func(FuncIndex::new(index as usize)),
-                    ExternalKind::Table => ExportIndex::Table(TableIndex::new(index as usize)),
-                    ExternalKind::Memory => ExportIndex::Memory(MemoryIndex::new(index as usize)),
-                    ExternalKind::Global => ExportIndex::Global(GlobalIndex::new(index as usize)),
-                };
-
                 info.write()
                     .unwrap()
                     .exports
-                    .insert(field.to_string(), export_index);
+                    .insert(field.to_string(), export_index(kind, index));
             }
             ParserState::StartSectionEntry(start_index) => {
                 info.write().unwrap().start_func = Some(FuncIndex::new(start_index as usize));
@@ -214,12 +168,10 @@ pub fn read_module<
                     info.write().unwrap().namespace_table =
                         namespace_builder.take().unwrap().finish();
                     info.write().unwrap().name_table = name_builder.take().unwrap().finish();
-                    mcg.feed_signatures(info.read().unwrap().signatures.clone())
-                        .map_err(|x| LoadError::Codegen(format!("{:?}", x)))?;
-                    mcg.feed_function_signatures(info.read().unwrap().func_assoc.clone())
-                        .map_err(|x| LoadError::Codegen(format!("{:?}", x)))?;
-                    mcg.check_precondition(&info.read().unwrap())
-                        .map_err(|x| LoadError::Codegen(format!("{:?}", x)))?;
+                    declared_func_imports = info.read().unwrap().imported_functions.len();
+                    feed_module_info::<MCG, FCG, RM, E>(mcg, middlewares, &info)?;
+                    func_shift = (info.read().unwrap().imported_functions.len()
+                        - declared_func_imports) as u32;
                 }
 
                 let fcg = mcg
@@ -252,6 +204,7 @@ pub fn read_module<
                 let mut body_begun = false;
 
                 loop {
+                    let offset = parser.current_position();
                     let state = parser.read();
                     match state {
                         ParserState::Error(err) => return Err(LoadError::Parse(*err)),
@@ -270,12 +223,24 @@ pub fn read_module<
                                     .run(
                                         Some(fcg),
                                         Event::Internal(InternalEvent::FunctionBegin(id as u32)),
+                                        None,
                                         &info.read().unwrap(),
                                     )
                                     .map_err(|x| LoadError::Codegen(x))?;
                             }
+                            let event = match *op {
+                                Operator::Call { function_index }
+                                    if func_shift != 0
+                                        && function_index as usize >= declared_func_imports =>
+                                {
+                                    Event::WasmOwned(Operator::Call {
+                                        function_index: function_index + func_shift,
+                                    })
+                                }
+                                _ => Event::Wasm(op),
+                            };
                             middlewares
-                                .run(Some(fcg), Event::Wasm(op), &info.read().unwrap())
+                                .run(Some(fcg), event, Some(offset), &info.read().unwrap())
                                 .map_err(|x| LoadError::Codegen(x))?;
                         }
                         ParserState::EndFunctionBody => break,
@@ -286,6 +251,7 @@ pub fn read_module<
                     .run(
                         Some(fcg),
                         Event::Internal(InternalEvent::FunctionEnd),
+                        None,
                         &info.read().unwrap(),
                     )
                     .map_err(|x| LoadError::Codegen(x))?;
@@ -328,6 +294,34 @@ pub fn read_module<
                 };
 
                 info.write().unwrap().elem_initializers.push(table_init);
+                elem_count += 1;
+            }
+            ParserState::BeginPassiveElementSectionEntry(_ty) => {
+                let mut elements: Option<Vec<FuncIndex>> = None;
+
+                loop {
+                    let state = parser.read();
+                    match *state {
+                        ParserState::Error(err) => return Err(LoadError::Parse(err)),
+                        ParserState::ElementSectionEntryBody(ref _elements) => {
+                            elements = Some(
+                                _elements
+                                    .iter()
+                                    .cloned()
+                                    .map(|index| FuncIndex::new(index as usize))
+                                    .collect(),
+                            );
+                        }
+                        ParserState::EndElementSectionEntry => break,
+                        _ => unreachable!(),
+                    }
+                }
+
+                info.write()
+                    .unwrap()
+                    .passive_elements
+                    .insert(ElemIndex::new(elem_count), elements.unwrap());
+                elem_count += 1;
             }
             ParserState::BeginActiveDataSectionEntry(memory_index) => {
                 let memory_index = MemoryIndex::new(memory_index as usize);
@@ -359,6 +353,30 @@ pub fn read_module<
                     data,
                 };
                 info.write().unwrap().data_initializers.push(data_init);
+                data_count += 1;
+            }
+            ParserState::BeginPassiveDataSectionEntry => {
+                let mut data: Vec<u8> = vec![];
+
+                loop {
+                    let state = parser.read();
+                    match *state {
+                        ParserState::Error(err) => return Err(LoadError::Parse(err)),
+                        ParserState::DataSectionEntryBodyChunk(chunk) => {
+                            data.extend_from_slice(chunk);
+                        }
+                        ParserState::BeginDataSectionEntryBody(_)
+                        | ParserState::EndDataSectionEntryBody => {}
+                        ParserState::EndDataSectionEntry => break,
+                        _ => unreachable!(),
+                    }
+                }
+
+                info.write()
+                    .unwrap()
+                    .passive_data
+                    .insert(DataIndex::new(data_count), data);
+                data_count += 1;
             }
             ParserState::BeginGlobalSectionEntry(ty) => {
                 let init = loop {
@@ -372,36 +390,357 @@ pub fn read_module<
                         _ => unreachable!(),
                     }
                 };
-                let desc = GlobalDescriptor {
-                    mutable: ty.mutable,
-                    ty: wp_type_to_type(ty.content_type)?,
-                };
-
+                let desc = global_type_to_global_desc(&ty)?;
                 let global_init = GlobalInit { desc, init };
 
                 info.write().unwrap().globals.push(global_init);
             }
-            ParserState::EndWasm => {
-                // TODO Consolidate with BeginFunction body if possible
-                if !mcg_info_fed {
-                    info.write().unwrap().namespace_table =
-                        namespace_builder.take().unwrap().finish();
-                    info.write().unwrap().name_table = name_builder.take().unwrap().finish();
-                    mcg.feed_signatures(info.read().unwrap().signatures.clone())
-                        .map_err(|x| LoadError::Codegen(format!("{:?}", x)))?;
-                    mcg.feed_function_signatures(info.read().unwrap().func_assoc.clone())
-                        .map_err(|x| LoadError::Codegen(format!("{:?}", x)))?;
-                    mcg.check_precondition(&info.read().unwrap())
-                        .map_err(|x| LoadError::Codegen(format!("{:?}", x)))?;
+            ParserState::BeginSection {
+                code:
+                    SectionCode::Custom {
+                        kind: CustomSectionKind::Name,
+                        ..
+                    },
+                ..
+            } => input = ParserInput::ReadSectionRawData,
+            ParserState::SectionRawData(data) => name_sections.push(data),
+            ParserState::EndWasm => break,
+            _ => {}
+        }
+    }
+    if !mcg_info_fed {
+        info.write().unwrap().namespace_table = namespace_builder.take().unwrap().finish();
+        info.write().unwrap().name_table = name_builder.take().unwrap().finish();
+        declared_func_imports = info.read().unwrap().imported_functions.len();
+        feed_module_info::<MCG, FCG, RM, E>(mcg, middlewares, &info)?;
+        func_shift = (info.read().unwrap().imported_functions.len() - declared_func_imports) as u32;
+    }
+
+    // A malformed name section only costs us the names.
+    match name_sections
+        .into_iter()
+        .map(read_name_section)
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(sections) => {
+            // Names refer to the functions as they were declared, before any imported
+            // function added by a module middleware.
+            let func_index = |index: u32| {
+                if index as usize >= declared_func_imports {
+                    FuncIndex::new((index + func_shift) as usize)
+                } else {
+                    FuncIndex::new(index as usize)
+                }
+            };
+            let mut info = info.write().unwrap();
+            for names in sections {
+                if names.module_name.is_some() {
+                    info.module_name = names.module_name;
+                }
+                for (index, name) in names.func_names {
+                    info.func_names.insert(func_index(index), name);
+                }
+                for (index, local_index, name) in names.local_names {
+                    info.local_names
+                        .entry(func_index(index))
+                        .or_insert_with(HashMap::new)
+                        .insert(local_index, name);
                 }
-                break;
             }
-            _ => {}
         }
+        Err(_err) => debug!("ignoring the malformed name section: {:?}", _err),
     }
+    middlewares
+        .finish_module(&mut info.write().unwrap())
+        .map_err(|x| LoadError::Codegen(x))?;
     Ok(info)
 }
 
+/// What a name section names, with the function indices as declared in the module.
+#[derive(Default)]
+struct Names {
+    module_name: Option<String>,
+    func_names: Vec<(u32, String)>,
+    local_names: Vec<(u32, u32, String)>,
+}
+
+/// Decodes the name section whose contents are `data`.
+///
+/// The parser of the module cannot go on past an error, so the section is wrapped in an
+/// otherwise empty module and read by a parser of its own.
+fn read_name_section(data: &[u8]) -> Result<Names, BinaryReaderError> {
+    use wasmparser::{NameEntry, Parser, ParserState};
+
+    let mut payload = b"\x04name".to_vec();
+    payload.extend_from_slice(data);
+    let mut wasm = b"\0asm\x01\0\0\0\0".to_vec();
+    let mut len = payload.len();
+    loop {
+        let byte = (len & 0x7f) as u8;
+        len >>= 7;
+        if len == 0 {
+            wasm.push(byte);
+            break;
+        }
+        wasm.push(byte | 0x80);
+    }
+    wasm.extend_from_slice(&payload);
+
+    let mut parser = Parser::new(&wasm);
+    let mut names = Names::default();
+    loop {
+        match *parser.read() {
+            ParserState::Error(err) => return Err(err),
+            ParserState::NameSectionEntry(ref entry) => match *entry {
+                NameEntry::Module(name) => names.module_name = Some(name.to_string()),
+                NameEntry::Function(ref functions) => {
+                    for naming in functions.iter() {
+                        names
+                            .func_names
+                            .push((naming.index, naming.name.to_string()));
+                    }
+                }
+                NameEntry::Local(ref functions) => {
+                    for function in functions.iter() {
+                        for naming in function.locals.iter() {
+                            names.local_names.push((
+                                function.index,
+                                naming.index,
+                                naming.name.to_string(),
+                            ));
+                        }
+                    }
+                }
+            },
+            ParserState::EndWasm => return Ok(names),
+            _ => {}
+        }
+    }
+}
+
+/// Runs the module middlewares on the module info, then gives the module code generator the
+/// imported functions they added, the signatures and the function signatures.
+fn feed_module_info<
+    MCG: ModuleCodeGenerator<FCG, RM, E>,
+    FCG: FunctionCodeGenerator<E>,
+    RM: RunnableModule,
+    E: Debug,
+>(
+    mcg: &mut MCG,
+    middlewares: &mut MiddlewareChain,
+    info: &RwLock<ModuleInfo>,
+) -> Result<(), LoadError> {
+    let declared_func_imports = info.read().unwrap().imported_functions.len();
+    middlewares
+        .run_module(&mut info.write().unwrap())
+        .map_err(|x| LoadError::Codegen(x))?;
+    for _ in declared_func_imports..info.read().unwrap().imported_functions.len() {
+        mcg.feed_import_function()
+            .map_err(|x| LoadError::Codegen(format!("{:?}", x)))?;
+    }
+    mcg.feed_signatures(info.read().unwrap().signatures.clone())
+        .map_err(|x| LoadError::Codegen(format!("{:?}", x)))?;
+    mcg.feed_function_signatures(info.read().unwrap().func_assoc.clone())
+        .map_err(|x| LoadError::Codegen(format!("{:?}", x)))?;
+    mcg.check_precondition(&info.read().unwrap())
+        .map_err(|x| LoadError::Codegen(format!("{:?}", x)))?;
+    Ok(())
+}
+
+/// Reads the imports and exports of a wasm binary with their types, without compiling it.
+///
+/// Only the sections declaring types, imports, functions, tables, memories, globals and
+/// exports are read. The module is not validated, so a module that this accepts may still
+/// fail to compile, but the type and export indices it refers to are checked.
+pub fn read_module_interface(wasm: &[u8]) -> Result<ModuleInterface, LoadError> {
+    use wasmparser::{ModuleReader, SectionCode};
+
+    let mut info = empty_module_info(Backend::Auto, None);
+    let mut namespace_builder = StringTableBuilder::new();
+    let mut name_builder = StringTableBuilder::new();
+
+    let mut reader = ModuleReader::new(wasm)?;
+    while !reader.eof() {
+        let section = reader.read()?;
+        match section.code {
+            SectionCode::Type => {
+                for ty in section.get_type_section_reader()? {
+                    info.signatures.push(func_type_to_func_sig(&ty?)?);
+                }
+            }
+            SectionCode::Import => {
+                for import in section.get_import_section_reader()? {
+                    let import = import?;
+                    let import_name = ImportName {
+                        namespace_index: namespace_builder.register(import.module),
+                        name_index: name_builder.register(import.field),
+                    };
+                    match import.ty {
+                        ImportSectionEntryType::Function(sigindex) => {
+                            check_index(
+                                sigindex,
+                                info.signatures.len(),
+                                "type index out of bounds",
+                            )?;
+                            info.imported_functions.push(import_name);
+                            info.func_assoc.push(SigIndex::new(sigindex as usize));
+                        }
+                        ImportSectionEntryType::Table(table_ty) => {
+                            let table_desc = table_type_to_table_desc(&table_ty)?;
+                            info.imported_tables.push((import_name, table_desc));
+                        }
+                        ImportSectionEntryType::Memory(memory_ty) => {
+                            let mem_desc = memory_type_to_memory_desc(&memory_ty)?;
+                            info.imported_memories.push((import_name, mem_desc));
+                        }
+                        ImportSectionEntryType::Global(global_ty) => {
+                            let global_desc = global_type_to_global_desc(&global_ty)?;
+                            info.imported_globals.push((import_name, global_desc));
+                        }
+                    }
+                }
+            }
+            SectionCode::Function => {
+                for sigindex in section.get_function_section_reader()? {
+                    let sigindex = sigindex?;
+                    check_index(sigindex, info.signatures.len(), "type index out of bounds")?;
+                    info.func_assoc.push(SigIndex::new(sigindex as usize));
+                }
+            }
+            SectionCode::Table => {
+                for table_ty in section.get_table_section_reader()? {
+                    info.tables.push(table_type_to_table_desc(&table_ty?)?);
+                }
+            }
+            SectionCode::Memory => {
+                for memory_ty in section.get_memory_section_reader()? {
+                    info.memories.push(memory_type_to_memory_desc(&memory_ty?)?);
+                }
+            }
+            SectionCode::Global => {
+                for global in section.get_global_section_reader()? {
+                    let global = global?;
+                    let desc = global_type_to_global_desc(&global.ty)?;
+                    let op = global.init_expr.get_operators_reader().read()?;
+                    let init = eval_init_expr(&op)?;
+                    info.globals.push(GlobalInit { desc, init });
+                }
+            }
+            SectionCode::Export => {
+                for export in section.get_export_section_reader()? {
+                    let export = export?;
+                    let len = match export.kind {
+                        ExternalKind::Function => info.func_assoc.len(),
+                        ExternalKind::Table => info.imported_tables.len() + info.tables.len(),
+                        ExternalKind::Memory => {
+                            info.imported_memories.len() + info.memories.len()
+                        }
+                        ExternalKind::Global => info.imported_globals.len() + info.globals.len(),
+                    };
+                    check_index(export.index, len, "export index out of bounds")?;
+                    info.exports.insert(
+                        export.field.to_string(),
+                        export_index(export.kind, export.index),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    info.namespace_table = namespace_builder.finish();
+    info.name_table = name_builder.finish();
+
+    Ok(ModuleInterface {
+        imports: info.imports(),
+        exports: info.exports(),
+    })
+}
+
+/// Creates the `ModuleInfo` of a module that declares nothing.
+fn empty_module_info(backend: Backend, em_symbol_map: Option<HashMap<u32, String>>) -> ModuleInfo {
+    ModuleInfo {
+        memories: Map::new(),
+        globals: Map::new(),
+        tables: Map::new(),
+
+        imported_functions: Map::new(),
+        imported_memories: Map::new(),
+        imported_tables: Map::new(),
+        imported_globals: Map::new(),
+
+        exports: Default::default(),
+
+        data_initializers: Vec::new(),
+        elem_initializers: Vec::new(),
+        passive_data: HashMap::new(),
+        passive_elements: HashMap::new(),
+
+        start_func: None,
+
+        func_assoc: Map::new(),
+        signatures: Map::new(),
+        backend,
+
+        namespace_table: StringTable::new(),
+        name_table: StringTable::new(),
+
+        em_symbol_map,
+
+        module_name: None,
+        func_names: HashMap::new(),
+        local_names: HashMap::new(),
+
+        custom_sections: HashMap::new(),
+
+        id: next_module_id(),
+        counters: 0,
+    }
+}
+
+fn check_index(index: u32, len: usize, message: &'static str) -> Result<(), BinaryReaderError> {
+    if (index as usize) < len {
+        Ok(())
+    } else {
+        Err(BinaryReaderError {
+            message,
+            offset: -1isize as usize,
+        })
+    }
+}
+
+fn export_index(kind: ExternalKind, index: u32) -> ExportIndex {
+    match kind {
+        ExternalKind::Function => ExportIndex::Func(FuncIndex::new(index as usize)),
+        ExternalKind::Table => ExportIndex::Table(TableIndex::new(index as usize)),
+        ExternalKind::Memory => ExportIndex::Memory(MemoryIndex::new(index as usize)),
+        ExternalKind::Global => ExportIndex::Global(GlobalIndex::new(index as usize)),
+    }
+}
+
+fn table_type_to_table_desc(table_ty: &TableType) -> Result<TableDescriptor, LoadError> {
+    Ok(TableDescriptor {
+        element: wp_type_to_element_type(table_ty.element_type)?,
+        minimum: table_ty.limits.initial,
+        maximum: table_ty.limits.maximum,
+    })
+}
+
+fn memory_type_to_memory_desc(memory_ty: &MemoryType) -> Result<MemoryDescriptor, LoadError> {
+    MemoryDescriptor::new(
+        Pages(memory_ty.limits.initial),
+        memory_ty.limits.maximum.map(|max| Pages(max)),
+        memory_ty.shared,
+    )
+    .map_err(|x| LoadError::Codegen(format!("{:?}", x)))
+}
+
+fn global_type_to_global_desc(global_ty: &GlobalType) -> Result<GlobalDescriptor, LoadError> {
+    Ok(GlobalDescriptor {
+        mutable: global_ty.mutable,
+        ty: wp_type_to_type(global_ty.content_type)?,
+    })
+}
+
 /// Convert given `WpType` to `Type`.
 pub fn wp_type_to_type(ty: WpType) -> Result<Type, BinaryReaderError> {
     match ty {
@@ -410,6 +749,8 @@ pub fn wp_type_to_type(ty: WpType) -> Result<Type, BinaryReaderError> {
         WpType::F32 => Ok(Type::F32),
         WpType::F64 => Ok(Type::F64),
         WpType::V128 => Ok(Type::V128),
+        WpType::AnyRef => Ok(Type::AnyRef),
+        WpType::AnyFunc => Ok(Type::FuncRef),
         _ => {
             return Err(BinaryReaderError {
                 message: "broken invariant, invalid type",
@@ -427,6 +768,19 @@ pub fn type_to_wp_type(ty: Type) -> WpType {
         Type::F32 => WpType::F32,
         Type::F64 => WpType::F64,
         Type::V128 => WpType::V128,
+        Type::AnyRef => WpType::AnyRef,
+        Type::FuncRef => WpType::AnyFunc,
+    }
+}
+
+fn wp_type_to_element_type(ty: WpType) -> Result<ElementType, BinaryReaderError> {
+    match ty {
+        WpType::AnyFunc => Ok(ElementType::Anyfunc),
+        WpType::AnyRef => Ok(ElementType::AnyRef),
+        _ => Err(BinaryReaderError {
+            message: "unsupported table element type",
+            offset: -1isize as usize,
+        }),
     }
 }
 
@@ -465,6 +819,7 @@ fn eval_init_expr(op: &Operator) -> Result<Initializer, BinaryReaderError> {
         Operator::V128Const { value } => {
             Initializer::Const(Value::V128(u128::from_le_bytes(*value.bytes())))
         }
+        Operator::RefNull => Initializer::Const(Value::AnyRef(AnyRef::null())),
         _ => {
             return Err(BinaryReaderError {
                 message: "init expr evaluation failed: unsupported opcode",
@@ -3,6 +3,15 @@
 //! generated code from one tier to another, or serializing state of a running instace.
 
 use crate::backend::{Backend, RunnableModule};
+use crate::error::{RestoreError, WasmFrame};
+use crate::global::Global;
+use crate::module::ModuleInfo;
+use crate::sig_registry::SigRegistry;
+use crate::structures::TypedIndex;
+use crate::table::{AnyRef, TableStorage};
+use crate::types::{FuncIndex, ImportedGlobalIndex, LocalGlobalIndex, Type};
+use crate::units::{Pages, WASM_PAGE_SIZE};
+use crate::vm::{self, Ctx};
 use std::collections::BTreeMap;
 use std::ops::Bound::{Included, Unbounded};
 use std::sync::Arc;
@@ -103,6 +112,8 @@ pub struct FunctionStateMap {
     pub wasm_function_header_target_offset: Option<SuspendOffset>,
     /// Wasm offset to target offset
     pub wasm_offset_to_target_offset: BTreeMap<usize, SuspendOffset>,
+    /// Wasm offset to the offset of the instruction in the wasm binary.
+    pub wasm_offset_to_binary_offset: BTreeMap<usize, usize>,
     /// Loop offsets.
     pub loop_offsets: BTreeMap<usize, OffsetInfo>, /* suspend_offset -> info */
     /// Call offsets.
@@ -149,6 +160,8 @@ pub struct WasmFunctionStateDump {
     pub local_function_id: usize,
     /// Wasm instruction offset.
     pub wasm_inst_offset: usize,
+    /// Offset of the instruction in the wasm binary, if the backend recorded it.
+    pub binary_offset: Option<usize>,
     /// Stack.
     pub stack: Vec<Option<u64>>,
     /// Locals.
@@ -162,17 +175,36 @@ pub struct ExecutionStateImage {
     pub frames: Vec<WasmFunctionStateDump>,
 }
 
-/// Represents an image of an `Instance` including its memory, globals, and execution state.
+/// Represents an image of an `Instance` including its memory, globals, tables and execution
+/// state.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstanceImage {
+    /// The version of the image format, `InstanceImage::VERSION` for the images made by this
+    /// runtime.
+    pub version: u32,
     /// Memory for this `InstanceImage`
     pub memory: Option<Vec<u8>>,
     /// Stored globals for this `InstanceImage`
     pub globals: Vec<u128>,
+    /// Values of the imported globals, in the order of the imports
+    pub imported_globals: Vec<u128>,
+    /// Elements of the local tables for this `InstanceImage`
+    pub tables: Vec<Vec<TableElementImage>>,
     /// `ExecutionStateImage` for this `InstanceImage`
     pub execution_state: ExecutionStateImage,
 }
 
+/// An element of a table in an `InstanceImage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TableElementImage {
+    /// A null element.
+    Null,
+    /// A function of the instance, imported or local.
+    Function(FuncIndex),
+    /// A function of another instance or a host reference. It cannot be restored.
+    Foreign,
+}
+
 /// A `CodeVersion` is a container for a unit of generated code for a module.
 #[derive(Clone)]
 pub struct CodeVersion {
@@ -270,6 +302,7 @@ impl FunctionStateMap {
             diffs: vec![],
             wasm_function_header_target_offset: None,
             wasm_offset_to_target_offset: BTreeMap::new(),
+            wasm_offset_to_binary_offset: BTreeMap::new(),
             loop_offsets: BTreeMap::new(),
             call_offsets: BTreeMap::new(),
             trappable_offsets: BTreeMap::new(),
@@ -384,13 +417,32 @@ impl MachineStateDiff {
 }
 
 impl ExecutionStateImage {
+    /// Describes the frames of this image as functions of the module described by `info`,
+    /// innermost first.
+    pub fn backtrace(&self, info: &ModuleInfo) -> Vec<WasmFrame> {
+        self.frames
+            .iter()
+            .map(|f| {
+                let func_index =
+                    FuncIndex::new(info.imported_functions.len() + f.local_function_id);
+                WasmFrame {
+                    func_index,
+                    name: info.func_name(func_index).map(|name| name.to_string()),
+                    offset: f.binary_offset,
+                }
+            })
+            .collect()
+    }
+
     /// Prints a backtrace if the `WASMER_BACKTRACE` environment variable is 1.
-    pub fn print_backtrace_if_needed(&self) {
+    ///
+    /// Functions and locals are named after the module described by `info`.
+    pub fn print_backtrace_if_needed(&self, info: &ModuleInfo) {
         use std::env;
 
         if let Ok(x) = env::var("WASMER_BACKTRACE") {
             if x == "1" {
-                eprintln!("{}", self.output());
+                eprintln!("{}", self.output(info));
                 return;
             }
         }
@@ -399,7 +451,10 @@ impl ExecutionStateImage {
     }
 
     /// Converts self into a `String`, used for display purposes.
-    pub fn output(&self) -> String {
+    ///
+    /// Functions and locals are named after the module described by `info`, where
+    /// it names them.
+    pub fn output(&self, info: &ModuleInfo) -> String {
         fn join_strings(x: impl Iterator<Item = String>, sep: &str) -> String {
             let mut ret = String::new();
             let mut first = true;
@@ -416,15 +471,19 @@ impl ExecutionStateImage {
             ret
         }
 
-        fn format_optional_u64_sequence(x: &[Option<u64>]) -> String {
+        fn format_optional_u64_sequence(
+            x: &[Option<u64>],
+            name: impl Fn(usize) -> Option<String>,
+        ) -> String {
             if x.len() == 0 {
                 "(empty)".into()
             } else {
                 join_strings(
                     x.iter().enumerate().map(|(i, x)| {
                         format!(
-                            "[{}] = {}",
+                            "[{}]{} = {}",
                             i,
+                            name(i).map(|x| format!(" {}", x)).unwrap_or_default(),
                             x.map(|x| format!("{}", x))
                                 .unwrap_or_else(|| "?".to_string())
                         )
@@ -443,18 +502,26 @@ impl ExecutionStateImage {
             ret += &"Backtrace:";
             ret += "\n";
             for (i, f) in self.frames.iter().enumerate() {
-                ret += &format!("* Frame {} @ Local function {}", i, f.local_function_id);
+                let func_index =
+                    FuncIndex::new(info.imported_functions.len() + f.local_function_id);
+                let function = match info.func_name(func_index) {
+                    Some(name) => format!("{} (local function {})", name, f.local_function_id),
+                    None => format!("Local function {}", f.local_function_id),
+                };
+                ret += &format!("* Frame {} @ {}", i, function);
                 ret += "\n";
                 ret += &format!("  {} {}\n", "Offset:", format!("{}", f.wasm_inst_offset),);
                 ret += &format!(
                     "  {} {}\n",
                     "Locals:",
-                    format_optional_u64_sequence(&f.locals)
+                    format_optional_u64_sequence(&f.locals, |i| info
+                        .local_name(func_index, i as u32)
+                        .map(|name| name.to_string()))
                 );
                 ret += &format!(
                     "  {} {}\n\n",
                     "Stack:",
-                    format_optional_u64_sequence(&f.stack)
+                    format_optional_u64_sequence(&f.stack, |_| None)
                 );
             }
         }
@@ -464,9 +531,20 @@ impl ExecutionStateImage {
 }
 
 impl InstanceImage {
+    /// The version of the image format. It is bumped whenever the layout of `InstanceImage`
+    /// changes, so that images persisted by another version of the runtime are rejected.
+    pub const VERSION: u32 = 1;
+
     /// Converts a slice of bytes into an `Option<InstanceImage>`
+    ///
+    /// Returns `None` if the bytes are not an image of the current `VERSION`.
     pub fn from_bytes(input: &[u8]) -> Option<InstanceImage> {
         use bincode::deserialize;
+        // The version comes first, so it can be read whatever the layout of the rest is.
+        match deserialize::<u32>(input) {
+            Ok(version) if version == InstanceImage::VERSION => {}
+            _ => return None,
+        }
         match deserialize(input) {
             Ok(x) => Some(x),
             Err(_) => None,
@@ -478,6 +556,268 @@ impl InstanceImage {
         use bincode::serialize;
         serialize(self).unwrap()
     }
+
+    /// Captures memory 0, the globals and the local tables of the instance of `vmctx`.
+    pub(crate) unsafe fn capture(
+        vmctx: &Ctx,
+        execution_state: ExecutionStateImage,
+    ) -> InstanceImage {
+        let info = &(*vmctx.module).info;
+        let local_backing = &*vmctx.local_backing;
+
+        let memory = if info.memories.len() + info.imported_memories.len() == 0 {
+            None
+        } else {
+            let memory = vmctx.memory(0).vm_local_memory();
+            Some(std::slice::from_raw_parts((*memory).base, (*memory).bound).to_vec())
+        };
+
+        let globals = local_backing
+            .globals
+            .iter()
+            .map(|(_, global)| global.get().to_u128())
+            .collect();
+        let imported_globals = (*vmctx.import_backing)
+            .globals
+            .iter()
+            .map(|(_, global)| global.get().to_u128())
+            .collect();
+
+        let functions = instance_functions(vmctx);
+        let tables = local_backing
+            .tables
+            .iter()
+            .map(|(_, table)| {
+                table.storage_direct_access_mut(|storage| match storage {
+                    TableStorage::Anyfunc(table) => table
+                        .backing
+                        .iter()
+                        .map(|anyfunc| {
+                            if anyfunc.func.is_null() {
+                                return TableElementImage::Null;
+                            }
+                            functions
+                                .iter()
+                                .find(|&&(_, func, ctx)| func == anyfunc.func && ctx == anyfunc.ctx)
+                                .map_or(TableElementImage::Foreign, |&(index, _, _)| {
+                                    TableElementImage::Function(index)
+                                })
+                        })
+                        .collect(),
+                    TableStorage::AnyRef(table) => table
+                        .backing
+                        .iter()
+                        .map(|r| {
+                            if r.is_null() {
+                                TableElementImage::Null
+                            } else {
+                                TableElementImage::Foreign
+                            }
+                        })
+                        .collect(),
+                })
+            })
+            .collect();
+
+        InstanceImage {
+            version: InstanceImage::VERSION,
+            memory,
+            globals,
+            imported_globals,
+            tables,
+            execution_state,
+        }
+    }
+
+    /// Writes the memory, globals and tables of this image into the instance of `vmctx`.
+    ///
+    /// Memories and tables are grown to the size they have in the image, but cannot shrink.
+    /// On error, the instance may be partially restored.
+    pub(crate) unsafe fn restore(&self, vmctx: &mut Ctx) -> Result<(), RestoreError> {
+        if self.version != InstanceImage::VERSION {
+            return Err(RestoreError::IncompatibleVersion(self.version));
+        }
+        let info = &(*vmctx.module).info;
+
+        let has_memory = info.memories.len() + info.imported_memories.len() != 0;
+        match (&self.memory, has_memory) {
+            (Some(image), true) => {
+                let memory = vmctx.memory(0);
+                let current = memory.size().bytes().0;
+                if image.len() < current || image.len() % WASM_PAGE_SIZE != 0 {
+                    return Err(RestoreError::Mismatch(format!(
+                        "the memory of the image has {} bytes, the instance has {}",
+                        image.len(),
+                        current
+                    )));
+                }
+                memory.grow(Pages(((image.len() - current) / WASM_PAGE_SIZE) as u32))?;
+
+                let local_memory = memory.vm_local_memory();
+                std::slice::from_raw_parts_mut((*local_memory).base, image.len())
+                    .copy_from_slice(image);
+                // Growing may have moved memory 0, whose location is cached in the context.
+                vmctx.internal.memory_base = (*local_memory).base;
+                vmctx.internal.memory_bound = (*local_memory).bound;
+            }
+            (None, false) => {}
+            _ => {
+                return Err(RestoreError::Mismatch(
+                    "the image and the instance disagree on having a memory".to_string(),
+                ))
+            }
+        }
+
+        self.restore_globals_and_tables(vmctx)
+    }
+
+    /// Writes the globals and tables of this image into the instance of `vmctx`, leaving
+    /// its memories alone. Imported globals are only written if they are mutable, and are
+    /// seen by every instance importing them.
+    pub(crate) unsafe fn restore_globals_and_tables(
+        &self,
+        vmctx: &mut Ctx,
+    ) -> Result<(), RestoreError> {
+        let info = &(*vmctx.module).info;
+        let local_backing = &mut *vmctx.local_backing;
+        if self.globals.len() != local_backing.globals.len() {
+            return Err(RestoreError::Mismatch(format!(
+                "the image has {} globals, the instance has {}",
+                self.globals.len(),
+                local_backing.globals.len()
+            )));
+        }
+        for (i, &value) in self.globals.iter().enumerate() {
+            let global = &mut local_backing.globals[LocalGlobalIndex::new(i)];
+            restore_global(global, value, || format!("global {}", i))?;
+        }
+
+        let import_backing = &mut *vmctx.import_backing;
+        if self.imported_globals.len() != import_backing.globals.len() {
+            return Err(RestoreError::Mismatch(format!(
+                "the image has {} imported globals, the instance has {}",
+                self.imported_globals.len(),
+                import_backing.globals.len()
+            )));
+        }
+        for (i, &value) in self.imported_globals.iter().enumerate() {
+            let global = &mut import_backing.globals[ImportedGlobalIndex::new(i)];
+            if global.descriptor().mutable {
+                restore_global(global, value, || format!("imported global {}", i))?;
+            }
+        }
+
+        if self.tables.len() != local_backing.tables.len() {
+            return Err(RestoreError::Mismatch(format!(
+                "the image has {} tables, the instance has {}",
+                self.tables.len(),
+                local_backing.tables.len()
+            )));
+        }
+        let functions = instance_functions(vmctx);
+        for ((_, table), elements) in local_backing.tables.iter().zip(&self.tables) {
+            let current = table.size() as usize;
+            if elements.len() < current {
+                return Err(RestoreError::Mismatch(format!(
+                    "a table of the image has {} elements, the instance has {}",
+                    elements.len(),
+                    current
+                )));
+            }
+            table.grow((elements.len() - current) as u32)?;
+
+            table.storage_direct_access_mut(|storage| {
+                for (i, element) in elements.iter().enumerate() {
+                    match (&mut *storage, *element) {
+                        (_, TableElementImage::Foreign) => {
+                            return Err(RestoreError::Unrestorable(format!(
+                                "table element {} is not a function of the instance",
+                                i
+                            )));
+                        }
+                        (TableStorage::Anyfunc(table), TableElementImage::Null) => {
+                            table.backing[i] = vm::Anyfunc::null();
+                        }
+                        (TableStorage::Anyfunc(table), TableElementImage::Function(index)) => {
+                            let &(_, func, ctx) = functions
+                                .iter()
+                                .find(|&&(function, _, _)| function == index)
+                                .ok_or_else(|| {
+                                    RestoreError::Mismatch(format!(
+                                        "the instance has no function {}",
+                                        index.index()
+                                    ))
+                                })?;
+                            let sig_index = info.func_assoc[index];
+                            let signature =
+                                SigRegistry.lookup_signature_ref(&info.signatures[sig_index]);
+                            let sig_id =
+                                vm::SigId(SigRegistry.lookup_sig_index(signature).index() as u32);
+                            table.backing[i] = vm::Anyfunc { func, ctx, sig_id };
+                        }
+                        (TableStorage::AnyRef(table), TableElementImage::Null) => {
+                            table.backing[i] = AnyRef::null();
+                        }
+                        (TableStorage::AnyRef(_), TableElementImage::Function(_)) => {
+                            return Err(RestoreError::Mismatch(
+                                "an anyref table of the image holds a function".to_string(),
+                            ));
+                        }
+                    }
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes `value`, as captured into an image, into `global`. References cannot be restored,
+/// so only null ones are accepted; `name` describes the global in the error.
+unsafe fn restore_global(
+    global: &mut Global,
+    value: u128,
+    name: impl Fn() -> String,
+) -> Result<(), RestoreError> {
+    match global.descriptor().ty {
+        Type::AnyRef | Type::FuncRef if value != 0 => {
+            return Err(RestoreError::Unrestorable(format!(
+                "{} holds a reference",
+                name()
+            )));
+        }
+        Type::AnyRef | Type::FuncRef => global.set_ref(AnyRef::null()),
+        _ => (*global.vm_local_global()).data = value,
+    }
+    Ok(())
+}
+
+/// The functions of the instance of `vmctx`, with the function pointer and context they have
+/// as table elements.
+unsafe fn instance_functions(vmctx: &Ctx) -> Vec<(FuncIndex, *const vm::Func, *mut Ctx)> {
+    let info = &(*vmctx.module).info;
+    let imported = (*vmctx.import_backing)
+        .vm_functions
+        .iter()
+        .map(|(index, imported)| {
+            (
+                index.convert_up(info),
+                imported.func,
+                imported.func_ctx.as_ref().vmctx.as_ptr(),
+            )
+        });
+    let local = (*vmctx.local_backing)
+        .local_functions
+        .iter()
+        .map(|(index, &func)| {
+            (
+                index.convert_up(info),
+                func,
+                vmctx as *const Ctx as *mut Ctx,
+            )
+        });
+    imported.chain(local).collect()
 }
 
 /// Declarations for x86-64 registers.
@@ -622,9 +962,6 @@ pub mod x64 {
     use crate::fault::{
         catch_unsafe_unwind, get_boundary_register_preservation, run_on_alternative_stack,
     };
-    use crate::structures::TypedIndex;
-    use crate::types::LocalGlobalIndex;
-    use crate::vm::Ctx;
     use std::any::Any;
 
     unsafe fn compute_vmctx_deref(vmctx: *const Ctx, seq: &[usize]) -> u64 {
@@ -986,6 +1323,12 @@ pub mod x64 {
             (*(*vmctx.local_backing).globals[LocalGlobalIndex::new(i)].vm_local_global()).data =
                 image.globals[i];
         }
+        for (i, &value) in image.imported_globals.iter().enumerate() {
+            let global = &mut (*vmctx.import_backing).globals[ImportedGlobalIndex::new(i)];
+            if global.descriptor().mutable {
+                (*global.vm_local_global()).data = value;
+            }
+        }
 
         drop(image); // free up host memory
 
@@ -1005,35 +1348,7 @@ pub mod x64 {
         vmctx: &mut Ctx,
         execution_state: ExecutionStateImage,
     ) -> InstanceImage {
-        unsafe {
-            let memory = if vmctx.internal.memory_base.is_null() {
-                None
-            } else {
-                Some(
-                    std::slice::from_raw_parts(
-                        vmctx.internal.memory_base,
-                        vmctx.internal.memory_bound,
-                    )
-                    .to_vec(),
-                )
-            };
-
-            // FIXME: Imported globals
-            let globals_len = (*vmctx.module).info.globals.len();
-            let globals: Vec<u128> = (0..globals_len)
-                .map(|i| {
-                    (*vmctx.local_backing).globals[LocalGlobalIndex::new(i)]
-                        .get()
-                        .to_u128()
-                })
-                .collect();
-
-            InstanceImage {
-                memory: memory,
-                globals: globals,
-                execution_state: execution_state,
-            }
-        }
+        unsafe { InstanceImage::capture(vmctx, execution_state) }
     }
 
     /// Returns a `ExecutionStateImage` for the given versions, stack, initial registers and
@@ -1251,6 +1566,10 @@ pub mod x64 {
             let wfs = WasmFunctionStateDump {
                 local_function_id: fsm.local_function_id,
                 wasm_inst_offset: state.wasm_inst_offset,
+                binary_offset: fsm
+                    .wasm_offset_to_binary_offset
+                    .get(&state.wasm_inst_offset)
+                    .cloned(),
                 stack: wasm_stack,
                 locals: wasm_locals,
             };
@@ -131,6 +131,37 @@ impl Memory {
         }
     }
 
+    /// Create a new inaccessible memory of the given size whose first bytes are mapped
+    /// copy-on-write from `image`. Writes to the memory never reach the image.
+    #[cfg(target_os = "linux")]
+    pub fn with_image(size: usize, image: &MemoryImage) -> Result<Self, MemoryCreationError> {
+        let memory = Memory::with_size(size)?;
+        let mapped = image.size.min(memory.size);
+        if mapped == 0 {
+            return Ok(memory);
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                memory.ptr as _,
+                mapped,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_FIXED,
+                image.fd.0,
+                0,
+            )
+        };
+
+        if ptr == -1 as _ {
+            Err(MemoryCreationError::VirtualMemoryAllocationFailed(
+                size,
+                errno::errno().to_string(),
+            ))
+        } else {
+            Ok(memory)
+        }
+    }
+
     /// Protect this memory with the given range bounds and protection.
     pub unsafe fn protect(
         &mut self,
@@ -293,6 +324,39 @@ impl Protect {
     }
 }
 
+/// Contents of a memory held in an anonymous file, from which memories are mapped
+/// copy-on-write with `Memory::with_image`.
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct MemoryImage {
+    fd: RawFd,
+    size: usize,
+}
+
+#[cfg(target_os = "linux")]
+impl MemoryImage {
+    /// Create an image holding `contents`, padded with zeroes to a whole number of pages.
+    pub fn new(contents: &[u8]) -> Result<Self, MemoryCreationError> {
+        use nix::sys::memfd::{memfd_create, MemFdCreateFlag};
+        use std::{ffi::CString, io::Write, os::unix::io::FromRawFd};
+
+        let name = CString::new("wasmer-memory-image").unwrap();
+        let fd = memfd_create(&name, MemFdCreateFlag::MFD_CLOEXEC).map_err(|e| {
+            MemoryCreationError::VirtualMemoryAllocationFailed(contents.len(), e.to_string())
+        })?;
+        let mut file = unsafe { File::from_raw_fd(fd) };
+
+        let size = round_up_to_page_size(contents.len(), page_size::get());
+        file.write_all(contents)?;
+        file.set_len(size as u64)?;
+
+        Ok(MemoryImage {
+            fd: RawFd::from_file(file),
+            size,
+        })
+    }
+}
+
 #[derive(Debug)]
 struct RawFd(i32);
 
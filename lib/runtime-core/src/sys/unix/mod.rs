@@ -1,3 +1,5 @@
 mod memory;
 
+#[cfg(target_os = "linux")]
+pub use self::memory::MemoryImage;
 pub use self::memory::{Memory, Protect};
@@ -1,9 +1,10 @@
 use crate::{
-    error::CreationError,
+    error::{CreationError, GrowError},
     instance::DynFunc,
+    limits::Limiters,
     sig_registry::SigRegistry,
     structures::TypedIndex,
-    types::{FuncSig, TableDescriptor},
+    types::{FuncSig, SigIndex, TableDescriptor},
     vm,
 };
 
@@ -15,6 +16,8 @@ enum AnyfuncInner<'a> {
         signature: Arc<FuncSig>,
     },
     Managed(DynFunc<'a>),
+    /// An element read from a table, put back as it was.
+    Element(vm::Anyfunc),
 }
 
 /// Anyfunc data type.
@@ -37,6 +40,30 @@ impl<'a> Anyfunc<'a> {
     }
 }
 
+impl Anyfunc<'_> {
+    /// Returns true if this is a null function reference, as found in the unset
+    /// elements of a table.
+    pub fn is_null(&self) -> bool {
+        match &self.inner {
+            AnyfuncInner::Element(anyfunc) => anyfunc.func.is_null(),
+            _ => false,
+        }
+    }
+
+    /// The signature of the function, unless it is null.
+    pub fn signature(&self) -> Option<Arc<FuncSig>> {
+        match &self.inner {
+            AnyfuncInner::Host { signature, .. } => Some(Arc::clone(signature)),
+            AnyfuncInner::Managed(func) => Some(Arc::clone(&func.signature)),
+            AnyfuncInner::Element(anyfunc) if anyfunc.func.is_null() => None,
+            AnyfuncInner::Element(anyfunc) => {
+                let sig_index = SigIndex::new(anyfunc.sig_id.0 as usize);
+                Some(SigRegistry.lookup_signature(sig_index))
+            }
+        }
+    }
+}
+
 impl<'a> From<DynFunc<'a>> for Anyfunc<'a> {
     fn from(function: DynFunc<'a>) -> Self {
         Anyfunc {
@@ -48,18 +75,23 @@ impl<'a> From<DynFunc<'a>> for Anyfunc<'a> {
 pub struct AnyfuncTable {
     pub(crate) backing: Vec<vm::Anyfunc>,
     max: Option<u32>,
+    limiters: Limiters,
 }
 
 impl AnyfuncTable {
     pub fn new(
         desc: TableDescriptor,
         local: &mut vm::LocalTable,
+        limiters: Limiters,
     ) -> Result<Box<Self>, CreationError> {
+        limiters.table_creation(&desc)?;
+
         let initial_table_backing_len = desc.minimum as usize;
 
         let mut storage = Box::new(AnyfuncTable {
             backing: vec![vm::Anyfunc::null(); initial_table_backing_len],
             max: desc.maximum,
+            limiters,
         });
 
         let storage_ptr: *mut AnyfuncTable = &mut *storage;
@@ -79,23 +111,34 @@ impl AnyfuncTable {
         &mut self.backing
     }
 
-    pub fn grow(&mut self, delta: u32, local: &mut vm::LocalTable) -> Option<u32> {
+    pub fn grow(&mut self, delta: u32, local: &mut vm::LocalTable) -> Result<u32, GrowError> {
         let starting_len = self.backing.len() as u32;
 
-        let new_len = starting_len.checked_add(delta)?;
+        let new_len = starting_len
+            .checked_add(delta)
+            .ok_or(GrowError::TableGrowError)?;
 
         if let Some(max) = self.max {
             if new_len > max {
-                return None;
+                return Err(GrowError::TableGrowError);
             }
         }
 
+        self.limiters.table_growth(starting_len, delta, self.max)?;
+
         self.backing.resize(new_len as usize, vm::Anyfunc::null());
 
         local.base = self.backing.as_mut_ptr() as *mut u8;
         local.count = self.backing.len();
 
-        Some(starting_len)
+        Ok(starting_len)
+    }
+
+    pub fn get<'a>(&self, index: u32) -> Option<Anyfunc<'a>> {
+        let anyfunc = self.backing.get(index as usize)?;
+        Some(Anyfunc {
+            inner: AnyfuncInner::Element(*anyfunc),
+        })
     }
 
     pub fn set(&mut self, index: u32, element: Anyfunc) -> Result<(), ()> {
@@ -121,6 +164,7 @@ impl AnyfuncTable {
                         sig_id,
                     }
                 }
+                AnyfuncInner::Element(anyfunc) => anyfunc,
             };
 
             *slot = anyfunc;
@@ -131,3 +175,9 @@ impl AnyfuncTable {
         }
     }
 }
+
+impl Drop for AnyfuncTable {
+    fn drop(&mut self) {
+        self.limiters.table_released(self.current_size());
+    }
+}
@@ -0,0 +1,257 @@
+use crate::{
+    error::{CreationError, GrowError},
+    limits::Limiters,
+    types::TableDescriptor,
+    vm,
+};
+use serde::{
+    de::{Deserialize, Deserializer},
+    ser::{Error as SerError, Serialize, Serializer},
+};
+use std::{any::Any, cell::RefCell, collections::HashMap, fmt, mem, sync::Arc};
+
+/// An opaque, reference-counted host reference (`anyref`).
+///
+/// WebAssembly code can store, pass around and compare these handles
+/// but never look inside them. A null `AnyRef` corresponds to `ref.null`.
+#[derive(Clone, Default)]
+pub struct AnyRef(Option<Arc<Box<dyn Any + Send + Sync>>>);
+
+impl AnyRef {
+    /// The null reference.
+    pub fn null() -> Self {
+        AnyRef(None)
+    }
+
+    /// Wrap a host value into a new reference.
+    pub fn new<T: Any + Send + Sync>(value: T) -> Self {
+        AnyRef(Some(Arc::new(Box::new(value))))
+    }
+
+    /// Returns true if this is the null reference.
+    pub fn is_null(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Borrow the host value if it is of type `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.as_ref().and_then(|inner| inner.downcast_ref::<T>())
+    }
+
+    /// Returns true if both references point to the same host value.
+    pub fn ptr_eq(&self, other: &AnyRef) -> bool {
+        self.as_raw() == other.as_raw()
+    }
+
+    /// The raw representation of this reference inside WebAssembly.
+    ///
+    /// This does not transfer ownership; the caller must keep the reference
+    /// alive for as long as the raw value can be observed.
+    pub(crate) fn as_raw(&self) -> u64 {
+        match self.0 {
+            Some(ref inner) => &**inner as *const Box<dyn Any + Send + Sync> as usize as u64,
+            None => 0,
+        }
+    }
+
+    /// Recover a new handle from a raw value produced by `as_raw`.
+    ///
+    /// The referenced value must still be alive.
+    pub(crate) unsafe fn from_raw(raw: u64) -> Self {
+        if raw == 0 {
+            return AnyRef(None);
+        }
+        let inner = Arc::from_raw(raw as usize as *const Box<dyn Any + Send + Sync>);
+        let cloned = Arc::clone(&inner);
+        mem::forget(inner);
+        AnyRef(Some(cloned))
+    }
+}
+
+impl PartialEq for AnyRef {
+    fn eq(&self, other: &AnyRef) -> bool {
+        self.ptr_eq(other)
+    }
+}
+
+impl fmt::Debug for AnyRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_null() {
+            write!(f, "AnyRef(null)")
+        } else {
+            write!(f, "AnyRef({:#x})", self.as_raw())
+        }
+    }
+}
+
+/// Only the null reference can be serialized, since it is the only one
+/// that can appear in a constant initializer.
+impl Serialize for AnyRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_null() {
+            serializer.serialize_unit()
+        } else {
+            Err(S::Error::custom("host references cannot be serialized"))
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AnyRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <()>::deserialize(deserializer)?;
+        Ok(AnyRef::null())
+    }
+}
+
+/// The references WebAssembly obtained on one stack.
+///
+/// Wasm locals, operand stacks and return values are not traced, so every raw
+/// reference handed to WebAssembly stays rooted here until the outermost call
+/// on the stack returns. Each fiber has a set of its own.
+#[derive(Default)]
+pub(crate) struct RefRoots {
+    depth: usize,
+    roots: HashMap<u64, AnyRef>,
+}
+
+thread_local! {
+    static REF_ROOTS: RefCell<RefRoots> = RefCell::new(RefRoots::default());
+}
+
+/// Keep `r` alive until the outermost call on the current stack returns, and
+/// return its raw value.
+pub(crate) fn root(r: &AnyRef) -> u64 {
+    let raw = r.as_raw();
+    if raw != 0 {
+        REF_ROOTS.with(|x| {
+            x.borrow_mut()
+                .roots
+                .entry(raw)
+                .or_insert_with(|| r.clone());
+        });
+    }
+    raw
+}
+
+/// Swap the roots of the current stack with `roots`.
+pub(crate) fn swap_ref_roots(roots: &mut RefRoots) {
+    REF_ROOTS.with(|x| mem::swap(&mut *x.borrow_mut(), roots));
+}
+
+/// Marks a call from the host into WebAssembly.
+///
+/// The references rooted on the stack are released when the outermost call ends.
+pub(crate) struct CallScope(());
+
+impl CallScope {
+    pub(crate) fn enter() -> Self {
+        REF_ROOTS.with(|x| x.borrow_mut().depth += 1);
+        CallScope(())
+    }
+}
+
+impl Drop for CallScope {
+    fn drop(&mut self) {
+        let released = REF_ROOTS.with(|x| {
+            let mut x = x.borrow_mut();
+            x.depth -= 1;
+            if x.depth == 0 {
+                mem::replace(&mut x.roots, HashMap::new())
+            } else {
+                HashMap::new()
+            }
+        });
+        // Host values may run arbitrary code when dropped, so release them
+        // outside of the borrow.
+        drop(released);
+    }
+}
+
+/// The host value behind a non-null `funcref` that has left its table.
+#[derive(Copy, Clone)]
+pub(crate) struct FuncRef(pub vm::Anyfunc);
+
+unsafe impl Send for FuncRef {}
+unsafe impl Sync for FuncRef {}
+
+pub struct AnyRefTable {
+    pub(crate) backing: Vec<AnyRef>,
+    max: Option<u32>,
+    limiters: Limiters,
+}
+
+impl AnyRefTable {
+    pub fn new(
+        desc: TableDescriptor,
+        local: &mut vm::LocalTable,
+        limiters: Limiters,
+    ) -> Result<Box<Self>, CreationError> {
+        limiters.table_creation(&desc)?;
+
+        let mut storage = Box::new(AnyRefTable {
+            backing: vec![AnyRef::null(); desc.minimum as usize],
+            max: desc.maximum,
+            limiters,
+        });
+
+        let storage_ptr: *mut AnyRefTable = &mut *storage;
+
+        local.base = storage.backing.as_mut_ptr() as *mut u8;
+        local.count = storage.backing.len();
+        local.table = storage_ptr as *mut ();
+
+        Ok(storage)
+    }
+
+    pub fn current_size(&self) -> u32 {
+        self.backing.len() as u32
+    }
+
+    pub fn grow(
+        &mut self,
+        delta: u32,
+        init: AnyRef,
+        local: &mut vm::LocalTable,
+    ) -> Result<u32, GrowError> {
+        let starting_len = self.backing.len() as u32;
+
+        let new_len = starting_len
+            .checked_add(delta)
+            .ok_or(GrowError::TableGrowError)?;
+
+        if let Some(max) = self.max {
+            if new_len > max {
+                return Err(GrowError::TableGrowError);
+            }
+        }
+
+        self.limiters.table_growth(starting_len, delta, self.max)?;
+
+        self.backing.resize(new_len as usize, init);
+
+        local.base = self.backing.as_mut_ptr() as *mut u8;
+        local.count = self.backing.len();
+
+        Ok(starting_len)
+    }
+
+    pub fn get(&self, index: u32) -> Option<AnyRef> {
+        self.backing.get(index as usize).cloned()
+    }
+
+    pub fn set(&mut self, index: u32, element: AnyRef) -> Result<(), ()> {
+        match self.backing.get_mut(index as usize) {
+            Some(slot) => {
+                *slot = element;
+                Ok(())
+            }
+            None => Err(()),
+        }
+    }
+}
+
+impl Drop for AnyRefTable {
+    fn drop(&mut self) {
+        self.limiters.table_released(self.current_size());
+    }
+}
@@ -4,6 +4,7 @@ use crate::{
     error::CreationError,
     export::Export,
     import::IsExport,
+    limits::Limiters,
     types::{ElementType, TableDescriptor},
     vm,
 };
@@ -13,15 +14,22 @@ use std::{
 };
 
 mod anyfunc;
+mod anyref;
 
 pub use self::anyfunc::Anyfunc;
 pub(crate) use self::anyfunc::AnyfuncTable;
+pub use self::anyref::AnyRef;
+pub(crate) use self::anyref::{
+    root as root_ref, swap_ref_roots, AnyRefTable, CallScope, FuncRef, RefRoots,
+};
 use crate::error::GrowError;
 
 /// Kind of table element.
 pub enum Element<'a> {
     /// Anyfunc.
     Anyfunc(Anyfunc<'a>),
+    /// Opaque host reference.
+    AnyRef(AnyRef),
 }
 
 /// Kind of table storage.
@@ -29,6 +37,8 @@ pub enum Element<'a> {
 pub enum TableStorage {
     /// This is intended to be a caller-checked Anyfunc.
     Anyfunc(Box<AnyfuncTable>),
+    /// Opaque host references.
+    AnyRef(Box<AnyRefTable>),
 }
 
 /// Container with a descriptor and a reference to a table storage.
@@ -60,6 +70,15 @@ impl Table {
     /// # }
     /// ```
     pub fn new(desc: TableDescriptor) -> Result<Self, CreationError> {
+        Table::new_limited(desc, &Limiters::process())
+    }
+
+    /// Create a new `Table`, if `limiters` allow its minimum size. The table is charged
+    /// to `limiters` for as long as it lives.
+    pub(crate) fn new_limited(
+        desc: TableDescriptor,
+        limiters: &Limiters,
+    ) -> Result<Self, CreationError> {
         if let Some(max) = desc.maximum {
             if max < desc.minimum {
                 return Err(CreationError::InvalidDescriptor(
@@ -74,8 +93,14 @@ impl Table {
             table: ptr::null_mut(),
         };
 
+        let limiters = limiters.clone();
         let storage = match desc.element {
-            ElementType::Anyfunc => TableStorage::Anyfunc(AnyfuncTable::new(desc, &mut local)?),
+            ElementType::Anyfunc => {
+                TableStorage::Anyfunc(AnyfuncTable::new(desc, &mut local, limiters)?)
+            }
+            ElementType::AnyRef => {
+                TableStorage::AnyRef(AnyRefTable::new(desc, &mut local, limiters)?)
+            }
         };
 
         Ok(Self {
@@ -89,16 +114,30 @@ impl Table {
         self.desc
     }
 
+    /// Get the element at index, or `None` if it is out of bounds.
+    ///
+    /// A function element refers to the function of an instance without keeping it alive,
+    /// so it only lives as long as the borrow of the table.
+    pub fn get(&self, index: u32) -> Option<Element<'_>> {
+        let storage = self.storage.lock().unwrap();
+        match &storage.0 {
+            TableStorage::Anyfunc(anyfunc_table) => anyfunc_table.get(index).map(Element::Anyfunc),
+            TableStorage::AnyRef(anyref_table) => anyref_table.get(index).map(Element::AnyRef),
+        }
+    }
+
     /// Set the element at index.
     pub fn set(&self, index: u32, element: Element) -> Result<(), ()> {
         let mut storage = self.storage.lock().unwrap();
         match &mut *storage {
-            (TableStorage::Anyfunc(ref mut anyfunc_table), _) => {
-                match element {
-                    Element::Anyfunc(anyfunc) => anyfunc_table.set(index, anyfunc),
-                    // _ => panic!("wrong element type for anyfunc table"),
-                }
-            }
+            (TableStorage::Anyfunc(ref mut anyfunc_table), _) => match element {
+                Element::Anyfunc(anyfunc) => anyfunc_table.set(index, anyfunc),
+                _ => panic!("wrong element type for anyfunc table"),
+            },
+            (TableStorage::AnyRef(ref mut anyref_table), _) => match element {
+                Element::AnyRef(anyref) => anyref_table.set(index, anyref),
+                _ => panic!("wrong element type for anyref table"),
+            },
         }
     }
 
@@ -109,14 +148,24 @@ impl Table {
         let mut storage = self.storage.lock().unwrap();
         match &mut *storage {
             (TableStorage::Anyfunc(ref mut anyfunc_table), _) => f(anyfunc_table.internal_buffer()),
+            (TableStorage::AnyRef(_), _) => panic!("anyref tables cannot hold functions"),
         }
     }
 
+    pub(crate) fn storage_direct_access_mut<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut TableStorage) -> R,
+    {
+        let mut storage = self.storage.lock().unwrap();
+        f(&mut storage.0)
+    }
+
     /// The current size of this table.
     pub fn size(&self) -> u32 {
         let storage = self.storage.lock().unwrap();
         match &*storage {
             (TableStorage::Anyfunc(ref anyfunc_table), _) => anyfunc_table.current_size(),
+            (TableStorage::AnyRef(ref anyref_table), _) => anyref_table.current_size(),
         }
     }
 
@@ -128,9 +177,12 @@ impl Table {
 
         let mut storage = self.storage.lock().unwrap();
         match &mut *storage {
-            (TableStorage::Anyfunc(ref mut anyfunc_table), ref mut local) => anyfunc_table
-                .grow(delta, local)
-                .ok_or(GrowError::TableGrowError),
+            (TableStorage::Anyfunc(ref mut anyfunc_table), ref mut local) => {
+                anyfunc_table.grow(delta, local)
+            }
+            (TableStorage::AnyRef(ref mut anyref_table), ref mut local) => {
+                anyref_table.grow(delta, AnyRef::null(), local)
+            }
         }
     }
 
@@ -180,4 +232,17 @@ mod table_tests {
         .unwrap();
         assert_eq!(table.size(), 10);
     }
+
+    #[test]
+    fn test_anyref_table_grow() {
+        let table = Table::new(TableDescriptor {
+            element: ElementType::AnyRef,
+            minimum: 1,
+            maximum: Some(2),
+        })
+        .unwrap();
+        assert_eq!(table.grow(1).unwrap(), 1);
+        assert_eq!(table.size(), 2);
+        assert!(table.grow(1).is_err());
+    }
 }
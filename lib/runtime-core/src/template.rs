@@ -0,0 +1,261 @@
+//! Pre-initialized instances from which new instances are stamped out quickly.
+//!
+//! Instantiating a module copies every data segment into a fresh memory and runs the start
+//! function. An [`InstanceTemplate`] does this once, optionally runs an initialization export
+//! on top, and freezes the resulting memory, globals and tables. Instances created from the
+//! template start from that state without copying it: on Linux the memory of the template is
+//! kept in a memfd and mapped copy-on-write into every new instance, so pages are only copied
+//! when an instance writes to them. An instance whose imports set a [`MemoryCreator`] gets
+//! its memory from that creator instead, with the contents of the template copied in.
+//!
+//! ```
+//! # use wasmer_runtime_core::{error::Result, imports, module::Module};
+//! # use wasmer_runtime_core::template::{InstanceTemplate, TemplateConfig};
+//! # fn serve(module: &Module) -> Result<()> {
+//! let config = TemplateConfig {
+//!     run_start: true,
+//!     init: Some("initialize".to_string()),
+//! };
+//! let template = InstanceTemplate::new(module, &imports! {}, config)?;
+//! let instance = template.instantiate(&imports! {})?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`InstanceTemplate`]: struct.InstanceTemplate.html
+//! [`MemoryCreator`]: ../memory/trait.MemoryCreator.html
+
+use crate::{
+    error::{CreationError, MemoryCreationError, Result},
+    import::ImportObject,
+    instance::Instance,
+    limits::Limiters,
+    memory::{Memory, MemoryCreator, MemoryRegion},
+    module::{Module, ModuleInner},
+    state::InstanceImage,
+    structures::{Map, TypedIndex},
+    sys,
+    types::{LocalMemoryIndex, MemoryDescriptor},
+    units::{Pages, WASM_PAGE_SIZE},
+};
+use std::{
+    slice,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// How an `InstanceTemplate` initializes the instance it freezes.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateConfig {
+    /// Run the start function when creating the template. Instances created from the
+    /// template then do not run it; otherwise each of them runs it when it is created.
+    pub run_start: bool,
+    /// The name of an exported function without parameters to call when creating the
+    /// template, after the start function if it runs.
+    pub init: Option<String>,
+}
+
+/// A frozen, initialized instance of a module from which new instances are created.
+pub struct InstanceTemplate {
+    module: Arc<ModuleInner>,
+    /// The globals and tables of the template. Its memory is held by `memory`.
+    image: InstanceImage,
+    /// The descriptor of the local memory, grown to the size of the template, and the
+    /// contents of the memory.
+    memory: Option<(MemoryDescriptor, Arc<TemplateImage>)>,
+    ran_start: bool,
+}
+
+impl InstanceTemplate {
+    /// Instantiates `module` with `imports`, initializes the instance as `config` says and
+    /// freezes it as a template.
+    ///
+    /// Fails if a table of the initialized instance holds a function of another instance
+    /// or a host reference, since it could not be copied into new instances.
+    pub fn new(
+        module: &Module,
+        imports: &ImportObject,
+        config: TemplateConfig,
+    ) -> Result<InstanceTemplate> {
+        let mut instance = Instance::new(Arc::clone(&module.inner), imports, None, false)?;
+        if config.run_start {
+            instance.call_start_func()?;
+        }
+        if let Some(init) = &config.init {
+            instance.call(init, &[])?;
+        }
+
+        let mut image = instance.snapshot();
+        // Check now that the globals and tables can be copied, instead of at every
+        // instantiation. Writing them back into the instance does not change it.
+        unsafe { image.restore_globals_and_tables(instance.context_mut())? };
+
+        let info = &module.inner.info;
+        // An imported memory belongs to the importer, so only a local one is captured.
+        let memory = match image.memory.take() {
+            Some(contents) if !info.memories.is_empty() => {
+                let desc = MemoryDescriptor {
+                    minimum: Pages((contents.len() / WASM_PAGE_SIZE) as u32),
+                    ..info.memories[LocalMemoryIndex::new(0)]
+                };
+                let image = TemplateImage::new(contents)
+                    .map_err(|_| CreationError::UnableToCreateMemory)?;
+                Some((desc, Arc::new(image)))
+            }
+            _ => None,
+        };
+
+        Ok(InstanceTemplate {
+            module: Arc::clone(&module.inner),
+            image,
+            memory,
+            ran_start: config.run_start,
+        })
+    }
+
+    /// Creates an instance in the state of the template.
+    ///
+    /// `imports` should provide the same imports as those the template was created with:
+    /// the state of the template may refer to them, e.g. through imported functions stored
+    /// in tables. Data segments are not copied again, except into imported memories.
+    pub fn instantiate(&self, imports: &ImportObject) -> Result<Instance> {
+        let limiters = Limiters::new(imports.resource_limiter.as_ref());
+        let mut memories = Map::new();
+        if let Some((desc, image)) = &self.memory {
+            let memory = match &imports.memory_creator {
+                Some(creator) => {
+                    let memory = Memory::new_limited(*desc, &limiters, Arc::clone(creator))?;
+                    unsafe {
+                        let local = &*memory.vm_local_memory();
+                        image
+                            .copy_to(slice::from_raw_parts_mut(local.base, local.bound))
+                            .map_err(|_| CreationError::UnableToCreateMemory)?;
+                    }
+                    memory
+                }
+                None => {
+                    let creator = Arc::new(TemplateMemoryCreator {
+                        image: Arc::clone(image),
+                        mapped: AtomicBool::new(false),
+                    });
+                    Memory::new_limited(*desc, &limiters, creator)?
+                }
+            };
+            memories.push(memory);
+        }
+        let memories = memories.into_boxed_map();
+
+        let mut instance =
+            Instance::new(Arc::clone(&self.module), imports, Some(&memories), false)?;
+        let vmctx = instance.context_mut();
+        unsafe { self.image.restore_globals_and_tables(vmctx)? };
+        if !self.ran_start {
+            instance.call_start_func()?;
+        }
+        Ok(instance)
+    }
+}
+
+/// The contents of the memory of a template.
+struct TemplateImage {
+    #[cfg(target_os = "linux")]
+    image: sys::MemoryImage,
+    #[cfg(target_os = "linux")]
+    len: usize,
+    #[cfg(not(target_os = "linux"))]
+    contents: Vec<u8>,
+}
+
+impl TemplateImage {
+    #[cfg(target_os = "linux")]
+    fn new(contents: Vec<u8>) -> std::result::Result<Self, MemoryCreationError> {
+        Ok(TemplateImage {
+            image: sys::MemoryImage::new(&contents)?,
+            len: contents.len(),
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn new(contents: Vec<u8>) -> std::result::Result<Self, MemoryCreationError> {
+        Ok(TemplateImage { contents })
+    }
+
+    /// Reserves a region of `size` bytes whose first bytes hold the contents.
+    #[cfg(target_os = "linux")]
+    fn map(&self, size: usize) -> std::result::Result<sys::Memory, MemoryCreationError> {
+        sys::Memory::with_image(size, &self.image)
+    }
+
+    /// Without memfds, the contents are copied into a fresh region.
+    #[cfg(not(target_os = "linux"))]
+    fn map(&self, size: usize) -> std::result::Result<sys::Memory, MemoryCreationError> {
+        let mut memory = sys::Memory::with_size(size)?;
+        let len = self.contents.len().min(memory.size());
+        if len != 0 {
+            let protect_error = |e: crate::error::MemoryProtectionError| {
+                MemoryCreationError::VirtualMemoryAllocationFailed(size, e.to_string())
+            };
+            unsafe {
+                memory
+                    .protect(0..len, sys::Protect::ReadWrite)
+                    .map_err(protect_error)?;
+                memory.as_slice_mut()[..len].copy_from_slice(&self.contents[..len]);
+                memory
+                    .protect(0..len, sys::Protect::None)
+                    .map_err(protect_error)?;
+            }
+        }
+        Ok(memory)
+    }
+
+    /// Copies the contents into the start of `dst`, which must be large enough.
+    #[cfg(target_os = "linux")]
+    fn copy_to(&self, dst: &mut [u8]) -> std::result::Result<(), MemoryCreationError> {
+        if self.len == 0 {
+            return Ok(());
+        }
+        let mut memory = self.map(self.len)?;
+        unsafe {
+            memory
+                .protect(0..self.len, sys::Protect::Read)
+                .map_err(|e| {
+                    MemoryCreationError::VirtualMemoryAllocationFailed(self.len, e.to_string())
+                })?;
+            dst[..self.len].copy_from_slice(&memory.as_slice()[..self.len]);
+        }
+        Ok(())
+    }
+
+    /// Copies the contents into the start of `dst`, which must be large enough.
+    #[cfg(not(target_os = "linux"))]
+    fn copy_to(&self, dst: &mut [u8]) -> std::result::Result<(), MemoryCreationError> {
+        dst[..self.contents.len()].copy_from_slice(&self.contents);
+        Ok(())
+    }
+}
+
+/// Creates the memory of one instance of a template.
+///
+/// The first region it reserves holds the contents of the template. Later ones, reserved
+/// to grow a dynamic memory, read as zeroes like those of other creators: the memory copies
+/// its contents into them itself.
+struct TemplateMemoryCreator {
+    image: Arc<TemplateImage>,
+    mapped: AtomicBool,
+}
+
+impl MemoryCreator for TemplateMemoryCreator {
+    fn reserve(
+        &self,
+        _desc: &MemoryDescriptor,
+        size: usize,
+    ) -> std::result::Result<Box<dyn MemoryRegion>, MemoryCreationError> {
+        if self.mapped.swap(true, Ordering::SeqCst) {
+            Ok(Box::new(sys::Memory::with_size(size)?))
+        } else {
+            Ok(Box::new(self.image.map(size)?))
+        }
+    }
+}
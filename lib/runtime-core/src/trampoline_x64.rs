@@ -7,6 +7,7 @@
 //! Variadic functions are not supported because `rax` is used by the trampoline code.
 
 use crate::loader::CodeMemory;
+use crate::types::Type;
 use crate::vm::Ctx;
 use std::fmt;
 use std::{mem, slice};
@@ -144,32 +145,52 @@ impl TrampolineBufferBuilder {
 
     /// Adds a callinfo trampoline.
     ///
-    /// This generates a trampoline function that collects `num_params` parameters into an array
-    /// and passes the array into `target` as the second argument when called. The first argument
-    /// of `target` is the `context` specified here.
+    /// This generates a trampoline function that collects parameters of the types in `params`
+    /// into an array of 64-bit slots and passes the array into `target` as the second argument
+    /// when called. The first argument of `target` is the `context` specified here.
     ///
-    /// Note that non-integer parameters/variadic functions are not supported.
+    /// Integer parameters are read from the general purpose registers and floating point
+    /// parameters from `xmm0`-`xmm7`, following the System V calling convention; a `f32` takes
+    /// the low 32 bits of its slot. The value returned by `target` is placed in both `rax` and
+    /// `xmm0`, so it can be an integer or the bits of a float.
+    ///
+    /// Note that `v128` parameters/variadic functions are not supported.
     pub fn add_callinfo_trampoline(
         &mut self,
         target: unsafe extern "C" fn(*const CallContext, *const u64) -> u64,
         context: *const CallContext,
-        num_params: u32,
+        params: &[Type],
     ) -> usize {
         let idx = self.offsets.len();
         self.offsets.push(self.code.len());
 
-        let mut stack_offset: u32 = num_params.checked_mul(8).unwrap();
+        let mut stack_offset: u32 = (params.len() as u32).checked_mul(8).unwrap();
         if stack_offset % 16 == 0 {
             stack_offset += 8;
         }
 
         self.code.extend_from_slice(&[0x48, 0x81, 0xec]); // sub ?, %rsp
         self.code.extend_from_slice(value_to_bytes(&stack_offset));
-        for i in 0..num_params {
-            match i {
-                0..=5 => {
+
+        let mut gprs = 0u32;
+        let mut xmms = 0u32;
+        // The number of parameters passed on the stack of the caller so far.
+        let mut stack_params = 0u32;
+        for (i, ty) in params.iter().enumerate() {
+            let i = i as u32;
+            match ty {
+                Type::V128 => panic!("v128 parameters are not supported"),
+                Type::F32 | Type::F64 if xmms < 8 => {
+                    // movsd %xmm?, ?(%rsp)
+                    let modrm = 0x84 | ((xmms as u8) << 3);
+                    self.code
+                        .extend_from_slice(&[0xf2, 0x0f, 0x11, modrm, 0x24]);
+                    self.code.extend_from_slice(value_to_bytes(&(i * 8u32)));
+                    xmms += 1;
+                }
+                Type::I32 | Type::I64 | Type::AnyRef | Type::FuncRef if gprs < 6 => {
                     // mov %?, ?(%rsp)
-                    let prefix: &[u8] = match i {
+                    let prefix: &[u8] = match gprs {
                         0 => &[0x48, 0x89, 0xbc, 0x24], // rdi
                         1 => &[0x48, 0x89, 0xb4, 0x24], // rsi
                         2 => &[0x48, 0x89, 0x94, 0x24], // rdx
@@ -180,17 +201,19 @@ impl TrampolineBufferBuilder {
                     };
                     self.code.extend_from_slice(prefix);
                     self.code.extend_from_slice(value_to_bytes(&(i * 8u32)));
+                    gprs += 1;
                 }
                 _ => {
                     self.code.extend_from_slice(&[
                         0x48, 0x8b, 0x84, 0x24, // mov ?(%rsp), %rax
                     ]);
                     self.code.extend_from_slice(value_to_bytes(
-                        &((i - 6) * 8u32 + stack_offset + 8/* ret addr */),
+                        &(stack_params * 8u32 + stack_offset + 8/* ret addr */),
                     ));
                     // mov %rax, ?(%rsp)
                     self.code.extend_from_slice(&[0x48, 0x89, 0x84, 0x24]);
                     self.code.extend_from_slice(value_to_bytes(&(i * 8u32)));
+                    stack_params += 1;
                 }
             }
         }
@@ -209,6 +232,9 @@ impl TrampolineBufferBuilder {
         self.code.extend_from_slice(&[
             0xff, 0xd0, // callq *%rax
         ]);
+        self.code.extend_from_slice(&[
+            0x66, 0x48, 0x0f, 0x6e, 0xc0, // movq %rax, %xmm0
+        ]);
         self.code.extend_from_slice(&[
             0x48, 0x81, 0xc4, // add ?, %rsp
         ]);
@@ -281,8 +307,11 @@ mod tests {
         }
         let mut builder = TrampolineBufferBuilder::new();
         let ctx = TestContext { value: 100 };
-        let idx =
-            builder.add_callinfo_trampoline(do_add, &ctx as *const TestContext as *const _, 8);
+        let idx = builder.add_callinfo_trampoline(
+            do_add,
+            &ctx as *const TestContext as *const _,
+            &[Type::I32; 8],
+        );
         let buf = builder.build();
         let t = buf.get_trampoline(idx);
         let ret = unsafe {
@@ -292,4 +321,34 @@ mod tests {
         };
         assert_eq!(ret, 136);
     }
+    #[test]
+    fn test_callinfo_trampoline_floats() {
+        unsafe extern "C" fn do_sum(_ctx: *const CallContext, args: *const u64) -> u64 {
+            let args: &[u64] = slice::from_raw_parts(args, 12);
+            let sum = args[0] as i32 as f64
+                + f32::from_bits(args[1] as u32) as f64
+                + args[2..10]
+                    .iter()
+                    .map(|x| f64::from_bits(*x))
+                    .fold(0.0, |a, b| a + b)
+                + args[10] as i64 as f64
+                + f32::from_bits(args[11] as u32) as f64;
+            sum.to_bits()
+        }
+        let mut builder = TrampolineBufferBuilder::new();
+        // The last two floats are passed on the stack once `xmm0`-`xmm7` are taken.
+        let mut params = vec![Type::I32, Type::F32];
+        params.extend_from_slice(&[Type::F64; 8]);
+        params.extend_from_slice(&[Type::I64, Type::F32]);
+        let idx = builder.add_callinfo_trampoline(do_sum, 0 as *const _, &params);
+        let buf = builder.build();
+        let t = buf.get_trampoline(idx);
+        let ret = unsafe {
+            mem::transmute::<
+                _,
+                extern "C" fn(i32, f32, f64, f64, f64, f64, f64, f64, f64, f64, i64, f32) -> f64,
+            >(t)(1, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11, 12.0)
+        };
+        assert_eq!(ret, 78.0);
+    }
 }
@@ -4,9 +4,19 @@ use crate::{
     error::RuntimeError,
     export::{Context, Export, FuncPointer},
     import::IsExport,
+    table::CallScope,
     types::{FuncSig, NativeWasmType, Type, WasmExternType},
     vm,
 };
+#[cfg(all(unix, target_arch = "x86_64"))]
+use crate::{
+    error::TrapCode,
+    table::{root_ref, AnyRef},
+    trampoline::{CallContext, TrampolineBuffer, TrampolineBufferBuilder},
+    types::Value,
+};
+#[cfg(all(unix, target_arch = "x86_64"))]
+use std::slice;
 use std::{
     any::Any,
     convert::Infallible,
@@ -19,7 +29,11 @@ use std::{
 };
 
 /// Wasm trap info.
+///
+/// Backends report traps with these codes, so the values must be kept in sync with the
+/// LLVM backend's `WasmTrapType`.
 #[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum WasmTrapInfo {
     /// Unreachable trap.
     Unreachable = 0,
@@ -33,10 +47,37 @@ pub enum WasmTrapInfo {
     IllegalArithmetic = 4,
     /// Misaligned atomic access trap.
     MisalignedAtomicAccess = 5,
+    /// Stack overflow trap.
+    StackOverflow = 6,
+    /// Integer division by zero trap.
+    IntegerDivisionByZero = 7,
+    /// Integer overflow trap.
+    IntegerOverflow = 8,
+    /// Invalid float to integer conversion trap.
+    BadConversionToInteger = 9,
     /// Unknown trap.
     Unknown,
 }
 
+impl WasmTrapInfo {
+    /// Decodes a trap code that a backend stored as a byte.
+    pub fn from_u8(code: u8) -> WasmTrapInfo {
+        match code {
+            0 => WasmTrapInfo::Unreachable,
+            1 => WasmTrapInfo::IncorrectCallIndirectSignature,
+            2 => WasmTrapInfo::MemoryOutOfBounds,
+            3 => WasmTrapInfo::CallIndirectOOB,
+            4 => WasmTrapInfo::IllegalArithmetic,
+            5 => WasmTrapInfo::MisalignedAtomicAccess,
+            6 => WasmTrapInfo::StackOverflow,
+            7 => WasmTrapInfo::IntegerDivisionByZero,
+            8 => WasmTrapInfo::IntegerOverflow,
+            9 => WasmTrapInfo::BadConversionToInteger,
+            _ => WasmTrapInfo::Unknown,
+        }
+    }
+}
+
 impl fmt::Display for WasmTrapInfo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -51,6 +92,10 @@ impl fmt::Display for WasmTrapInfo {
                 WasmTrapInfo::CallIndirectOOB => "`call_indirect` out-of-bounds",
                 WasmTrapInfo::IllegalArithmetic => "illegal arithmetic operation",
                 WasmTrapInfo::MisalignedAtomicAccess => "misaligned atomic access",
+                WasmTrapInfo::StackOverflow => "call stack exhausted",
+                WasmTrapInfo::IntegerDivisionByZero => "integer division by zero",
+                WasmTrapInfo::IntegerOverflow => "integer overflow",
+                WasmTrapInfo::BadConversionToInteger => "invalid conversion to integer",
                 WasmTrapInfo::Unknown => "unknown",
             }
         )
@@ -129,6 +174,9 @@ pub trait WasmTypeList {
     /// the WebAssembly function.
     fn empty_ret_array() -> Self::RetArray;
 
+    /// Transforms Rust values into an array of returned values.
+    fn into_ret_array(self) -> Self::RetArray;
+
     /// Transforms C values into Rust values.
     fn from_c_struct(c_struct: Self::CStruct) -> Self;
 
@@ -317,6 +365,10 @@ impl WasmTypeList for Infallible {
         unreachable!()
     }
 
+    fn into_ret_array(self) -> Self::RetArray {
+        unreachable!()
+    }
+
     fn from_c_struct(_: Self::CStruct) -> Self {
         unreachable!()
     }
@@ -370,6 +422,13 @@ macro_rules! impl_traits {
                 [0; count_idents!( $( $x ),* )]
             }
 
+            #[allow(unused_parens, non_snake_case)]
+            fn into_ret_array(self) -> Self::RetArray {
+                let ( $( $x ),* ) = self;
+
+                [ $( WasmExternType::to_native($x).to_binary() ),* ]
+            }
+
             fn from_c_struct(c_struct: Self::CStruct) -> Self {
                 #[allow(non_snake_case)]
                 let $struct_name ( $( $x ),* ) = c_struct;
@@ -403,6 +462,7 @@ macro_rules! impl_traits {
                 let mut rets = Rets::empty_ret_array();
                 let mut trap = WasmTrapInfo::Unknown;
                 let mut user_error = None;
+                let _scope = CallScope::enter();
 
                 if (wasm.invoke)(
                     wasm.trampoline,
@@ -417,9 +477,9 @@ macro_rules! impl_traits {
                     Ok(Rets::from_ret_array(rets))
                 } else {
                     if let Some(data) = user_error {
-                        Err(RuntimeError::Error { data })
+                        Err(RuntimeError::from_user_error(data, &(*(*ctx).module).info))
                     } else {
-                        Err(RuntimeError::Trap { msg: trap.to_string().into() })
+                        Err(RuntimeError::from_trap_info(trap))
                     }
                 }
             }
@@ -434,27 +494,22 @@ macro_rules! impl_traits {
         {
             #[allow(non_snake_case)]
             fn to_raw(self) -> (NonNull<vm::Func>, Option<NonNull<vm::FuncEnv>>) {
-                // The `wrap` function is a wrapper around the
-                // imported function. It manages the argument passed
+                // The `call_imported` function calls the imported
+                // function. It manages the argument passed
                 // to the imported function (in this case, the
                 // `vmctx` along with the regular WebAssembly
                 // arguments), and it manages the trapping.
-                //
-                // It is also required for the LLVM backend to be
-                // able to unwind through this function.
-                #[cfg_attr(nightly, unwind(allowed))]
-                extern fn wrap<$( $x, )* Rets, Trap, FN>(
-                    vmctx: &vm::Ctx $( , $x: <$x as WasmExternType>::Native )*
-                ) -> Rets::CStruct
+                fn call_imported<$( $x, )* Rets, Trap, FN>(
+                    vmctx: &vm::Ctx,
+                    self_pointer: *const vm::Func
+                    $( , $x: <$x as WasmExternType>::Native )*
+                ) -> Rets
                 where
                     $( $x: WasmExternType, )*
                     Rets: WasmTypeList,
                     Trap: TrapEarly<Rets>,
                     FN: Fn(&mut vm::Ctx, $( $x, )*) -> Trap,
                 {
-                    // Get the pointer to this `wrap` function.
-                    let self_pointer = wrap::<$( $x, )* Rets, Trap, FN> as *const vm::Func;
-
                     // Get the collection of imported functions.
                     let vm_imported_functions = unsafe { &(*vmctx.import_backing).vm_functions };
 
@@ -504,7 +559,7 @@ macro_rules! impl_traits {
                             }
                         )
                     ) {
-                        Ok(Ok(returns)) => return returns.into_c_struct(),
+                        Ok(Ok(returns)) => return returns,
                         Ok(Err(err)) => {
                             let b: Box<_> = err.into();
                             b as Box<dyn Any + Send>
@@ -519,6 +574,57 @@ macro_rules! impl_traits {
                     }
                 }
 
+                // The `wrap` function is a wrapper around the
+                // imported function, returning its results the C
+                // way.
+                //
+                // It is also required for the LLVM backend to be
+                // able to unwind through this function.
+                #[cfg_attr(nightly, unwind(allowed))]
+                extern fn wrap<$( $x, )* Rets, Trap, FN>(
+                    vmctx: &vm::Ctx $( , $x: <$x as WasmExternType>::Native )*
+                ) -> Rets::CStruct
+                where
+                    $( $x: WasmExternType, )*
+                    Rets: WasmTypeList,
+                    Trap: TrapEarly<Rets>,
+                    FN: Fn(&mut vm::Ctx, $( $x, )*) -> Trap,
+                {
+                    // Get the pointer to this `wrap` function.
+                    let self_pointer = wrap::<$( $x, )* Rets, Trap, FN> as *const vm::Func;
+
+                    call_imported::<$( $x, )* Rets, Trap, FN>(vmctx, self_pointer $( , $x )*)
+                        .into_c_struct()
+                }
+
+                // The `wrap_multi_value` function is used instead
+                // of `wrap` when the imported function has several
+                // results. They are written into the return area
+                // passed as the last argument, see
+                // `FuncSig::has_return_area`.
+                #[cfg_attr(nightly, unwind(allowed))]
+                extern fn wrap_multi_value<$( $x, )* Rets, Trap, FN>(
+                    vmctx: &vm::Ctx $( , $x: <$x as WasmExternType>::Native )*,
+                    rets: *mut u64
+                )
+                where
+                    $( $x: WasmExternType, )*
+                    Rets: WasmTypeList,
+                    Trap: TrapEarly<Rets>,
+                    FN: Fn(&mut vm::Ctx, $( $x, )*) -> Trap,
+                {
+                    // Get the pointer to this `wrap_multi_value` function.
+                    let self_pointer =
+                        wrap_multi_value::<$( $x, )* Rets, Trap, FN> as *const vm::Func;
+
+                    let mut returns =
+                        call_imported::<$( $x, )* Rets, Trap, FN>(vmctx, self_pointer $( , $x )*)
+                            .into_ret_array();
+                    let returns = returns.as_mut();
+
+                    unsafe { ptr::copy_nonoverlapping(returns.as_ptr(), rets, returns.len()) };
+                }
+
                 // Extract the captured environment of the imported
                 // function if any.
                 let func_env: Option<NonNull<vm::FuncEnv>> =
@@ -533,10 +639,13 @@ macro_rules! impl_traits {
                         NonNull::new(Box::into_raw(Box::new(self))).map(NonNull::cast)
                     };
 
-                (
-                    NonNull::new(wrap::<$( $x, )* Rets, Trap, Self> as *mut vm::Func).unwrap(),
-                    func_env
-                )
+                let func = if Rets::types().len() > 1 {
+                    wrap_multi_value::<$( $x, )* Rets, Trap, Self> as *mut vm::Func
+                } else {
+                    wrap::<$( $x, )* Rets, Trap, Self> as *mut vm::Func
+                };
+
+                (NonNull::new(func).unwrap(), func_env)
             }
         }
 
@@ -549,27 +658,22 @@ macro_rules! impl_traits {
         {
             #[allow(non_snake_case)]
             fn to_raw(self) -> (NonNull<vm::Func>, Option<NonNull<vm::FuncEnv>>) {
-                // The `wrap` function is a wrapper around the
-                // imported function. It manages the argument passed
+                // The `call_imported` function calls the imported
+                // function. It manages the argument passed
                 // to the imported function (in this case, only the
                 // regular WebAssembly arguments), and it manages the
                 // trapping.
-                //
-                // It is also required for the LLVM backend to be
-                // able to unwind through this function.
-                #[cfg_attr(nightly, unwind(allowed))]
-                extern fn wrap<$( $x, )* Rets, Trap, FN>(
-                    vmctx: &vm::Ctx $( , $x: <$x as WasmExternType>::Native )*
-                ) -> Rets::CStruct
+                fn call_imported<$( $x, )* Rets, Trap, FN>(
+                    vmctx: &vm::Ctx,
+                    self_pointer: *const vm::Func
+                    $( , $x: <$x as WasmExternType>::Native )*
+                ) -> Rets
                 where
                     $( $x: WasmExternType, )*
                     Rets: WasmTypeList,
                     Trap: TrapEarly<Rets>,
                     FN: Fn($( $x, )*) -> Trap,
                 {
-                    // Get the pointer to this `wrap` function.
-                    let self_pointer = wrap::<$( $x, )* Rets, Trap, FN> as *const vm::Func;
-
                     // Get the collection of imported functions.
                     let vm_imported_functions = unsafe { &(*vmctx.import_backing).vm_functions };
 
@@ -616,7 +720,7 @@ macro_rules! impl_traits {
                             }
                         )
                     ) {
-                        Ok(Ok(returns)) => return returns.into_c_struct(),
+                        Ok(Ok(returns)) => return returns,
                         Ok(Err(err)) => {
                             let b: Box<_> = err.into();
                             b as Box<dyn Any + Send>
@@ -631,6 +735,57 @@ macro_rules! impl_traits {
                     }
                 }
 
+                // The `wrap` function is a wrapper around the
+                // imported function, returning its results the C
+                // way.
+                //
+                // It is also required for the LLVM backend to be
+                // able to unwind through this function.
+                #[cfg_attr(nightly, unwind(allowed))]
+                extern fn wrap<$( $x, )* Rets, Trap, FN>(
+                    vmctx: &vm::Ctx $( , $x: <$x as WasmExternType>::Native )*
+                ) -> Rets::CStruct
+                where
+                    $( $x: WasmExternType, )*
+                    Rets: WasmTypeList,
+                    Trap: TrapEarly<Rets>,
+                    FN: Fn($( $x, )*) -> Trap,
+                {
+                    // Get the pointer to this `wrap` function.
+                    let self_pointer = wrap::<$( $x, )* Rets, Trap, FN> as *const vm::Func;
+
+                    call_imported::<$( $x, )* Rets, Trap, FN>(vmctx, self_pointer $( , $x )*)
+                        .into_c_struct()
+                }
+
+                // The `wrap_multi_value` function is used instead
+                // of `wrap` when the imported function has several
+                // results. They are written into the return area
+                // passed as the last argument, see
+                // `FuncSig::has_return_area`.
+                #[cfg_attr(nightly, unwind(allowed))]
+                extern fn wrap_multi_value<$( $x, )* Rets, Trap, FN>(
+                    vmctx: &vm::Ctx $( , $x: <$x as WasmExternType>::Native )*,
+                    rets: *mut u64
+                )
+                where
+                    $( $x: WasmExternType, )*
+                    Rets: WasmTypeList,
+                    Trap: TrapEarly<Rets>,
+                    FN: Fn($( $x, )*) -> Trap,
+                {
+                    // Get the pointer to this `wrap_multi_value` function.
+                    let self_pointer =
+                        wrap_multi_value::<$( $x, )* Rets, Trap, FN> as *const vm::Func;
+
+                    let mut returns =
+                        call_imported::<$( $x, )* Rets, Trap, FN>(vmctx, self_pointer $( , $x )*)
+                            .into_ret_array();
+                    let returns = returns.as_mut();
+
+                    unsafe { ptr::copy_nonoverlapping(returns.as_ptr(), rets, returns.len()) };
+                }
+
                 // Extract the captured environment of the imported
                 // function if any.
                 let func_env: Option<NonNull<vm::FuncEnv>> =
@@ -645,10 +800,13 @@ macro_rules! impl_traits {
                         NonNull::new(Box::into_raw(Box::new(self))).map(NonNull::cast)
                     };
 
-                (
-                    NonNull::new(wrap::<$( $x, )* Rets, Trap, Self> as *mut vm::Func).unwrap(),
-                    func_env
-                )
+                let func = if Rets::types().len() > 1 {
+                    wrap_multi_value::<$( $x, )* Rets, Trap, Self> as *mut vm::Func
+                } else {
+                    wrap::<$( $x, )* Rets, Trap, Self> as *mut vm::Func
+                };
+
+                (NonNull::new(func).unwrap(), func_env)
             }
         }
 
@@ -733,6 +891,190 @@ where
     }
 }
 
+/// A host function whose signature is only known at runtime.
+///
+/// The function receives its arguments as a slice of [`Value`]s and returns its results as a
+/// vector, so a single closure type can implement imports of any signature. A trampoline that
+/// adapts the native calling convention to this form is generated when the function is
+/// created.
+///
+/// The closure and the trampoline are freed once the last clone of the `DynamicFunc` is
+/// dropped and no instance importing it is left. Like the functions of an instance, it can
+/// only be called through a table shared with other instances while an instance importing
+/// it is alive.
+///
+/// [`Value`]: ../types/enum.Value.html
+#[cfg(all(unix, target_arch = "x86_64"))]
+#[derive(Clone)]
+pub struct DynamicFunc {
+    inner: Arc<DynamicFuncInner>,
+    signature: Arc<FuncSig>,
+}
+
+#[cfg(all(unix, target_arch = "x86_64"))]
+struct DynamicFuncInner {
+    /// Only read through the trampoline, which refers to it by address.
+    _ctx: Box<DynamicCtx>,
+    buffer: TrampolineBuffer,
+    idx: usize,
+}
+
+#[cfg(all(unix, target_arch = "x86_64"))]
+impl DynamicFuncInner {
+    fn func(&self) -> *const vm::Func {
+        self.buffer.get_trampoline(self.idx) as *const vm::Func
+    }
+}
+
+#[cfg(all(unix, target_arch = "x86_64"))]
+struct DynamicCtx {
+    func: Box<
+        dyn Fn(&mut vm::Ctx, &[Value]) -> Result<Vec<Value>, RuntimeError> + Send + Sync + 'static,
+    >,
+    signature: Arc<FuncSig>,
+}
+
+#[cfg(all(unix, target_arch = "x86_64"))]
+impl DynamicFunc {
+    /// Creates a host function with the signature `signature` that calls `func`.
+    ///
+    /// `func` must return as many values as the signature has results, each of the declared
+    /// type; otherwise the call traps. An error returned by `func` is raised as a trap in the
+    /// calling WebAssembly code.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the signature has a `v128` parameter or result.
+    pub fn new<F>(signature: Arc<FuncSig>, func: F) -> DynamicFunc
+    where
+        F: Fn(&mut vm::Ctx, &[Value]) -> Result<Vec<Value>, RuntimeError> + Send + Sync + 'static,
+    {
+        if signature
+            .params()
+            .iter()
+            .chain(signature.returns())
+            .any(|ty| *ty == Type::V128)
+        {
+            panic!("dynamic functions do not support `v128` parameters or results");
+        }
+
+        // The native function takes the `vm::Ctx`, then the parameters, then the return area
+        // if there is one.
+        let mut params = vec![Type::I64];
+        params.extend_from_slice(signature.params());
+        if signature.has_return_area() {
+            params.push(Type::I64);
+        }
+
+        let ctx = Box::new(DynamicCtx {
+            func: Box::new(func),
+            signature: signature.clone(),
+        });
+        let mut builder = TrampolineBufferBuilder::new();
+        let idx = builder.add_callinfo_trampoline(
+            enter_host_dynamic,
+            &*ctx as *const DynamicCtx as *const CallContext,
+            &params,
+        );
+        let inner = Arc::new(DynamicFuncInner {
+            _ctx: ctx,
+            buffer: builder.build(),
+            idx,
+        });
+
+        DynamicFunc { inner, signature }
+    }
+
+    /// Returns the signature of the function.
+    pub fn signature(&self) -> &FuncSig {
+        &self.signature
+    }
+}
+
+#[cfg(all(unix, target_arch = "x86_64"))]
+impl fmt::Debug for DynamicFunc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DynamicFunc {{ signature: {} }}", self.signature)
+    }
+}
+
+#[cfg(all(unix, target_arch = "x86_64"))]
+impl IsExport for DynamicFunc {
+    fn to_export(&self) -> Export {
+        Export::Function {
+            func: unsafe { FuncPointer::with_owner(self.inner.func(), self.inner.clone()) },
+            ctx: Context::Internal,
+            signature: self.signature.clone(),
+        }
+    }
+}
+
+/// The target of the trampolines of `DynamicFunc`s. `args` holds the `vm::Ctx`, the
+/// parameters and the return area, if any, in 64-bit slots.
+#[cfg(all(unix, target_arch = "x86_64"))]
+#[cfg_attr(nightly, unwind(allowed))]
+unsafe extern "C" fn enter_host_dynamic(ctx: *const CallContext, args: *const u64) -> u64 {
+    let ctx = &*(ctx as *const DynamicCtx);
+    let signature = &ctx.signature;
+    let args = slice::from_raw_parts(
+        args,
+        1 + signature.params().len() + signature.has_return_area() as usize,
+    );
+    let vmctx = &mut *(args[0] as *mut vm::Ctx);
+
+    let params: Vec<Value> = signature
+        .params()
+        .iter()
+        .zip(&args[1..])
+        .map(|(ty, &raw)| match ty {
+            Type::I32 => Value::I32(raw as i32),
+            Type::I64 => Value::I64(raw as i64),
+            Type::F32 => Value::F32(f32::from_bits(raw as u32)),
+            Type::F64 => Value::F64(f64::from_bits(raw)),
+            Type::V128 => unreachable!("rejected by `DynamicFunc::new`"),
+            Type::AnyRef | Type::FuncRef => Value::AnyRef(AnyRef::from_raw(raw)),
+        })
+        .collect();
+
+    let err = match panic::catch_unwind(panic::AssertUnwindSafe(|| (ctx.func)(vmctx, &params))) {
+        Ok(Ok(ref returns))
+            if returns.len() == signature.returns().len()
+                && returns
+                    .iter()
+                    .zip(signature.returns())
+                    .all(|(value, ty)| value.is_of_type(*ty)) =>
+        {
+            let mut raw_returns = returns.iter().map(|value| match value {
+                Value::AnyRef(r) => root_ref(r),
+                _ => value.to_u128() as u64,
+            });
+            if signature.has_return_area() {
+                let return_area = args[args.len() - 1] as *mut u64;
+                for (i, raw) in raw_returns.enumerate() {
+                    *return_area.add(i) = raw;
+                }
+                return 0;
+            }
+            return raw_returns.next().unwrap_or(0);
+        }
+        Ok(Ok(returns)) => {
+            let err = RuntimeError::trap(
+                TrapCode::Other,
+                format!(
+                    "dynamic function returned {:?}, expected values of type {:?}",
+                    returns,
+                    signature.returns()
+                ),
+            );
+            Box::new(err) as Box<dyn Any + Send>
+        }
+        Ok(Err(err)) => Box::new(err) as Box<dyn Any + Send>,
+        Err(err) => err,
+    };
+
+    (&*vmctx.module).runnable_module.do_early_trap(err)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -815,6 +1157,25 @@ mod tests {
         let _f = Func::new(foo);
     }
 
+    #[test]
+    fn test_multi_value_ret_array() {
+        let array = (1i32, -2i64, 3.5f32, 4.25f64).into_ret_array();
+        assert_eq!(array[1], -2i64 as u64);
+
+        let values = <(i32, i64, f32, f64)>::from_ret_array(array);
+        assert_eq!(values, (1, -2, 3.5, 4.25));
+    }
+
+    #[test]
+    fn test_multi_value_signature() {
+        let f = Func::new(|a: i32, b: i64| (b, a));
+        assert_eq!(f.params(), &[Type::I32, Type::I64]);
+        assert_eq!(f.returns(), &[Type::I64, Type::I32]);
+
+        let sig = FuncSig::new(f.params(), f.returns());
+        assert!(sig.has_return_area());
+    }
+
     #[test]
     fn test_imports() {
         use crate::{func, imports};
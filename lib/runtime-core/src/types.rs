@@ -1,7 +1,13 @@
 //! The runtime types modules represent type used within the wasm runtime and helper functions to
 //! convert to other represenations.
 
-use crate::{memory::MemoryType, module::ModuleInfo, structures::TypedIndex, units::Pages};
+use crate::{
+    memory::MemoryType,
+    module::ModuleInfo,
+    structures::TypedIndex,
+    table::{AnyRef, FuncRef},
+    units::Pages,
+};
 use std::borrow::Cow;
 
 /// Represents a WebAssembly type.
@@ -17,6 +23,20 @@ pub enum Type {
     F64,
     /// The `v128` type.
     V128,
+    /// The `anyref` type.
+    AnyRef,
+    /// The `funcref` type.
+    FuncRef,
+}
+
+impl Type {
+    /// Returns true for the reference types, `anyref` and `funcref`.
+    pub fn is_reference(self) -> bool {
+        match self {
+            Type::AnyRef | Type::FuncRef => true,
+            _ => false,
+        }
+    }
 }
 
 impl std::fmt::Display for Type {
@@ -41,6 +61,8 @@ pub enum Value {
     F64(f64),
     /// The `v128` type.
     V128(u128),
+    /// The `anyref` type, also used for `funcref` values.
+    AnyRef(AnyRef),
 }
 
 impl Value {
@@ -52,10 +74,26 @@ impl Value {
             Value::F32(_) => Type::F32,
             Value::F64(_) => Type::F64,
             Value::V128(_) => Type::V128,
+            Value::AnyRef(_) => Type::AnyRef,
+        }
+    }
+
+    /// Returns true if this `Value` can be used where a `ty` is expected.
+    ///
+    /// A reference can be used as a `funcref` if it is null or holds a function.
+    pub fn is_of_type(&self, ty: Type) -> bool {
+        match (self, ty) {
+            (Value::AnyRef(r), Type::FuncRef) => {
+                r.is_null() || r.downcast_ref::<FuncRef>().is_some()
+            }
+            _ => self.ty() == ty,
         }
     }
 
     /// Convert this `Value` to a u128 binary representation.
+    ///
+    /// References are converted to their raw representation, which stays
+    /// valid only as long as the reference is kept alive.
     pub fn to_u128(&self) -> u128 {
         match *self {
             Value::I32(x) => x as u128,
@@ -63,6 +101,7 @@ impl Value {
             Value::F32(x) => f32::to_bits(x) as u128,
             Value::F64(x) => f64::to_bits(x) as u128,
             Value::V128(x) => x,
+            Value::AnyRef(ref r) => r.as_raw() as u128,
         }
     }
 }
@@ -97,6 +136,12 @@ impl From<u128> for Value {
     }
 }
 
+impl From<AnyRef> for Value {
+    fn from(r: AnyRef) -> Self {
+        Value::AnyRef(r)
+    }
+}
+
 /// Represents a native wasm type.
 pub unsafe trait NativeWasmType: Copy + Into<Value>
 where
@@ -293,11 +338,13 @@ convert_value_impl!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
 pub enum ElementType {
     /// Any wasm function.
     Anyfunc,
+    /// Opaque host references.
+    AnyRef,
 }
 
 /// Describes the properties of a table including the element types, minimum and optional maximum,
 /// number of elements in the table.
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TableDescriptor {
     /// Type of data stored in this table.
     pub element: ElementType,
@@ -425,14 +472,24 @@ impl FuncSig {
         &self.returns
     }
 
+    /// Returns true if the results are passed through a return area.
+    ///
+    /// A function with more than one result takes a pointer to the return
+    /// area as its last argument, after the regular parameters. It writes
+    /// each result into its own 64-bit slot (two slots for a `v128`) and
+    /// returns nothing.
+    pub fn has_return_area(&self) -> bool {
+        self.returns.len() > 1
+    }
+
     /// Returns true if parameter types match the function signature.
     pub fn check_param_value_types(&self, params: &[Value]) -> bool {
         self.params.len() == params.len()
             && self
                 .params
                 .iter()
-                .zip(params.iter().map(|val| val.ty()))
-                .all(|(t0, ref t1)| t0 == t1)
+                .zip(params.iter())
+                .all(|(&ty, val)| val.is_of_type(ty))
     }
 }
 
@@ -454,6 +511,28 @@ impl std::fmt::Display for FuncSig {
     }
 }
 
+/// The type of a function import or export.
+pub type FuncType = FuncSig;
+/// The type of a memory import or export.
+pub type MemoryType = MemoryDescriptor;
+/// The type of a table import or export.
+pub type TableType = TableDescriptor;
+/// The type of a global import or export.
+pub type GlobalType = GlobalDescriptor;
+
+/// The type of an import or export.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExternType {
+    /// A function.
+    Function(FuncType),
+    /// A memory.
+    Memory(MemoryType),
+    /// A table.
+    Table(TableType),
+    /// A global.
+    Global(GlobalType),
+}
+
 /// Trait that represents Local or Import.
 pub trait LocalImport {
     /// Local type.
@@ -560,6 +639,36 @@ impl TypedIndex for SigIndex {
     }
 }
 
+/// Index for a data segment.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DataIndex(u32);
+impl TypedIndex for DataIndex {
+    #[doc(hidden)]
+    fn new(index: usize) -> Self {
+        DataIndex(index as _)
+    }
+
+    #[doc(hidden)]
+    fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Index for an element segment.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ElemIndex(u32);
+impl TypedIndex for ElemIndex {
+    #[doc(hidden)]
+    fn new(index: usize) -> Self {
+        ElemIndex(index as _)
+    }
+
+    #[doc(hidden)]
+    fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
 /// Kind of local or import type.
 pub enum LocalOrImport<T>
 where
@@ -124,6 +124,9 @@ pub struct InternalCtx {
     /// Mutable pointer to memory base.
     pub memory_base: *mut u8,
     /// Memory bound.
+    ///
+    /// For a shared memory this is only the bound when this context last saw it, since
+    /// other instances may grow the memory; read `LocalMemory::bound` instead.
     pub memory_bound: usize,
 
     /// Mutable pointer to internal fields.
@@ -131,6 +134,9 @@ pub struct InternalCtx {
 
     /// Interrupt signal mem.
     pub interrupt_signal_mem: *mut u8,
+
+    /// A pointer to the counters of the instance, see `ModuleInfo::counters`.
+    pub counters: *mut u64,
 }
 
 static INTERNAL_FIELDS: AtomicUsize = AtomicUsize::new(0);
@@ -180,6 +186,38 @@ pub struct Intrinsics {
     pub memory_grow: *const Func,
     /// Const pointer to memory size `Func`.
     pub memory_size: *const Func,
+    /// Const pointer to memory copy `Func`.
+    pub memory_copy: *const Func,
+    /// Const pointer to memory fill `Func`.
+    pub memory_fill: *const Func,
+    /// Const pointer to memory init `Func`.
+    pub memory_init: *const Func,
+    /// Const pointer to data drop `Func`.
+    pub data_drop: *const Func,
+    /// Const pointer to table copy `Func`.
+    pub table_copy: *const Func,
+    /// Const pointer to table init `Func`.
+    pub table_init: *const Func,
+    /// Const pointer to elem drop `Func`.
+    pub elem_drop: *const Func,
+    /// Const pointer to table get `Func`.
+    pub table_get: *const Func,
+    /// Const pointer to table set `Func`.
+    pub table_set: *const Func,
+    /// Const pointer to table size `Func`.
+    pub table_size: *const Func,
+    /// Const pointer to table grow `Func`.
+    pub table_grow: *const Func,
+    /// Const pointer to memory atomic wait32 `Func`.
+    pub memory_atomic_wait32: *const Func,
+    /// Const pointer to memory atomic wait64 `Func`.
+    pub memory_atomic_wait64: *const Func,
+    /// Const pointer to memory atomic notify `Func`.
+    pub memory_atomic_notify: *const Func,
+    /// Const pointer to global get ref `Func`.
+    pub global_get_ref: *const Func,
+    /// Const pointer to global set ref `Func`.
+    pub global_set_ref: *const Func,
     /*pub memory_grow: unsafe extern "C" fn(
         ctx: &mut Ctx,
         memory_index: usize,
@@ -204,32 +242,204 @@ impl Intrinsics {
     pub const fn offset_memory_size() -> u8 {
         (1 * ::std::mem::size_of::<usize>()) as u8
     }
+    /// Offset of the `memory_copy` field.
+    pub const fn offset_memory_copy() -> u8 {
+        (2 * ::std::mem::size_of::<usize>()) as u8
+    }
+    /// Offset of the `memory_fill` field.
+    pub const fn offset_memory_fill() -> u8 {
+        (3 * ::std::mem::size_of::<usize>()) as u8
+    }
+    /// Offset of the `memory_init` field.
+    pub const fn offset_memory_init() -> u8 {
+        (4 * ::std::mem::size_of::<usize>()) as u8
+    }
+    /// Offset of the `data_drop` field.
+    pub const fn offset_data_drop() -> u8 {
+        (5 * ::std::mem::size_of::<usize>()) as u8
+    }
+    /// Offset of the `table_copy` field.
+    pub const fn offset_table_copy() -> u8 {
+        (6 * ::std::mem::size_of::<usize>()) as u8
+    }
+    /// Offset of the `table_init` field.
+    pub const fn offset_table_init() -> u8 {
+        (7 * ::std::mem::size_of::<usize>()) as u8
+    }
+    /// Offset of the `elem_drop` field.
+    pub const fn offset_elem_drop() -> u8 {
+        (8 * ::std::mem::size_of::<usize>()) as u8
+    }
+    /// Offset of the `table_get` field.
+    pub const fn offset_table_get() -> u8 {
+        (9 * ::std::mem::size_of::<usize>()) as u8
+    }
+    /// Offset of the `table_set` field.
+    pub const fn offset_table_set() -> u8 {
+        (10 * ::std::mem::size_of::<usize>()) as u8
+    }
+    /// Offset of the `table_size` field.
+    pub const fn offset_table_size() -> u8 {
+        (11 * ::std::mem::size_of::<usize>()) as u8
+    }
+    /// Offset of the `table_grow` field.
+    pub const fn offset_table_grow() -> u8 {
+        (12 * ::std::mem::size_of::<usize>()) as u8
+    }
+    /// Offset of the `memory_atomic_wait32` field.
+    pub const fn offset_memory_atomic_wait32() -> u8 {
+        (13 * ::std::mem::size_of::<usize>()) as u8
+    }
+    /// Offset of the `memory_atomic_wait64` field.
+    pub const fn offset_memory_atomic_wait64() -> u8 {
+        (14 * ::std::mem::size_of::<usize>()) as u8
+    }
+    /// Offset of the `memory_atomic_notify` field.
+    pub const fn offset_memory_atomic_notify() -> u8 {
+        (15 * ::std::mem::size_of::<usize>()) as u8
+    }
+    /// Offset of the `global_get_ref` field.
+    pub const fn offset_global_get_ref() -> u8 {
+        (16 * ::std::mem::size_of::<usize>()) as u8
+    }
+    /// Offset of the `global_set_ref` field.
+    pub const fn offset_global_set_ref() -> u8 {
+        (17 * ::std::mem::size_of::<usize>()) as u8
+    }
 }
 
 /// Local static memory intrinsics
 pub static INTRINSICS_LOCAL_STATIC_MEMORY: Intrinsics = Intrinsics {
     memory_grow: vmcalls::local_static_memory_grow as _,
     memory_size: vmcalls::local_static_memory_size as _,
+    memory_copy: vmcalls::local_memory_copy as _,
+    memory_fill: vmcalls::local_memory_fill as _,
+    memory_init: vmcalls::memory_init as _,
+    data_drop: vmcalls::data_drop as _,
+    table_copy: vmcalls::table_copy as _,
+    table_init: vmcalls::table_init as _,
+    elem_drop: vmcalls::elem_drop as _,
+    table_get: vmcalls::table_get as _,
+    table_set: vmcalls::table_set as _,
+    table_size: vmcalls::table_size as _,
+    table_grow: vmcalls::table_grow as _,
+    memory_atomic_wait32: vmcalls::memory_atomic_wait32 as _,
+    memory_atomic_wait64: vmcalls::memory_atomic_wait64 as _,
+    memory_atomic_notify: vmcalls::memory_atomic_notify as _,
+    global_get_ref: vmcalls::global_get_ref as _,
+    global_set_ref: vmcalls::global_set_ref as _,
 };
 /// Local dynamic memory intrinsics
 pub static INTRINSICS_LOCAL_DYNAMIC_MEMORY: Intrinsics = Intrinsics {
     memory_grow: vmcalls::local_dynamic_memory_grow as _,
     memory_size: vmcalls::local_dynamic_memory_size as _,
+    memory_copy: vmcalls::local_memory_copy as _,
+    memory_fill: vmcalls::local_memory_fill as _,
+    memory_init: vmcalls::memory_init as _,
+    data_drop: vmcalls::data_drop as _,
+    table_copy: vmcalls::table_copy as _,
+    table_init: vmcalls::table_init as _,
+    elem_drop: vmcalls::elem_drop as _,
+    table_get: vmcalls::table_get as _,
+    table_set: vmcalls::table_set as _,
+    table_size: vmcalls::table_size as _,
+    table_grow: vmcalls::table_grow as _,
+    memory_atomic_wait32: vmcalls::memory_atomic_wait32 as _,
+    memory_atomic_wait64: vmcalls::memory_atomic_wait64 as _,
+    memory_atomic_notify: vmcalls::memory_atomic_notify as _,
+    global_get_ref: vmcalls::global_get_ref as _,
+    global_set_ref: vmcalls::global_set_ref as _,
+};
+/// Local shared memory intrinsics
+pub static INTRINSICS_LOCAL_SHARED_MEMORY: Intrinsics = Intrinsics {
+    memory_grow: vmcalls::local_shared_memory_grow as _,
+    memory_size: vmcalls::local_shared_memory_size as _,
+    memory_copy: vmcalls::local_memory_copy as _,
+    memory_fill: vmcalls::local_memory_fill as _,
+    memory_init: vmcalls::memory_init as _,
+    data_drop: vmcalls::data_drop as _,
+    table_copy: vmcalls::table_copy as _,
+    table_init: vmcalls::table_init as _,
+    elem_drop: vmcalls::elem_drop as _,
+    table_get: vmcalls::table_get as _,
+    table_set: vmcalls::table_set as _,
+    table_size: vmcalls::table_size as _,
+    table_grow: vmcalls::table_grow as _,
+    memory_atomic_wait32: vmcalls::memory_atomic_wait32 as _,
+    memory_atomic_wait64: vmcalls::memory_atomic_wait64 as _,
+    memory_atomic_notify: vmcalls::memory_atomic_notify as _,
+    global_get_ref: vmcalls::global_get_ref as _,
+    global_set_ref: vmcalls::global_set_ref as _,
 };
 /// Imported static memory intrinsics
 pub static INTRINSICS_IMPORTED_STATIC_MEMORY: Intrinsics = Intrinsics {
     memory_grow: vmcalls::imported_static_memory_grow as _,
     memory_size: vmcalls::imported_static_memory_size as _,
+    memory_copy: vmcalls::imported_memory_copy as _,
+    memory_fill: vmcalls::imported_memory_fill as _,
+    memory_init: vmcalls::memory_init as _,
+    data_drop: vmcalls::data_drop as _,
+    table_copy: vmcalls::table_copy as _,
+    table_init: vmcalls::table_init as _,
+    elem_drop: vmcalls::elem_drop as _,
+    table_get: vmcalls::table_get as _,
+    table_set: vmcalls::table_set as _,
+    table_size: vmcalls::table_size as _,
+    table_grow: vmcalls::table_grow as _,
+    memory_atomic_wait32: vmcalls::memory_atomic_wait32 as _,
+    memory_atomic_wait64: vmcalls::memory_atomic_wait64 as _,
+    memory_atomic_notify: vmcalls::memory_atomic_notify as _,
+    global_get_ref: vmcalls::global_get_ref as _,
+    global_set_ref: vmcalls::global_set_ref as _,
 };
 /// Imported dynamic memory intrinsics
 pub static INTRINSICS_IMPORTED_DYNAMIC_MEMORY: Intrinsics = Intrinsics {
     memory_grow: vmcalls::imported_dynamic_memory_grow as _,
     memory_size: vmcalls::imported_dynamic_memory_size as _,
+    memory_copy: vmcalls::imported_memory_copy as _,
+    memory_fill: vmcalls::imported_memory_fill as _,
+    memory_init: vmcalls::memory_init as _,
+    data_drop: vmcalls::data_drop as _,
+    table_copy: vmcalls::table_copy as _,
+    table_init: vmcalls::table_init as _,
+    elem_drop: vmcalls::elem_drop as _,
+    table_get: vmcalls::table_get as _,
+    table_set: vmcalls::table_set as _,
+    table_size: vmcalls::table_size as _,
+    table_grow: vmcalls::table_grow as _,
+    memory_atomic_wait32: vmcalls::memory_atomic_wait32 as _,
+    memory_atomic_wait64: vmcalls::memory_atomic_wait64 as _,
+    memory_atomic_notify: vmcalls::memory_atomic_notify as _,
+    global_get_ref: vmcalls::global_get_ref as _,
+    global_set_ref: vmcalls::global_set_ref as _,
+};
+/// Imported shared memory intrinsics
+pub static INTRINSICS_IMPORTED_SHARED_MEMORY: Intrinsics = Intrinsics {
+    memory_grow: vmcalls::imported_shared_memory_grow as _,
+    memory_size: vmcalls::imported_shared_memory_size as _,
+    memory_copy: vmcalls::imported_memory_copy as _,
+    memory_fill: vmcalls::imported_memory_fill as _,
+    memory_init: vmcalls::memory_init as _,
+    data_drop: vmcalls::data_drop as _,
+    table_copy: vmcalls::table_copy as _,
+    table_init: vmcalls::table_init as _,
+    elem_drop: vmcalls::elem_drop as _,
+    table_get: vmcalls::table_get as _,
+    table_set: vmcalls::table_set as _,
+    table_size: vmcalls::table_size as _,
+    table_grow: vmcalls::table_grow as _,
+    memory_atomic_wait32: vmcalls::memory_atomic_wait32 as _,
+    memory_atomic_wait64: vmcalls::memory_atomic_wait64 as _,
+    memory_atomic_notify: vmcalls::memory_atomic_notify as _,
+    global_get_ref: vmcalls::global_get_ref as _,
+    global_set_ref: vmcalls::global_set_ref as _,
 };
 
 fn get_intrinsics_for_module(m: &ModuleInfo) -> *const Intrinsics {
     if m.memories.len() == 0 && m.imported_memories.len() == 0 {
-        ::std::ptr::null()
+        // The memory intrinsics are never called without a memory, but the
+        // table ones still are.
+        &INTRINSICS_LOCAL_STATIC_MEMORY
     } else {
         match MemoryIndex::new(0).local_or_import(m) {
             LocalOrImport::Local(local_mem_index) => {
@@ -237,7 +447,7 @@ fn get_intrinsics_for_module(m: &ModuleInfo) -> *const Intrinsics {
                 match mem_desc.memory_type() {
                     MemoryType::Dynamic => &INTRINSICS_LOCAL_DYNAMIC_MEMORY,
                     MemoryType::Static => &INTRINSICS_LOCAL_STATIC_MEMORY,
-                    MemoryType::SharedStatic => &INTRINSICS_LOCAL_STATIC_MEMORY,
+                    MemoryType::SharedStatic => &INTRINSICS_LOCAL_SHARED_MEMORY,
                 }
             }
             LocalOrImport::Import(import_mem_index) => {
@@ -245,7 +455,7 @@ fn get_intrinsics_for_module(m: &ModuleInfo) -> *const Intrinsics {
                 match mem_desc.memory_type() {
                     MemoryType::Dynamic => &INTRINSICS_IMPORTED_DYNAMIC_MEMORY,
                     MemoryType::Static => &INTRINSICS_IMPORTED_STATIC_MEMORY,
-                    MemoryType::SharedStatic => &INTRINSICS_IMPORTED_STATIC_MEMORY,
+                    MemoryType::SharedStatic => &INTRINSICS_IMPORTED_SHARED_MEMORY,
                 }
             }
         }
@@ -303,6 +513,8 @@ impl Ctx {
                 internals: &mut local_backing.internals.0,
 
                 interrupt_signal_mem: get_interrupt_signal_mem(),
+
+                counters: local_backing.counters.as_mut_ptr(),
             },
             local_functions: local_backing.local_functions.as_ptr(),
 
@@ -356,6 +568,8 @@ impl Ctx {
                 internals: &mut local_backing.internals.0,
 
                 interrupt_signal_mem: get_interrupt_signal_mem(),
+
+                counters: local_backing.counters.as_mut_ptr(),
             },
             local_functions: local_backing.local_functions.as_ptr(),
 
@@ -438,6 +652,16 @@ impl Ctx {
         }
     }
 
+    /// Returns the counters of this instance, see `ModuleInfo::counters`.
+    pub fn counters(&self) -> &[u64] {
+        unsafe { &(*self.local_backing).counters }
+    }
+
+    /// Returns the counters of this instance mutably, see `ModuleInfo::counters`.
+    pub fn counters_mut(&mut self) -> &mut [u64] {
+        unsafe { &mut (*self.local_backing).counters }
+    }
+
     /// Calls a host or Wasm function at the given table index
     pub fn call_with_table_index(
         &mut self,
@@ -533,9 +757,13 @@ impl Ctx {
         13 * (mem::size_of::<usize>() as u8)
     }
 
-    pub const fn offset_local_functions() -> u8 {
+    pub const fn offset_counters() -> u8 {
         14 * (mem::size_of::<usize>() as u8)
     }
+
+    pub const fn offset_local_functions() -> u8 {
+        15 * (mem::size_of::<usize>() as u8)
+    }
 }
 
 /// Represents a function pointer. It is mostly used in the
@@ -766,7 +994,8 @@ impl Anyfunc {
 #[cfg(test)]
 mod vm_offset_tests {
     use super::{
-        Anyfunc, Ctx, FuncCtx, ImportedFunc, InternalCtx, LocalGlobal, LocalMemory, LocalTable,
+        Anyfunc, Ctx, FuncCtx, ImportedFunc, InternalCtx, Intrinsics, LocalGlobal, LocalMemory,
+        LocalTable,
     };
 
     // Inspired by https://internals.rust-lang.org/t/discussion-on-offset-of/7440/2.
@@ -896,12 +1125,110 @@ mod vm_offset_tests {
             offset_of!(InternalCtx, interrupt_signal_mem),
         );
 
+        assert_eq!(
+            Ctx::offset_counters() as usize,
+            offset_of!(InternalCtx, counters),
+        );
+
         assert_eq!(
             Ctx::offset_local_functions() as usize,
             offset_of!(Ctx, local_functions),
         );
     }
 
+    #[test]
+    fn intrinsics() {
+        assert_eq!(
+            Intrinsics::offset_memory_grow() as usize,
+            offset_of!(Intrinsics, memory_grow),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_memory_size() as usize,
+            offset_of!(Intrinsics, memory_size),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_memory_copy() as usize,
+            offset_of!(Intrinsics, memory_copy),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_memory_fill() as usize,
+            offset_of!(Intrinsics, memory_fill),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_memory_init() as usize,
+            offset_of!(Intrinsics, memory_init),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_data_drop() as usize,
+            offset_of!(Intrinsics, data_drop),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_table_copy() as usize,
+            offset_of!(Intrinsics, table_copy),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_table_init() as usize,
+            offset_of!(Intrinsics, table_init),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_elem_drop() as usize,
+            offset_of!(Intrinsics, elem_drop),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_table_get() as usize,
+            offset_of!(Intrinsics, table_get),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_table_set() as usize,
+            offset_of!(Intrinsics, table_set),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_table_size() as usize,
+            offset_of!(Intrinsics, table_size),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_table_grow() as usize,
+            offset_of!(Intrinsics, table_grow),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_memory_atomic_wait32() as usize,
+            offset_of!(Intrinsics, memory_atomic_wait32),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_memory_atomic_wait64() as usize,
+            offset_of!(Intrinsics, memory_atomic_wait64),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_memory_atomic_notify() as usize,
+            offset_of!(Intrinsics, memory_atomic_notify),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_global_get_ref() as usize,
+            offset_of!(Intrinsics, global_get_ref),
+        );
+
+        assert_eq!(
+            Intrinsics::offset_global_set_ref() as usize,
+            offset_of!(Intrinsics, global_set_ref),
+        );
+    }
+
     #[test]
     fn func_ctx() {
         assert_eq!(FuncCtx::offset_vmctx() as usize, 0,);
@@ -974,6 +1301,7 @@ mod vm_ctx_tests {
     use super::{Ctx, ImportBacking, LocalBacking};
     use crate::module::{ModuleInfo, ModuleInner, StringTable};
     use crate::structures::Map;
+    use std::collections::{HashMap, HashSet};
     use std::ffi::c_void;
     use std::sync::Arc;
 
@@ -1023,7 +1351,13 @@ mod vm_ctx_tests {
             dynamic_sigindices: Map::new().into_boxed_map(),
             local_functions: Map::new().into_boxed_map(),
 
+            dropped_data: HashSet::new(),
+            dropped_elems: HashSet::new(),
+
+            funcrefs: HashMap::new(),
+
             internals: crate::backing::Internals([0; crate::backing::INTERNALS_SIZE]),
+            counters: Box::new([]),
         };
 
         let mut import_backing = ImportBacking {
@@ -1035,6 +1369,8 @@ mod vm_ctx_tests {
             vm_memories: Map::new().into_boxed_map(),
             vm_tables: Map::new().into_boxed_map(),
             vm_globals: Map::new().into_boxed_map(),
+
+            function_owners: vec![],
         };
 
         let module = generate_module();
@@ -1113,6 +1449,8 @@ mod vm_ctx_tests {
 
                 data_initializers: Vec::new(),
                 elem_initializers: Vec::new(),
+                passive_data: HashMap::new(),
+                passive_elements: HashMap::new(),
 
                 start_func: None,
 
@@ -1125,7 +1463,14 @@ mod vm_ctx_tests {
 
                 em_symbol_map: None,
 
+                module_name: None,
+                func_names: HashMap::new(),
+                local_names: HashMap::new(),
+
                 custom_sections: HashMap::new(),
+
+                id: 0,
+                counters: 0,
             },
         }
     }
@@ -1,12 +1,24 @@
 #![allow(clippy::cast_ptr_alignment)]
 
 use crate::{
-    memory::{DynamicMemory, StaticMemory},
+    error::{RuntimeError, TrapCode},
+    global::Global,
+    memory::{wait, DynamicMemory, SharedMemoryInternal, StaticMemory},
+    sig_registry::SigRegistry,
     structures::TypedIndex,
-    types::{ImportedMemoryIndex, LocalMemoryIndex, LocalTableIndex},
+    table::{root_ref, AnyRef, AnyRefTable, AnyfuncTable, FuncRef},
+    types::{
+        DataIndex, ElemIndex, ElementType, FuncIndex, GlobalIndex, ImportedMemoryIndex,
+        LocalMemoryIndex, LocalOrImport, MemoryIndex, TableIndex,
+    },
     units::Pages,
     vm,
 };
+use std::{
+    ptr,
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+    time::Duration,
+};
 
 // +*****************************+
 // |       LOCAL MEMORIES        |
@@ -139,22 +151,562 @@ pub unsafe extern "C" fn imported_dynamic_memory_size(
 }
 
 // +*****************************+
-// |        LOCAL TABLES         |
+// |       SHARED MEMORIES       |
 // +*****************************+
 
-pub unsafe extern "C" fn local_table_grow(
+pub unsafe extern "C" fn local_shared_memory_grow(
+    ctx: &mut vm::Ctx,
+    memory_index: LocalMemoryIndex,
+    delta: Pages,
+) -> i32 {
+    let local_memory = *ctx.internal.memories.add(memory_index.index());
+    shared_memory_grow(ctx, local_memory, delta)
+}
+
+pub unsafe extern "C" fn local_shared_memory_size(
+    ctx: &vm::Ctx,
+    memory_index: LocalMemoryIndex,
+) -> Pages {
+    let local_memory = *ctx.internal.memories.add(memory_index.index());
+    let memory = (*local_memory).memory as *const SharedMemoryInternal;
+
+    (*memory).size()
+}
+
+pub unsafe extern "C" fn imported_shared_memory_grow(
     ctx: &mut vm::Ctx,
-    table_index: LocalTableIndex,
+    memory_index: ImportedMemoryIndex,
+    delta: Pages,
+) -> i32 {
+    let local_memory = *ctx.internal.imported_memories.add(memory_index.index());
+    shared_memory_grow(ctx, local_memory, delta)
+}
+
+pub unsafe extern "C" fn imported_shared_memory_size(
+    ctx: &vm::Ctx,
+    memory_index: ImportedMemoryIndex,
+) -> Pages {
+    let local_memory = *ctx.internal.imported_memories.add(memory_index.index());
+    let memory = (*local_memory).memory as *const SharedMemoryInternal;
+
+    (*memory).size()
+}
+
+/// Grows a shared memory under its lock, since other threads may be growing it too.
+unsafe fn shared_memory_grow(
+    ctx: &mut vm::Ctx,
+    local_memory: *mut vm::LocalMemory,
+    delta: Pages,
+) -> i32 {
+    let memory = (*local_memory).memory as *const SharedMemoryInternal;
+
+    let ret = match (*memory).grow(delta) {
+        Ok(old) => old.0 as i32,
+        Err(_) => -1,
+    };
+
+    ctx.internal.memory_base = (*local_memory).base;
+    ctx.internal.memory_bound = (*local_memory).bound;
+
+    ret
+}
+
+// +*****************************+
+// |           TABLES            |
+// +*****************************+
+
+pub unsafe extern "C" fn table_get(ctx: &mut vm::Ctx, table_index: TableIndex, index: u32) -> u64 {
+    let local_table = get_local_table(ctx, table_index);
+    if index as usize >= (*local_table).count {
+        trap(ctx, TrapCode::TableOutOfBounds);
+    }
+
+    match table_element_type(ctx, table_index) {
+        ElementType::Anyfunc => {
+            let anyfunc = *((*local_table).base as *const vm::Anyfunc).add(index as usize);
+            funcref_to_raw(ctx, anyfunc)
+        }
+        ElementType::AnyRef => {
+            let table = &*((*local_table).table as *const AnyRefTable);
+            let r = &table.backing[index as usize];
+            root_ref(r)
+        }
+    }
+}
+
+pub unsafe extern "C" fn table_set(
+    ctx: &mut vm::Ctx,
+    table_index: TableIndex,
+    index: u32,
+    value: u64,
+) {
+    let local_table = get_local_table(ctx, table_index);
+    if index as usize >= (*local_table).count {
+        trap(ctx, TrapCode::TableOutOfBounds);
+    }
+
+    match table_element_type(ctx, table_index) {
+        ElementType::Anyfunc => {
+            let anyfunc = raw_to_anyfunc(ctx, value);
+            *((*local_table).base as *mut vm::Anyfunc).add(index as usize) = anyfunc;
+        }
+        ElementType::AnyRef => {
+            let table = &mut *((*local_table).table as *mut AnyRefTable);
+            table.backing[index as usize] = AnyRef::from_raw(value);
+        }
+    }
+}
+
+pub unsafe extern "C" fn table_size(ctx: &mut vm::Ctx, table_index: TableIndex) -> u32 {
+    (*get_local_table(ctx, table_index)).count as u32
+}
+
+pub unsafe extern "C" fn table_grow(
+    ctx: &mut vm::Ctx,
+    table_index: TableIndex,
+    init: u64,
     delta: u32,
 ) -> i32 {
-    let _ = table_index;
-    let _ = delta;
-    let _ = ctx;
-    unimplemented!("vmcalls::local_table_grow")
+    let local_table = get_local_table(ctx, table_index);
+    let old_size = match table_element_type(ctx, table_index) {
+        ElementType::Anyfunc => {
+            let init = raw_to_anyfunc(ctx, init);
+            let table = &mut *((*local_table).table as *mut AnyfuncTable);
+            let old_size = table.grow(delta, &mut *local_table);
+            if old_size.is_ok() {
+                for slot in &mut table.internal_buffer()[(*local_table).count - delta as usize..] {
+                    *slot = init;
+                }
+            }
+            old_size
+        }
+        ElementType::AnyRef => {
+            let table = &mut *((*local_table).table as *mut AnyRefTable);
+            table.grow(delta, AnyRef::from_raw(init), &mut *local_table)
+        }
+    };
+
+    match old_size {
+        Ok(old_size) => old_size as i32,
+        Err(_) => -1,
+    }
+}
+
+// +*****************************+
+// |           GLOBALS           |
+// +*****************************+
+
+/// Reads a global of reference type, rooting the reference on the current stack.
+pub unsafe extern "C" fn global_get_ref(ctx: &mut vm::Ctx, global_index: GlobalIndex) -> u64 {
+    root_ref(&get_global(ctx, global_index).get_ref())
+}
+
+/// Stores a reference into a global of reference type, which then owns it.
+pub unsafe extern "C" fn global_set_ref(ctx: &mut vm::Ctx, global_index: GlobalIndex, value: u64) {
+    get_global(ctx, global_index).set_ref(AnyRef::from_raw(value));
+}
+
+// +*****************************+
+// |         BULK MEMORY         |
+// +*****************************+
+
+pub unsafe extern "C" fn local_memory_copy(
+    ctx: &mut vm::Ctx,
+    memory_index: LocalMemoryIndex,
+    dst: u32,
+    src: u32,
+    len: u32,
+) {
+    let local_memory = *ctx.internal.memories.add(memory_index.index());
+    memory_copy(ctx, local_memory, dst, src, len)
+}
+
+pub unsafe extern "C" fn imported_memory_copy(
+    ctx: &mut vm::Ctx,
+    memory_index: ImportedMemoryIndex,
+    dst: u32,
+    src: u32,
+    len: u32,
+) {
+    let local_memory = *ctx.internal.imported_memories.add(memory_index.index());
+    memory_copy(ctx, local_memory, dst, src, len)
+}
+
+pub unsafe extern "C" fn local_memory_fill(
+    ctx: &mut vm::Ctx,
+    memory_index: LocalMemoryIndex,
+    dst: u32,
+    value: u32,
+    len: u32,
+) {
+    let local_memory = *ctx.internal.memories.add(memory_index.index());
+    memory_fill(ctx, local_memory, dst, value, len)
 }
 
-pub unsafe extern "C" fn local_table_size(ctx: &vm::Ctx, table_index: LocalTableIndex) -> u32 {
-    let _ = table_index;
-    let _ = ctx;
-    unimplemented!("vmcalls::local_table_size")
+pub unsafe extern "C" fn imported_memory_fill(
+    ctx: &mut vm::Ctx,
+    memory_index: ImportedMemoryIndex,
+    dst: u32,
+    value: u32,
+    len: u32,
+) {
+    let local_memory = *ctx.internal.imported_memories.add(memory_index.index());
+    memory_fill(ctx, local_memory, dst, value, len)
+}
+
+pub unsafe extern "C" fn memory_init(
+    ctx: &mut vm::Ctx,
+    memory_index: MemoryIndex,
+    segment_index: DataIndex,
+    dst: u32,
+    src: u32,
+    len: u32,
+) {
+    let local_memory = get_local_memory(ctx, memory_index);
+
+    // Active and dropped segments behave as if they were empty.
+    let data: &[u8] = if (*ctx.local_backing).dropped_data.contains(&segment_index) {
+        &[]
+    } else {
+        match (*ctx.module).info.passive_data.get(&segment_index) {
+            Some(data) => data,
+            None => &[],
+        }
+    };
+
+    if !in_bounds(src, len, data.len()) || !in_bounds(dst, len, (*local_memory).bound) {
+        trap(ctx, TrapCode::MemoryOutOfBounds);
+    }
+
+    ptr::copy_nonoverlapping(
+        data.as_ptr().add(src as usize),
+        (*local_memory).base.add(dst as usize),
+        len as usize,
+    );
+}
+
+pub unsafe extern "C" fn data_drop(ctx: &mut vm::Ctx, segment_index: DataIndex) {
+    (*ctx.local_backing).dropped_data.insert(segment_index);
+}
+
+pub unsafe extern "C" fn table_copy(
+    ctx: &mut vm::Ctx,
+    dst_table_index: TableIndex,
+    src_table_index: TableIndex,
+    dst: u32,
+    src: u32,
+    len: u32,
+) {
+    let dst_table = get_local_table(ctx, dst_table_index);
+    let src_table = get_local_table(ctx, src_table_index);
+
+    if !in_bounds(src, len, (*src_table).count) || !in_bounds(dst, len, (*dst_table).count) {
+        trap(ctx, TrapCode::TableOutOfBounds);
+    }
+
+    match table_element_type(ctx, dst_table_index) {
+        ElementType::Anyfunc => ptr::copy(
+            ((*src_table).base as *const vm::Anyfunc).add(src as usize),
+            ((*dst_table).base as *mut vm::Anyfunc).add(dst as usize),
+            len as usize,
+        ),
+        ElementType::AnyRef => {
+            let elements: Vec<AnyRef> = (&(*((*src_table).table as *const AnyRefTable)).backing)
+                [src as usize..(src + len) as usize]
+                .to_vec();
+            let dst_table = &mut *((*dst_table).table as *mut AnyRefTable);
+            for (slot, element) in dst_table.backing[dst as usize..].iter_mut().zip(elements) {
+                *slot = element;
+            }
+        }
+    }
+}
+
+pub unsafe extern "C" fn table_init(
+    ctx: &mut vm::Ctx,
+    table_index: TableIndex,
+    segment_index: ElemIndex,
+    dst: u32,
+    src: u32,
+    len: u32,
+) {
+    let local_table = get_local_table(ctx, table_index);
+    let module = &*ctx.module;
+
+    // Active and dropped segments behave as if they were empty.
+    let elements: &[FuncIndex] = if (*ctx.local_backing).dropped_elems.contains(&segment_index) {
+        &[]
+    } else {
+        match module.info.passive_elements.get(&segment_index) {
+            Some(elements) => elements,
+            None => &[],
+        }
+    };
+
+    if !in_bounds(src, len, elements.len()) || !in_bounds(dst, len, (*local_table).count) {
+        trap(ctx, TrapCode::TableOutOfBounds);
+    }
+
+    let table_base = (*local_table).base as *mut vm::Anyfunc;
+    for (i, &func_index) in elements[src as usize..(src + len) as usize]
+        .iter()
+        .enumerate()
+    {
+        *table_base.add(dst as usize + i) = get_anyfunc(ctx, func_index);
+    }
+}
+
+pub unsafe extern "C" fn elem_drop(ctx: &mut vm::Ctx, segment_index: ElemIndex) {
+    (*ctx.local_backing).dropped_elems.insert(segment_index);
+}
+
+// +*****************************+
+// |           THREADS           |
+// +*****************************+
+
+pub unsafe extern "C" fn memory_atomic_wait32(
+    ctx: &mut vm::Ctx,
+    memory_index: MemoryIndex,
+    offset: u32,
+    addr: u32,
+    expected: u32,
+    timeout: i64,
+) -> u32 {
+    let address = atomic_address(ctx, memory_index, offset, addr, 4) as *const AtomicU32;
+    if !memory_is_shared(ctx, memory_index) {
+        trap_with_message(ctx, EXPECTED_SHARED_MEMORY);
+    }
+
+    wait::wait(
+        address as usize,
+        || (*address).load(Ordering::SeqCst) == expected,
+        wait_timeout(timeout),
+    ) as u32
+}
+
+pub unsafe extern "C" fn memory_atomic_wait64(
+    ctx: &mut vm::Ctx,
+    memory_index: MemoryIndex,
+    offset: u32,
+    addr: u32,
+    expected: u64,
+    timeout: i64,
+) -> u32 {
+    let address = atomic_address(ctx, memory_index, offset, addr, 8) as *const AtomicU64;
+    if !memory_is_shared(ctx, memory_index) {
+        trap_with_message(ctx, EXPECTED_SHARED_MEMORY);
+    }
+
+    wait::wait(
+        address as usize,
+        || (*address).load(Ordering::SeqCst) == expected,
+        wait_timeout(timeout),
+    ) as u32
+}
+
+pub unsafe extern "C" fn memory_atomic_notify(
+    ctx: &mut vm::Ctx,
+    memory_index: MemoryIndex,
+    offset: u32,
+    addr: u32,
+    count: u32,
+) -> u32 {
+    let address = atomic_address(ctx, memory_index, offset, addr, 4);
+
+    // Nothing can be waiting on an unshared memory.
+    if !memory_is_shared(ctx, memory_index) {
+        return 0;
+    }
+
+    wait::notify(address as usize, count)
+}
+
+const EXPECTED_SHARED_MEMORY: &str = "expected shared memory";
+
+/// Computes the host address of an atomic access, trapping if it is misaligned or out of bounds.
+unsafe fn atomic_address(
+    ctx: &vm::Ctx,
+    memory_index: MemoryIndex,
+    offset: u32,
+    addr: u32,
+    size: u32,
+) -> *mut u8 {
+    let local_memory = get_local_memory(ctx, memory_index);
+    let address = addr as u64 + offset as u64;
+
+    if address % size as u64 != 0 {
+        trap(ctx, TrapCode::MisalignedAtomicAccess);
+    }
+    if address + size as u64 > (*local_memory).bound as u64 {
+        trap(ctx, TrapCode::MemoryOutOfBounds);
+    }
+
+    (*local_memory).base.add(address as usize)
+}
+
+unsafe fn memory_is_shared(ctx: &vm::Ctx, memory_index: MemoryIndex) -> bool {
+    let info = &(*ctx.module).info;
+    match memory_index.local_or_import(info) {
+        LocalOrImport::Local(local_memory_index) => info.memories[local_memory_index].shared,
+        LocalOrImport::Import(imported_memory_index) => {
+            info.imported_memories[imported_memory_index].1.shared
+        }
+    }
+}
+
+/// A negative timeout, in nanoseconds, means waiting forever.
+fn wait_timeout(timeout: i64) -> Option<Duration> {
+    if timeout < 0 {
+        None
+    } else {
+        Some(Duration::from_nanos(timeout as u64))
+    }
+}
+
+/// Checks that `[start, start + len)` lies within `[0, bound)`.
+fn in_bounds(start: u32, len: u32, bound: usize) -> bool {
+    start as u64 + len as u64 <= bound as u64
+}
+
+unsafe fn get_local_memory(ctx: &vm::Ctx, memory_index: MemoryIndex) -> *mut vm::LocalMemory {
+    match memory_index.local_or_import(&(*ctx.module).info) {
+        LocalOrImport::Local(local_memory_index) => {
+            *ctx.internal.memories.add(local_memory_index.index())
+        }
+        LocalOrImport::Import(imported_memory_index) => *ctx
+            .internal
+            .imported_memories
+            .add(imported_memory_index.index()),
+    }
+}
+
+unsafe fn trap(ctx: &vm::Ctx, code: TrapCode) -> ! {
+    raise(ctx, RuntimeError::trap(code, code.to_string()))
+}
+
+unsafe fn trap_with_message(ctx: &vm::Ctx, msg: &str) -> ! {
+    raise(ctx, RuntimeError::trap(TrapCode::Other, msg))
+}
+
+unsafe fn raise(ctx: &vm::Ctx, error: RuntimeError) -> ! {
+    (&*ctx.module)
+        .runnable_module
+        .do_early_trap(Box::new(error))
+}
+
+unsafe fn memory_copy(
+    ctx: &vm::Ctx,
+    local_memory: *mut vm::LocalMemory,
+    dst: u32,
+    src: u32,
+    len: u32,
+) {
+    let bound = (*local_memory).bound;
+    if !in_bounds(src, len, bound) || !in_bounds(dst, len, bound) {
+        trap(ctx, TrapCode::MemoryOutOfBounds);
+    }
+
+    let base = (*local_memory).base;
+    ptr::copy(base.add(src as usize), base.add(dst as usize), len as usize);
+}
+
+unsafe fn memory_fill(
+    ctx: &vm::Ctx,
+    local_memory: *mut vm::LocalMemory,
+    dst: u32,
+    value: u32,
+    len: u32,
+) {
+    if !in_bounds(dst, len, (*local_memory).bound) {
+        trap(ctx, TrapCode::MemoryOutOfBounds);
+    }
+
+    ptr::write_bytes(
+        (*local_memory).base.add(dst as usize),
+        value as u8,
+        len as usize,
+    );
+}
+
+unsafe fn get_global(ctx: &vm::Ctx, global_index: GlobalIndex) -> &Global {
+    match global_index.local_or_import(&(*ctx.module).info) {
+        LocalOrImport::Local(local_global_index) => {
+            &(*ctx.local_backing).globals[local_global_index]
+        }
+        LocalOrImport::Import(imported_global_index) => {
+            &(*ctx.import_backing).globals[imported_global_index]
+        }
+    }
+}
+
+unsafe fn get_local_table(ctx: &vm::Ctx, table_index: TableIndex) -> *mut vm::LocalTable {
+    match table_index.local_or_import(&(*ctx.module).info) {
+        LocalOrImport::Local(local_table_index) => {
+            *ctx.internal.tables.add(local_table_index.index())
+        }
+        LocalOrImport::Import(imported_table_index) => *ctx
+            .internal
+            .imported_tables
+            .add(imported_table_index.index()),
+    }
+}
+
+unsafe fn table_element_type(ctx: &vm::Ctx, table_index: TableIndex) -> ElementType {
+    let info = &(*ctx.module).info;
+    match table_index.local_or_import(info) {
+        LocalOrImport::Local(local_table_index) => info.tables[local_table_index].element,
+        LocalOrImport::Import(imported_table_index) => {
+            info.imported_tables[imported_table_index].1.element
+        }
+    }
+}
+
+/// Converts a function out of an `anyfunc` table into a reference rooted in this instance.
+unsafe fn funcref_to_raw(ctx: &mut vm::Ctx, anyfunc: vm::Anyfunc) -> u64 {
+    if anyfunc.func.is_null() {
+        return 0;
+    }
+    let local_backing = &mut *ctx.local_backing;
+    local_backing
+        .funcrefs
+        .entry((anyfunc.func as usize, anyfunc.ctx as usize))
+        .or_insert_with(|| AnyRef::new(FuncRef(anyfunc)))
+        .as_raw()
+}
+
+unsafe fn raw_to_anyfunc(ctx: &vm::Ctx, raw: u64) -> vm::Anyfunc {
+    let r = AnyRef::from_raw(raw);
+    if r.is_null() {
+        return vm::Anyfunc::null();
+    }
+    match r.downcast_ref::<FuncRef>() {
+        Some(&FuncRef(anyfunc)) => anyfunc,
+        None => trap_with_message(ctx, "reference is not a function"),
+    }
+}
+
+unsafe fn get_anyfunc(ctx: &mut vm::Ctx, func_index: FuncIndex) -> vm::Anyfunc {
+    let info = &(*ctx.module).info;
+    let signature = SigRegistry.lookup_signature_ref(&info.signatures[info.func_assoc[func_index]]);
+    let sig_id = vm::SigId(SigRegistry.lookup_sig_index(signature).index() as u32);
+
+    let (func, func_ctx) = match func_index.local_or_import(info) {
+        LocalOrImport::Local(local_func_index) => (
+            *ctx.local_functions.add(local_func_index.index()),
+            ctx as *mut vm::Ctx,
+        ),
+        LocalOrImport::Import(imported_func_index) => {
+            let imported_func = &*ctx.internal.imported_funcs.add(imported_func_index.index());
+            (
+                imported_func.func,
+                imported_func.func_ctx.as_ref().vmctx.as_ptr(),
+            )
+        }
+    };
+
+    vm::Anyfunc {
+        func,
+        ctx: func_ctx,
+        sig_id,
+    }
 }
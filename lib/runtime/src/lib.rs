@@ -96,7 +96,7 @@ pub use wasmer_runtime_core::codegen::{MiddlewareChain, StreamingCompiler};
 pub use wasmer_runtime_core::export::Export;
 pub use wasmer_runtime_core::global::Global;
 pub use wasmer_runtime_core::import::{ImportObject, LikeNamespace};
-pub use wasmer_runtime_core::instance::{DynFunc, Instance};
+pub use wasmer_runtime_core::instance::{DynFunc, Instance, InstanceSpawner};
 pub use wasmer_runtime_core::memory::ptr::{Array, Item, WasmPtr};
 pub use wasmer_runtime_core::memory::Memory;
 pub use wasmer_runtime_core::module::Module;
@@ -104,6 +104,8 @@ pub use wasmer_runtime_core::table::Table;
 pub use wasmer_runtime_core::types::Value;
 pub use wasmer_runtime_core::vm::Ctx;
 
+#[cfg(all(unix, target_arch = "x86_64"))]
+pub use wasmer_runtime_core::DynamicFunc;
 pub use wasmer_runtime_core::Func;
 pub use wasmer_runtime_core::{compile_with, validate};
 pub use wasmer_runtime_core::{func, imports};
@@ -117,7 +119,7 @@ pub mod memory {
 pub mod wasm {
     //! Various types exposed by the Wasmer Runtime.
     pub use wasmer_runtime_core::global::Global;
-    pub use wasmer_runtime_core::table::Table;
+    pub use wasmer_runtime_core::table::{AnyRef, Table};
     pub use wasmer_runtime_core::types::{
         FuncSig, GlobalDescriptor, MemoryDescriptor, TableDescriptor, Type, Value,
     };
@@ -141,6 +143,7 @@ pub mod types {
 }
 
 pub mod cache;
+pub mod linker;
 
 pub use wasmer_runtime_core::backend::{Compiler, CompilerConfig};
 
@@ -0,0 +1,311 @@
+//! Links several modules together, resolving the imports of each module from the exports of
+//! the others.
+//!
+//! A [`Linker`] is given named modules and host namespaces. An import whose namespace is the
+//! name of a module is resolved from the exports of that module's instance, so a module
+//! importing a memory or a table shares it with the module exporting it. Other imports are
+//! resolved from the host namespaces. Every import is checked before anything is
+//! instantiated, and the modules are then instantiated in dependency order.
+//!
+//! ```
+//! # use wasmer_runtime::{error, linker::Linker, Instance, Module};
+//! # fn link(host: Instance, libc: Module, plugin: Module) -> error::Result<()> {
+//! let mut linker = Linker::new();
+//! linker
+//!     .namespace("env", host)
+//!     .module("libc", libc)
+//!     .module("plugin", plugin);
+//! let instances = linker.instantiate()?;
+//! let plugin = instances["plugin"].lock().unwrap();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Linker`]: struct.Linker.html
+
+use crate::{
+    error::{Error, LinkError, Result},
+    Export, ImportObject, Instance, LikeNamespace, Module,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use wasmer_runtime_core::module::ExportIndex;
+
+/// Resolves imports between named modules and host namespaces.
+pub struct Linker {
+    modules: Vec<(String, Module)>,
+    host: ImportObject,
+}
+
+impl Linker {
+    /// Creates a linker without modules or host namespaces.
+    pub fn new() -> Self {
+        Linker {
+            modules: vec![],
+            host: ImportObject::new(),
+        }
+    }
+
+    /// Adds `module` under `name`. Imports from the namespace `name` are resolved from the
+    /// exports of its instance. A module previously added under the same name is replaced.
+    pub fn module<S: Into<String>>(&mut self, name: S, module: Module) -> &mut Self {
+        let name = name.into();
+        match self.modules.iter_mut().find(|(n, _)| *n == name) {
+            Some(entry) => entry.1 = module,
+            None => self.modules.push((name, module)),
+        }
+        self
+    }
+
+    /// Adds a host namespace under `name`, e.g. a `Namespace` or an already instantiated
+    /// `Instance`. A namespace previously added under the same name is replaced.
+    pub fn namespace<S, N>(&mut self, name: S, namespace: N) -> &mut Self
+    where
+        S: Into<String>,
+        N: LikeNamespace + Send + 'static,
+    {
+        self.host.register(name, namespace);
+        self
+    }
+
+    /// Instantiates every module, each after the modules it imports from, and returns the
+    /// instances by module name.
+    ///
+    /// # Errors
+    /// If an import is missing or has the wrong type, if modules import from each other in
+    /// a cycle, or if a module has the same name as a host namespace, nothing is
+    /// instantiated and all of these are returned together as `Error::LinkError`.
+    pub fn instantiate(&self) -> Result<HashMap<String, Arc<Mutex<Instance>>>> {
+        let mut link_errors = vec![];
+        let mut namespaces = Vec::with_capacity(self.modules.len());
+        for (name, module) in &self.modules {
+            if self.is_host_namespace(name) {
+                link_errors.push(LinkError::Generic {
+                    message: format!(
+                        "`{}` is the name of both a module and a host namespace",
+                        name
+                    ),
+                });
+            }
+            namespaces.push(self.check_imports(module, &mut link_errors));
+        }
+
+        let dependencies: Vec<Vec<usize>> = namespaces
+            .iter()
+            .map(|names| names.iter().filter_map(|n| self.module_index(n)).collect())
+            .collect();
+
+        let order = match self.instantiation_order(&dependencies) {
+            Ok(order) => order,
+            Err(cycle) => {
+                link_errors.push(LinkError::Generic {
+                    message: format!("modules import from each other in a cycle: {}", cycle),
+                });
+                vec![]
+            }
+        };
+        if !link_errors.is_empty() {
+            return Err(Error::LinkError(link_errors));
+        }
+
+        let mut instances: HashMap<String, Arc<Mutex<Instance>>> =
+            HashMap::with_capacity(self.modules.len());
+        for index in order {
+            let (name, module) = &self.modules[index];
+            let mut imports = ImportObject::new();
+            for &namespace in &namespaces[index] {
+                if let Some(instance) = instances.get(namespace) {
+                    imports.register(namespace, Arc::clone(instance));
+                } else if self.is_host_namespace(namespace) {
+                    imports.register(
+                        namespace,
+                        HostNamespace {
+                            imports: self.host.clone_ref(),
+                            namespace: namespace.to_string(),
+                        },
+                    );
+                }
+            }
+            let instance = module.instantiate(&imports)?;
+            instances.insert(name.clone(), Arc::new(Mutex::new(instance)));
+        }
+        Ok(instances)
+    }
+
+    fn is_host_namespace(&self, namespace: &str) -> bool {
+        self.host.with_namespace(namespace, |_| ()).is_some()
+    }
+
+    fn module_index(&self, name: &str) -> Option<usize> {
+        self.modules.iter().position(|(n, _)| n == name)
+    }
+
+    /// Checks that every import of `module` is provided with the right type, and returns
+    /// the namespaces it imports from.
+    fn check_imports<'a>(
+        &self,
+        module: &'a Module,
+        link_errors: &mut Vec<LinkError>,
+    ) -> Vec<&'a str> {
+        let info = module.info();
+        let functions = info.imported_functions.iter().map(|(index, name)| {
+            let sig_index = info.func_assoc[index.convert_up(info)];
+            (name, "function", Some(&info.signatures[sig_index]))
+        });
+        let memories = info
+            .imported_memories
+            .iter()
+            .map(|(_, (n, _))| (n, "memory", None));
+        let tables = info
+            .imported_tables
+            .iter()
+            .map(|(_, (n, _))| (n, "table", None));
+        let globals = info
+            .imported_globals
+            .iter()
+            .map(|(_, (n, _))| (n, "global", None));
+
+        let mut namespaces = vec![];
+        for (import_name, expected_type, expected_sig) in
+            functions.chain(memories).chain(tables).chain(globals)
+        {
+            let namespace = info.namespace_table.get(import_name.namespace_index);
+            let name = info.name_table.get(import_name.name_index);
+
+            if !namespaces.contains(&namespace) {
+                namespaces.push(namespace);
+            }
+
+            let found = if let Some(index) = self.module_index(namespace) {
+                let provider = self.modules[index].1.info();
+                provider.exports.get(name).map(|export| match export {
+                    ExportIndex::Func(func_index) => {
+                        let sig_index = provider.func_assoc[*func_index];
+                        ("function", Some(provider.signatures[sig_index].clone()))
+                    }
+                    ExportIndex::Memory(_) => ("memory", None),
+                    ExportIndex::Table(_) => ("table", None),
+                    ExportIndex::Global(_) => ("global", None),
+                })
+            } else {
+                let export = self
+                    .host
+                    .maybe_with_namespace(namespace, |ns| ns.get_export(name));
+                export.map(|export| match export {
+                    Export::Function { signature, .. } => ("function", Some((*signature).clone())),
+                    Export::Memory(_) => ("memory", None),
+                    Export::Table(_) => ("table", None),
+                    Export::Global(_) => ("global", None),
+                })
+            };
+
+            match (found, expected_sig) {
+                (None, _) => link_errors.push(LinkError::ImportNotFound {
+                    namespace: namespace.to_string(),
+                    name: name.to_string(),
+                }),
+                (Some((found_type, _)), _) if found_type != expected_type => {
+                    link_errors.push(LinkError::IncorrectImportType {
+                        namespace: namespace.to_string(),
+                        name: name.to_string(),
+                        expected: expected_type.to_string(),
+                        found: found_type.to_string(),
+                    })
+                }
+                (Some((_, Some(ref found_sig))), Some(expected_sig))
+                    if found_sig != expected_sig =>
+                {
+                    link_errors.push(LinkError::IncorrectImportSignature {
+                        namespace: namespace.to_string(),
+                        name: name.to_string(),
+                        expected: expected_sig.clone(),
+                        found: found_sig.clone(),
+                    })
+                }
+                _ => {}
+            }
+        }
+        namespaces
+    }
+
+    /// Orders the modules so that each comes after those it imports from, keeping the order
+    /// in which they were added otherwise. On a cycle, returns the names along it.
+    fn instantiation_order(
+        &self,
+        dependencies: &[Vec<usize>],
+    ) -> ::std::result::Result<Vec<usize>, String> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            Unvisited,
+            InProgress,
+            Done,
+        }
+
+        fn visit(
+            index: usize,
+            dependencies: &[Vec<usize>],
+            marks: &mut [Mark],
+            path: &mut Vec<usize>,
+            order: &mut Vec<usize>,
+        ) -> ::std::result::Result<(), Vec<usize>> {
+            match marks[index] {
+                Mark::Done => return Ok(()),
+                Mark::InProgress => {
+                    let start = path.iter().position(|&i| i == index).unwrap();
+                    let mut cycle = path[start..].to_vec();
+                    cycle.push(index);
+                    return Err(cycle);
+                }
+                Mark::Unvisited => {}
+            }
+            marks[index] = Mark::InProgress;
+            path.push(index);
+            for &dependency in &dependencies[index] {
+                visit(dependency, dependencies, marks, path, order)?;
+            }
+            path.pop();
+            marks[index] = Mark::Done;
+            order.push(index);
+            Ok(())
+        }
+
+        let mut marks = vec![Mark::Unvisited; dependencies.len()];
+        let mut order = Vec::with_capacity(dependencies.len());
+        for index in 0..dependencies.len() {
+            visit(index, dependencies, &mut marks, &mut vec![], &mut order).map_err(|cycle| {
+                let names: Vec<&str> = cycle.iter().map(|&i| &*self.modules[i].0).collect();
+                names.join(" -> ")
+            })?;
+        }
+        Ok(order)
+    }
+}
+
+impl Default for Linker {
+    fn default() -> Self {
+        Linker::new()
+    }
+}
+
+/// A host namespace, looked up in the shared host imports of a `Linker`.
+struct HostNamespace {
+    imports: ImportObject,
+    namespace: String,
+}
+
+impl LikeNamespace for HostNamespace {
+    fn get_export(&self, name: &str) -> Option<Export> {
+        self.imports
+            .maybe_with_namespace(&self.namespace, |ns| ns.get_export(name))
+    }
+
+    fn get_exports(&self) -> Vec<(String, Export)> {
+        self.imports
+            .with_namespace(&self.namespace, |ns| ns.get_exports())
+            .unwrap_or_default()
+    }
+
+    fn maybe_insert(&mut self, _name: &str, _export: Export) -> Option<()> {
+        None
+    }
+}
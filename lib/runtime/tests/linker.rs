@@ -0,0 +1,90 @@
+use wabt::wat2wasm;
+use wasmer_runtime::{compile, error::Error, func, linker::Linker, Func};
+use wasmer_runtime_core::import::Namespace;
+
+static LIBC: &str = r#"
+(module
+  (import "env" "print" (func $print (param i32)))
+  (memory (export "memory") 1)
+  (func (export "store") (param i32 i32)
+    get_local 0
+    get_local 1
+    i32.store)
+  (func (export "puts") (param i32)
+    get_local 0
+    call $print))
+"#;
+
+static PLUGIN: &str = r#"
+(module
+  (import "libc" "memory" (memory 1))
+  (import "libc" "store" (func $store (param i32 i32)))
+  (import "libc" "puts" (func $puts (param i32)))
+  (func (export "run") (result i32)
+    i32.const 16
+    i32.const 42
+    call $store
+    i32.const 7
+    call $puts
+    i32.const 16
+    i32.load))
+"#;
+
+#[test]
+fn links_modules_in_dependency_order() {
+    let libc = compile(&wat2wasm(LIBC).unwrap()).unwrap();
+    let plugin = compile(&wat2wasm(PLUGIN).unwrap()).unwrap();
+
+    let mut env = Namespace::new();
+    env.insert("print", func!(|_: i32| {}));
+
+    let mut linker = Linker::new();
+    // The plugin is added first but depends on libc.
+    linker
+        .module("plugin", plugin)
+        .module("libc", libc)
+        .namespace("env", env);
+    let instances = linker.instantiate().unwrap();
+
+    let plugin = instances["plugin"].lock().unwrap();
+    let run: Func<(), i32> = plugin.func("run").unwrap();
+    assert_eq!(run.call().unwrap(), 42);
+
+    // The memory is shared with libc rather than copied.
+    let libc = instances["libc"].lock().unwrap();
+    let memory = libc.context().memory(0);
+    assert_eq!(memory.view::<u32>()[4].get(), 42);
+}
+
+#[test]
+fn reports_all_link_errors_together() {
+    let libc = compile(&wat2wasm(LIBC).unwrap()).unwrap();
+    let plugin = compile(&wat2wasm(PLUGIN).unwrap()).unwrap();
+    let cyclic = compile(
+        &wat2wasm(
+            r#"
+            (module
+              (import "cyclic" "f" (func))
+              (func (export "f")))
+            "#,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    // `env` is missing, and `cyclic` imports from itself.
+    let mut linker = Linker::new();
+    linker
+        .module("libc", libc)
+        .module("plugin", plugin)
+        .module("cyclic", cyclic);
+    match linker.instantiate() {
+        Err(Error::LinkError(errors)) => {
+            let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            assert_eq!(messages.len(), 2, "{:?}", messages);
+            assert!(messages[0].contains("namespace: env, name: print"));
+            assert!(messages[1].contains("cyclic -> cyclic"));
+        }
+        other => panic!("expected link errors, got {:?}", other.map(|_| ())),
+    }
+}
@@ -216,16 +216,27 @@ pub struct X64FunctionCode {
     >,
     returns: SmallVec<[WpType; 1]>,
     locals: Vec<Location>,
+    /// Where to store the results of this function, if it has several.
+    return_area: Option<Location>,
+    /// Slots used to move several values to a branch target or out of a call.
+    scratch: Vec<Location>,
+    /// Holds the address of `scratch`, passed as the return area of calls.
+    scratch_pointer: Option<Location>,
     num_params: usize,
     num_locals: usize,
     value_stack: Vec<Location>,
     control_stack: Vec<ControlFrame>,
     machine: Machine,
     unreachable_depth: usize,
+    /// The offset in the wasm binary of the operator being translated.
+    binary_offset: Option<usize>,
 
     config: Arc<CodegenConfig>,
 }
 
+/// Set in the environment of `invoke` for functions that take a return area.
+const INVOKE_RETURN_AREA: usize = 1 << (mem::size_of::<usize>() * 8 - 1);
+
 enum FuncPtrInner {}
 #[repr(transparent)]
 #[derive(Copy, Clone, Debug)]
@@ -271,8 +282,10 @@ pub struct ControlFrame {
     pub label: DynamicLabel,
     pub loop_like: bool,
     pub if_else: IfElseState,
+    pub params: SmallVec<[WpType; 1]>,
     pub returns: SmallVec<[WpType; 1]>,
     pub value_stack_depth: usize,
+    pub base_stack_depth: usize,
     pub state: MachineState,
     pub state_diff_id: usize,
 }
@@ -322,6 +335,10 @@ impl RunnableModule for X64ExecutionContext {
         Some(self.breakpoints.clone())
     }
 
+    fn supports_interrupts(&self) -> bool {
+        true
+    }
+
     unsafe fn patch_local_function(&self, idx: usize, target_address: usize) -> bool {
         /*
         0:       48 b8 42 42 42 42 42 42 42 42   movabsq $4774451407313060418, %rax
@@ -369,8 +386,14 @@ impl RunnableModule for X64ExecutionContext {
         ) -> bool {
             let rm: &Box<dyn RunnableModule> = &(&*(*ctx).module).runnable_module;
 
-            let args =
-                slice::from_raw_parts(args, num_params_plus_one.unwrap().as_ptr() as usize - 1);
+            let env = num_params_plus_one.unwrap().as_ptr() as usize;
+            let has_return_area = env & INVOKE_RETURN_AREA != 0;
+            let args = slice::from_raw_parts(args, (env & !INVOKE_RETURN_AREA) - 1);
+            let mut args: SmallVec<[u64; 8]> = args.iter().cloned().collect();
+            if has_return_area {
+                // Results are written directly to `rets`.
+                args.push(rets as u64);
+            }
 
             let ret = match protect_unix::call_protected(
                 || {
@@ -502,7 +525,7 @@ impl RunnableModule for X64ExecutionContext {
                 rm.get_breakpoints(),
             ) {
                 Ok(x) => {
-                    if !rets.is_null() {
+                    if !rets.is_null() && !has_return_area {
                         *rets = x;
                     }
                     true
@@ -527,13 +550,13 @@ impl RunnableModule for X64ExecutionContext {
             unreachable!()
         }
 
-        Some(unsafe {
-            Wasm::from_raw_parts(
-                dummy_trampoline,
-                invoke,
-                NonNull::new((self.signatures.get(sig_index).unwrap().params().len() + 1) as _), // +1 to keep it non-zero
-            )
-        })
+        let sig = self.signatures.get(sig_index).unwrap();
+        let mut env = sig.params().len() + 1; // +1 to keep it non-zero
+        if sig.has_return_area() {
+            env |= INVOKE_RETURN_AREA;
+        }
+
+        Some(unsafe { Wasm::from_raw_parts(dummy_trampoline, invoke, NonNull::new(env as _)) })
     }
 
     unsafe fn do_early_trap(&self, data: Box<dyn Any + Send>) -> ! {
@@ -700,12 +723,16 @@ impl ModuleCodeGenerator<X64FunctionCode, X64ExecutionContext, CodegenError>
             breakpoints: Some(breakpoints),
             returns: smallvec![],
             locals: vec![],
+            return_area: None,
+            scratch: vec![],
+            scratch_pointer: None,
             num_params: 0,
             num_locals: 0,
             value_stack: vec![],
             control_stack: vec![],
             machine,
             unreachable_depth: 0,
+            binary_offset: None,
             config: self.config.as_ref().unwrap().clone(),
         };
         self.functions.push(code);
@@ -1858,6 +1885,62 @@ impl X64FunctionCode {
         Ok(())
     }
 
+    /// Emits a call to the vm intrinsic found at `intrinsic_offset` in `vm::Intrinsics`.
+    ///
+    /// The immediate parameters are passed first, followed by the top `n_stack_params`
+    /// values of the value stack, which are popped.
+    fn emit_call_intrinsic(
+        a: &mut Assembler,
+        m: &mut Machine,
+        value_stack: &mut Vec<Location>,
+        intrinsic_offset: u8,
+        imm_params: &[u32],
+        n_stack_params: usize,
+    ) -> Result<(), CodegenError> {
+        let params: SmallVec<[_; 8]> = value_stack
+            .drain(value_stack.len() - n_stack_params..)
+            .collect();
+        m.release_locations_only_regs(&params);
+
+        a.emit_mov(
+            Size::S64,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                vm::Ctx::offset_intrinsics() as i32,
+            ),
+            Location::GPR(GPR::RAX),
+        );
+        a.emit_mov(
+            Size::S64,
+            Location::Memory(GPR::RAX, intrinsic_offset as i32),
+            Location::GPR(GPR::RAX),
+        );
+
+        m.release_locations_only_osr_state(params.len());
+
+        Self::emit_call_sysv(
+            a,
+            m,
+            |a| {
+                let label = a.get_label();
+                let after = a.get_label();
+                a.emit_jmp(Condition::None, after);
+                a.emit_label(label);
+                a.emit_host_redirection(GPR::RAX);
+                a.emit_label(after);
+                a.emit_call_label(label);
+            },
+            imm_params
+                .iter()
+                .map(|&x| Location::Imm32(x))
+                .chain(params.iter().cloned()),
+            None,
+        )?;
+
+        m.release_locations_only_stack(a, &params);
+        Ok(())
+    }
+
     /// Emits a System V call sequence, specialized for labels as the call target.
     fn emit_call_sysv_label<I: Iterator<Item = Location>>(
         a: &mut Assembler,
@@ -1870,6 +1953,128 @@ impl X64FunctionCode {
         Ok(())
     }
 
+    /// Resolves the parameter and result types of a block.
+    fn block_signature(
+        signatures: &Map<SigIndex, FuncSig>,
+        ty: WpTypeOrFuncType,
+    ) -> (SmallVec<[WpType; 1]>, SmallVec<[WpType; 1]>) {
+        match ty {
+            WpTypeOrFuncType::Type(WpType::EmptyBlockType) => (smallvec![], smallvec![]),
+            WpTypeOrFuncType::Type(inner_ty) => (smallvec![], smallvec![inner_ty]),
+            WpTypeOrFuncType::FuncType(index) => {
+                let sig = signatures.get(SigIndex::new(index as usize)).unwrap();
+                (
+                    sig.params().iter().cloned().map(type_to_wp_type).collect(),
+                    sig.returns().iter().cloned().map(type_to_wp_type).collect(),
+                )
+            }
+        }
+    }
+
+    /// Pushes copies of the parameters of a block, keeping the originals
+    /// below the block so that they can be used again.
+    fn emit_copy_params(
+        a: &mut Assembler,
+        m: &mut Machine,
+        params: &[WpType],
+        value_stack: &mut Vec<Location>,
+    ) {
+        let depth = value_stack.len();
+        let copies = m.acquire_locations(
+            a,
+            &params
+                .iter()
+                .enumerate()
+                .map(|(i, &ty)| (ty, MachineValue::WasmStack(depth + i)))
+                .collect::<SmallVec<[_; 8]>>(),
+            false,
+        );
+        for (i, &loc) in copies.iter().enumerate() {
+            let original = value_stack[depth - params.len() + i];
+            Self::emit_relaxed_binop(a, m, Assembler::emit_mov, Size::S64, original, loc);
+        }
+        value_stack.extend(copies);
+    }
+
+    /// Moves values leaving a block, or a function call, to where the
+    /// receiving side loads them from: a single value goes through RAX and
+    /// several go through the scratch slots.
+    fn emit_store_results(
+        a: &mut Assembler,
+        m: &mut Machine,
+        values: &[Location],
+        scratch: &[Location],
+    ) {
+        if values.len() == 1 {
+            Self::emit_relaxed_binop(
+                a,
+                m,
+                Assembler::emit_mov,
+                Size::S64,
+                values[0],
+                Location::GPR(GPR::RAX),
+            );
+        } else {
+            for (&value, &slot) in values.iter().zip(scratch) {
+                Self::emit_relaxed_binop(a, m, Assembler::emit_mov, Size::S64, value, slot);
+            }
+        }
+    }
+
+    /// Pushes the values stored by `emit_store_results`.
+    fn emit_load_results(
+        a: &mut Assembler,
+        m: &mut Machine,
+        returns: &[WpType],
+        value_stack: &mut Vec<Location>,
+        scratch: &[Location],
+    ) {
+        let depth = value_stack.len();
+        let locs = m.acquire_locations(
+            a,
+            &returns
+                .iter()
+                .enumerate()
+                .map(|(i, &ty)| (ty, MachineValue::WasmStack(depth + i)))
+                .collect::<SmallVec<[_; 8]>>(),
+            false,
+        );
+        if locs.len() == 1 {
+            a.emit_mov(Size::S64, Location::GPR(GPR::RAX), locs[0]);
+        } else {
+            for (&slot, &loc) in scratch.iter().zip(locs.iter()) {
+                Self::emit_relaxed_binop(a, m, Assembler::emit_mov, Size::S64, slot, loc);
+            }
+        }
+        value_stack.extend(locs);
+    }
+
+    /// Passes the values a branch carries to `frame` and jumps to it.
+    fn emit_branch(
+        a: &mut Assembler,
+        m: &mut Machine,
+        value_stack: &[Location],
+        frame: &ControlFrame,
+        scratch: &[Location],
+    ) {
+        if frame.loop_like {
+            // The parameters of a loop are written back to the originals,
+            // which are copied again at the top of the loop.
+            let n = frame.params.len();
+            let values = &value_stack[value_stack.len() - n..];
+            let params = &value_stack[frame.value_stack_depth - n..frame.value_stack_depth];
+            for (&value, &param) in values.iter().zip(params) {
+                Self::emit_relaxed_binop(a, m, Assembler::emit_mov, Size::S64, value, param);
+            }
+        } else if frame.returns.len() > 0 {
+            let values = &value_stack[value_stack.len() - frame.returns.len()..];
+            Self::emit_store_results(a, m, values, scratch);
+        }
+        let released = &value_stack[frame.value_stack_depth..];
+        m.release_locations_keep_state(a, released);
+        a.emit_jmp(Condition::None, frame.label);
+    }
+
     /// Emits a memory operation.
     fn emit_memory_op<F: FnOnce(&mut Assembler, &mut Machine, GPR) -> Result<(), CodegenError>>(
         module_info: &ModuleInfo,
@@ -1914,14 +2119,38 @@ impl X64FunctionCode {
         if need_check {
             let tmp_bound = m.acquire_temp_gpr().unwrap();
 
-            a.emit_mov(
-                Size::S64,
-                Location::Memory(
-                    Machine::get_vmctx_reg(),
-                    vm::Ctx::offset_memory_bound() as i32,
-                ),
-                Location::GPR(tmp_bound),
-            );
+            if mem_desc.shared {
+                // Another instance may have grown a shared memory since the bound was cached in
+                // our context, so read it from the `LocalMemory` all the sharers point to.
+                let offset_memories = match MemoryIndex::new(0).local_or_import(module_info) {
+                    LocalOrImport::Local(_) => vm::Ctx::offset_memories(),
+                    LocalOrImport::Import(_) => vm::Ctx::offset_imported_memories(),
+                };
+                a.emit_mov(
+                    Size::S64,
+                    Location::Memory(Machine::get_vmctx_reg(), offset_memories as i32),
+                    Location::GPR(tmp_bound),
+                );
+                a.emit_mov(
+                    Size::S64,
+                    Location::Memory(tmp_bound, 0),
+                    Location::GPR(tmp_bound),
+                );
+                a.emit_mov(
+                    Size::S64,
+                    Location::Memory(tmp_bound, vm::LocalMemory::offset_bound() as i32),
+                    Location::GPR(tmp_bound),
+                );
+            } else {
+                a.emit_mov(
+                    Size::S64,
+                    Location::Memory(
+                        Machine::get_vmctx_reg(),
+                        vm::Ctx::offset_memory_bound() as i32,
+                    ),
+                    Location::GPR(tmp_bound),
+                );
+            }
             // Adds base to bound so `tmp_bound` now holds the end of linear memory.
             a.emit_add(Size::S64, Location::GPR(tmp_base), Location::GPR(tmp_bound));
             a.emit_mov(Size::S32, addr, Location::GPR(tmp_addr));
@@ -1949,7 +2178,7 @@ impl X64FunctionCode {
             // Trap if the end address of the requested area is above that of the linear memory.
             a.emit_add(Size::S64, Location::GPR(tmp_base), Location::GPR(tmp_addr));
             a.emit_cmp(Size::S64, Location::GPR(tmp_bound), Location::GPR(tmp_addr));
-            a.emit_conditional_trap(Condition::Above);
+            a.emit_conditional_trap(Condition::Above, WasmTrapInfo::MemoryOutOfBounds);
 
             m.release_temp_gpr(tmp_bound);
         }
@@ -1989,7 +2218,7 @@ impl X64FunctionCode {
                 Location::Imm32(align - 1),
                 Location::GPR(tmp_aligncheck),
             );
-            a.emit_conditional_trap(Condition::NotEqual);
+            a.emit_conditional_trap(Condition::NotEqual, WasmTrapInfo::MisalignedAtomicAccess);
             m.release_temp_gpr(tmp_aligncheck);
         }
 
@@ -2117,7 +2346,7 @@ impl X64FunctionCode {
 
         Self::emit_f32_int_conv_check(a, m, reg, lower_bound, upper_bound, trap, trap, trap, end);
         a.emit_label(trap);
-        a.emit_ud2();
+        a.emit_trap(WasmTrapInfo::BadConversionToInteger);
         a.emit_label(end);
     }
 
@@ -2241,7 +2470,7 @@ impl X64FunctionCode {
 
         Self::emit_f64_int_conv_check(a, m, reg, lower_bound, upper_bound, trap, trap, trap, end);
         a.emit_label(trap);
-        a.emit_ud2();
+        a.emit_trap(WasmTrapInfo::BadConversionToInteger);
         a.emit_label(end);
     }
 
@@ -2365,12 +2594,47 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                 ),
                 Location::GPR(GPR::RSP),
             );
-            a.emit_conditional_trap(Condition::Below);
+            a.emit_conditional_trap(Condition::Below, WasmTrapInfo::StackOverflow);
         }
 
-        self.locals = self
+        let has_return_area = self.returns.len() > 1;
+        let num_scratch = self
+            .signatures
+            .iter()
+            .map(|(_, sig)| sig.returns().len())
+            .filter(|&n| n > 1)
+            .max()
+            .unwrap_or(0);
+        let num_extra =
+            has_return_area as usize + if num_scratch > 0 { num_scratch + 1 } else { 0 };
+
+        let mut locals = self
             .machine
-            .init_locals(a, self.num_locals, self.num_params);
+            .init_locals(a, self.num_locals, self.num_params, num_extra);
+        let mut extra = locals.split_off(self.num_locals).into_iter();
+        self.locals = locals;
+
+        if has_return_area {
+            // The return area is passed after all the wasm parameters.
+            let loc = extra.next().unwrap();
+            Self::emit_relaxed_binop(
+                a,
+                &mut self.machine,
+                Assembler::emit_mov,
+                Size::S64,
+                Machine::get_param_location(self.num_params + 1),
+                loc,
+            );
+            self.return_area = Some(loc);
+        }
+        if num_scratch > 0 {
+            let pointer = extra.next().unwrap();
+            // Extra slots are allocated downwards.
+            self.scratch = extra.rev().collect();
+            a.emit_lea(Size::S64, self.scratch[0], Location::GPR(GPR::RAX));
+            a.emit_mov(Size::S64, Location::GPR(GPR::RAX), pointer);
+            self.scratch_pointer = Some(pointer);
+        }
 
         self.machine.state.register_values
             [X64Register::GPR(Machine::get_vmctx_reg()).to_index().0] = MachineValue::Vmctx;
@@ -2396,8 +2660,10 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
             label: a.get_label(),
             loop_like: false,
             if_else: IfElseState::None,
+            params: smallvec![],
             returns: self.returns.clone(),
             value_stack_depth: 0,
+            base_stack_depth: 0,
             state: self.machine.state.clone(),
             state_diff_id,
         });
@@ -2442,6 +2708,10 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
         Ok(())
     }
 
+    fn set_binary_offset(&mut self, offset: Option<usize>) {
+        self.binary_offset = offset;
+    }
+
     fn feed_event(&mut self, ev: Event, module_info: &ModuleInfo) -> Result<(), CodegenError> {
         let a = self.assembler.as_mut().unwrap();
 
@@ -2454,6 +2724,11 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
         }
 
         self.machine.state.wasm_inst_offset = self.machine.state.wasm_inst_offset.wrapping_add(1);
+        if let Some(offset) = self.binary_offset {
+            self.fsm
+                .wasm_offset_to_binary_offset
+                .insert(self.machine.state.wasm_inst_offset, offset);
+        }
 
         //println!("{:?} {}", op, self.value_stack.len());
         let was_unreachable;
@@ -2582,6 +2857,32 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                             Location::Memory(tmp, (idx * 8) as i32),
                         );
                         self.machine.release_temp_gpr(tmp);
+                    }
+                    InternalEvent::IncrementCounter(idx) => {
+                        let offset = idx as usize * 8;
+                        if offset > i32::max_value() as usize {
+                            return Err(CodegenError {
+                                message: format!("IncrementCounter: incorrect index value"),
+                            });
+                        }
+
+                        let tmp = self.machine.acquire_temp_gpr().unwrap();
+
+                        // Load `counters` pointer.
+                        a.emit_mov(
+                            Size::S64,
+                            Location::Memory(
+                                Machine::get_vmctx_reg(),
+                                vm::Ctx::offset_counters() as i32,
+                            ),
+                            Location::GPR(tmp),
+                        );
+                        a.emit_add(
+                            Size::S64,
+                            Location::Imm32(1),
+                            Location::Memory(tmp, offset as i32),
+                        );
+                        self.machine.release_temp_gpr(tmp);
                     } //_ => unimplemented!(),
                 }
                 return Ok(());
@@ -2589,6 +2890,43 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
         };
 
         match *op {
+            Operator::GlobalGet { global_index }
+                if module_info
+                    .global_desc(GlobalIndex::new(global_index as usize))
+                    .ty
+                    .is_reference() =>
+            {
+                Self::emit_call_intrinsic(
+                    a,
+                    &mut self.machine,
+                    &mut self.value_stack,
+                    vm::Intrinsics::offset_global_get_ref(),
+                    &[global_index],
+                    0,
+                )?;
+                let ret = self.machine.acquire_locations(
+                    a,
+                    &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
+                    false,
+                )[0];
+                self.value_stack.push(ret);
+                a.emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
+            }
+            Operator::GlobalSet { global_index }
+                if module_info
+                    .global_desc(GlobalIndex::new(global_index as usize))
+                    .ty
+                    .is_reference() =>
+            {
+                Self::emit_call_intrinsic(
+                    a,
+                    &mut self.machine,
+                    &mut self.value_stack,
+                    vm::Intrinsics::offset_global_set_ref(),
+                    &[global_index],
+                    1,
+                )?;
+            }
             Operator::GlobalGet { global_index } => {
                 let global_index = global_index as usize;
 
@@ -6135,35 +6473,31 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
 
                 self.machine.release_locations_only_osr_state(params.len());
 
+                let return_area = if return_types.len() > 1 {
+                    self.scratch_pointer
+                } else {
+                    None
+                };
+
                 Self::emit_call_sysv_label(
                     a,
                     &mut self.machine,
                     label,
-                    params.iter().map(|x| *x),
+                    params.iter().map(|x| *x).chain(return_area),
                     Some((&mut self.fsm, &mut self.control_stack)),
                 )?;
 
                 self.machine.release_locations_only_stack(a, &params);
 
-                if return_types.len() > 0 {
-                    let ret = self.machine.acquire_locations(
-                        a,
-                        &[(
-                            return_types[0],
-                            MachineValue::WasmStack(self.value_stack.len()),
-                        )],
-                        false,
-                    )[0];
-                    self.value_stack.push(ret);
-                    a.emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
-                }
+                Self::emit_load_results(
+                    a,
+                    &mut self.machine,
+                    &return_types,
+                    &mut self.value_stack,
+                    &self.scratch,
+                );
             }
             Operator::CallIndirect { index, table_index } => {
-                if table_index != 0 {
-                    return Err(CodegenError {
-                        message: format!("CallIndirect: table_index is not 0"),
-                    });
-                }
                 let sig = self.signatures.get(SigIndex::new(index as usize)).unwrap();
                 let param_types: SmallVec<[WpType; 8]> =
                     sig.params().iter().cloned().map(type_to_wp_type).collect();
@@ -6183,20 +6517,25 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                 let table_count = self.machine.acquire_temp_gpr().unwrap();
                 let sigidx = self.machine.acquire_temp_gpr().unwrap();
 
+                let (tables_offset, table_ptr_offset) =
+                    match TableIndex::new(table_index as usize).local_or_import(module_info) {
+                        LocalOrImport::Local(local_table_index) => {
+                            (vm::Ctx::offset_tables(), local_table_index.index() * 8)
+                        }
+                        LocalOrImport::Import(import_table_index) => (
+                            vm::Ctx::offset_imported_tables(),
+                            import_table_index.index() * 8,
+                        ),
+                    };
+
                 a.emit_mov(
                     Size::S64,
-                    Location::Memory(
-                        Machine::get_vmctx_reg(),
-                        match TableIndex::new(0).local_or_import(module_info) {
-                            LocalOrImport::Local(_) => vm::Ctx::offset_tables(),
-                            LocalOrImport::Import(_) => vm::Ctx::offset_imported_tables(),
-                        } as i32,
-                    ),
+                    Location::Memory(Machine::get_vmctx_reg(), tables_offset as i32),
                     Location::GPR(table_base),
                 );
                 a.emit_mov(
                     Size::S64,
-                    Location::Memory(table_base, 0),
+                    Location::Memory(table_base, table_ptr_offset as i32),
                     Location::GPR(table_base),
                 );
                 a.emit_mov(
@@ -6210,7 +6549,7 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                     Location::GPR(table_base),
                 );
                 a.emit_cmp(Size::S32, func_index, Location::GPR(table_count));
-                a.emit_conditional_trap(Condition::BelowEqual);
+                a.emit_conditional_trap(Condition::BelowEqual, WasmTrapInfo::CallIndirectOOB);
                 a.emit_mov(Size::S64, func_index, Location::GPR(table_count));
                 a.emit_imul_imm32_gpr64(vm::Anyfunc::size() as u32, table_count);
                 a.emit_add(
@@ -6236,7 +6575,10 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                     Location::GPR(sigidx),
                     Location::Memory(table_count, (vm::Anyfunc::offset_sig_id() as usize) as i32),
                 );
-                a.emit_conditional_trap(Condition::NotEqual);
+                a.emit_conditional_trap(
+                    Condition::NotEqual,
+                    WasmTrapInfo::IncorrectCallIndirectSignature,
+                );
 
                 self.machine.release_temp_gpr(sigidx);
                 self.machine.release_temp_gpr(table_count);
@@ -6252,6 +6594,12 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
 
                 self.machine.release_locations_only_osr_state(params.len());
 
+                let return_area = if return_types.len() > 1 {
+                    self.scratch_pointer
+                } else {
+                    None
+                };
+
                 Self::emit_call_sysv(
                     a,
                     &mut self.machine,
@@ -6268,24 +6616,19 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                             ));
                         }
                     },
-                    params.iter().map(|x| *x),
+                    params.iter().map(|x| *x).chain(return_area),
                     Some((&mut self.fsm, &mut self.control_stack)),
                 )?;
 
                 self.machine.release_locations_only_stack(a, &params);
 
-                if return_types.len() > 0 {
-                    let ret = self.machine.acquire_locations(
-                        a,
-                        &[(
-                            return_types[0],
-                            MachineValue::WasmStack(self.value_stack.len()),
-                        )],
-                        false,
-                    )[0];
-                    self.value_stack.push(ret);
-                    a.emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
-                }
+                Self::emit_load_results(
+                    a,
+                    &mut self.machine,
+                    &return_types,
+                    &mut self.value_stack,
+                    &self.scratch,
+                );
             }
             Operator::If { ty } => {
                 let label_end = a.get_label();
@@ -6294,19 +6637,14 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                 let cond =
                     get_location_released(a, &mut self.machine, self.value_stack.pop().unwrap());
 
+                let (params, returns) = Self::block_signature(&self.signatures, ty);
                 let frame = ControlFrame {
                     label: label_end,
                     loop_like: false,
                     if_else: IfElseState::If(label_else),
-                    returns: match ty {
-                        WpTypeOrFuncType::Type(WpType::EmptyBlockType) => smallvec![],
-                        WpTypeOrFuncType::Type(inner_ty) => smallvec![inner_ty],
-                        _ => {
-                            return Err(CodegenError {
-                                message: format!("If: multi-value returns not yet implemented"),
-                            })
-                        }
-                    },
+                    base_stack_depth: self.value_stack.len() - params.len(),
+                    params,
+                    returns,
                     value_stack_depth: self.value_stack.len(),
                     state: self.machine.state.clone(),
                     state_diff_id: Self::get_state_diff(
@@ -6325,20 +6663,18 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                     cond,
                 );
                 a.emit_jmp(Condition::Equal, label_else);
+
+                let params = &self.control_stack.last().unwrap().params;
+                if params.len() > 0 {
+                    Self::emit_copy_params(a, &mut self.machine, params, &mut self.value_stack);
+                }
             }
             Operator::Else => {
                 let mut frame = self.control_stack.last_mut().unwrap();
 
                 if !was_unreachable && frame.returns.len() > 0 {
-                    let loc = *self.value_stack.last().unwrap();
-                    Self::emit_relaxed_binop(
-                        a,
-                        &mut self.machine,
-                        Assembler::emit_mov,
-                        Size::S64,
-                        loc,
-                        Location::GPR(GPR::RAX),
-                    );
+                    let values = &self.value_stack[self.value_stack.len() - frame.returns.len()..];
+                    Self::emit_store_results(a, &mut self.machine, values, &self.scratch);
                 }
 
                 let released: &[Location] = &self.value_stack[frame.value_stack_depth..];
@@ -6350,6 +6686,14 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                         a.emit_jmp(Condition::None, frame.label);
                         a.emit_label(label);
                         frame.if_else = IfElseState::Else;
+                        if frame.params.len() > 0 {
+                            Self::emit_copy_params(
+                                a,
+                                &mut self.machine,
+                                &frame.params,
+                                &mut self.value_stack,
+                            );
+                        }
                     }
                     _ => {
                         return Err(CodegenError {
@@ -6409,20 +6753,19 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                 a.emit_label(end_label);
             }
             Operator::Block { ty } => {
+                let (params, returns) = Self::block_signature(&self.signatures, ty);
+                let base_stack_depth = self.value_stack.len() - params.len();
+                if params.len() > 0 {
+                    Self::emit_copy_params(a, &mut self.machine, &params, &mut self.value_stack);
+                }
                 let frame = ControlFrame {
                     label: a.get_label(),
                     loop_like: false,
                     if_else: IfElseState::None,
-                    returns: match ty {
-                        WpTypeOrFuncType::Type(WpType::EmptyBlockType) => smallvec![],
-                        WpTypeOrFuncType::Type(inner_ty) => smallvec![inner_ty],
-                        _ => {
-                            return Err(CodegenError {
-                                message: format!("Block: multi-value returns not yet implemented"),
-                            })
-                        }
-                    },
+                    params,
+                    returns,
                     value_stack_depth: self.value_stack.len(),
+                    base_stack_depth,
                     state: self.machine.state.clone(),
                     state_diff_id: Self::get_state_diff(
                         &self.machine,
@@ -6433,6 +6776,14 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                 self.control_stack.push(frame);
             }
             Operator::Loop { ty } => {
+                let (params, returns) = Self::block_signature(&self.signatures, ty);
+                let base_stack_depth = self.value_stack.len() - params.len();
+                if params.len() > 0 {
+                    // Branches back to the loop need locations of their own to
+                    // write the parameters to.
+                    Self::emit_copy_params(a, &mut self.machine, &params, &mut self.value_stack);
+                }
+
                 let label = a.get_label();
                 let state_diff_id =
                     Self::get_state_diff(&self.machine, &mut self.fsm, &mut self.control_stack);
@@ -6442,16 +6793,10 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                     label: label,
                     loop_like: true,
                     if_else: IfElseState::None,
-                    returns: match ty {
-                        WpTypeOrFuncType::Type(WpType::EmptyBlockType) => smallvec![],
-                        WpTypeOrFuncType::Type(inner_ty) => smallvec![inner_ty],
-                        _ => {
-                            return Err(CodegenError {
-                                message: format!("Loop: multi-value returns not yet implemented"),
-                            })
-                        }
-                    },
+                    params,
+                    returns,
                     value_stack_depth: self.value_stack.len(),
+                    base_stack_depth,
                     state: self.machine.state.clone(),
                     state_diff_id,
                 });
@@ -6483,6 +6828,11 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                     Location::Memory(GPR::RAX, 0),
                     Location::GPR(GPR::RAX),
                 );
+
+                let params = &self.control_stack.last().unwrap().params;
+                if params.len() > 0 {
+                    Self::emit_copy_params(a, &mut self.machine, params, &mut self.value_stack);
+                }
             }
             Operator::Nop => {}
             Operator::MemorySize { reserved } => {
@@ -6572,6 +6922,151 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                 self.value_stack.push(ret);
                 a.emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
             }
+            Operator::MemoryCopy => {
+                Self::emit_call_intrinsic(
+                    a,
+                    &mut self.machine,
+                    &mut self.value_stack,
+                    vm::Intrinsics::offset_memory_copy(),
+                    &[0],
+                    3,
+                )?;
+            }
+            Operator::MemoryFill => {
+                Self::emit_call_intrinsic(
+                    a,
+                    &mut self.machine,
+                    &mut self.value_stack,
+                    vm::Intrinsics::offset_memory_fill(),
+                    &[0],
+                    3,
+                )?;
+            }
+            Operator::MemoryInit { segment } => {
+                Self::emit_call_intrinsic(
+                    a,
+                    &mut self.machine,
+                    &mut self.value_stack,
+                    vm::Intrinsics::offset_memory_init(),
+                    &[0, segment],
+                    3,
+                )?;
+            }
+            Operator::DataDrop { segment } => {
+                Self::emit_call_intrinsic(
+                    a,
+                    &mut self.machine,
+                    &mut self.value_stack,
+                    vm::Intrinsics::offset_data_drop(),
+                    &[segment],
+                    0,
+                )?;
+            }
+            Operator::TableCopy => {
+                Self::emit_call_intrinsic(
+                    a,
+                    &mut self.machine,
+                    &mut self.value_stack,
+                    vm::Intrinsics::offset_table_copy(),
+                    &[0, 0],
+                    3,
+                )?;
+            }
+            Operator::TableInit { segment } => {
+                Self::emit_call_intrinsic(
+                    a,
+                    &mut self.machine,
+                    &mut self.value_stack,
+                    vm::Intrinsics::offset_table_init(),
+                    &[0, segment],
+                    3,
+                )?;
+            }
+            Operator::ElemDrop { segment } => {
+                Self::emit_call_intrinsic(
+                    a,
+                    &mut self.machine,
+                    &mut self.value_stack,
+                    vm::Intrinsics::offset_elem_drop(),
+                    &[segment],
+                    0,
+                )?;
+            }
+            Operator::TableGet { table } => {
+                Self::emit_call_intrinsic(
+                    a,
+                    &mut self.machine,
+                    &mut self.value_stack,
+                    vm::Intrinsics::offset_table_get(),
+                    &[table],
+                    1,
+                )?;
+                let ret = self.machine.acquire_locations(
+                    a,
+                    &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
+                    false,
+                )[0];
+                self.value_stack.push(ret);
+                a.emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
+            }
+            Operator::TableSet { table } => {
+                Self::emit_call_intrinsic(
+                    a,
+                    &mut self.machine,
+                    &mut self.value_stack,
+                    vm::Intrinsics::offset_table_set(),
+                    &[table],
+                    2,
+                )?;
+            }
+            Operator::TableGrow { table } => {
+                Self::emit_call_intrinsic(
+                    a,
+                    &mut self.machine,
+                    &mut self.value_stack,
+                    vm::Intrinsics::offset_table_grow(),
+                    &[table],
+                    2,
+                )?;
+                let ret = self.machine.acquire_locations(
+                    a,
+                    &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
+                    false,
+                )[0];
+                self.value_stack.push(ret);
+                a.emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
+            }
+            Operator::TableSize { table } => {
+                Self::emit_call_intrinsic(
+                    a,
+                    &mut self.machine,
+                    &mut self.value_stack,
+                    vm::Intrinsics::offset_table_size(),
+                    &[table],
+                    0,
+                )?;
+                let ret = self.machine.acquire_locations(
+                    a,
+                    &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
+                    false,
+                )[0];
+                self.value_stack.push(ret);
+                a.emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
+            }
+            Operator::RefNull => {
+                self.value_stack.push(Location::Imm64(0));
+                self.machine
+                    .state
+                    .wasm_stack
+                    .push(WasmAbstractValue::Const(0));
+            }
+            Operator::RefIsNull => Self::emit_cmpop_i64_dynamic_b(
+                a,
+                &mut self.machine,
+                &mut self.value_stack,
+                Condition::Equal,
+                Location::Imm64(0),
+            )?,
             Operator::I32Load { ref memarg } => {
                 let target =
                     get_location_released(a, &mut self.machine, self.value_stack.pop().unwrap());
@@ -7298,47 +7793,30 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
             }
             Operator::Unreachable => {
                 Self::mark_trappable(a, &self.machine, &mut self.fsm, &mut self.control_stack);
-                a.emit_ud2();
+                a.emit_trap(WasmTrapInfo::Unreachable);
                 self.unreachable_depth = 1;
             }
             Operator::Return => {
                 let frame = &self.control_stack[0];
-                if frame.returns.len() > 0 {
-                    if frame.returns.len() != 1 {
-                        return Err(CodegenError {
-                            message: format!("Return: incorrect frame.returns"),
-                        });
-                    }
-                    let loc = *self.value_stack.last().unwrap();
-                    Self::emit_relaxed_binop(
-                        a,
-                        &mut self.machine,
-                        Assembler::emit_mov,
-                        Size::S64,
-                        loc,
-                        Location::GPR(GPR::RAX),
-                    );
-                }
-                let released = &self.value_stack[frame.value_stack_depth..];
-                self.machine.release_locations_keep_state(a, released);
-                a.emit_jmp(Condition::None, frame.label);
+                Self::emit_branch(
+                    a,
+                    &mut self.machine,
+                    &self.value_stack,
+                    frame,
+                    &self.scratch,
+                );
                 self.unreachable_depth = 1;
             }
             Operator::Br { relative_depth } => {
                 let frame =
                     &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
-                if !frame.loop_like && frame.returns.len() > 0 {
-                    if frame.returns.len() != 1 {
-                        return Err(CodegenError {
-                            message: format!("Br: incorrect frame.returns"),
-                        });
-                    }
-                    let loc = *self.value_stack.last().unwrap();
-                    a.emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                }
-                let released = &self.value_stack[frame.value_stack_depth..];
-                self.machine.release_locations_keep_state(a, released);
-                a.emit_jmp(Condition::None, frame.label);
+                Self::emit_branch(
+                    a,
+                    &mut self.machine,
+                    &self.value_stack,
+                    frame,
+                    &self.scratch,
+                );
                 self.unreachable_depth = 1;
             }
             Operator::BrIf { relative_depth } => {
@@ -7357,18 +7835,13 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
 
                 let frame =
                     &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
-                if !frame.loop_like && frame.returns.len() > 0 {
-                    if frame.returns.len() != 1 {
-                        return Err(CodegenError {
-                            message: format!("BrIf: incorrect frame.returns"),
-                        });
-                    }
-                    let loc = *self.value_stack.last().unwrap();
-                    a.emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                }
-                let released = &self.value_stack[frame.value_stack_depth..];
-                self.machine.release_locations_keep_state(a, released);
-                a.emit_jmp(Condition::None, frame.label);
+                Self::emit_branch(
+                    a,
+                    &mut self.machine,
+                    &self.value_stack,
+                    frame,
+                    &self.scratch,
+                );
 
                 a.emit_label(after);
             }
@@ -7405,39 +7878,26 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                     table.push(label);
                     let frame =
                         &self.control_stack[self.control_stack.len() - 1 - (*target as usize)];
-                    if !frame.loop_like && frame.returns.len() > 0 {
-                        if frame.returns.len() != 1 {
-                            return Err(CodegenError {
-                                message: format!(
-                                    "BrTable: incorrect frame.returns for {:?}",
-                                    target
-                                ),
-                            });
-                        }
-                        let loc = *self.value_stack.last().unwrap();
-                        a.emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                    }
-                    let released = &self.value_stack[frame.value_stack_depth..];
-                    self.machine.release_locations_keep_state(a, released);
-                    a.emit_jmp(Condition::None, frame.label);
+                    Self::emit_branch(
+                        a,
+                        &mut self.machine,
+                        &self.value_stack,
+                        frame,
+                        &self.scratch,
+                    );
                 }
                 a.emit_label(default_br);
 
                 {
                     let frame = &self.control_stack
                         [self.control_stack.len() - 1 - (default_target as usize)];
-                    if !frame.loop_like && frame.returns.len() > 0 {
-                        if frame.returns.len() != 1 {
-                            return Err(CodegenError {
-                                message: format!("BrTable: incorrect frame.returns"),
-                            });
-                        }
-                        let loc = *self.value_stack.last().unwrap();
-                        a.emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                    }
-                    let released = &self.value_stack[frame.value_stack_depth..];
-                    self.machine.release_locations_keep_state(a, released);
-                    a.emit_jmp(Condition::None, frame.label);
+                    Self::emit_branch(
+                        a,
+                        &mut self.machine,
+                        &self.value_stack,
+                        frame,
+                        &self.scratch,
+                    );
                 }
 
                 a.emit_label(table_label);
@@ -7453,19 +7913,23 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                 let frame = self.control_stack.pop().unwrap();
 
                 if !was_unreachable && frame.returns.len() > 0 {
-                    let loc = *self.value_stack.last().unwrap();
-                    Self::emit_relaxed_binop(
-                        a,
-                        &mut self.machine,
-                        Assembler::emit_mov,
-                        Size::S64,
-                        loc,
-                        Location::GPR(GPR::RAX),
-                    );
+                    let values = &self.value_stack[self.value_stack.len() - frame.returns.len()..];
+                    Self::emit_store_results(a, &mut self.machine, values, &self.scratch);
                 }
 
                 if self.control_stack.len() == 0 {
                     a.emit_label(frame.label);
+                    if let Some(return_area) = self.return_area {
+                        a.emit_mov(Size::S64, return_area, Location::GPR(GPR::RCX));
+                        for (i, &slot) in self.scratch[..frame.returns.len()].iter().enumerate() {
+                            a.emit_mov(Size::S64, slot, Location::GPR(GPR::RAX));
+                            a.emit_mov(
+                                Size::S64,
+                                Location::GPR(GPR::RAX),
+                                Location::Memory(GPR::RCX, (i * 8) as i32),
+                            );
+                        }
+                    }
                     self.machine.finalize_locals(a, &self.locals);
                     a.emit_mov(Size::S64, Location::GPR(GPR::RBP), Location::GPR(GPR::RSP));
                     a.emit_pop(Size::S64, Location::GPR(GPR::RBP));
@@ -7475,31 +7939,39 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                     self.machine.release_locations(a, released);
                     self.value_stack.truncate(frame.value_stack_depth);
 
-                    if !frame.loop_like {
-                        a.emit_label(frame.label);
+                    match frame.if_else {
+                        IfElseState::If(label) if frame.params.len() > 0 => {
+                            // Without an else branch, the parameters are the results.
+                            a.emit_jmp(Condition::None, frame.label);
+                            a.emit_label(label);
+                            let params =
+                                &self.value_stack[frame.base_stack_depth..frame.value_stack_depth];
+                            Self::emit_store_results(a, &mut self.machine, params, &self.scratch);
+                            a.emit_label(frame.label);
+                        }
+                        IfElseState::If(label) => {
+                            a.emit_label(frame.label);
+                            a.emit_label(label);
+                        }
+                        _ => {
+                            if !frame.loop_like {
+                                a.emit_label(frame.label);
+                            }
+                        }
                     }
 
-                    if let IfElseState::If(label) = frame.if_else {
-                        a.emit_label(label);
-                    }
+                    // Release the parameters kept below the block.
+                    let released = &self.value_stack[frame.base_stack_depth..];
+                    self.machine.release_locations(a, released);
+                    self.value_stack.truncate(frame.base_stack_depth);
 
-                    if frame.returns.len() > 0 {
-                        if frame.returns.len() != 1 {
-                            return Err(CodegenError {
-                                message: format!("End: incorrect frame.returns"),
-                            });
-                        }
-                        let loc = self.machine.acquire_locations(
-                            a,
-                            &[(
-                                frame.returns[0],
-                                MachineValue::WasmStack(self.value_stack.len()),
-                            )],
-                            false,
-                        )[0];
-                        a.emit_mov(Size::S64, Location::GPR(GPR::RAX), loc);
-                        self.value_stack.push(loc);
-                    }
+                    Self::emit_load_results(
+                        a,
+                        &mut self.machine,
+                        &frame.returns,
+                        &mut self.value_stack,
+                        &self.scratch,
+                    );
                 }
             }
             Operator::AtomicFence { flags: _ } => {
@@ -7511,6 +7983,57 @@ impl FunctionCodeGenerator<CodegenError> for X64FunctionCode {
                 // it would lead to data races that weren't present in the
                 // original source language.
             }
+            Operator::I32AtomicWait { ref memarg } => {
+                Self::emit_call_intrinsic(
+                    a,
+                    &mut self.machine,
+                    &mut self.value_stack,
+                    vm::Intrinsics::offset_memory_atomic_wait32(),
+                    &[0, memarg.offset],
+                    3,
+                )?;
+                let ret = self.machine.acquire_locations(
+                    a,
+                    &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
+                    false,
+                )[0];
+                self.value_stack.push(ret);
+                a.emit_mov(Size::S32, Location::GPR(GPR::RAX), ret);
+            }
+            Operator::I64AtomicWait { ref memarg } => {
+                Self::emit_call_intrinsic(
+                    a,
+                    &mut self.machine,
+                    &mut self.value_stack,
+                    vm::Intrinsics::offset_memory_atomic_wait64(),
+                    &[0, memarg.offset],
+                    3,
+                )?;
+                let ret = self.machine.acquire_locations(
+                    a,
+                    &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
+                    false,
+                )[0];
+                self.value_stack.push(ret);
+                a.emit_mov(Size::S32, Location::GPR(GPR::RAX), ret);
+            }
+            Operator::AtomicNotify { ref memarg } => {
+                Self::emit_call_intrinsic(
+                    a,
+                    &mut self.machine,
+                    &mut self.value_stack,
+                    vm::Intrinsics::offset_memory_atomic_notify(),
+                    &[0, memarg.offset],
+                    2,
+                )?;
+                let ret = self.machine.acquire_locations(
+                    a,
+                    &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
+                    false,
+                )[0];
+                self.value_stack.push(ret);
+                a.emit_mov(Size::S32, Location::GPR(GPR::RAX), ret);
+            }
             Operator::I32AtomicLoad { ref memarg } => {
                 let target =
                     get_location_released(a, &mut self.machine, self.value_stack.pop().unwrap());
@@ -9625,6 +10148,8 @@ fn type_to_wp_type(ty: Type) -> WpType {
         Type::F32 => WpType::F32,
         Type::F64 => WpType::F64,
         Type::V128 => WpType::V128,
+        Type::AnyRef => WpType::AnyRef,
+        Type::FuncRef => WpType::AnyFunc,
     }
 }
 
@@ -1,6 +1,8 @@
 use dynasmrt::{x64::Assembler, AssemblyOffset, DynamicLabel, DynasmApi, DynasmLabelApi};
 use wasmer_runtime_core::backend::InlineBreakpointType;
+use wasmer_runtime_core::fault::TRAP_CODE_MARKER;
 pub use wasmer_runtime_core::state::x64_decl::{GPR, XMM};
+use wasmer_runtime_core::typed_func::WasmTrapInfo;
 
 fn _dummy(_a: &Assembler) {
     dynasm!(
@@ -80,7 +82,7 @@ pub trait Emitter {
     fn emit_xor(&mut self, sz: Size, src: Location, dst: Location);
     fn emit_jmp(&mut self, condition: Condition, label: Self::Label);
     fn emit_jmp_location(&mut self, loc: Location);
-    fn emit_conditional_trap(&mut self, condition: Condition);
+    fn emit_conditional_trap(&mut self, condition: Condition, code: WasmTrapInfo);
     fn emit_set(&mut self, condition: Condition, dst: GPR);
     fn emit_push(&mut self, sz: Size, src: Location);
     fn emit_pop(&mut self, sz: Size, dst: Location);
@@ -190,6 +192,7 @@ pub trait Emitter {
     fn emit_test_gpr_64(&mut self, reg: GPR);
 
     fn emit_ud2(&mut self);
+    fn emit_trap(&mut self, code: WasmTrapInfo);
     fn emit_ret(&mut self);
     fn emit_call_label(&mut self, label: Self::Label);
     fn emit_call_location(&mut self, loc: Location);
@@ -461,12 +464,14 @@ macro_rules! jmp_op {
 }
 
 macro_rules! trap_op {
-    ($ins:ident, $assembler:tt) => {
+    ($ins:ident, $assembler:tt, $code:expr) => {
         dynasm!($assembler
             ; $ins >trap
             ; jmp >after
             ; trap:
-            ; ud2
+        );
+        $assembler.emit_trap($code);
+        dynasm!($assembler
             ; after:
         );
     }
@@ -780,20 +785,20 @@ impl Emitter for Assembler {
             _ => panic!("singlepass can't emit JMP {:?}", loc),
         }
     }
-    fn emit_conditional_trap(&mut self, condition: Condition) {
+    fn emit_conditional_trap(&mut self, condition: Condition, code: WasmTrapInfo) {
         match condition {
-            Condition::None => trap_op!(jmp, self),
-            Condition::Above => trap_op!(ja, self),
-            Condition::AboveEqual => trap_op!(jae, self),
-            Condition::Below => trap_op!(jb, self),
-            Condition::BelowEqual => trap_op!(jbe, self),
-            Condition::Greater => trap_op!(jg, self),
-            Condition::GreaterEqual => trap_op!(jge, self),
-            Condition::Less => trap_op!(jl, self),
-            Condition::LessEqual => trap_op!(jle, self),
-            Condition::Equal => trap_op!(je, self),
-            Condition::NotEqual => trap_op!(jne, self),
-            Condition::Signed => trap_op!(js, self),
+            Condition::None => trap_op!(jmp, self, code),
+            Condition::Above => trap_op!(ja, self, code),
+            Condition::AboveEqual => trap_op!(jae, self, code),
+            Condition::Below => trap_op!(jb, self, code),
+            Condition::BelowEqual => trap_op!(jbe, self, code),
+            Condition::Greater => trap_op!(jg, self, code),
+            Condition::GreaterEqual => trap_op!(jge, self, code),
+            Condition::Less => trap_op!(jl, self, code),
+            Condition::LessEqual => trap_op!(jle, self, code),
+            Condition::Equal => trap_op!(je, self, code),
+            Condition::NotEqual => trap_op!(jne, self, code),
+            Condition::Signed => trap_op!(js, self, code),
         }
     }
     fn emit_set(&mut self, condition: Condition, dst: GPR) {
@@ -1292,6 +1297,14 @@ impl Emitter for Assembler {
     fn emit_ud2(&mut self) {
         dynasm!(self ; ud2);
     }
+    fn emit_trap(&mut self, code: WasmTrapInfo) {
+        // The fault handler reads the code back from the bytes following `ud2`.
+        dynasm!(self
+            ; ud2
+            ; .byte TRAP_CODE_MARKER as i8
+            ; .byte code as u8 as i8
+        );
+    }
     fn emit_ret(&mut self) {
         dynasm!(self ; ret);
     }
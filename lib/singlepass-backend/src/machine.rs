@@ -152,7 +152,9 @@ impl Machine {
         for (ty, mv) in tys {
             let loc = match *ty {
                 WpType::F32 | WpType::F64 => self.pick_xmm().map(Location::XMM),
-                WpType::I32 | WpType::I64 => self.pick_gpr().map(Location::GPR),
+                WpType::I32 | WpType::I64 | WpType::AnyRef | WpType::AnyFunc => {
+                    self.pick_gpr().map(Location::GPR)
+                }
                 _ => unreachable!(),
             };
 
@@ -323,11 +325,14 @@ impl Machine {
         }
     }
 
+    /// Allocates `n` locals, of which the first `n_params` are parameters, followed by
+    /// `n_extra` uninitialized machine stack slots.
     pub fn init_locals<E: Emitter>(
         &mut self,
         a: &mut E,
         n: usize,
         n_params: usize,
+        n_extra: usize,
     ) -> Vec<Location> {
         // Use callee-saved registers for locals.
         fn get_local_location(idx: usize) -> Location {
@@ -367,7 +372,18 @@ impl Machine {
             allocated += 1;
         }
 
+        // Extra slots are always in memory, after the memory slots of locals.
+        allocated = allocated.max(4);
+        for _ in 0..n_extra {
+            locations.push(get_local_location(allocated));
+            allocated += 1;
+        }
+
         for (i, loc) in locations.iter().enumerate() {
+            if i >= n {
+                self.state.stack_values.push(MachineValue::Undefined);
+                continue;
+            }
             match *loc {
                 Location::GPR(x) => {
                     self.state.register_values[X64Register::GPR(x).to_index().0] =
@@ -3,6 +3,7 @@
 use crate::emitter_x64::*;
 use dynasmrt::{aarch64::Assembler, AssemblyOffset, DynamicLabel, DynasmApi, DynasmLabelApi};
 use wasmer_runtime_core::backend::InlineBreakpointType;
+use wasmer_runtime_core::typed_func::WasmTrapInfo;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct AX(pub u32);
@@ -753,7 +754,7 @@ impl Emitter for Assembler {
         }
     }
 
-    fn emit_conditional_trap(&mut self, condition: Condition) {
+    fn emit_conditional_trap(&mut self, condition: Condition, _code: WasmTrapInfo) {
         use Condition::*;
 
         match condition {
@@ -1548,6 +1549,9 @@ impl Emitter for Assembler {
     fn emit_ud2(&mut self) {
         dynasm!(self ; .dword 0 ; .dword 2)
     }
+    fn emit_trap(&mut self, _code: WasmTrapInfo) {
+        self.emit_ud2();
+    }
     fn emit_ret(&mut self) {
         dynasm!(self
             ; ldr x_tmp1, [x_rsp]
@@ -0,0 +1,1738 @@
+;;
+;; Tests of the bulk memory operations proposal, generated like its
+;; test/meta/generate_memory_copy.js.
+;;
+
+(module
+  (memory (export "memory0") 1 1)
+  (data (i32.const 2) "\03\01\04\01")
+  (data (i32.const 12) "\07\05\02\03\06")
+  (func (export "test")
+    (nop))
+  (func (export "load8_u") (param i32) (result i32)
+    (i32.load8_u (local.get 0))))
+
+(invoke "test")
+
+(assert_return (invoke "load8_u" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 2)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 3)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 4)) (i32.const 4))
+(assert_return (invoke "load8_u" (i32.const 5)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 6)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 7)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 8)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 9)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 10)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 11)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 12)) (i32.const 7))
+(assert_return (invoke "load8_u" (i32.const 13)) (i32.const 5))
+(assert_return (invoke "load8_u" (i32.const 14)) (i32.const 2))
+(assert_return (invoke "load8_u" (i32.const 15)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 16)) (i32.const 6))
+(assert_return (invoke "load8_u" (i32.const 17)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 18)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 19)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 20)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 21)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 22)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 23)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 24)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 25)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 26)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 27)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 28)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 29)) (i32.const 0))
+
+(module
+  (memory (export "memory0") 1 1)
+  (data (i32.const 2) "\03\01\04\01")
+  (data (i32.const 12) "\07\05\02\03\06")
+  (func (export "test")
+    (memory.copy (i32.const 13) (i32.const 2) (i32.const 3)))
+  (func (export "load8_u") (param i32) (result i32)
+    (i32.load8_u (local.get 0))))
+
+(invoke "test")
+
+(assert_return (invoke "load8_u" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 2)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 3)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 4)) (i32.const 4))
+(assert_return (invoke "load8_u" (i32.const 5)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 6)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 7)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 8)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 9)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 10)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 11)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 12)) (i32.const 7))
+(assert_return (invoke "load8_u" (i32.const 13)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 14)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 15)) (i32.const 4))
+(assert_return (invoke "load8_u" (i32.const 16)) (i32.const 6))
+(assert_return (invoke "load8_u" (i32.const 17)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 18)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 19)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 20)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 21)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 22)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 23)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 24)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 25)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 26)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 27)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 28)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 29)) (i32.const 0))
+
+(module
+  (memory (export "memory0") 1 1)
+  (data (i32.const 2) "\03\01\04\01")
+  (data (i32.const 12) "\07\05\02\03\06")
+  (func (export "test")
+    (memory.copy (i32.const 25) (i32.const 15) (i32.const 2)))
+  (func (export "load8_u") (param i32) (result i32)
+    (i32.load8_u (local.get 0))))
+
+(invoke "test")
+
+(assert_return (invoke "load8_u" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 2)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 3)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 4)) (i32.const 4))
+(assert_return (invoke "load8_u" (i32.const 5)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 6)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 7)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 8)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 9)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 10)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 11)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 12)) (i32.const 7))
+(assert_return (invoke "load8_u" (i32.const 13)) (i32.const 5))
+(assert_return (invoke "load8_u" (i32.const 14)) (i32.const 2))
+(assert_return (invoke "load8_u" (i32.const 15)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 16)) (i32.const 6))
+(assert_return (invoke "load8_u" (i32.const 17)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 18)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 19)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 20)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 21)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 22)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 23)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 24)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 25)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 26)) (i32.const 6))
+(assert_return (invoke "load8_u" (i32.const 27)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 28)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 29)) (i32.const 0))
+
+(module
+  (memory (export "memory0") 1 1)
+  (data (i32.const 2) "\03\01\04\01")
+  (data (i32.const 12) "\07\05\02\03\06")
+  (func (export "test")
+    (memory.copy (i32.const 13) (i32.const 25) (i32.const 3)))
+  (func (export "load8_u") (param i32) (result i32)
+    (i32.load8_u (local.get 0))))
+
+(invoke "test")
+
+(assert_return (invoke "load8_u" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 2)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 3)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 4)) (i32.const 4))
+(assert_return (invoke "load8_u" (i32.const 5)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 6)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 7)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 8)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 9)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 10)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 11)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 12)) (i32.const 7))
+(assert_return (invoke "load8_u" (i32.const 13)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 14)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 15)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 16)) (i32.const 6))
+(assert_return (invoke "load8_u" (i32.const 17)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 18)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 19)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 20)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 21)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 22)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 23)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 24)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 25)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 26)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 27)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 28)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 29)) (i32.const 0))
+
+(module
+  (memory (export "memory0") 1 1)
+  (data (i32.const 2) "\03\01\04\01")
+  (data (i32.const 12) "\07\05\02\03\06")
+  (func (export "test")
+    (memory.copy (i32.const 20) (i32.const 22) (i32.const 4)))
+  (func (export "load8_u") (param i32) (result i32)
+    (i32.load8_u (local.get 0))))
+
+(invoke "test")
+
+(assert_return (invoke "load8_u" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 2)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 3)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 4)) (i32.const 4))
+(assert_return (invoke "load8_u" (i32.const 5)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 6)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 7)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 8)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 9)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 10)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 11)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 12)) (i32.const 7))
+(assert_return (invoke "load8_u" (i32.const 13)) (i32.const 5))
+(assert_return (invoke "load8_u" (i32.const 14)) (i32.const 2))
+(assert_return (invoke "load8_u" (i32.const 15)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 16)) (i32.const 6))
+(assert_return (invoke "load8_u" (i32.const 17)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 18)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 19)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 20)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 21)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 22)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 23)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 24)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 25)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 26)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 27)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 28)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 29)) (i32.const 0))
+
+(module
+  (memory (export "memory0") 1 1)
+  (data (i32.const 2) "\03\01\04\01")
+  (data (i32.const 12) "\07\05\02\03\06")
+  (func (export "test")
+    (memory.copy (i32.const 25) (i32.const 1) (i32.const 3)))
+  (func (export "load8_u") (param i32) (result i32)
+    (i32.load8_u (local.get 0))))
+
+(invoke "test")
+
+(assert_return (invoke "load8_u" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 2)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 3)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 4)) (i32.const 4))
+(assert_return (invoke "load8_u" (i32.const 5)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 6)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 7)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 8)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 9)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 10)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 11)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 12)) (i32.const 7))
+(assert_return (invoke "load8_u" (i32.const 13)) (i32.const 5))
+(assert_return (invoke "load8_u" (i32.const 14)) (i32.const 2))
+(assert_return (invoke "load8_u" (i32.const 15)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 16)) (i32.const 6))
+(assert_return (invoke "load8_u" (i32.const 17)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 18)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 19)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 20)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 21)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 22)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 23)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 24)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 25)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 26)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 27)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 28)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 29)) (i32.const 0))
+
+(module
+  (memory (export "memory0") 1 1)
+  (data (i32.const 2) "\03\01\04\01")
+  (data (i32.const 12) "\07\05\02\03\06")
+  (func (export "test")
+    (memory.copy (i32.const 10) (i32.const 12) (i32.const 7)))
+  (func (export "load8_u") (param i32) (result i32)
+    (i32.load8_u (local.get 0))))
+
+(invoke "test")
+
+(assert_return (invoke "load8_u" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 2)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 3)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 4)) (i32.const 4))
+(assert_return (invoke "load8_u" (i32.const 5)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 6)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 7)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 8)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 9)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 10)) (i32.const 7))
+(assert_return (invoke "load8_u" (i32.const 11)) (i32.const 5))
+(assert_return (invoke "load8_u" (i32.const 12)) (i32.const 2))
+(assert_return (invoke "load8_u" (i32.const 13)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 14)) (i32.const 6))
+(assert_return (invoke "load8_u" (i32.const 15)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 16)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 17)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 18)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 19)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 20)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 21)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 22)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 23)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 24)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 25)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 26)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 27)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 28)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 29)) (i32.const 0))
+
+(module
+  (memory (export "memory0") 1 1)
+  (data (i32.const 2) "\03\01\04\01")
+  (data (i32.const 12) "\07\05\02\03\06")
+  (func (export "test")
+    (memory.copy (i32.const 12) (i32.const 10) (i32.const 7)))
+  (func (export "load8_u") (param i32) (result i32)
+    (i32.load8_u (local.get 0))))
+
+(invoke "test")
+
+(assert_return (invoke "load8_u" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 2)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 3)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 4)) (i32.const 4))
+(assert_return (invoke "load8_u" (i32.const 5)) (i32.const 1))
+(assert_return (invoke "load8_u" (i32.const 6)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 7)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 8)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 9)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 10)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 11)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 12)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 13)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 14)) (i32.const 7))
+(assert_return (invoke "load8_u" (i32.const 15)) (i32.const 5))
+(assert_return (invoke "load8_u" (i32.const 16)) (i32.const 2))
+(assert_return (invoke "load8_u" (i32.const 17)) (i32.const 3))
+(assert_return (invoke "load8_u" (i32.const 18)) (i32.const 6))
+(assert_return (invoke "load8_u" (i32.const 19)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 20)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 21)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 22)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 23)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 24)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 25)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 26)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 27)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 28)) (i32.const 0))
+(assert_return (invoke "load8_u" (i32.const 29)) (i32.const 0))
+
+(module
+  (memory 1 1)
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "test")
+    (memory.fill (i32.const 0) (i32.const 0x55) (i32.const 0x8000))
+    (memory.fill (i32.const 0x8000) (i32.const 0xAA) (i32.const 0x8000))
+    (memory.copy (i32.const 65280) (i32.const 32768) (i32.const 257))))
+
+(assert_trap (invoke "test") "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 32768) (i32.const 85)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 32768) (i32.const 65536) (i32.const 170)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "test")
+    (memory.fill (i32.const 0) (i32.const 0x55) (i32.const 0x8000))
+    (memory.fill (i32.const 0x8000) (i32.const 0xAA) (i32.const 0x8000))
+    (memory.copy (i32.const -256) (i32.const 16384) (i32.const 257))))
+
+(assert_trap (invoke "test") "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 32768) (i32.const 85)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 32768) (i32.const 65536) (i32.const 170)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "test")
+    (memory.fill (i32.const 0) (i32.const 0x55) (i32.const 0x8000))
+    (memory.fill (i32.const 0x8000) (i32.const 0xAA) (i32.const 0x8000))
+    (memory.copy (i32.const 32768) (i32.const 65280) (i32.const 257))))
+
+(assert_trap (invoke "test") "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 32768) (i32.const 85)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 32768) (i32.const 65536) (i32.const 170)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "test")
+    (memory.fill (i32.const 0) (i32.const 0x55) (i32.const 0x8000))
+    (memory.fill (i32.const 0x8000) (i32.const 0xAA) (i32.const 0x8000))
+    (memory.copy (i32.const 16384) (i32.const -256) (i32.const 257))))
+
+(assert_trap (invoke "test") "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 32768) (i32.const 85)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 32768) (i32.const 65536) (i32.const 170)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "test")
+    (memory.fill (i32.const 0) (i32.const 0x55) (i32.const 0x8000))
+    (memory.fill (i32.const 0x8000) (i32.const 0xAA) (i32.const 0x8000))
+    (memory.copy (i32.const 36864) (i32.const 28672) (i32.const 0))))
+
+(invoke "test")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 32768) (i32.const 85)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 32768) (i32.const 65536) (i32.const 170)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "test")
+    (memory.fill (i32.const 0) (i32.const 0x55) (i32.const 0x8000))
+    (memory.fill (i32.const 0x8000) (i32.const 0xAA) (i32.const 0x8000))
+    (memory.copy (i32.const 65536) (i32.const 28672) (i32.const 0))))
+
+(invoke "test")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 32768) (i32.const 85)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 32768) (i32.const 65536) (i32.const 170)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "test")
+    (memory.fill (i32.const 0) (i32.const 0x55) (i32.const 0x8000))
+    (memory.fill (i32.const 0x8000) (i32.const 0xAA) (i32.const 0x8000))
+    (memory.copy (i32.const 131072) (i32.const 28672) (i32.const 0))))
+
+(assert_trap (invoke "test") "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 32768) (i32.const 85)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 32768) (i32.const 65536) (i32.const 170)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "test")
+    (memory.fill (i32.const 0) (i32.const 0x55) (i32.const 0x8000))
+    (memory.fill (i32.const 0x8000) (i32.const 0xAA) (i32.const 0x8000))
+    (memory.copy (i32.const 36864) (i32.const 65536) (i32.const 0))))
+
+(invoke "test")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 32768) (i32.const 85)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 32768) (i32.const 65536) (i32.const 170)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "test")
+    (memory.fill (i32.const 0) (i32.const 0x55) (i32.const 0x8000))
+    (memory.fill (i32.const 0x8000) (i32.const 0xAA) (i32.const 0x8000))
+    (memory.copy (i32.const 36864) (i32.const 131072) (i32.const 0))))
+
+(assert_trap (invoke "test") "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 32768) (i32.const 85)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 32768) (i32.const 65536) (i32.const 170)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "test")
+    (memory.fill (i32.const 0) (i32.const 0x55) (i32.const 0x8000))
+    (memory.fill (i32.const 0x8000) (i32.const 0xAA) (i32.const 0x8000))
+    (memory.copy (i32.const 65536) (i32.const 65536) (i32.const 0))))
+
+(invoke "test")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 32768) (i32.const 85)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 32768) (i32.const 65536) (i32.const 170)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "test")
+    (memory.fill (i32.const 0) (i32.const 0x55) (i32.const 0x8000))
+    (memory.fill (i32.const 0x8000) (i32.const 0xAA) (i32.const 0x8000))
+    (memory.copy (i32.const 131072) (i32.const 131072) (i32.const 0))))
+
+(assert_trap (invoke "test") "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 32768) (i32.const 85)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 32768) (i32.const 65536) (i32.const 170)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "test")
+    (memory.fill (i32.const 0) (i32.const 0x55) (i32.const 0x8000))
+    (memory.fill (i32.const 0x8000) (i32.const 0xAA) (i32.const 0x8000))
+    (memory.copy (i32.const 65280) (i32.const 32512) (i32.const 256))))
+
+(invoke "test")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 32768) (i32.const 85)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 32768) (i32.const 65280) (i32.const 170)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65280) (i32.const 65536) (i32.const 85)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "test")
+    (memory.fill (i32.const 0) (i32.const 0x55) (i32.const 0x8000))
+    (memory.fill (i32.const 0x8000) (i32.const 0xAA) (i32.const 0x8000))
+    (memory.copy (i32.const 32512) (i32.const 65280) (i32.const 256))))
+
+(invoke "test")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 32512) (i32.const 85)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 32512) (i32.const 65536) (i32.const 170)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "test")
+    (memory.fill (i32.const 48560) (i32.const 1) (i32.const 1783))
+    (memory.fill (i32.const 12272) (i32.const 2) (i32.const 385))
+    (memory.fill (i32.const 8136) (i32.const 3) (i32.const 2067))
+    (memory.fill (i32.const 37160) (i32.const 4) (i32.const 1878))
+    (memory.fill (i32.const 17694) (i32.const 5) (i32.const 2995))
+    (memory.fill (i32.const 21723) (i32.const 6) (i32.const 675))
+    (memory.fill (i32.const 33448) (i32.const 7) (i32.const 989))
+    (memory.fill (i32.const 13204) (i32.const 8) (i32.const 2858))
+    (memory.fill (i32.const 13768) (i32.const 9) (i32.const 440))
+    (memory.fill (i32.const 11234) (i32.const 10) (i32.const 1916))
+    (memory.fill (i32.const 60130) (i32.const 11) (i32.const 2570))
+    (memory.fill (i32.const 28624) (i32.const 12) (i32.const 2724))
+    (memory.fill (i32.const 60977) (i32.const 13) (i32.const 2690))
+    (memory.fill (i32.const 51126) (i32.const 14) (i32.const 1118))
+    (memory.fill (i32.const 43095) (i32.const 15) (i32.const 1898))
+    (memory.fill (i32.const 29326) (i32.const 16) (i32.const 3092))
+    (memory.fill (i32.const 6622) (i32.const 17) (i32.const 2662))
+    (memory.fill (i32.const 45745) (i32.const 18) (i32.const 2244))
+    (memory.copy (i32.const 21544) (i32.const 20674) (i32.const 1837))
+    (memory.copy (i32.const 23650) (i32.const 20711) (i32.const 5799))
+    (memory.copy (i32.const 59711) (i32.const 57378) (i32.const 2353))
+    (memory.copy (i32.const 54776) (i32.const 54675) (i32.const 1068))
+    (memory.copy (i32.const 13486) (i32.const 14153) (i32.const 5645))
+    (memory.copy (i32.const 44969) (i32.const 41872) (i32.const 4128))
+    (memory.copy (i32.const 41799) (i32.const 40980) (i32.const 3947))
+    (memory.copy (i32.const 52884) (i32.const 55591) (i32.const 5868))
+    (memory.copy (i32.const 18496) (i32.const 19227) (i32.const 1148))
+    (memory.copy (i32.const 12621) (i32.const 13026) (i32.const 5879))
+    (memory.copy (i32.const 45147) (i32.const 45311) (i32.const 2645))
+    (memory.copy (i32.const 41109) (i32.const 42278) (i32.const 3323))))
+(invoke "test")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 6622) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 6622) (i32.const 9284) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 9284) (i32.const 10203) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 10203) (i32.const 11234) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 11234) (i32.const 12745) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 12745) (i32.const 12799) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 12799) (i32.const 13081) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 13081) (i32.const 13136) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 13136) (i32.const 14990) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 14990) (i32.const 16622) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 16622) (i32.const 20689) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 20689) (i32.const 21544) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 21544) (i32.const 21559) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 21559) (i32.const 22593) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 22593) (i32.const 23268) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 23268) (i32.const 24483) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 24483) (i32.const 24498) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 24498) (i32.const 25532) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 25532) (i32.const 26207) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 26207) (i32.const 29449) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 29449) (i32.const 32418) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 32418) (i32.const 33448) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 33448) (i32.const 34437) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 34437) (i32.const 37160) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 37160) (i32.const 39038) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 39038) (i32.const 42745) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 42745) (i32.const 44413) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 44413) (i32.const 44432) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 44432) (i32.const 45582) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 45582) (i32.const 46028) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 46028) (i32.const 48090) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 48090) (i32.const 48842) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 48842) (i32.const 49097) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 49097) (i32.const 50343) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 50343) (i32.const 51126) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 51126) (i32.const 52244) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 52244) (i32.const 62064) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 62064) (i32.const 63667) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 63667) (i32.const 65536) (i32.const 0)) (i32.const -1))
+
+(assert_invalid
+  (module
+    (func (export "testfn")
+      (memory.copy (i32.const 10) (i32.const 20) (i32.const 30))))
+  "unknown memory 0")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i32.const 1) (i32.const 1) (f32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i32.const 1) (i32.const 1) (i64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i32.const 1) (i32.const 1) (f64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i32.const 1) (f32.const 1) (i32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i32.const 1) (f32.const 1) (f32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i32.const 1) (f32.const 1) (i64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i32.const 1) (f32.const 1) (f64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i32.const 1) (i64.const 1) (i32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i32.const 1) (i64.const 1) (f32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i32.const 1) (i64.const 1) (i64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i32.const 1) (i64.const 1) (f64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i32.const 1) (f64.const 1) (i32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i32.const 1) (f64.const 1) (f32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i32.const 1) (f64.const 1) (i64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i32.const 1) (f64.const 1) (f64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f32.const 1) (i32.const 1) (i32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f32.const 1) (i32.const 1) (f32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f32.const 1) (i32.const 1) (i64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f32.const 1) (i32.const 1) (f64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f32.const 1) (f32.const 1) (i32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f32.const 1) (f32.const 1) (f32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f32.const 1) (f32.const 1) (i64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f32.const 1) (f32.const 1) (f64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f32.const 1) (i64.const 1) (i32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f32.const 1) (i64.const 1) (f32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f32.const 1) (i64.const 1) (i64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f32.const 1) (i64.const 1) (f64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f32.const 1) (f64.const 1) (i32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f32.const 1) (f64.const 1) (f32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f32.const 1) (f64.const 1) (i64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f32.const 1) (f64.const 1) (f64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i64.const 1) (i32.const 1) (i32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i64.const 1) (i32.const 1) (f32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i64.const 1) (i32.const 1) (i64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i64.const 1) (i32.const 1) (f64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i64.const 1) (f32.const 1) (i32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i64.const 1) (f32.const 1) (f32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i64.const 1) (f32.const 1) (i64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i64.const 1) (f32.const 1) (f64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i64.const 1) (i64.const 1) (i32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i64.const 1) (i64.const 1) (f32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i64.const 1) (i64.const 1) (i64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i64.const 1) (i64.const 1) (f64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i64.const 1) (f64.const 1) (i32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i64.const 1) (f64.const 1) (f32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i64.const 1) (f64.const 1) (i64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (i64.const 1) (f64.const 1) (f64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f64.const 1) (i32.const 1) (i32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f64.const 1) (i32.const 1) (f32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f64.const 1) (i32.const 1) (i64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f64.const 1) (i32.const 1) (f64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f64.const 1) (f32.const 1) (i32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f64.const 1) (f32.const 1) (f32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f64.const 1) (f32.const 1) (i64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f64.const 1) (f32.const 1) (f64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f64.const 1) (i64.const 1) (i32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f64.const 1) (i64.const 1) (f32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f64.const 1) (i64.const 1) (i64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f64.const 1) (i64.const 1) (f64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f64.const 1) (f64.const 1) (i32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f64.const 1) (f64.const 1) (f32.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f64.const 1) (f64.const 1) (i64.const 1))))
+  "type mismatch")
+
+(assert_invalid
+  (module
+    (memory 1)
+    (func (export "testfn")
+      (memory.copy (f64.const 1) (f64.const 1) (f64.const 1))))
+  "type mismatch")
+
+(module
+  (memory 1 1)
+  (data (i32.const 0) "\00\01\02\03\04\05\06\07\08\09\0a\0b\0c\0d\0e\0f\10\11\12\13")
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "run") (param $targetOffs i32) (param $srcOffs i32) (param $len i32)
+    (memory.copy (local.get $targetOffs) (local.get $srcOffs) (local.get $len))))
+
+(assert_trap (invoke "run" (i32.const 65516) (i32.const 0) (i32.const 40))
+    "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 1) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 1) (i32.const 2) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 2) (i32.const 3) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 3) (i32.const 4) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 4) (i32.const 5) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 5) (i32.const 6) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 6) (i32.const 7) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 7) (i32.const 8) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 8) (i32.const 9) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 9) (i32.const 10) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 10) (i32.const 11) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 11) (i32.const 12) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 12) (i32.const 13) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 13) (i32.const 14) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 14) (i32.const 15) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 15) (i32.const 16) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 16) (i32.const 17) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 17) (i32.const 18) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 18) (i32.const 19) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 19) (i32.const 20) (i32.const 19)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 1) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 1) (i32.const 2) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 2) (i32.const 3) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 3) (i32.const 4) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 4) (i32.const 5) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 5) (i32.const 6) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 6) (i32.const 7) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 7) (i32.const 8) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 8) (i32.const 9) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 9) (i32.const 10) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 10) (i32.const 11) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 11) (i32.const 12) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 12) (i32.const 13) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 13) (i32.const 14) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 14) (i32.const 15) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 15) (i32.const 16) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 16) (i32.const 17) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 17) (i32.const 18) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 18) (i32.const 19) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 19) (i32.const 20) (i32.const 19)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 20) (i32.const 65536) (i32.const 0)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (data (i32.const 0) "\00\01\02\03\04\05\06\07\08\09\0a\0b\0c\0d\0e\0f\10\11\12\13")
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "run") (param $targetOffs i32) (param $srcOffs i32) (param $len i32)
+    (memory.copy (local.get $targetOffs) (local.get $srcOffs) (local.get $len))))
+
+(assert_trap (invoke "run" (i32.const 65515) (i32.const 0) (i32.const 39))
+    "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 1) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 1) (i32.const 2) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 2) (i32.const 3) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 3) (i32.const 4) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 4) (i32.const 5) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 5) (i32.const 6) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 6) (i32.const 7) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 7) (i32.const 8) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 8) (i32.const 9) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 9) (i32.const 10) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 10) (i32.const 11) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 11) (i32.const 12) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 12) (i32.const 13) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 13) (i32.const 14) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 14) (i32.const 15) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 15) (i32.const 16) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 16) (i32.const 17) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 17) (i32.const 18) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 18) (i32.const 19) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 19) (i32.const 20) (i32.const 19)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 1) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 1) (i32.const 2) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 2) (i32.const 3) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 3) (i32.const 4) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 4) (i32.const 5) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 5) (i32.const 6) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 6) (i32.const 7) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 7) (i32.const 8) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 8) (i32.const 9) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 9) (i32.const 10) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 10) (i32.const 11) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 11) (i32.const 12) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 12) (i32.const 13) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 13) (i32.const 14) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 14) (i32.const 15) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 15) (i32.const 16) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 16) (i32.const 17) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 17) (i32.const 18) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 18) (i32.const 19) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 19) (i32.const 20) (i32.const 19)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 20) (i32.const 65536) (i32.const 0)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (data (i32.const 65516) "\00\01\02\03\04\05\06\07\08\09\0a\0b\0c\0d\0e\0f\10\11\12\13")
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "run") (param $targetOffs i32) (param $srcOffs i32) (param $len i32)
+    (memory.copy (local.get $targetOffs) (local.get $srcOffs) (local.get $len))))
+
+(assert_trap (invoke "run" (i32.const 0) (i32.const 65516) (i32.const 40))
+    "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 65516) (i32.const 65517) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65517) (i32.const 65518) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65518) (i32.const 65519) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65519) (i32.const 65520) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65520) (i32.const 65521) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65521) (i32.const 65522) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65522) (i32.const 65523) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65523) (i32.const 65524) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65524) (i32.const 65525) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65525) (i32.const 65526) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65526) (i32.const 65527) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65527) (i32.const 65528) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65528) (i32.const 65529) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65529) (i32.const 65530) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65530) (i32.const 65531) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65531) (i32.const 65532) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65532) (i32.const 65533) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65533) (i32.const 65534) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65534) (i32.const 65535) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65535) (i32.const 65536) (i32.const 19)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 65517) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65517) (i32.const 65518) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65518) (i32.const 65519) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65519) (i32.const 65520) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65520) (i32.const 65521) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65521) (i32.const 65522) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65522) (i32.const 65523) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65523) (i32.const 65524) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65524) (i32.const 65525) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65525) (i32.const 65526) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65526) (i32.const 65527) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65527) (i32.const 65528) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65528) (i32.const 65529) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65529) (i32.const 65530) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65530) (i32.const 65531) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65531) (i32.const 65532) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65532) (i32.const 65533) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65533) (i32.const 65534) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65534) (i32.const 65535) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65535) (i32.const 65536) (i32.const 19)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (data (i32.const 65516) "\00\01\02\03\04\05\06\07\08\09\0a\0b\0c\0d\0e\0f\10\11\12\13")
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "run") (param $targetOffs i32) (param $srcOffs i32) (param $len i32)
+    (memory.copy (local.get $targetOffs) (local.get $srcOffs) (local.get $len))))
+
+(assert_trap (invoke "run" (i32.const 0) (i32.const 65515) (i32.const 39))
+    "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 65516) (i32.const 65517) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65517) (i32.const 65518) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65518) (i32.const 65519) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65519) (i32.const 65520) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65520) (i32.const 65521) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65521) (i32.const 65522) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65522) (i32.const 65523) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65523) (i32.const 65524) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65524) (i32.const 65525) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65525) (i32.const 65526) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65526) (i32.const 65527) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65527) (i32.const 65528) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65528) (i32.const 65529) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65529) (i32.const 65530) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65530) (i32.const 65531) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65531) (i32.const 65532) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65532) (i32.const 65533) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65533) (i32.const 65534) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65534) (i32.const 65535) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65535) (i32.const 65536) (i32.const 19)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 65517) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65517) (i32.const 65518) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65518) (i32.const 65519) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65519) (i32.const 65520) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65520) (i32.const 65521) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65521) (i32.const 65522) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65522) (i32.const 65523) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65523) (i32.const 65524) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65524) (i32.const 65525) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65525) (i32.const 65526) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65526) (i32.const 65527) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65527) (i32.const 65528) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65528) (i32.const 65529) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65529) (i32.const 65530) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65530) (i32.const 65531) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65531) (i32.const 65532) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65532) (i32.const 65533) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65533) (i32.const 65534) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65534) (i32.const 65535) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65535) (i32.const 65536) (i32.const 19)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (data (i32.const 65516) "\00\01\02\03\04\05\06\07\08\09\0a\0b\0c\0d\0e\0f\10\11\12\13")
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "run") (param $targetOffs i32) (param $srcOffs i32) (param $len i32)
+    (memory.copy (local.get $targetOffs) (local.get $srcOffs) (local.get $len))))
+
+(assert_trap (invoke "run" (i32.const 65516) (i32.const 65506) (i32.const 40))
+    "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 65516) (i32.const 65517) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65517) (i32.const 65518) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65518) (i32.const 65519) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65519) (i32.const 65520) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65520) (i32.const 65521) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65521) (i32.const 65522) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65522) (i32.const 65523) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65523) (i32.const 65524) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65524) (i32.const 65525) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65525) (i32.const 65526) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65526) (i32.const 65527) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65527) (i32.const 65528) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65528) (i32.const 65529) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65529) (i32.const 65530) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65530) (i32.const 65531) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65531) (i32.const 65532) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65532) (i32.const 65533) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65533) (i32.const 65534) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65534) (i32.const 65535) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65535) (i32.const 65536) (i32.const 19)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 65517) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65517) (i32.const 65518) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65518) (i32.const 65519) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65519) (i32.const 65520) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65520) (i32.const 65521) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65521) (i32.const 65522) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65522) (i32.const 65523) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65523) (i32.const 65524) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65524) (i32.const 65525) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65525) (i32.const 65526) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65526) (i32.const 65527) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65527) (i32.const 65528) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65528) (i32.const 65529) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65529) (i32.const 65530) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65530) (i32.const 65531) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65531) (i32.const 65532) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65532) (i32.const 65533) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65533) (i32.const 65534) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65534) (i32.const 65535) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65535) (i32.const 65536) (i32.const 19)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (data (i32.const 65516) "\00\01\02\03\04\05\06\07\08\09\0a\0b\0c\0d\0e\0f\10\11\12\13")
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "run") (param $targetOffs i32) (param $srcOffs i32) (param $len i32)
+    (memory.copy (local.get $targetOffs) (local.get $srcOffs) (local.get $len))))
+
+(assert_trap (invoke "run" (i32.const 65506) (i32.const 65516) (i32.const 40))
+    "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 65516) (i32.const 65517) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65517) (i32.const 65518) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65518) (i32.const 65519) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65519) (i32.const 65520) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65520) (i32.const 65521) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65521) (i32.const 65522) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65522) (i32.const 65523) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65523) (i32.const 65524) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65524) (i32.const 65525) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65525) (i32.const 65526) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65526) (i32.const 65527) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65527) (i32.const 65528) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65528) (i32.const 65529) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65529) (i32.const 65530) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65530) (i32.const 65531) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65531) (i32.const 65532) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65532) (i32.const 65533) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65533) (i32.const 65534) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65534) (i32.const 65535) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65535) (i32.const 65536) (i32.const 19)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 65517) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65517) (i32.const 65518) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65518) (i32.const 65519) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65519) (i32.const 65520) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65520) (i32.const 65521) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65521) (i32.const 65522) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65522) (i32.const 65523) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65523) (i32.const 65524) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65524) (i32.const 65525) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65525) (i32.const 65526) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65526) (i32.const 65527) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65527) (i32.const 65528) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65528) (i32.const 65529) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65529) (i32.const 65530) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65530) (i32.const 65531) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65531) (i32.const 65532) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65532) (i32.const 65533) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65533) (i32.const 65534) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65534) (i32.const 65535) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65535) (i32.const 65536) (i32.const 19)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (data (i32.const 65516) "\00\01\02\03\04\05\06\07\08\09\0a\0b\0c\0d\0e\0f\10\11\12\13")
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "run") (param $targetOffs i32) (param $srcOffs i32) (param $len i32)
+    (memory.copy (local.get $targetOffs) (local.get $srcOffs) (local.get $len))))
+
+(assert_trap (invoke "run" (i32.const 65516) (i32.const 65516) (i32.const 40))
+    "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 65516) (i32.const 65517) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65517) (i32.const 65518) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65518) (i32.const 65519) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65519) (i32.const 65520) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65520) (i32.const 65521) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65521) (i32.const 65522) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65522) (i32.const 65523) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65523) (i32.const 65524) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65524) (i32.const 65525) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65525) (i32.const 65526) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65526) (i32.const 65527) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65527) (i32.const 65528) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65528) (i32.const 65529) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65529) (i32.const 65530) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65530) (i32.const 65531) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65531) (i32.const 65532) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65532) (i32.const 65533) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65533) (i32.const 65534) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65534) (i32.const 65535) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65535) (i32.const 65536) (i32.const 19)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 65517) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65517) (i32.const 65518) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65518) (i32.const 65519) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65519) (i32.const 65520) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65520) (i32.const 65521) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65521) (i32.const 65522) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65522) (i32.const 65523) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65523) (i32.const 65524) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65524) (i32.const 65525) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65525) (i32.const 65526) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65526) (i32.const 65527) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65527) (i32.const 65528) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65528) (i32.const 65529) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65529) (i32.const 65530) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65530) (i32.const 65531) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65531) (i32.const 65532) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65532) (i32.const 65533) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65533) (i32.const 65534) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65534) (i32.const 65535) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65535) (i32.const 65536) (i32.const 19)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (data (i32.const 0) "\00\01\02\03\04\05\06\07\08\09\0a\0b\0c\0d\0e\0f\10\11\12\13")
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "run") (param $targetOffs i32) (param $srcOffs i32) (param $len i32)
+    (memory.copy (local.get $targetOffs) (local.get $srcOffs) (local.get $len))))
+
+(assert_trap (invoke "run" (i32.const 65516) (i32.const 61440) (i32.const -256))
+    "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 1) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 1) (i32.const 2) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 2) (i32.const 3) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 3) (i32.const 4) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 4) (i32.const 5) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 5) (i32.const 6) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 6) (i32.const 7) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 7) (i32.const 8) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 8) (i32.const 9) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 9) (i32.const 10) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 10) (i32.const 11) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 11) (i32.const 12) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 12) (i32.const 13) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 13) (i32.const 14) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 14) (i32.const 15) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 15) (i32.const 16) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 16) (i32.const 17) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 17) (i32.const 18) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 18) (i32.const 19) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 19) (i32.const 20) (i32.const 19)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 1) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 1) (i32.const 2) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 2) (i32.const 3) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 3) (i32.const 4) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 4) (i32.const 5) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 5) (i32.const 6) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 6) (i32.const 7) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 7) (i32.const 8) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 8) (i32.const 9) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 9) (i32.const 10) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 10) (i32.const 11) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 11) (i32.const 12) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 12) (i32.const 13) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 13) (i32.const 14) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 14) (i32.const 15) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 15) (i32.const 16) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 16) (i32.const 17) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 17) (i32.const 18) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 18) (i32.const 19) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 19) (i32.const 20) (i32.const 19)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 20) (i32.const 65536) (i32.const 0)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (data (i32.const 65516) "\00\01\02\03\04\05\06\07\08\09\0a\0b\0c\0d\0e\0f\10\11\12\13")
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "run") (param $targetOffs i32) (param $srcOffs i32) (param $len i32)
+    (memory.copy (local.get $targetOffs) (local.get $srcOffs) (local.get $len))))
+
+(assert_trap (invoke "run" (i32.const 0) (i32.const 65516) (i32.const -4096))
+    "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 65516) (i32.const 65517) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65517) (i32.const 65518) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65518) (i32.const 65519) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65519) (i32.const 65520) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65520) (i32.const 65521) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65521) (i32.const 65522) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65522) (i32.const 65523) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65523) (i32.const 65524) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65524) (i32.const 65525) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65525) (i32.const 65526) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65526) (i32.const 65527) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65527) (i32.const 65528) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65528) (i32.const 65529) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65529) (i32.const 65530) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65530) (i32.const 65531) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65531) (i32.const 65532) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65532) (i32.const 65533) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65533) (i32.const 65534) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65534) (i32.const 65535) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65535) (i32.const 65536) (i32.const 19)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 65517) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65517) (i32.const 65518) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65518) (i32.const 65519) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65519) (i32.const 65520) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65520) (i32.const 65521) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65521) (i32.const 65522) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65522) (i32.const 65523) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65523) (i32.const 65524) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65524) (i32.const 65525) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65525) (i32.const 65526) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65526) (i32.const 65527) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65527) (i32.const 65528) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65528) (i32.const 65529) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65529) (i32.const 65530) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65530) (i32.const 65531) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65531) (i32.const 65532) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65532) (i32.const 65533) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65533) (i32.const 65534) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65534) (i32.const 65535) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65535) (i32.const 65536) (i32.const 19)) (i32.const -1))
+
+(module
+  (memory 1 1)
+  (data (i32.const 65516) "\00\01\02\03\04\05\06\07\08\09\0a\0b\0c\0d\0e\0f\10\11\12\13")
+  (func (export "checkRange") (param $from i32) (param $to i32) (param $expected i32) (result i32)
+    (loop $cont
+      (if (i32.eq (local.get $from) (local.get $to))
+        (then
+          (return (i32.const -1))))
+      (if (i32.eq (i32.load8_u (local.get $from)) (local.get $expected))
+        (then
+          (local.set $from (i32.add (local.get $from) (i32.const 1)))
+          (br $cont))))
+    (return (local.get $from)))
+  (func (export "run") (param $targetOffs i32) (param $srcOffs i32) (param $len i32)
+    (memory.copy (local.get $targetOffs) (local.get $srcOffs) (local.get $len))))
+
+(assert_trap (invoke "run" (i32.const 61440) (i32.const 65516) (i32.const -256))
+    "out of bounds memory access")
+(assert_return (invoke "checkRange" (i32.const 65516) (i32.const 65517) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65517) (i32.const 65518) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65518) (i32.const 65519) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65519) (i32.const 65520) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65520) (i32.const 65521) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65521) (i32.const 65522) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65522) (i32.const 65523) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65523) (i32.const 65524) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65524) (i32.const 65525) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65525) (i32.const 65526) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65526) (i32.const 65527) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65527) (i32.const 65528) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65528) (i32.const 65529) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65529) (i32.const 65530) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65530) (i32.const 65531) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65531) (i32.const 65532) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65532) (i32.const 65533) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65533) (i32.const 65534) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65534) (i32.const 65535) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65535) (i32.const 65536) (i32.const 19)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 0) (i32.const 65517) (i32.const 0)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65517) (i32.const 65518) (i32.const 1)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65518) (i32.const 65519) (i32.const 2)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65519) (i32.const 65520) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65520) (i32.const 65521) (i32.const 4)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65521) (i32.const 65522) (i32.const 5)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65522) (i32.const 65523) (i32.const 6)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65523) (i32.const 65524) (i32.const 7)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65524) (i32.const 65525) (i32.const 8)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65525) (i32.const 65526) (i32.const 9)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65526) (i32.const 65527) (i32.const 10)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65527) (i32.const 65528) (i32.const 11)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65528) (i32.const 65529) (i32.const 12)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65529) (i32.const 65530) (i32.const 13)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65530) (i32.const 65531) (i32.const 14)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65531) (i32.const 65532) (i32.const 15)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65532) (i32.const 65533) (i32.const 16)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65533) (i32.const 65534) (i32.const 17)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65534) (i32.const 65535) (i32.const 18)) (i32.const -1))
+(assert_return (invoke "checkRange" (i32.const 65535) (i32.const 65536) (i32.const 19)) (i32.const -1))
@@ -249,6 +249,9 @@ mod tests {
         let mut features = wabt::Features::new();
         features.enable_simd();
         features.enable_threads();
+        features.enable_bulk_memory();
+        features.enable_reference_types();
+        features.enable_multi_value();
         features.enable_sign_extension();
         features.enable_sat_float_to_int();
         let mut parser: ScriptParser =
@@ -296,6 +299,9 @@ mod tests {
                             features: Features {
                                 simd: true,
                                 threads: true,
+                                bulk_memory: true,
+                                reference_types: true,
+                                multi_value: true,
                             },
                             ..Default::default()
                         };
@@ -657,7 +663,9 @@ mod tests {
                                                     // TODO assert message?
                                                     test_report.count_passed()
                                                 }
-                                                RuntimeError::Error { .. } => {
+                                                RuntimeError::Error { .. }
+                                                | RuntimeError::Interrupted
+                                                | RuntimeError::ExecutionLimitExceeded => {
                                                     test_report.add_failure(
                                                         SpecFailure {
                                                             file: filename.to_string(),
@@ -702,6 +710,9 @@ mod tests {
                             features: Features {
                                 simd: true,
                                 threads: true,
+                                bulk_memory: true,
+                                reference_types: true,
+                                multi_value: true,
                             },
                             ..Default::default()
                         };
@@ -754,6 +765,9 @@ mod tests {
                             features: Features {
                                 simd: true,
                                 threads: true,
+                                bulk_memory: true,
+                                reference_types: true,
+                                multi_value: true,
                             },
                             ..Default::default()
                         };
@@ -805,6 +819,9 @@ mod tests {
                         features: Features {
                             simd: true,
                             threads: true,
+                            bulk_memory: true,
+                            reference_types: true,
+                            multi_value: true,
                         },
                         ..Default::default()
                     };
@@ -900,6 +917,9 @@ mod tests {
                             features: Features {
                                 simd: true,
                                 threads: true,
+                                bulk_memory: true,
+                                reference_types: true,
+                                multi_value: true,
                             },
                             ..Default::default()
                         };
@@ -1068,6 +1088,7 @@ mod tests {
             wasmer_runtime::types::Value::F32(x) => format!("{:#x}", x.to_bits()),
             wasmer_runtime::types::Value::F64(x) => format!("{:#x}", x.to_bits()),
             wasmer_runtime::types::Value::V128(x) => format!("{:#x}", x),
+            wasmer_runtime::types::Value::AnyRef(r) => format!("{:?}", r),
         }
     }
 
@@ -1087,6 +1108,9 @@ mod tests {
             wasmer_runtime::types::Value::F32(v) => SpectestValue::F32(v.to_bits()),
             wasmer_runtime::types::Value::F64(v) => SpectestValue::F64(v.to_bits()),
             wasmer_runtime::types::Value::V128(v) => SpectestValue::V128(v),
+            wasmer_runtime::types::Value::AnyRef(_) => {
+                panic!("reference values cannot be compared in assertions")
+            }
         }
     }
 
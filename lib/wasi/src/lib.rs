@@ -69,6 +69,7 @@ pub fn generate_import_object(
             fs: WasiFs::new(&preopened_files, &mapped_dirs).expect("Could not create WASI FS"),
             args: args.clone(),
             envs: envs.clone(),
+            memory_index: 0,
         });
 
         (
@@ -152,6 +153,7 @@ fn generate_import_object_snapshot0(
             fs: WasiFs::new(&preopened_files, &mapped_dirs).expect("Could not create WASI FS"),
             args: args.clone(),
             envs: envs.clone(),
+            memory_index: 0,
         });
 
         (
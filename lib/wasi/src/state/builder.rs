@@ -3,6 +3,7 @@
 use crate::state::{WasiFs, WasiState};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use wasmer_runtime_core::{module::ExportIndex, structures::TypedIndex, Module};
 
 /// Creates an empty [`WasiStateBuilder`].
 pub(crate) fn create_wasi_state(program_name: &str) -> WasiStateBuilder {
@@ -20,6 +21,8 @@ pub struct WasiStateBuilder {
     preopened_files: Vec<PathBuf>,
     mapped_dirs: Vec<(String, PathBuf)>,
     setup_fs_fn: Option<Rc<dyn Fn(&mut WasiFs) -> Result<(), String> + Send>>,
+    /// The name of the memory export chosen with `memory`, and its index if it exists.
+    memory_export: Option<(String, Option<u32>)>,
 }
 
 impl std::fmt::Debug for WasiStateBuilder {
@@ -30,6 +33,7 @@ impl std::fmt::Debug for WasiStateBuilder {
             .field("preopend_files", &self.preopened_files)
             .field("mapped_dirs", &self.mapped_dirs)
             .field("setup_fs_fn exists", &self.setup_fs_fn.is_some())
+            .field("memory_export", &self.memory_export)
             .finish()
     }
 }
@@ -43,6 +47,7 @@ pub enum WasiStateCreationError {
     MappedDirAliasFormattingError(String),
     WasiFsCreationError(String),
     WasiFsSetupError(String),
+    MemoryExportNotFound(String),
 }
 
 fn validate_mapped_dir_alias(alias: &str) -> Result<(), WasiStateCreationError> {
@@ -214,6 +219,18 @@ impl WasiStateBuilder {
         self
     }
 
+    /// Make WASI calls operate on the memory that `module` exports as `name`, instead of
+    /// memory 0.
+    pub fn memory(&mut self, module: &Module, name: &str) -> &mut Self {
+        let memory_index = match module.info().exports.get(name) {
+            Some(ExportIndex::Memory(memory_index)) => Some(memory_index.index() as u32),
+            _ => None,
+        };
+        self.memory_export = Some((name.to_string(), memory_index));
+
+        self
+    }
+
     /// Consumes the [`WasiStateBuilder`] and produces a [`WasiState`]
     ///
     /// Returns the error from `WasiFs::new` if there's an error
@@ -279,6 +296,13 @@ impl WasiStateBuilder {
             }
             validate_mapped_dir_alias(&alias)?;
         }
+        let memory_index = match &self.memory_export {
+            Some((_, Some(memory_index))) => *memory_index,
+            Some((name, None)) => {
+                return Err(WasiStateCreationError::MemoryExportNotFound(name.clone()));
+            }
+            None => 0,
+        };
         let mut wasi_fs = WasiFs::new(&self.preopened_files, &self.mapped_dirs)
             .map_err(WasiStateCreationError::WasiFsCreationError)?;
         if let Some(f) = &self.setup_fs_fn {
@@ -288,6 +312,7 @@ impl WasiStateBuilder {
             fs: wasi_fs,
             args: self.args.clone(),
             envs: self.envs.clone(),
+            memory_index,
         })
     }
 }
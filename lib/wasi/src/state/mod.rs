@@ -1286,6 +1286,9 @@ pub struct WasiState {
     pub fs: WasiFs,
     pub args: Vec<Vec<u8>>,
     pub envs: Vec<Vec<u8>>,
+    /// The index of the memory that WASI calls read from and write to.
+    #[serde(default)]
+    pub memory_index: u32,
 }
 
 impl WasiState {
@@ -1,6 +1,6 @@
 use crate::ptr::{Array, WasmPtr};
-use crate::syscalls;
 use crate::syscalls::types::{self, snapshot0};
+use crate::syscalls::{self, get_memory};
 use wasmer_runtime_core::{debug, vm::Ctx};
 
 /// Wrapper around `syscalls::fd_filestat_get` with extra logic to handle the size
@@ -14,7 +14,7 @@ pub fn fd_filestat_get(
     fd: types::__wasi_fd_t,
     buf: WasmPtr<snapshot0::__wasi_filestat_t>,
 ) -> types::__wasi_errno_t {
-    let memory = ctx.memory(0);
+    let memory = get_memory(ctx);
 
     // transmute the WasmPtr<T1> into a WasmPtr<T2> where T2 > T1, this will read extra memory.
     // The edge case of this causing an OOB is not handled, if the new field is OOB, then the entire
@@ -30,7 +30,7 @@ pub fn fd_filestat_get(
     let result = syscalls::fd_filestat_get(ctx, fd, new_buf);
 
     // reborrow memory
-    let memory = ctx.memory(0);
+    let memory = get_memory(ctx);
 
     // get the values written to memory
     let new_filestat = wasi_try!(new_buf.deref(memory)).get();
@@ -68,7 +68,7 @@ pub fn path_filestat_get(
     buf: WasmPtr<snapshot0::__wasi_filestat_t>,
 ) -> types::__wasi_errno_t {
     // see `fd_filestat_get` in this file for an explanation of this strange behavior
-    let memory = ctx.memory(0);
+    let memory = get_memory(ctx);
 
     let new_buf: WasmPtr<types::__wasi_filestat_t> = unsafe { std::mem::transmute(buf) };
     let new_filestat_setup: types::__wasi_filestat_t =
@@ -76,7 +76,7 @@ pub fn path_filestat_get(
 
     let result = syscalls::path_filestat_get(ctx, fd, flags, path, path_len, new_buf);
 
-    let memory = ctx.memory(0);
+    let memory = get_memory(ctx);
     let new_filestat = wasi_try!(new_buf.deref(memory)).get();
     let old_stat = snapshot0::__wasi_filestat_t {
         st_dev: new_filestat.st_dev,
@@ -127,7 +127,7 @@ pub fn poll_oneoff(
     // we just need to readjust and copy it
 
     // we start by adjusting `in_` into a format that the new code can understand
-    let memory = ctx.memory(0);
+    let memory = get_memory(ctx);
     let mut in_origs: Vec<snapshot0::__wasi_subscription_t> = vec![];
     for in_sub in wasi_try!(in_.deref(memory, 0, nsubscriptions)) {
         in_origs.push(in_sub.get().clone());
@@ -165,7 +165,7 @@ pub fn poll_oneoff(
     let result = syscalls::poll_oneoff(ctx, in_new_type_ptr, out_, nsubscriptions, nevents);
 
     // replace the old values of in, in case the calling code reuses the memory
-    let memory = ctx.memory(0);
+    let memory = get_memory(ctx);
 
     for (in_sub, orig) in wasi_try!(in_.deref(memory, 0, nsubscriptions))
         .iter()
@@ -31,11 +31,15 @@ pub use windows::*;
 
 /// This function is not safe
 #[allow(clippy::mut_from_ref)]
-pub(crate) fn get_memory_and_wasi_state(
-    ctx: &mut Ctx,
-    mem_index: u32,
-) -> (&Memory, &mut WasiState) {
-    unsafe { ctx.memory_and_data_mut(mem_index) }
+pub(crate) fn get_memory_and_wasi_state(ctx: &mut Ctx) -> (&Memory, &mut WasiState) {
+    let memory_index = unsafe { state::get_wasi_state(ctx) }.memory_index;
+    unsafe { ctx.memory_and_data_mut(memory_index) }
+}
+
+/// The memory that WASI calls operate on, see `WasiState::memory_index`.
+pub(crate) fn get_memory(ctx: &Ctx) -> &Memory {
+    let memory_index = unsafe { &*(ctx.data as *const WasiState) }.memory_index;
+    ctx.memory(memory_index)
 }
 
 fn write_bytes_inner<T: Write>(
@@ -137,7 +141,7 @@ pub fn args_get(
     argv_buf: WasmPtr<u8, Array>,
 ) -> __wasi_errno_t {
     debug!("wasi::args_get");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
 
     let result = write_buffer_array(memory, &*state.args, argv, argv_buf);
 
@@ -172,7 +176,7 @@ pub fn args_sizes_get(
     argv_buf_size: WasmPtr<u32>,
 ) -> __wasi_errno_t {
     debug!("wasi::args_sizes_get");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
 
     let argc = wasi_try!(argc.deref(memory));
     let argv_buf_size = wasi_try!(argv_buf_size.deref(memory));
@@ -201,7 +205,7 @@ pub fn clock_res_get(
     resolution: WasmPtr<__wasi_timestamp_t>,
 ) -> __wasi_errno_t {
     debug!("wasi::clock_res_get");
-    let memory = ctx.memory(0);
+    let memory = get_memory(ctx);
 
     let out_addr = wasi_try!(resolution.deref(memory));
     platform_clock_res_get(clock_id, out_addr)
@@ -227,7 +231,7 @@ pub fn clock_time_get(
         "wasi::clock_time_get clock_id: {}, precision: {}",
         clock_id, precision
     );
-    let memory = ctx.memory(0);
+    let memory = get_memory(ctx);
 
     let out_addr = wasi_try!(time.deref(memory));
     let result = platform_clock_time_get(clock_id, precision, out_addr);
@@ -253,7 +257,7 @@ pub fn environ_get(
     environ_buf: WasmPtr<u8, Array>,
 ) -> __wasi_errno_t {
     debug!("wasi::environ_get");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
 
     write_buffer_array(memory, &*state.envs, environ, environ_buf)
 }
@@ -271,7 +275,7 @@ pub fn environ_sizes_get(
     environ_buf_size: WasmPtr<u32>,
 ) -> __wasi_errno_t {
     debug!("wasi::environ_sizes_get");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
 
     let environ_count = wasi_try!(environ_count.deref(memory));
     let environ_buf_size = wasi_try!(environ_buf_size.deref(memory));
@@ -330,7 +334,7 @@ pub fn fd_allocate(
     len: __wasi_filesize_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_allocate");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let fd_entry = wasi_try!(state.fs.get_fd(fd)).clone();
     let inode = fd_entry.inode;
 
@@ -372,7 +376,7 @@ pub fn fd_allocate(
 pub fn fd_close(ctx: &mut Ctx, fd: __wasi_fd_t) -> __wasi_errno_t {
     debug!("wasi::fd_close");
     debug!("=> fd={}", fd);
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
 
     let fd_entry = wasi_try!(state.fs.get_fd(fd)).clone();
 
@@ -388,7 +392,7 @@ pub fn fd_close(ctx: &mut Ctx, fd: __wasi_fd_t) -> __wasi_errno_t {
 ///     The file descriptor to sync
 pub fn fd_datasync(ctx: &mut Ctx, fd: __wasi_fd_t) -> __wasi_errno_t {
     debug!("wasi::fd_datasync");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let fd_entry = wasi_try!(state.fs.get_fd(fd)).clone();
     if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_DATASYNC) {
         return __WASI_EACCES;
@@ -419,7 +423,7 @@ pub fn fd_fdstat_get(
         fd,
         buf_ptr.offset()
     );
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let fd_entry = wasi_try!(state.fs.get_fd(fd)).clone();
 
     let stat = wasi_try!(state.fs.fdstat(fd));
@@ -443,7 +447,7 @@ pub fn fd_fdstat_set_flags(
     flags: __wasi_fdflags_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_fdstat_set_flags");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
 
     if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_FDSTAT_SET_FLAGS) {
@@ -470,7 +474,7 @@ pub fn fd_fdstat_set_rights(
     fs_rights_inheriting: __wasi_rights_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_fdstat_set_rights");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
 
     // ensure new rights are a subset of current rights
@@ -500,7 +504,7 @@ pub fn fd_filestat_get(
     buf: WasmPtr<__wasi_filestat_t>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_filestat_get");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
     if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_FILESTAT_GET) {
         return __WASI_EACCES;
@@ -527,7 +531,7 @@ pub fn fd_filestat_set_size(
     st_size: __wasi_filesize_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_filestat_set_size");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let fd_entry = wasi_try!(state.fs.get_fd(fd)).clone();
     let inode = fd_entry.inode;
 
@@ -571,7 +575,7 @@ pub fn fd_filestat_set_times(
     fst_flags: __wasi_fstflags_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_filestat_set_times");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
 
     if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_FILESTAT_SET_TIMES) {
@@ -650,7 +654,7 @@ pub fn fd_pread(
     nread: WasmPtr<u32>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_pread");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
 
     let iov_cells = wasi_try!(iovs.deref(memory, 0, iovs_len));
     let nread_cell = wasi_try!(nread.deref(memory));
@@ -716,7 +720,7 @@ pub fn fd_prestat_get(
     buf: WasmPtr<__wasi_prestat_t>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_prestat_get: fd={}", fd);
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
 
     let prestat_ptr = wasi_try!(buf.deref(memory));
 
@@ -735,7 +739,7 @@ pub fn fd_prestat_dir_name(
         "wasi::fd_prestat_dir_name: fd={}, path_len={}",
         fd, path_len
     );
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let path_chars = wasi_try!(path.deref(memory, 0, path_len));
 
     let real_fd = wasi_try!(state.fs.fd_map.get(&fd).ok_or(__WASI_EBADF));
@@ -795,7 +799,7 @@ pub fn fd_pwrite(
 ) -> __wasi_errno_t {
     debug!("wasi::fd_pwrite");
     // TODO: refactor, this is just copied from `fd_write`...
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let iovs_arr_cell = wasi_try!(iovs.deref(memory, 0, iovs_len));
     let nwritten_cell = wasi_try!(nwritten.deref(memory));
 
@@ -880,7 +884,7 @@ pub fn fd_read(
     nread: WasmPtr<u32>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_read: fd={}", fd);
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
 
     let iovs_arr_cell = wasi_try!(iovs.deref(memory, 0, iovs_len));
     let nread_cell = wasi_try!(nread.deref(memory));
@@ -961,7 +965,7 @@ pub fn fd_readdir(
     bufused: WasmPtr<u32>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_readdir");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     // TODO: figure out how this is supposed to work;
     // is it supposed to pack the buffer full every time until it can't? or do one at a time?
 
@@ -1057,7 +1061,7 @@ pub fn fd_readdir(
 ///     Location to copy file descriptor to
 pub fn fd_renumber(ctx: &mut Ctx, from: __wasi_fd_t, to: __wasi_fd_t) -> __wasi_errno_t {
     debug!("wasi::fd_renumber: from={}, to={}", from, to);
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let fd_entry = wasi_try!(state.fs.fd_map.get(&from).ok_or(__WASI_EBADF));
     let new_fd_entry = Fd {
         // TODO: verify this is correct
@@ -1090,7 +1094,7 @@ pub fn fd_seek(
     newoffset: WasmPtr<__wasi_filesize_t>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_seek: fd={}, offset={}", fd, offset);
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let new_offset_cell = wasi_try!(newoffset.deref(memory));
 
     let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
@@ -1149,7 +1153,7 @@ pub fn fd_seek(
 pub fn fd_sync(ctx: &mut Ctx, fd: __wasi_fd_t) -> __wasi_errno_t {
     debug!("wasi::fd_sync");
     debug!("=> fd={}", fd);
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
     if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_SYNC) {
         return __WASI_EACCES;
@@ -1186,7 +1190,7 @@ pub fn fd_tell(
     offset: WasmPtr<__wasi_filesize_t>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_tell");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let offset_cell = wasi_try!(offset.deref(memory));
 
     let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
@@ -1222,7 +1226,7 @@ pub fn fd_write(
     nwritten: WasmPtr<u32>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_write: fd={}", fd);
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let iovs_arr_cell = wasi_try!(iovs.deref(memory, 0, iovs_len));
     let nwritten_cell = wasi_try!(nwritten.deref(memory));
 
@@ -1307,7 +1311,7 @@ pub fn path_create_directory(
     path_len: u32,
 ) -> __wasi_errno_t {
     debug!("wasi::path_create_directory");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
 
     let working_dir = wasi_try!(state.fs.get_fd(fd)).clone();
     if let Kind::Root { .. } = &state.fs.inodes[working_dir.inode].kind {
@@ -1412,7 +1416,7 @@ pub fn path_filestat_get(
     buf: WasmPtr<__wasi_filestat_t>,
 ) -> __wasi_errno_t {
     debug!("wasi::path_filestat_get");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
 
     let root_dir = wasi_try!(state.fs.get_fd(fd));
 
@@ -1467,7 +1471,7 @@ pub fn path_filestat_set_times(
     fst_flags: __wasi_fstflags_t,
 ) -> __wasi_errno_t {
     debug!("wasi::path_filestat_set_times");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let fd_entry = wasi_try!(state.fs.get_fd(fd)).clone();
     let fd_inode = fd_entry.inode;
     if !has_rights(fd_entry.rights, __WASI_RIGHT_PATH_FILESTAT_SET_TIMES) {
@@ -1564,7 +1568,7 @@ pub fn path_link(
     if old_flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0 {
         debug!("  - will follow symlinks when opening path");
     }
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let old_path_str = get_input_str!(memory, old_path, old_path_len);
     let new_path_str = get_input_str!(memory, new_path, new_path_len);
     let source_fd = wasi_try!(state.fs.get_fd(old_fd));
@@ -1649,7 +1653,7 @@ pub fn path_open(
     if dirflags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0 {
         debug!("  - will follow symlinks when opening path");
     }
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     /* TODO: find actual upper bound on name size (also this is a path, not a name :think-fish:) */
     if path_len > 1024 * 1024 {
         return __WASI_ENAMETOOLONG;
@@ -1884,7 +1888,7 @@ pub fn path_readlink(
     buf_used: WasmPtr<u32>,
 ) -> __wasi_errno_t {
     debug!("wasi::path_readlink");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
 
     let base_dir = wasi_try!(state.fs.fd_map.get(&dir_fd).ok_or(__WASI_EBADF));
     if !has_rights(base_dir.rights, __WASI_RIGHT_PATH_READLINK) {
@@ -1927,7 +1931,7 @@ pub fn path_remove_directory(
 ) -> __wasi_errno_t {
     // TODO check if fd is a dir, ensure it's within sandbox, etc.
     debug!("wasi::path_remove_directory");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
 
     let base_dir = wasi_try!(state.fs.fd_map.get(&fd), __WASI_EBADF);
     let path_str = get_input_str!(memory, path, path_len);
@@ -2007,7 +2011,7 @@ pub fn path_rename(
     new_path_len: u32,
 ) -> __wasi_errno_t {
     debug!("wasi::path_rename");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let source_str = get_input_str!(memory, old_path, old_path_len);
     let source_path = std::path::Path::new(source_str);
     let target_str = get_input_str!(memory, new_path, new_path_len);
@@ -2115,7 +2119,7 @@ pub fn path_symlink(
     new_path_len: u32,
 ) -> __wasi_errno_t {
     debug!("wasi::path_symlink");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
     let old_path_str = get_input_str!(memory, old_path, old_path_len);
     let new_path_str = get_input_str!(memory, new_path, new_path_len);
     let base_fd = wasi_try!(state.fs.get_fd(fd));
@@ -2192,7 +2196,7 @@ pub fn path_unlink_file(
     path_len: u32,
 ) -> __wasi_errno_t {
     debug!("wasi::path_unlink_file");
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
 
     let base_dir = wasi_try!(state.fs.fd_map.get(&fd).ok_or(__WASI_EBADF));
     if !has_rights(base_dir.rights, __WASI_RIGHT_PATH_UNLINK_FILE) {
@@ -2288,7 +2292,7 @@ pub fn poll_oneoff(
 ) -> __wasi_errno_t {
     debug!("wasi::poll_oneoff");
     debug!("  => nsubscriptions = {}", nsubscriptions);
-    let (memory, state) = get_memory_and_wasi_state(ctx, 0);
+    let (memory, state) = get_memory_and_wasi_state(ctx);
 
     let subscription_array = wasi_try!(in_.deref(memory, 0, nsubscriptions));
     let event_array = wasi_try!(out_.deref(memory, 0, nsubscriptions));
@@ -2484,7 +2488,7 @@ pub fn proc_raise(ctx: &mut Ctx, sig: __wasi_signal_t) -> __wasi_errno_t {
 ///     The number of bytes that will be written
 pub fn random_get(ctx: &mut Ctx, buf: WasmPtr<u8, Array>, buf_len: u32) -> __wasi_errno_t {
     debug!("wasi::random_get buf_len: {}", buf_len);
-    let memory = ctx.memory(0);
+    let memory = get_memory(ctx);
 
     let buf = wasi_try!(buf.deref(memory, 0, buf_len));
 
@@ -87,6 +87,18 @@ struct PrestandardFeatures {
     #[structopt(long = "enable-threads")]
     threads: bool,
 
+    /// Enable support for the bulk memory proposal.
+    #[structopt(long = "enable-bulk-memory")]
+    bulk_memory: bool,
+
+    /// Enable support for the reference types proposal.
+    #[structopt(long = "enable-reference-types")]
+    reference_types: bool,
+
+    /// Enable support for the multi-value proposal.
+    #[structopt(long = "enable-multi-value")]
+    multi_value: bool,
+
     /// Enable support for all pre-standard proposals.
     #[structopt(long = "enable-all")]
     all: bool,
@@ -102,6 +114,15 @@ impl PrestandardFeatures {
         if self.threads || self.all {
             features.enable_threads();
         }
+        if self.bulk_memory || self.all {
+            features.enable_bulk_memory();
+        }
+        if self.reference_types || self.all {
+            features.enable_reference_types();
+        }
+        if self.multi_value || self.all {
+            features.enable_multi_value();
+        }
         features.enable_sign_extension();
         features.enable_sat_float_to_int();
         features
@@ -112,6 +133,9 @@ impl PrestandardFeatures {
         Features {
             simd: self.simd || self.all,
             threads: self.threads || self.all,
+            bulk_memory: self.bulk_memory || self.all,
+            reference_types: self.reference_types || self.all,
+            multi_value: self.multi_value || self.all,
         }
     }
 }
@@ -464,7 +488,7 @@ fn execute_wasi(
                         },
                     )
                     .collect(),
-                interactive_shell,
+                |ctx| interactive_shell(ctx, module.info()),
             )?
         };
     }
@@ -517,12 +541,19 @@ fn execute_wasi(
 
         if let Err(ref err) = result {
             match err {
-                RuntimeError::Trap { msg } => return Err(format!("wasm trap occured: {}", msg)),
+                RuntimeError::Trap { msg, backtrace, .. } => {
+                    let mut message = format!("wasm trap occured: {}", msg);
+                    for frame in backtrace {
+                        message += &format!("\n    at {}", frame);
+                    }
+                    return Err(message);
+                }
                 RuntimeError::Error { data } => {
                     if let Some(error_code) = data.downcast_ref::<wasmer_wasi::ExitCode>() {
                         std::process::exit(error_code.code as i32)
                     }
                 }
+                RuntimeError::Interrupted | RuntimeError::ExecutionLimitExceeded => {}
             }
             return Err(format!("error: {:?}", err));
         }
@@ -862,7 +893,10 @@ fn execute_wasm(options: &Run) -> Result<(), String> {
 }
 
 #[cfg(feature = "managed")]
-fn interactive_shell(mut ctx: InteractiveShellContext) -> ShellExitOperation {
+fn interactive_shell(
+    mut ctx: InteractiveShellContext,
+    info: &wasmer_runtime_core::module::ModuleInfo,
+) -> ShellExitOperation {
     use std::io::Write;
 
     let mut stdout = ::std::io::stdout();
@@ -918,7 +952,7 @@ fn interactive_shell(mut ctx: InteractiveShellContext) -> ShellExitOperation {
             }
             "backtrace" | "bt" => {
                 if let Some(ref image) = ctx.image {
-                    println!("{}", image.execution_state.output());
+                    println!("{}", image.execution_state.output(info));
                 } else {
                     println!("State not available");
                 }
@@ -1018,7 +1052,9 @@ fn get_compiler_by_backend(backend: Backend, _opts: &Run) -> Option<Box<dyn Comp
                 let mut middlewares = MiddlewareChain::new();
                 if opts.call_trace {
                     use wasmer_middleware_common::call_trace::CallTrace;
-                    middlewares.push(CallTrace::new());
+                    let call_trace = CallTrace::new();
+                    middlewares.push_module(call_trace.clone());
+                    middlewares.push(call_trace);
                 }
                 if opts.block_trace {
                     use wasmer_middleware_common::block_trace::BlockTrace;
@@ -1,7 +1,10 @@
 //! Utility functions for the WebAssembly module
 
-use wasmer_runtime::{types::Type, Module, Value};
-use wasmer_runtime_core::{backend::SigRegistry, module::ExportIndex};
+use wasmer_runtime::{
+    types::{ExternType, Type},
+    Module, Value,
+};
+use wasmer_runtime_core::table::AnyRef;
 
 /// Detect if a provided binary is a Wasm file
 pub fn is_wasm_binary(binary: &[u8]) -> bool {
@@ -22,21 +25,15 @@ pub fn parse_args(
     fn_name: &str,
     args: &[String],
 ) -> Result<Vec<Value>, InvokeError> {
-    let export_index = module
-        .info()
-        .exports
-        .get(fn_name)
+    let export = module
+        .exports()
+        .into_iter()
+        .find(|export| export.name == fn_name)
         .ok_or(InvokeError::CouldNotFindFunction)?;
 
-    let signature = if let ExportIndex::Func(func_index) = export_index {
-        let sig_index = module
-            .info()
-            .func_assoc
-            .get(*func_index)
-            .expect("broken invariant, incorrect func index");
-        SigRegistry.lookup_signature_ref(&module.info().signatures[*sig_index])
-    } else {
-        return Err(InvokeError::ExportNotFunction);
+    let signature = match export.ty {
+        ExternType::Function(signature) => signature,
+        _ => return Err(InvokeError::ExportNotFunction),
     };
 
     let parameter_types = signature.params();
@@ -104,6 +101,17 @@ pub fn parse_args(
                                 )));
                                 None
                             }),
+                        Type::AnyRef | Type::FuncRef => {
+                            if argument == "null" {
+                                Some(Value::AnyRef(AnyRef::null()))
+                            } else {
+                                arg_error = Some(InvokeError::CouldNotParseArg(format!(
+                                    "Only `null` can be passed as a reference, found `{:?}`",
+                                    argument
+                                )));
+                                None
+                            }
+                        }
                     } {
                         accumulator.push(value);
 